@@ -0,0 +1,146 @@
+//! # Activities
+//!
+//! Activities record what each entity or resource is doing over simulated time. They are
+//! started and finished against the scheduler's clock with [`EventScheduler::begin_activity`]
+//! and [`EventScheduler::end_activity`], and can be exported as CSV or as a Mermaid Gantt
+//! chart for visualization.
+
+use crate::csv::csv_field;
+use crate::EventScheduler;
+use std::io::{self, Write};
+
+/// A completed activity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activity {
+    /// Who or what performed the activity, such as an entity or resource name.
+    pub subject: String,
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Activity {
+    /// Returns the length of the activity.
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// The activities recorded by a scheduler.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityLog {
+    open: Vec<(String, String, f64)>,
+    completed: Vec<Activity>,
+}
+
+impl ActivityLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        ActivityLog::default()
+    }
+
+    /// Returns the completed activities in the order they finished.
+    pub fn completed(&self) -> &[Activity] {
+        &self.completed
+    }
+
+    /// Returns the `(subject, name, start)` of activities that have begun but not ended.
+    pub fn open(&self) -> impl Iterator<Item = (&str, &str, f64)> {
+        self.open.iter().map(|(subject, name, start)| (subject.as_str(), name.as_str(), *start))
+    }
+
+    /// Writes the completed activities as CSV with columns `subject,name,start,end`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "subject,name,start,end")?;
+        for activity in &self.completed {
+            writeln!(writer, "{},{},{},{}", csv_field(&activity.subject), csv_field(&activity.name), activity.start, activity.end)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the completed activities as a Mermaid Gantt chart with one section per subject,
+    /// in order of first appearance.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.begin_activity("car", "park");
+    /// scheduler.current_time = 5.0;
+    /// scheduler.end_activity("car", "park");
+    ///
+    /// let mut chart = Vec::new();
+    /// scheduler.activities.write_mermaid_gantt(&mut chart).unwrap();
+    /// let chart = String::from_utf8(chart).unwrap();
+    /// assert!(chart.contains("section car"));
+    /// assert!(chart.contains("park : 0, 5"));
+    /// ```
+    pub fn write_mermaid_gantt<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "gantt")?;
+        writeln!(writer, "    dateFormat X")?;
+        writeln!(writer, "    axisFormat %s")?;
+        let mut subjects: Vec<&str> = Vec::new();
+        for activity in &self.completed {
+            if !subjects.contains(&activity.subject.as_str()) {
+                subjects.push(&activity.subject);
+            }
+        }
+        for subject in subjects {
+            writeln!(writer, "    section {}", mermaid_text(subject))?;
+            for activity in self.completed.iter().filter(|a| a.subject == subject) {
+                writeln!(writer, "    {} : {}, {}", mermaid_text(&activity.name), activity.start, activity.end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Strips characters that would break a Mermaid Gantt line.
+fn mermaid_text(text: &str) -> String {
+    text.replace([':', '\n', '#', ';'], " ")
+}
+
+impl EventScheduler {
+    /// Starts an activity for `subject` at the current time.
+    pub fn begin_activity(&mut self, subject: impl Into<String>, name: impl Into<String>) {
+        self.activities.open.push((subject.into(), name.into(), self.current_time));
+    }
+
+    /// Ends the earliest open activity of `subject` called `name` at the current time.
+    ///
+    /// # Returns
+    /// `true` if a matching open activity was found.
+    pub fn end_activity(&mut self, subject: &str, name: &str) -> bool {
+        let found = self.activities.open.iter().position(|(s, n, _)| s == subject && n == name);
+        match found {
+            Some(index) => {
+                let (subject, name, start) = self.activities.open.remove(index);
+                self.activities.completed.push(Activity { subject, name, start, end: self.current_time });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_lifecycle_and_csv() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.begin_activity("pump, north", "refuel");
+        assert!(!scheduler.end_activity("pump, north", "wash"));
+        scheduler.current_time = 3.5;
+        assert!(scheduler.end_activity("pump, north", "refuel"));
+        scheduler.begin_activity("pump, north", "idle");
+        assert_eq!(scheduler.activities.open().count(), 1);
+        assert_eq!(scheduler.activities.completed()[0].duration(), 3.5);
+
+        let mut out = Vec::new();
+        scheduler.activities.write_csv(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "subject,name,start,end\n\"pump, north\",refuel,0,3.5\n");
+    }
+}
@@ -0,0 +1,297 @@
+//! # Agents
+//!
+//! A light agent-based modeling layer. Each [`Agent`] is activated by events: when activated it
+//! acts and may ask to be activated again after a delay. Agents can also exchange messages,
+//! delivered by events after a chosen delay, and can be created and removed while the
+//! simulation runs. An [`AgentManager`] owns the agents and schedules all of this on an
+//! ordinary [`EventScheduler`].
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Identifies an agent within its manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AgentId(pub u64);
+
+impl fmt::Display for AgentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "agent-{}", self.0)
+    }
+}
+
+/// An agent exchanging messages of type `M`.
+pub trait Agent<M> {
+    /// Called when the agent is activated.
+    ///
+    /// # Returns
+    /// The delay until the agent's next activation, or `None` to wait until it is activated
+    /// again explicitly.
+    fn act(&mut self, ctx: &mut AgentContext<'_, M>) -> Option<f64>;
+
+    /// Called when a message sent to the agent is delivered. Messages are ignored by default.
+    fn receive(&mut self, ctx: &mut AgentContext<'_, M>, from: AgentId, message: M) {
+        let _ = (ctx, from, message);
+    }
+}
+
+/// What an agent can see and do while it acts or receives a message.
+pub struct AgentContext<'a, M> {
+    /// The agent being activated.
+    pub id: AgentId,
+    pub scheduler: &'a mut EventScheduler,
+    manager: AgentManager<M>,
+}
+
+impl<M: 'static> AgentContext<'_, M> {
+    /// Sends a message from this agent, to be delivered after `delay`.
+    pub fn send(&mut self, to: AgentId, message: M, delay: f64) {
+        self.manager.send(self.scheduler, self.id, to, message, delay);
+    }
+
+    /// Creates a new agent, first activated at the current time.
+    pub fn spawn(&mut self, agent: impl Agent<M> + 'static) -> AgentId {
+        self.manager.spawn(self.scheduler, agent)
+    }
+
+    /// Removes an agent, possibly this one.
+    pub fn remove(&mut self, id: AgentId) -> bool {
+        self.manager.remove(self.scheduler, id)
+    }
+
+    /// Returns the ids of the live agents in creation order.
+    pub fn agents(&self) -> Vec<AgentId> {
+        self.manager.ids()
+    }
+}
+
+struct Slot<M> {
+    // Taken out while the agent acts, so that it can use the manager.
+    agent: Option<Box<dyn Agent<M>>>,
+    activation: Option<EventId>,
+}
+
+struct ManagerState<M> {
+    agents: BTreeMap<AgentId, Slot<M>>,
+    next_id: u64,
+}
+
+/// Owns a population of agents and schedules their activations and messages.
+///
+/// Clones manage the same population, so an event action can spawn, activate, or message
+/// agents through its own clone.
+///
+/// # Example
+/// ```
+/// use desru::{Agent, AgentContext, AgentId, AgentManager, EventScheduler};
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// // Pings its peer every 2 time units; the peer counts the pings.
+/// struct Pinger(AgentId);
+/// struct Counter(Rc<Cell<u32>>);
+///
+/// impl Agent<&'static str> for Pinger {
+///     fn act(&mut self, ctx: &mut AgentContext<'_, &'static str>) -> Option<f64> {
+///         ctx.send(self.0, "ping", 0.5);
+///         Some(2.0)
+///     }
+/// }
+///
+/// impl Agent<&'static str> for Counter {
+///     fn act(&mut self, _: &mut AgentContext<'_, &'static str>) -> Option<f64> {
+///         None
+///     }
+///
+///     fn receive(&mut self, _: &mut AgentContext<'_, &'static str>, _: AgentId, message: &'static str) {
+///         assert_eq!(message, "ping");
+///         self.0.set(self.0.get() + 1);
+///     }
+/// }
+///
+/// let pings = Rc::new(Cell::new(0));
+/// let mut scheduler = EventScheduler::new();
+/// let agents = AgentManager::new();
+/// let counter = agents.spawn(&mut scheduler, Counter(pings.clone()));
+/// agents.spawn(&mut scheduler, Pinger(counter));
+/// scheduler.run_until_max_time(10.0);
+/// assert_eq!(pings.get(), 5);
+/// ```
+pub struct AgentManager<M> {
+    state: Rc<RefCell<ManagerState<M>>>,
+}
+
+impl<M> Clone for AgentManager<M> {
+    fn clone(&self) -> Self {
+        AgentManager { state: self.state.clone() }
+    }
+}
+
+impl<M: 'static> AgentManager<M> {
+    /// Creates a manager with no agents.
+    pub fn new() -> Self {
+        AgentManager { state: Rc::new(RefCell::new(ManagerState { agents: BTreeMap::new(), next_id: 0 })) }
+    }
+
+    /// Adds an agent and activates it at the current time.
+    pub fn spawn(&self, scheduler: &mut EventScheduler, agent: impl Agent<M> + 'static) -> AgentId {
+        let id = {
+            let mut state = self.state.borrow_mut();
+            let id = AgentId(state.next_id);
+            state.next_id += 1;
+            state.agents.insert(id, Slot { agent: Some(Box::new(agent)), activation: None });
+            id
+        };
+        self.activate(scheduler, id, 0.0);
+        id
+    }
+
+    /// Removes an agent and cancels its pending activation. Messages still in flight to it
+    /// are dropped on delivery.
+    ///
+    /// # Returns
+    /// `true` if the agent existed.
+    pub fn remove(&self, scheduler: &mut EventScheduler, id: AgentId) -> bool {
+        let Some(slot) = self.state.borrow_mut().agents.remove(&id) else {
+            return false;
+        };
+        if let Some(activation) = slot.activation {
+            scheduler.cancel(activation);
+        }
+        true
+    }
+
+    /// Activates an agent after `delay`, replacing any activation already pending.
+    pub fn activate(&self, scheduler: &mut EventScheduler, id: AgentId, delay: f64) {
+        let previous = match self.state.borrow_mut().agents.get_mut(&id) {
+            Some(slot) => slot.activation.take(),
+            None => return,
+        };
+        if let Some(previous) = previous {
+            scheduler.cancel(previous);
+        }
+        let manager = self.clone();
+        let event = ScheduledAction::at(scheduler.time_after(delay)).with_action(move |s| {
+            manager.run_activation(s, id);
+            None
+        });
+        let activation = scheduler.schedule(event);
+        if let Some(slot) = self.state.borrow_mut().agents.get_mut(&id) {
+            slot.activation = Some(activation);
+        }
+    }
+
+    /// Sends a message from one agent to another, delivered after `delay`.
+    pub fn send(&self, scheduler: &mut EventScheduler, from: AgentId, to: AgentId, message: M, delay: f64) {
+        let manager = self.clone();
+        let mut message = Some(message);
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(delay)).with_action(move |s| {
+            if let Some(message) = message.take() {
+                manager.deliver(s, from, to, message);
+            }
+            None
+        }));
+    }
+
+    /// Returns `true` if the agent exists.
+    pub fn contains(&self, id: AgentId) -> bool {
+        self.state.borrow().agents.contains_key(&id)
+    }
+
+    /// Returns the number of live agents.
+    pub fn len(&self) -> usize {
+        self.state.borrow().agents.len()
+    }
+
+    /// Returns `true` if there are no live agents.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the ids of the live agents in creation order.
+    pub fn ids(&self) -> Vec<AgentId> {
+        self.state.borrow().agents.keys().copied().collect()
+    }
+
+    fn take(&self, id: AgentId) -> Option<Box<dyn Agent<M>>> {
+        self.state.borrow_mut().agents.get_mut(&id).and_then(|slot| slot.agent.take())
+    }
+
+    /// Returns an agent to its slot, unless it was removed meanwhile.
+    fn put_back(&self, id: AgentId, agent: Box<dyn Agent<M>>) -> bool {
+        match self.state.borrow_mut().agents.get_mut(&id) {
+            Some(slot) => {
+                slot.agent = Some(agent);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn run_activation(&self, scheduler: &mut EventScheduler, id: AgentId) {
+        if let Some(slot) = self.state.borrow_mut().agents.get_mut(&id) {
+            slot.activation = None;
+        }
+        let Some(mut agent) = self.take(id) else {
+            return;
+        };
+        let next = agent.act(&mut AgentContext { id, scheduler, manager: self.clone() });
+        if self.put_back(id, agent) {
+            if let Some(delay) = next {
+                self.activate(scheduler, id, delay);
+            }
+        }
+    }
+
+    fn deliver(&self, scheduler: &mut EventScheduler, from: AgentId, to: AgentId, message: M) {
+        let Some(mut agent) = self.take(to) else {
+            return;
+        };
+        agent.receive(&mut AgentContext { id: to, scheduler, manager: self.clone() }, from, message);
+        self.put_back(to, agent);
+    }
+}
+
+impl<M: 'static> Default for AgentManager<M> {
+    fn default() -> Self {
+        AgentManager::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Optionally splits on its first activation and dies on its second.
+    struct Cell {
+        splits: bool,
+        age: u32,
+    }
+
+    impl Agent<()> for Cell {
+        fn act(&mut self, ctx: &mut AgentContext<'_, ()>) -> Option<f64> {
+            self.age += 1;
+            if self.age == 1 && self.splits {
+                ctx.spawn(Cell { splits: false, age: 0 });
+            }
+            if self.age == 2 {
+                ctx.remove(ctx.id);
+            }
+            Some(1.0)
+        }
+    }
+
+    #[test]
+    fn test_agents_spawn_and_remove_at_runtime() {
+        let mut scheduler = EventScheduler::new();
+        let agents = AgentManager::new();
+        agents.spawn(&mut scheduler, Cell { splits: true, age: 0 });
+        scheduler.run_until_max_time(0.5);
+        assert_eq!(agents.len(), 2);
+        scheduler.run_until_max_time(10.0);
+        assert!(agents.is_empty());
+        assert!(scheduler.event_queue.is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+//! # Output Analysis
+//!
+//! Post-processing for simulation output:
+//!
+//! - [`confidence_interval`]: A Student-t confidence interval for the mean of independent
+//!   observations, such as one summary value per replication.
+//! - [`batch_means`]: Splits one long, autocorrelated run into batches whose means are nearly
+//!   independent, so that a confidence interval can be built from a single run.
+//! - [`welch_moving_average`]: Welch's method for choosing a warm-up period by eye, averaging
+//!   several replications and smoothing the result with a moving window.
+//!
+//! [`Tally`] and [`Monitored`] expose the same operations as methods.
+
+use crate::{Monitored, Tally};
+
+/// A two-sided confidence interval for a mean.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    pub mean: f64,
+    pub half_width: f64,
+    /// The confidence level, such as `0.95`.
+    pub level: f64,
+}
+
+impl ConfidenceInterval {
+    /// Returns the lower bound of the interval.
+    pub fn lower(&self) -> f64 {
+        self.mean - self.half_width
+    }
+
+    /// Returns the upper bound of the interval.
+    pub fn upper(&self) -> f64 {
+        self.mean + self.half_width
+    }
+
+    /// Returns `true` if `value` lies within the interval.
+    pub fn contains(&self, value: f64) -> bool {
+        self.lower() <= value && value <= self.upper()
+    }
+}
+
+/// Computes a Student-t confidence interval for the mean of independent observations.
+///
+/// # Parameters
+/// - `values`: The observations.
+/// - `level`: The confidence level, strictly between `0` and `1`.
+///
+/// # Returns
+/// The interval, or `None` with fewer than two observations.
+///
+/// # Example
+/// ```
+/// use desru::confidence_interval;
+///
+/// let ci = confidence_interval(&[9.8, 10.2, 10.1, 9.9, 10.0], 0.95).unwrap();
+/// assert!((ci.mean - 10.0).abs() < 1e-12);
+/// assert!((ci.half_width - 0.1963).abs() < 1e-4);
+/// ```
+pub fn confidence_interval(values: &[f64], level: f64) -> Option<ConfidenceInterval> {
+    assert!(level > 0.0 && level < 1.0, "confidence level must be in (0, 1)");
+    let n = values.len();
+    if n < 2 {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let t = student_t_quantile(0.5 + level / 2.0, (n - 1) as f64);
+    Some(ConfidenceInterval { mean, half_width: t * (variance / n as f64).sqrt(), level })
+}
+
+/// Returns the means of `batches` consecutive, equally sized batches of `values`.
+///
+/// When `values.len()` is not a multiple of `batches`, the leftover observations are dropped
+/// from the start, where the run is furthest from steady state.
+///
+/// # Panics
+/// Panics if `batches` is zero.
+pub fn batch_means(values: &[f64], batches: usize) -> Vec<f64> {
+    assert!(batches > 0, "batch_means requires at least one batch");
+    let size = values.len() / batches;
+    if size == 0 {
+        return Vec::new();
+    }
+    values[values.len() - size * batches..]
+        .chunks(size)
+        .map(|batch| batch.iter().sum::<f64>() / size as f64)
+        .collect()
+}
+
+/// Averages replications observation by observation and smooths the averages with Welch's
+/// moving window of half-width `window`.
+///
+/// Plotting the result against the observation index shows where the output levels off; the
+/// warm-up period is the number of observations before that point. Replications are truncated
+/// to the shortest one.
+///
+/// # Returns
+/// `m - window` smoothed values, where `m` is the length of the shortest replication, or an
+/// empty vector if there is too little data.
+///
+/// # Example
+/// ```
+/// use desru::welch_moving_average;
+///
+/// let runs = [vec![0.0, 2.0, 3.0, 3.0, 3.0, 3.0], vec![0.0, 2.0, 3.0, 3.0, 3.0, 3.0]];
+/// let smoothed = welch_moving_average(&runs, 1);
+/// assert_eq!(smoothed, vec![0.0, 5.0 / 3.0, 8.0 / 3.0, 3.0, 3.0]);
+/// ```
+pub fn welch_moving_average<T: AsRef<[f64]>>(replications: &[T], window: usize) -> Vec<f64> {
+    let Some(m) = replications.iter().map(|r| r.as_ref().len()).min() else {
+        return Vec::new();
+    };
+    let averages: Vec<f64> = (0..m)
+        .map(|i| replications.iter().map(|r| r.as_ref()[i]).sum::<f64>() / replications.len() as f64)
+        .collect();
+    (0..m.saturating_sub(window))
+        .map(|i| {
+            // Near the start the window shrinks so that it stays centred on observation `i`.
+            let half = i.min(window);
+            let slice = &averages[i - half..=i + half];
+            slice.iter().sum::<f64>() / slice.len() as f64
+        })
+        .collect()
+}
+
+/// Returns the `p`-quantile of Student's t distribution with `df` degrees of freedom.
+///
+/// # Example
+/// ```
+/// use desru::student_t_quantile;
+///
+/// assert!((student_t_quantile(0.975, 10.0) - 2.228).abs() < 1e-3);
+/// assert_eq!(student_t_quantile(0.5, 3.0), 0.0);
+/// ```
+pub fn student_t_quantile(p: f64, df: f64) -> f64 {
+    assert!(p > 0.0 && p < 1.0, "quantile probability must be in (0, 1)");
+    if p < 0.5 {
+        return -student_t_quantile(1.0 - p, df);
+    }
+    if p == 0.5 {
+        return 0.0;
+    }
+    let mut high = 1.0;
+    while student_t_cdf(high, df) < p {
+        high *= 2.0;
+    }
+    let mut low = 0.0;
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if student_t_cdf(mid, df) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+fn student_t_cdf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * regularized_beta(df / (df + t * t), df / 2.0, 0.5);
+    if t >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// The regularized incomplete beta function `I_x(a, b)`, by Lentz's continued fraction.
+fn regularized_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly only below the mean of the distribution.
+    if x > (a + 1.0) / (a + b + 2.0) {
+        return 1.0 - regularized_beta(1.0 - x, b, a);
+    }
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = 1.0 / if d.abs() < TINY { TINY } else { d };
+    let mut result = d;
+    for m in 1..300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            result *= c * d;
+        }
+        if (c * d - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    front * result / a
+}
+
+/// The natural logarithm of the gamma function, by the Lanczos approximation.
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..].iter().enumerate().fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+impl Tally {
+    /// Returns a Student-t confidence interval for the mean of the observations, or `None`
+    /// with fewer than two observations.
+    pub fn confidence_interval(&self, level: f64) -> Option<ConfidenceInterval> {
+        confidence_interval(self.values(), level)
+    }
+
+    /// Returns the means of `batches` consecutive batches of the observations, as a new tally.
+    pub fn batch_means(&self, batches: usize) -> Tally {
+        let mut means = Tally::new();
+        for mean in batch_means(self.values(), batches) {
+            means.record(mean);
+        }
+        means
+    }
+}
+
+impl Monitored {
+    /// Splits the time from the first record up to `now` into `batches` equal intervals and
+    /// returns the time-weighted average of each, as a tally.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::Monitored;
+    ///
+    /// let mut busy = Monitored::new(0.0);
+    /// busy.record(0.0, 1.0);
+    /// busy.record(5.0, 0.0);
+    /// assert_eq!(busy.batch_means(10.0, 4).values(), &[1.0, 1.0, 0.0, 0.0]);
+    /// ```
+    pub fn batch_means(&self, now: f64, batches: usize) -> Tally {
+        assert!(batches > 0, "batch_means requires at least one batch");
+        let mut means = Tally::new();
+        let trajectory = self.trajectory();
+        let Some(&(start, _)) = trajectory.first() else {
+            return means;
+        };
+        let width = (now - start) / batches as f64;
+        if width <= 0.0 {
+            return means;
+        }
+        for batch in 0..batches {
+            let (from, to) = (start + batch as f64 * width, start + (batch + 1) as f64 * width);
+            let mut area = 0.0;
+            for (i, &(time, value)) in trajectory.iter().enumerate() {
+                let until = trajectory.get(i + 1).map_or(now, |&(next, _)| next);
+                let overlap = until.min(to) - time.max(from);
+                if overlap > 0.0 {
+                    area += value * overlap;
+                }
+            }
+            means.record(area / width);
+        }
+        means
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_t_quantiles_match_tables() {
+        for (p, df, expected) in [(0.975, 1.0, 12.706), (0.975, 4.0, 2.776), (0.95, 30.0, 1.697), (0.995, 120.0, 2.617)] {
+            assert!((student_t_quantile(p, df) - expected).abs() < 1e-3, "t({}, {})", p, df);
+        }
+    }
+
+    #[test]
+    fn test_batch_means_drop_leading_leftovers() {
+        let values: Vec<f64> = (0..7).map(|x| x as f64).collect();
+        assert_eq!(batch_means(&values, 3), vec![1.5, 3.5, 5.5]);
+        let mut tally = Tally::new();
+        values.iter().for_each(|&v| tally.record(v));
+        assert_eq!(tally.batch_means(2).count(), 2);
+        assert!(tally.confidence_interval(0.9).unwrap().contains(3.0));
+    }
+}
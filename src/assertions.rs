@@ -0,0 +1,146 @@
+//! # Log Assertions
+//!
+//! [`LogAssertions`] adds fluent assertions to event logs, so tests over simulation output can
+//! say what they expect in one line instead of filtering records by hand. Each step narrows the
+//! set of matching records and panics, listing the candidates, as soon as none are left.
+
+use crate::EventRecord;
+
+/// Fluent assertions over a slice of [`EventRecord`]s, such as a [`crate::RunResult`] or
+/// `scheduler.event_log`.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, LogAssertions, ScheduledAction};
+///
+/// let mut scheduler = EventScheduler::new();
+/// scheduler.schedule(ScheduledAction::at(5.0).with_label("arrival").with_action(|_| Some("ok: admitted".to_string())));
+/// let log = scheduler.run_until_max_time(10.0);
+///
+/// log.assert_event_at(5.0).with_label("arrival").with_result_containing("ok");
+/// log.assert_event_labeled("arrival").count(1);
+/// log.assert_no_event_labeled("departure");
+/// ```
+pub trait LogAssertions {
+    /// Starts an assertion over the records logged at `time`.
+    ///
+    /// # Panics
+    /// Panics if no record was logged at `time`.
+    fn assert_event_at(&self, time: f64) -> EventAssertion<'_>;
+
+    /// Starts an assertion over the records with `label`.
+    ///
+    /// # Panics
+    /// Panics if no record has `label`.
+    fn assert_event_labeled(&self, label: &str) -> EventAssertion<'_>;
+
+    /// Asserts that no record has `label`.
+    ///
+    /// # Panics
+    /// Panics, listing the offending records, if any record has `label`.
+    fn assert_no_event_labeled(&self, label: &str);
+}
+
+impl LogAssertions for [EventRecord] {
+    #[track_caller]
+    fn assert_event_at(&self, time: f64) -> EventAssertion<'_> {
+        EventAssertion { matches: self.iter().collect() }.narrow(&format!("at time {}", time), |r| r.time == time)
+    }
+
+    #[track_caller]
+    fn assert_event_labeled(&self, label: &str) -> EventAssertion<'_> {
+        EventAssertion { matches: self.iter().collect() }.narrow(&format!("labeled {:?}", label), |r| r.label.as_deref() == Some(label))
+    }
+
+    #[track_caller]
+    fn assert_no_event_labeled(&self, label: &str) {
+        let found: Vec<_> = self.iter().filter(|r| r.label.as_deref() == Some(label)).collect();
+        assert!(found.is_empty(), "expected no event labeled {:?}, found {:?}", label, found);
+    }
+}
+
+/// The records still matching a chain of assertions, see [`LogAssertions`].
+#[derive(Debug, Clone)]
+pub struct EventAssertion<'a> {
+    matches: Vec<&'a EventRecord>,
+}
+
+impl<'a> EventAssertion<'a> {
+    /// Keeps the records with `label`.
+    #[track_caller]
+    pub fn with_label(self, label: &str) -> Self {
+        self.narrow(&format!("labeled {:?}", label), |r| r.label.as_deref() == Some(label))
+    }
+
+    /// Keeps the records whose result contains `text`.
+    #[track_caller]
+    pub fn with_result_containing(self, text: &str) -> Self {
+        self.narrow(&format!("with a result containing {:?}", text), |r| r.result.as_deref().is_some_and(|result| result.contains(text)))
+    }
+
+    /// Keeps the records that returned no result.
+    #[track_caller]
+    pub fn without_result(self) -> Self {
+        self.narrow("without a result", |r| r.result.is_none())
+    }
+
+    /// Keeps the records whose context maps `key` to `value`.
+    #[track_caller]
+    pub fn with_context(self, key: &str, value: &str) -> Self {
+        self.narrow(&format!("with context {}={}", key, value), |r| r.context.get(key).is_some_and(|v| v == value))
+    }
+
+    /// Asserts that exactly `expected` records still match.
+    ///
+    /// # Panics
+    /// Panics if a different number of records match.
+    #[track_caller]
+    pub fn count(self, expected: usize) -> Self {
+        assert_eq!(self.matches.len(), expected, "expected {} matching events, found {:?}", expected, self.matches);
+        self
+    }
+
+    /// Returns the first matching record.
+    pub fn record(&self) -> &'a EventRecord {
+        self.matches[0]
+    }
+
+    /// Returns every matching record in log order.
+    pub fn records(&self) -> &[&'a EventRecord] {
+        &self.matches
+    }
+
+    #[track_caller]
+    fn narrow(self, description: &str, keep: impl Fn(&EventRecord) -> bool) -> Self {
+        let matches: Vec<_> = self.matches.iter().copied().filter(|r| keep(r)).collect();
+        assert!(!matches.is_empty(), "expected an event {}, candidates were {:?}", description, self.matches);
+        EventAssertion { matches }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, EventScheduler, ScheduledAction};
+
+    fn log() -> Vec<EventRecord> {
+        let mut scheduler = EventScheduler::new();
+        let ward = Context::from([("ward".to_string(), "B".to_string())]);
+        scheduler.schedule(ScheduledAction::at(1.0).with_label("arrival").with_context(ward));
+        scheduler.schedule(ScheduledAction::at(1.0).with_label("triage").with_action(|_| Some("urgent".to_string())));
+        scheduler.run_until_max_time(10.0).to_vec()
+    }
+
+    #[test]
+    fn test_chained_narrowing() {
+        let log = log();
+        let arrival = log.assert_event_at(1.0).count(2).without_result().with_context("ward", "B").record();
+        assert_eq!(arrival.label.as_deref(), Some("arrival"));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected an event with a result containing \"routine\"")]
+    fn test_failure_names_the_missing_step() {
+        log().assert_event_at(1.0).with_label("triage").with_result_containing("routine");
+    }
+}
@@ -0,0 +1,187 @@
+//! # Background Log Writing
+//!
+//! Formatting and writing a log row costs more than running many simple events, so a run that
+//! logs heavily spends most of its time in its sink. A [`BackgroundSink`] moves that work to a
+//! writer thread: the event loop only sends each record over a bounded channel, and the
+//! thread formats and writes it with the wrapped [`LogSink`].
+//!
+//! The channel holds at most `capacity` records. When the writer falls that far behind, the
+//! event loop waits for it rather than buffering without limit, so memory stays bounded; how
+//! often that happened is reported by [`BackgroundSink::stalls`] as a sign that the writer,
+//! not the model, limits the run.
+
+use crate::{EventRecord, LogSink, SeedAudit};
+use std::io;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+/// Work sent to the writer thread.
+enum Message {
+    Event(Box<EventRecord>),
+    Sample(String, f64, f64),
+    Seeds(f64, SeedAudit),
+    Flush,
+}
+
+/// A [`LogSink`] that writes to another sink on a background thread.
+///
+/// A write fails only once the writer thread has stopped after an error; the error itself is
+/// returned by [`BackgroundSink::finish`].
+///
+/// # Example
+/// ```
+/// use desru::{BackgroundSink, CsvSink, EventScheduler, StreamSink};
+///
+/// let sink = StreamSink::new(BackgroundSink::new(CsvSink::new(Vec::new()), 1024));
+/// let mut scheduler = EventScheduler::builder().hook(sink.hook()).build();
+/// for t in 1..=3 {
+///     scheduler.timeout(f64::from(t), None, None);
+/// }
+/// scheduler.run_until_max_time(10.0);
+/// drop(scheduler);
+///
+/// let csv = sink.finish().unwrap().finish().unwrap().into_inner();
+/// assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 4);
+/// ```
+pub struct BackgroundSink<S> {
+    sender: Option<SyncSender<Message>>,
+    writer: Option<JoinHandle<(S, io::Result<()>)>>,
+    stalls: u64,
+}
+
+impl<S: LogSink + Send + 'static> BackgroundSink<S> {
+    /// Starts a writer thread for `sink`, with room for `capacity` records in flight.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero or the thread cannot be started.
+    pub fn new(sink: S, capacity: usize) -> Self {
+        assert!(capacity > 0, "the channel capacity must be positive");
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let writer = thread::Builder::new()
+            .name("desru-log-writer".to_string())
+            .spawn(move || {
+                let mut sink = sink;
+                let mut outcome = Ok(());
+                for message in receiver {
+                    outcome = match message {
+                        Message::Event(record) => sink.write_event(&record),
+                        Message::Sample(series, time, value) => sink.write_sample(&series, time, value),
+                        Message::Seeds(time, seeds) => sink.write_seeds(time, &seeds),
+                        Message::Flush => sink.flush(),
+                    };
+                    if outcome.is_err() {
+                        break;
+                    }
+                }
+                if outcome.is_ok() {
+                    outcome = sink.flush();
+                }
+                (sink, outcome)
+            })
+            .expect("failed to start the log writer thread");
+        BackgroundSink { sender: Some(sender), writer: Some(writer), stalls: 0 }
+    }
+
+    /// Returns how many writes had to wait because the channel was full.
+    pub fn stalls(&self) -> u64 {
+        self.stalls
+    }
+
+    /// Waits for the writer thread to write everything sent, flushes the sink, and returns it.
+    ///
+    /// # Errors
+    /// Returns the first error the sink raised, after which later records were dropped.
+    pub fn finish(mut self) -> io::Result<S> {
+        self.sender = None;
+        let writer = self.writer.take().expect("the writer thread is joined only once");
+        let (sink, outcome) = writer.join().map_err(|_| io::Error::other("the log writer thread panicked"))?;
+        outcome.map(|_| sink)
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        let sender = self.sender.as_ref().expect("the sender lives until the sink is finished");
+        let message = match sender.try_send(message) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(message)) => message,
+            Err(TrySendError::Disconnected(_)) => return Err(stopped()),
+        };
+        self.stalls += 1;
+        sender.send(message).map_err(|_| stopped())
+    }
+}
+
+impl<S: LogSink + Send + 'static> LogSink for BackgroundSink<S> {
+    fn write_event(&mut self, record: &EventRecord) -> io::Result<()> {
+        self.send(Message::Event(Box::new(record.clone())))
+    }
+
+    fn write_sample(&mut self, series: &str, time: f64, value: f64) -> io::Result<()> {
+        self.send(Message::Sample(series.to_string(), time, value))
+    }
+
+    fn write_seeds(&mut self, time: f64, seeds: &SeedAudit) -> io::Result<()> {
+        self.send(Message::Seeds(time, seeds.clone()))
+    }
+
+    /// Asks the writer thread to flush the sink once it has written what was sent before,
+    /// without waiting for it.
+    fn flush(&mut self) -> io::Result<()> {
+        self.send(Message::Flush)
+    }
+}
+
+impl<S> Drop for BackgroundSink<S> {
+    /// Waits for the writer thread to write everything sent, discarding any error.
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the log writer thread stopped after an error")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, CsvSink, EventId};
+    use std::io::Write;
+
+    /// Accepts a fixed number of bytes, then fails.
+    #[derive(Debug)]
+    struct Limited(usize);
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.0 {
+                return Err(io::Error::other("disk full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writes_in_order_and_reports_the_first_error() {
+        let record = |id| EventRecord { id: EventId(id), time: id as f64, label: None, context: Context::new(), result: None };
+        let mut sink = BackgroundSink::new(CsvSink::new(Vec::new()), 1);
+        for id in 0..500 {
+            sink.write_event(&record(id)).unwrap();
+        }
+        let csv = String::from_utf8(sink.finish().unwrap().into_inner()).unwrap();
+        let ids: Vec<&str> = csv.lines().skip(1).map(|line| line.split(',').nth(4).unwrap()).collect();
+        assert_eq!(ids, (0..500).map(|id| id.to_string()).collect::<Vec<_>>());
+
+        let mut failing = BackgroundSink::new(CsvSink::new(Limited(100)), 4);
+        let sent = (0..500).take_while(|&id| failing.write_event(&record(id)).is_ok()).count();
+        assert!(sent < 500);
+        assert_eq!(failing.finish().unwrap_err().to_string(), "disk full");
+    }
+}
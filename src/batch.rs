@@ -0,0 +1,174 @@
+//! # Batching
+//!
+//! A [`Batcher`] accumulates arriving items and releases them together, either when a batch
+//! is full or when the oldest item has waited for a timeout, whichever comes first.
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The downstream callback receiving each completed batch.
+type BatchHandler<T> = Rc<RefCell<dyn FnMut(&mut EventScheduler, Vec<T>)>>;
+
+struct BatchState<T> {
+    size: usize,
+    timeout: Option<f64>,
+    items: Vec<T>,
+    // The pending timeout of the current batch, cancelled when the batch is released early.
+    timer: Option<EventId>,
+}
+
+/// Groups items into batches of a fixed size, with an optional timeout.
+///
+/// Each completed batch is delivered to the downstream callback by an event at the time it
+/// completes. Clones add to the same pending batch, so each producer can capture its own.
+///
+/// # Example
+/// ```
+/// use desru::{Batcher, EventScheduler};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let shipped = Rc::new(RefCell::new(Vec::new()));
+/// let record = shipped.clone();
+/// let pallet = Batcher::new(3, move |s, boxes: Vec<u32>| {
+///     record.borrow_mut().push((s.current_time, boxes));
+/// }).with_timeout(10.0);
+///
+/// let mut scheduler = EventScheduler::new();
+/// for (i, time) in [1.0, 2.0, 3.0, 4.0].into_iter().enumerate() {
+///     let pallet = pallet.clone();
+///     scheduler.timeout(time, Some(Box::new(move |s| {
+///         pallet.add(s, i as u32);
+///         None
+///     })), None);
+/// }
+/// scheduler.run_until_max_time(20.0);
+/// // The first three boxes fill a pallet; the fourth ships alone when its timeout expires.
+/// assert_eq!(*shipped.borrow(), vec![(3.0, vec![0, 1, 2]), (14.0, vec![3])]);
+/// ```
+pub struct Batcher<T> {
+    state: Rc<RefCell<BatchState<T>>>,
+    on_batch: BatchHandler<T>,
+}
+
+impl<T> Clone for Batcher<T> {
+    fn clone(&self) -> Self {
+        Batcher { state: self.state.clone(), on_batch: self.on_batch.clone() }
+    }
+}
+
+impl<T: 'static> Batcher<T> {
+    /// Creates a batcher releasing batches of `size` items to `on_batch`.
+    ///
+    /// # Panics
+    /// Panics if `size` is zero.
+    pub fn new<F>(size: usize, on_batch: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler, Vec<T>) + 'static,
+    {
+        assert!(size > 0, "batch size must be positive");
+        Batcher {
+            state: Rc::new(RefCell::new(BatchState { size, timeout: None, items: Vec::new(), timer: None })),
+            on_batch: Rc::new(RefCell::new(on_batch)),
+        }
+    }
+
+    /// Releases a partial batch once its oldest item has waited `timeout` time units.
+    pub fn with_timeout(self, timeout: f64) -> Self {
+        self.state.borrow_mut().timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the number of items waiting for the current batch to complete.
+    pub fn pending(&self) -> usize {
+        self.state.borrow().items.len()
+    }
+
+    /// Adds an item, releasing the batch if it is now full.
+    pub fn add(&self, scheduler: &mut EventScheduler, item: T) {
+        let (full, start_timer) = {
+            let mut state = self.state.borrow_mut();
+            state.items.push(item);
+            (state.items.len() >= state.size, state.items.len() == 1)
+        };
+        if full {
+            self.flush(scheduler);
+        } else if start_timer {
+            self.start_timer(scheduler);
+        }
+    }
+
+    /// Releases the current partial batch immediately, if it is not empty.
+    pub fn flush(&self, scheduler: &mut EventScheduler) {
+        let (items, timer) = {
+            let mut state = self.state.borrow_mut();
+            if state.items.is_empty() {
+                return;
+            }
+            (std::mem::take(&mut state.items), state.timer.take())
+        };
+        if let Some(timer) = timer {
+            scheduler.cancel(timer);
+        }
+        let on_batch = self.on_batch.clone();
+        let mut items = Some(items);
+        scheduler.schedule(ScheduledAction::new(
+            scheduler.current_time,
+            Some(Box::new(move |s: &mut EventScheduler| {
+                if let Some(items) = items.take() {
+                    (on_batch.borrow_mut())(s, items);
+                }
+                None
+            })),
+            None,
+        ));
+    }
+
+    fn start_timer(&self, scheduler: &mut EventScheduler) {
+        let Some(timeout) = self.state.borrow().timeout else {
+            return;
+        };
+        let batcher = self.clone();
+        let timer = scheduler.timeout(
+            timeout,
+            Some(Box::new(move |s: &mut EventScheduler| {
+                batcher.state.borrow_mut().timer = None;
+                batcher.flush(s);
+                None
+            })),
+            None,
+        );
+        self.state.borrow_mut().timer = Some(timer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_batch_cancels_timeout() {
+        let batches = Rc::new(RefCell::new(Vec::new()));
+        let record = batches.clone();
+        let batcher = Batcher::new(2, move |s, items: Vec<char>| record.borrow_mut().push((s.current_time, items)))
+            .with_timeout(5.0);
+        let mut scheduler = EventScheduler::new();
+        for (time, item) in [(0.0, 'a'), (1.0, 'b'), (3.0, 'c')] {
+            let batcher = batcher.clone();
+            scheduler.timeout(time, Some(Box::new(move |s| {
+                batcher.add(s, item);
+                None
+            })), None);
+        }
+        scheduler.run_until_max_time(4.0);
+        assert_eq!(batcher.pending(), 1);
+        // The timer started for 'a' was cancelled when 'b' filled the batch; only the timer
+        // for 'c' is left.
+        assert_eq!(scheduler.event_queue.len(), 1);
+        scheduler.run_until_max_time(100.0);
+        assert_eq!(*batches.borrow(), vec![(1.0, vec!['a', 'b']), (8.0, vec!['c'])]);
+        // Three additions, two releases, and the one timer that expired.
+        assert_eq!(scheduler.event_log.len(), 6);
+    }
+}
@@ -0,0 +1,198 @@
+//! # Blackboard
+//!
+//! The [`Blackboard`] is a simulation-wide key-value store on the scheduler. Components post
+//! values under agreed keys with [`EventScheduler::post`] and read them back through
+//! `scheduler.blackboard`, so they can share data without holding handles to each other. Every
+//! entry records when it was last written, and a component can [`EventScheduler::watch`] a key
+//! to be woken the next time it is posted, or register a condition over the blackboard with
+//! [`EventScheduler::when`].
+//!
+//! Like event contexts, values are stored as strings so that they can be logged; typed values
+//! go in through `Display` and come out through [`Blackboard::get_as`].
+
+use crate::{EventScheduler, ScheduledAction};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A value on the blackboard together with when it was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlackboardEntry {
+    pub value: String,
+    /// The simulation time of the last write.
+    pub time: f64,
+    /// The number of times the key has been written.
+    pub version: u64,
+}
+
+/// Identifies a watch registered with [`EventScheduler::watch`], so that it can be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WatchId(pub u64);
+
+/// The continuation of a watcher.
+type Watcher = Box<dyn FnOnce(&mut EventScheduler, BlackboardEntry)>;
+
+/// Shared, time-stamped key-value data.
+#[derive(Default)]
+pub struct Blackboard {
+    entries: HashMap<String, BlackboardEntry>,
+    watchers: HashMap<String, Vec<(WatchId, Watcher)>>,
+    next_watch: u64,
+}
+
+impl Blackboard {
+    /// Creates an empty blackboard.
+    pub fn new() -> Self {
+        Blackboard::default()
+    }
+
+    /// Returns the value posted under `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(|entry| entry.value.as_str())
+    }
+
+    /// Parses the value posted under `key` as a `T`.
+    ///
+    /// # Returns
+    /// `None` if the key is missing or its value does not parse.
+    pub fn get_as<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// Returns the entry posted under `key`, including its timestamp.
+    pub fn entry(&self, key: &str) -> Option<&BlackboardEntry> {
+        self.entries.get(key)
+    }
+
+    /// Returns the posted keys in arbitrary order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Returns the number of posted keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if nothing has been posted.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of watchers waiting for `key` to be posted.
+    pub fn watching(&self, key: &str) -> usize {
+        self.watchers.get(key).map_or(0, Vec::len)
+    }
+
+    /// Cancels a watch that has not fired yet.
+    ///
+    /// # Returns
+    /// `true` if the watch was waiting.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        for waiting in self.watchers.values_mut() {
+            let before = waiting.len();
+            waiting.retain(|(existing, _)| *existing != id);
+            if waiting.len() != before {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl fmt::Debug for Blackboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blackboard")
+            .field("entries", &self.entries)
+            .field("watchers", &self.watchers.values().map(Vec::len).sum::<usize>())
+            .finish()
+    }
+}
+
+impl EventScheduler {
+    /// Writes `value` under `key` at the current time and wakes every watcher of the key, each
+    /// by an event at the current time in the order they started watching.
+    ///
+    /// Every post counts as a change, even if the value is the same as before. Conditions
+    /// registered with [`EventScheduler::when`] are checked once the watchers are scheduled.
+    ///
+    /// # Returns
+    /// The entry that was replaced, if any.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let woken = Rc::new(Cell::new(None));
+    /// let record = woken.clone();
+    /// scheduler.watch("weather", move |s, entry| record.set(Some((s.current_time, entry.value == "storm"))));
+    ///
+    /// scheduler.timeout(3.0, Some(Box::new(|s| {
+    ///     s.post("weather", "storm");
+    ///     None
+    /// })), None);
+    /// scheduler.run_until_max_time(10.0);
+    /// assert_eq!(woken.get(), Some((3.0, true)));
+    /// assert_eq!(scheduler.blackboard.entry("weather").map(|e| e.time), Some(3.0));
+    /// ```
+    pub fn post(&mut self, key: impl Into<String>, value: impl fmt::Display) -> Option<BlackboardEntry> {
+        let key = key.into();
+        let version = self.blackboard.entries.get(&key).map_or(1, |entry| entry.version + 1);
+        let entry = BlackboardEntry { value: value.to_string(), time: self.current_time, version };
+        let previous = self.blackboard.entries.insert(key.clone(), entry.clone());
+        for (_, watcher) in self.blackboard.watchers.remove(&key).unwrap_or_default() {
+            let mut wake = Some((watcher, entry.clone()));
+            self.schedule(ScheduledAction::at(self.current_time).with_action(move |s| {
+                if let Some((watcher, entry)) = wake.take() {
+                    watcher(s, entry);
+                }
+                None
+            }));
+        }
+        self.check_conditions();
+        previous
+    }
+
+    /// Calls `on_change` with the new entry the next time `key` is posted.
+    ///
+    /// A watch fires once; watch again from `on_change` to keep following the key.
+    pub fn watch<F>(&mut self, key: impl Into<String>, on_change: F) -> WatchId
+    where
+        F: FnOnce(&mut EventScheduler, BlackboardEntry) + 'static,
+    {
+        let id = WatchId(self.blackboard.next_watch);
+        self.blackboard.next_watch += 1;
+        self.blackboard.watchers.entry(key.into()).or_default().push((id, Box::new(on_change)));
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_versions_and_unwatch() {
+        let woken = Rc::new(RefCell::new(Vec::new()));
+        let mut scheduler = EventScheduler::new();
+        let first = woken.clone();
+        let cancelled = scheduler.watch("level", move |_, entry| first.borrow_mut().push(entry.version));
+        let second = woken.clone();
+        scheduler.watch("level", move |_, entry| second.borrow_mut().push(entry.version));
+        assert_eq!(scheduler.blackboard.watching("level"), 2);
+        assert!(scheduler.blackboard.unwatch(cancelled));
+
+        assert_eq!(scheduler.post("level", 3), None);
+        let previous = scheduler.post("level", 5).unwrap();
+        assert_eq!(previous.value, "3");
+        scheduler.run_until_max_time(1.0);
+        assert_eq!(*woken.borrow(), vec![1]);
+        assert_eq!(scheduler.blackboard.get_as::<u32>("level"), Some(5));
+        assert_eq!(scheduler.blackboard.entry("level").unwrap().version, 2);
+    }
+}
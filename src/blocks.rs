@@ -0,0 +1,571 @@
+//! # Queueing Network Blocks
+//!
+//! Ready-made components for assembling queueing networks: a [`Source`] creates entities, a
+//! [`Queue`] holds them until one of its [`Server`]s is free, and a [`Sink`] absorbs them and
+//! records their cycle times. Components are wired by connecting each one's output to the next
+//! one's input and then schedule their own events, so a Jackson-style network takes a few lines.
+//! A [`crate::Router`] splits a flow between several blocks.
+//!
+//! Every block is a cheaply cloneable handle, like [`crate::Resource`].
+
+use crate::entity::{Entity, Milestone};
+use crate::stats::{Monitored, Tally};
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+
+/// Draws a delay, such as an interarrival or service time, typically from the scheduler's RNG.
+type DelayFn = Box<dyn FnMut(&mut EventScheduler) -> f64>;
+
+/// A component that entities can be sent to.
+pub trait Block {
+    /// Hands an entity to the block at the current time.
+    fn accept(&self, scheduler: &mut EventScheduler, entity: Entity);
+
+    /// Returns the number of entities at the block, as seen by load-aware routing.
+    fn load(&self) -> usize {
+        0
+    }
+}
+
+/// Sends an entity to an optional downstream block; entities with nowhere to go are dropped.
+fn forward(output: &Option<Rc<dyn Block>>, scheduler: &mut EventScheduler, entity: Entity) {
+    if let Some(output) = output {
+        output.accept(scheduler, entity);
+    }
+}
+
+struct SourceState {
+    interarrival: DelayFn,
+    limit: Option<usize>,
+    generated: usize,
+    output: Option<Rc<dyn Block>>,
+}
+
+/// Creates entities with interarrival times drawn from a closure.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Queue, Server, Sink, Source};
+///
+/// let mut scheduler = EventScheduler::new();
+/// let arrivals = Source::new(|_| 1.0).with_limit(5);
+/// let line = Queue::new();
+/// let teller = Server::new(1, |_| 1.5);
+/// let exit = Sink::new();
+/// arrivals.connect(&line);
+/// line.connect(&teller);
+/// teller.connect(&exit);
+///
+/// arrivals.start(&mut scheduler);
+/// scheduler.run_until_max_time(100.0);
+/// assert_eq!(exit.count(), 5);
+/// // Customers arrive every 1.0 but take 1.5 to serve, so waits grow by 0.5 each time.
+/// assert_eq!(line.waiting_times().values(), &[0.0, 0.5, 1.0, 1.5, 2.0]);
+/// ```
+pub struct Source {
+    state: Rc<RefCell<SourceState>>,
+}
+
+impl Clone for Source {
+    fn clone(&self) -> Self {
+        Source { state: self.state.clone() }
+    }
+}
+
+impl Source {
+    /// Creates a source whose interarrival times are drawn from `interarrival`.
+    pub fn new<F>(interarrival: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler) -> f64 + 'static,
+    {
+        Source {
+            state: Rc::new(RefCell::new(SourceState {
+                interarrival: Box::new(interarrival),
+                limit: None,
+                generated: 0,
+                output: None,
+            })),
+        }
+    }
+
+    /// Stops after creating `limit` entities.
+    pub fn with_limit(self, limit: usize) -> Self {
+        self.state.borrow_mut().limit = Some(limit);
+        self
+    }
+
+    /// Sends created entities to `target`.
+    pub fn connect<B: Block + Clone + 'static>(&self, target: &B) {
+        self.state.borrow_mut().output = Some(Rc::new(target.clone()));
+    }
+
+    /// Returns the number of entities created so far.
+    pub fn generated(&self) -> usize {
+        self.state.borrow().generated
+    }
+
+    /// Schedules the first arrival, one interarrival time from now.
+    pub fn start(&self, scheduler: &mut EventScheduler) {
+        let delay = {
+            let mut state = self.state.borrow_mut();
+            if state.limit.is_some_and(|limit| state.generated >= limit) {
+                return;
+            }
+            (state.interarrival)(scheduler)
+        };
+        let source = self.clone();
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(delay)).with_action(move |s| {
+            source.arrive(s);
+            None
+        }));
+    }
+
+    fn arrive(&self, scheduler: &mut EventScheduler) {
+        let entity = scheduler.create_entity();
+        let output = {
+            let mut state = self.state.borrow_mut();
+            state.generated += 1;
+            state.output.clone()
+        };
+        forward(&output, scheduler, entity);
+        self.start(scheduler);
+    }
+}
+
+struct QueueState {
+    waiting: VecDeque<(Entity, f64)>,
+    capacity: Option<usize>,
+    servers: Vec<Server>,
+    dropped: usize,
+    length: Monitored,
+    waits: Tally,
+}
+
+/// A first-in, first-out waiting line in front of one or more servers.
+///
+/// An entity goes straight to a free server if there is one and otherwise waits. With a
+/// capacity, entities arriving to a full queue are lost.
+pub struct Queue {
+    state: Rc<RefCell<QueueState>>,
+}
+
+impl Clone for Queue {
+    fn clone(&self) -> Self {
+        Queue { state: self.state.clone() }
+    }
+}
+
+impl Queue {
+    /// Creates an unbounded queue.
+    pub fn new() -> Self {
+        Queue {
+            state: Rc::new(RefCell::new(QueueState {
+                waiting: VecDeque::new(),
+                capacity: None,
+                servers: Vec::new(),
+                dropped: 0,
+                length: Monitored::new(0.0),
+                waits: Tally::new(),
+            })),
+        }
+    }
+
+    /// Limits the number of waiting entities, not counting those in service.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        self.state.borrow_mut().capacity = Some(capacity);
+        self
+    }
+
+    /// Adds a server that takes entities from this queue.
+    ///
+    /// # Panics
+    /// Panics if the server is already attached to a queue.
+    pub fn connect(&self, server: &Server) {
+        let mut server_state = server.state.borrow_mut();
+        assert!(server_state.upstream.is_none(), "server is already attached to a queue");
+        server_state.upstream = Some(Rc::downgrade(&self.state));
+        self.state.borrow_mut().servers.push(server.clone());
+    }
+
+    /// Returns the number of waiting entities.
+    pub fn len(&self) -> usize {
+        self.state.borrow().waiting.len()
+    }
+
+    /// Returns `true` if no entities are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of entities lost because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.state.borrow().dropped
+    }
+
+    /// Returns the waiting time of every entity that has started service, in order.
+    pub fn waiting_times(&self) -> Tally {
+        self.state.borrow().waits.clone()
+    }
+
+    /// Returns the time-average number of waiting entities up to `now`.
+    pub fn average_length(&self, now: f64) -> f64 {
+        self.state.borrow().length.time_average(now)
+    }
+
+    /// Moves waiting entities to free servers.
+    fn dispatch(&self, scheduler: &mut EventScheduler) {
+        loop {
+            let (server, entity) = {
+                let mut state = self.state.borrow_mut();
+                let Some(server) = state.servers.iter().find(|server| server.is_available()).cloned() else {
+                    return;
+                };
+                let Some((entity, arrived)) = state.waiting.pop_front() else {
+                    return;
+                };
+                state.waits.record(scheduler.current_time - arrived);
+                let length = state.waiting.len() as f64;
+                state.length.record(scheduler.current_time, length);
+                (server, entity)
+            };
+            server.start(scheduler, entity);
+        }
+    }
+}
+
+impl Block for Queue {
+    fn accept(&self, scheduler: &mut EventScheduler, entity: Entity) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.waiting.push_back((entity, scheduler.current_time));
+            let length = state.waiting.len() as f64;
+            state.length.record(scheduler.current_time, length);
+        }
+        self.dispatch(scheduler);
+        let mut state = self.state.borrow_mut();
+        if state.capacity.is_some_and(|capacity| state.waiting.len() > capacity) {
+            state.waiting.pop_back();
+            state.dropped += 1;
+            let length = state.waiting.len() as f64;
+            state.length.record(scheduler.current_time, length);
+        }
+    }
+
+    /// Counts waiting entities and those in service at this queue's servers.
+    fn load(&self) -> usize {
+        let state = self.state.borrow();
+        state.waiting.len() + state.servers.iter().map(Server::busy).sum::<usize>()
+    }
+}
+
+impl Default for Queue {
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Classifies an entity for sequence-dependent setups.
+type ClassifyFn = Box<dyn FnMut(&mut EventScheduler, &Entity) -> usize>;
+
+struct Setup {
+    classify: ClassifyFn,
+    times: Vec<Vec<f64>>,
+}
+
+/// One of a server's parallel channels, and the job class it is currently set up for.
+#[derive(Debug, Clone, Copy, Default)]
+struct ServerChannel {
+    busy: bool,
+    class: Option<usize>,
+}
+
+struct ServerState {
+    channels: Vec<ServerChannel>,
+    service: DelayFn,
+    setup: Option<Setup>,
+    served: usize,
+    changeovers: usize,
+    setup_time: f64,
+    utilization: Monitored,
+    upstream: Option<Weak<RefCell<QueueState>>>,
+    output: Option<Rc<dyn Block>>,
+}
+
+impl ServerState {
+    fn busy(&self) -> usize {
+        self.channels.iter().filter(|channel| channel.busy).count()
+    }
+}
+
+/// Serves entities taken from its [`Queue`], up to `capacity` at a time.
+///
+/// Service times are drawn from a closure. Each entity records
+/// [`Milestone::StartedService`] when its service begins and is sent downstream when it ends.
+pub struct Server {
+    state: Rc<RefCell<ServerState>>,
+}
+
+impl Clone for Server {
+    fn clone(&self) -> Self {
+        Server { state: self.state.clone() }
+    }
+}
+
+impl Server {
+    /// Creates a server with `capacity` parallel channels and service times from `service`.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn new<F>(capacity: usize, service: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler) -> f64 + 'static,
+    {
+        assert!(capacity > 0, "server capacity must be positive");
+        Server {
+            state: Rc::new(RefCell::new(ServerState {
+                channels: vec![ServerChannel::default(); capacity],
+                service: Box::new(service),
+                setup: None,
+                served: 0,
+                changeovers: 0,
+                setup_time: 0.0,
+                utilization: Monitored::new(0.0),
+                upstream: None,
+                output: None,
+            })),
+        }
+    }
+
+    /// Adds sequence-dependent setup times.
+    ///
+    /// `classify` assigns each entity a job class when it is taken from the queue. When a
+    /// channel's next job has a different class from its previous one, a changeover taking
+    /// `times[previous][next]` runs before service starts; a channel's first job needs no
+    /// setup. A free channel already set up for the job's class is preferred, then one that
+    /// has not been used yet.
+    ///
+    /// # Panics
+    /// Panics if `times` is not square, or later if `classify` returns a class outside it.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, Queue, Server, Sink, Source};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let jobs = Source::new(|_| 1.0).with_limit(4);
+    /// let buffer = Queue::new();
+    /// // Jobs alternate between two colours; switching colour takes 0.5 to clean the nozzle.
+    /// let painter = Server::new(1, |_| 0.25).with_setup(|_, job| job.id.0 as usize % 2, vec![vec![0.0, 0.5], vec![0.5, 0.0]]);
+    /// let done = Sink::new();
+    /// jobs.connect(&buffer);
+    /// buffer.connect(&painter);
+    /// painter.connect(&done);
+    /// jobs.start(&mut scheduler);
+    /// scheduler.run_until_max_time(10.0);
+    /// assert_eq!(done.count(), 4);
+    /// assert_eq!(painter.changeovers(), 3);
+    /// assert_eq!(painter.setup_time(), 1.5);
+    /// ```
+    pub fn with_setup<C>(self, classify: C, times: Vec<Vec<f64>>) -> Self
+    where
+        C: FnMut(&mut EventScheduler, &Entity) -> usize + 'static,
+    {
+        assert!(times.iter().all(|row| row.len() == times.len()), "setup matrix must be square");
+        self.state.borrow_mut().setup = Some(Setup { classify: Box::new(classify), times });
+        self
+    }
+
+    /// Sends entities to `target` when their service ends.
+    pub fn connect<B: Block + Clone + 'static>(&self, target: &B) {
+        self.state.borrow_mut().output = Some(Rc::new(target.clone()));
+    }
+
+    /// Returns the number of channels in setup or service.
+    pub fn busy(&self) -> usize {
+        self.state.borrow().busy()
+    }
+
+    /// Returns `true` if a channel is free.
+    pub fn is_available(&self) -> bool {
+        self.state.borrow().channels.iter().any(|channel| !channel.busy)
+    }
+
+    /// Returns the number of entities whose service has ended.
+    pub fn served(&self) -> usize {
+        self.state.borrow().served
+    }
+
+    /// Returns the number of changeovers between job classes.
+    pub fn changeovers(&self) -> usize {
+        self.state.borrow().changeovers
+    }
+
+    /// Returns the total time spent on setups.
+    pub fn setup_time(&self) -> f64 {
+        self.state.borrow().setup_time
+    }
+
+    /// Returns the time-average fraction of channels busy, including setups, up to `now`.
+    pub fn utilization(&self, now: f64) -> f64 {
+        let state = self.state.borrow();
+        state.utilization.time_average(now) / state.channels.len() as f64
+    }
+
+    fn start(&self, scheduler: &mut EventScheduler, entity: Entity) {
+        let (channel, setup) = {
+            let mut state = self.state.borrow_mut();
+            let class = state.setup.as_mut().map(|setup| (setup.classify)(scheduler, &entity));
+            let channel = class
+                .and_then(|class| state.channels.iter().position(|c| !c.busy && c.class == Some(class)))
+                .or_else(|| state.channels.iter().position(|c| !c.busy && c.class.is_none()))
+                .or_else(|| state.channels.iter().position(|c| !c.busy))
+                .expect("server has no free channel");
+            let previous = state.channels[channel].class;
+            state.channels[channel] = ServerChannel { busy: true, class };
+            let busy = state.busy() as f64;
+            state.utilization.record(scheduler.current_time, busy);
+            let setup = match (&state.setup, previous, class) {
+                (Some(setup), Some(from), Some(to)) if from != to => Some(setup.times[from][to]),
+                _ => None,
+            };
+            if let Some(duration) = setup {
+                state.changeovers += 1;
+                state.setup_time += duration;
+            }
+            (channel, setup)
+        };
+        match setup {
+            Some(duration) => {
+                let server = self.clone();
+                scheduler.schedule(ScheduledAction::at(scheduler.time_after(duration)).with_action(move |s| {
+                    server.serve(s, channel, entity);
+                    None
+                }));
+            }
+            None => self.serve(scheduler, channel, entity),
+        }
+    }
+
+    fn serve(&self, scheduler: &mut EventScheduler, channel: usize, entity: Entity) {
+        scheduler.record_milestone(entity.id, Milestone::StartedService);
+        let duration = (self.state.borrow_mut().service)(scheduler);
+        let server = self.clone();
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(duration)).with_action(move |s| {
+            server.finish(s, channel, entity);
+            None
+        }));
+    }
+
+    fn finish(&self, scheduler: &mut EventScheduler, channel: usize, entity: Entity) {
+        let (output, upstream) = {
+            let mut state = self.state.borrow_mut();
+            state.channels[channel].busy = false;
+            state.served += 1;
+            let busy = state.busy() as f64;
+            state.utilization.record(scheduler.current_time, busy);
+            (state.output.clone(), state.upstream.as_ref().and_then(Weak::upgrade))
+        };
+        forward(&output, scheduler, entity);
+        if let Some(state) = upstream {
+            Queue { state }.dispatch(scheduler);
+        }
+    }
+}
+
+struct SinkState {
+    count: usize,
+    cycle_times: Tally,
+}
+
+/// Absorbs entities, recording [`Milestone::Departed`] and their time in the system.
+pub struct Sink {
+    state: Rc<RefCell<SinkState>>,
+}
+
+impl Clone for Sink {
+    fn clone(&self) -> Self {
+        Sink { state: self.state.clone() }
+    }
+}
+
+impl Sink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Sink { state: Rc::new(RefCell::new(SinkState { count: 0, cycle_times: Tally::new() })) }
+    }
+
+    /// Returns the number of entities absorbed.
+    pub fn count(&self) -> usize {
+        self.state.borrow().count
+    }
+
+    /// Returns the time from creation to arrival at the sink of every absorbed entity.
+    pub fn cycle_times(&self) -> Tally {
+        self.state.borrow().cycle_times.clone()
+    }
+}
+
+impl Block for Sink {
+    fn accept(&self, scheduler: &mut EventScheduler, entity: Entity) {
+        scheduler.record_milestone(entity.id, Milestone::Departed);
+        let mut state = self.state.borrow_mut();
+        state.count += 1;
+        state.cycle_times.record(scheduler.current_time - entity.created);
+    }
+}
+
+impl Default for Sink {
+    fn default() -> Self {
+        Sink::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tandem_network_with_finite_buffer() {
+        let mut scheduler = EventScheduler::new();
+        let source = Source::new(|_| 1.0).with_limit(6);
+        let first = Queue::new();
+        let fast = Server::new(2, |_| 1.0);
+        let second = Queue::new().with_capacity(1);
+        let slow = Server::new(1, |_| 3.0);
+        let sink = Sink::new();
+        source.connect(&first);
+        first.connect(&fast);
+        fast.connect(&second);
+        second.connect(&slow);
+        slow.connect(&sink);
+
+        source.start(&mut scheduler);
+        scheduler.run_until_max_time(100.0);
+        assert_eq!(source.generated(), 6);
+        assert_eq!(fast.served(), 6);
+        assert_eq!(second.dropped() + sink.count(), 6);
+        assert_eq!(sink.count(), 3);
+        assert!(first.waiting_times().values().iter().all(|&w| w == 0.0));
+        assert_eq!(scheduler.entities.cycle_times().values(), sink.cycle_times().values());
+    }
+
+    #[test]
+    fn test_setup_prefers_channel_set_up_for_class() {
+        let mut scheduler = EventScheduler::new();
+        let source = Source::new(|_| 1.0).with_limit(4);
+        let queue = Queue::new();
+        // Classes 0, 1, 0, 1 arriving every 1.0 and served in 0.5 keep two channels dedicated.
+        let server = Server::new(2, |_| 0.5).with_setup(|_, e| e.id.0 as usize % 2, vec![vec![0.0, 9.0], vec![9.0, 0.0]]);
+        let sink = Sink::new();
+        source.connect(&queue);
+        queue.connect(&server);
+        server.connect(&sink);
+        source.start(&mut scheduler);
+        scheduler.run_until_max_time(100.0);
+        assert_eq!(sink.count(), 4);
+        assert_eq!(server.changeovers(), 0);
+        assert_eq!(sink.cycle_times().values(), &[0.5; 4]);
+    }
+}
@@ -0,0 +1,171 @@
+//! # Breakdowns
+//!
+//! A [`Breakdown`] subjects a [`Resource`] to alternating periods of operation and repair, with
+//! durations drawn from closures. When the resource fails, its current users are interrupted
+//! and no new requests are granted until the repair completes. Jobs started with
+//! [`Resource::serve`] rejoin the queue and continue or restart once the resource is repaired.
+
+use crate::resource::Resource;
+use crate::stats::{Monitored, Tally};
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Draws the length of an operating or repair period.
+type DelayFn = Box<dyn FnMut(&mut EventScheduler) -> f64>;
+
+struct BreakdownState {
+    resource: Resource,
+    time_to_failure: DelayFn,
+    time_to_repair: DelayFn,
+    up: Monitored,
+    failed_at: f64,
+    repairs: Tally,
+}
+
+/// Failure and repair events for a resource.
+///
+/// The breakdown owns the resource's availability, so it should not be combined with a
+/// [`crate::Calendar`] driving [`Resource::set_available`] on the same resource. Clones report
+/// on the same failure and repair cycle; cloning does not start a second one.
+///
+/// # Example
+/// ```
+/// use desru::{Breakdown, EventScheduler, PreemptionMode, Resource};
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// let mut scheduler = EventScheduler::new();
+/// let lathe = Resource::new(1);
+/// let breakdown = Breakdown::new(&lathe, |_| 10.0, |_| 2.0);
+/// breakdown.start(&mut scheduler);
+///
+/// let finished = Rc::new(Cell::new(0.0));
+/// let record = finished.clone();
+/// lathe.serve(&mut scheduler, 0, 15.0, PreemptionMode::Resume, move |s| record.set(s.current_time));
+/// scheduler.run_until_max_time(20.0);
+///
+/// // The job runs from 0 to 10, waits out the repair, and finishes 5 after it.
+/// assert_eq!(finished.get(), 17.0);
+/// assert_eq!(breakdown.failures(), 1);
+/// assert_eq!(breakdown.availability(20.0), 0.9);
+/// ```
+pub struct Breakdown {
+    state: Rc<RefCell<BreakdownState>>,
+}
+
+impl Clone for Breakdown {
+    fn clone(&self) -> Self {
+        Breakdown { state: self.state.clone() }
+    }
+}
+
+impl Breakdown {
+    /// Creates a breakdown process for `resource`, with operating times drawn from
+    /// `time_to_failure` and repair times from `time_to_repair`.
+    pub fn new<F, R>(resource: &Resource, time_to_failure: F, time_to_repair: R) -> Self
+    where
+        F: FnMut(&mut EventScheduler) -> f64 + 'static,
+        R: FnMut(&mut EventScheduler) -> f64 + 'static,
+    {
+        Breakdown {
+            state: Rc::new(RefCell::new(BreakdownState {
+                resource: resource.clone(),
+                time_to_failure: Box::new(time_to_failure),
+                time_to_repair: Box::new(time_to_repair),
+                up: Monitored::new(1.0),
+                failed_at: 0.0,
+                repairs: Tally::new(),
+            })),
+        }
+    }
+
+    /// Starts the first operating period at the current time.
+    pub fn start(&self, scheduler: &mut EventScheduler) {
+        self.state.borrow_mut().up.record(scheduler.current_time, 1.0);
+        self.schedule_failure(scheduler);
+    }
+
+    /// Returns `true` unless the resource is under repair.
+    pub fn is_up(&self) -> bool {
+        self.state.borrow().up.value() == 1.0
+    }
+
+    /// Returns the number of failures so far.
+    pub fn failures(&self) -> usize {
+        self.state.borrow().up.trajectory().iter().filter(|&&(_, up)| up == 0.0).count()
+    }
+
+    /// Returns the duration of every completed repair, in order.
+    pub fn repair_times(&self) -> Tally {
+        self.state.borrow().repairs.clone()
+    }
+
+    /// Returns the fraction of time the resource was up, from [`Breakdown::start`] to `now`.
+    pub fn availability(&self, now: f64) -> f64 {
+        self.state.borrow().up.time_average(now)
+    }
+
+    fn schedule_failure(&self, scheduler: &mut EventScheduler) {
+        let delay = (self.state.borrow_mut().time_to_failure)(scheduler);
+        let breakdown = self.clone();
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(delay)).with_action(move |s| {
+            breakdown.fail(s);
+            None
+        }));
+    }
+
+    fn fail(&self, scheduler: &mut EventScheduler) {
+        let (resource, delay) = {
+            let mut state = self.state.borrow_mut();
+            state.up.record(scheduler.current_time, 0.0);
+            state.failed_at = scheduler.current_time;
+            let delay = (state.time_to_repair)(scheduler);
+            (state.resource.clone(), delay)
+        };
+        resource.set_available(scheduler, false);
+        resource.interrupt(scheduler);
+        let breakdown = self.clone();
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(delay)).with_action(move |s| {
+            breakdown.repair(s);
+            None
+        }));
+    }
+
+    fn repair(&self, scheduler: &mut EventScheduler) {
+        let resource = {
+            let mut state = self.state.borrow_mut();
+            state.up.record(scheduler.current_time, 1.0);
+            let downtime = scheduler.current_time - state.failed_at;
+            state.repairs.record(downtime);
+            state.resource.clone()
+        };
+        resource.set_available(scheduler, true);
+        self.schedule_failure(scheduler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PreemptionMode;
+
+    #[test]
+    fn test_repeated_failures_restart_jobs() {
+        let mut scheduler = EventScheduler::new();
+        let press = Resource::new(1);
+        let breakdown = Breakdown::new(&press, |_| 4.0, |_| 1.0);
+        breakdown.start(&mut scheduler);
+        let finished = Rc::new(RefCell::new(Vec::new()));
+        let record = finished.clone();
+        press.serve(&mut scheduler, 0, 3.0, PreemptionMode::Repeat, move |s| record.borrow_mut().push(s.current_time));
+        let record = finished.clone();
+        press.serve(&mut scheduler, 0, 3.0, PreemptionMode::Repeat, move |s| record.borrow_mut().push(s.current_time));
+        scheduler.run_until_max_time(12.0);
+        // The second job starts at 3, fails at 4, restarts at 5 and completes at 8.
+        assert_eq!(*finished.borrow(), vec![3.0, 8.0]);
+        assert_eq!(breakdown.failures(), 2);
+        assert_eq!(breakdown.repair_times().values(), &[1.0, 1.0]);
+        assert!((breakdown.availability(12.0) - 10.0 / 12.0).abs() < 1e-12);
+    }
+}
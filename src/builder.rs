@@ -0,0 +1,339 @@
+//! # Scheduler Builder
+//!
+//! [`EventSchedulerBuilder`] collects the configuration options of an [`EventScheduler`] so that
+//! `EventScheduler::new()` can stay a zero-argument constructor for the common case.
+
+use crate::{ActionRegistry, ActivityLog, Blackboard, CausalityGraph, Cells, Clock, ClockMode, EntityTracker, EventGraph, EventHook, EventQueue, EventScheduler, Profiler, QueueBackend, RngStreams, SimRng, TagMetrics, TimeComparison, TimeUnit, WorldState, DEFAULT_SEED};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Configures and builds an [`EventScheduler`].
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, QueueBackend};
+///
+/// let scheduler = EventScheduler::builder()
+///     .start_time(8.0)
+///     .queue_backend(QueueBackend::Calendar)
+///     .warm_up(10.0)
+///     .seed(2024)
+///     .build();
+/// assert_eq!(scheduler.current_time, 8.0);
+/// assert_eq!(scheduler.warm_up, 10.0);
+/// ```
+pub struct EventSchedulerBuilder {
+    start_time: f64,
+    queue_backend: QueueBackend,
+    label_index: bool,
+    logging: bool,
+    warm_up: f64,
+    max_events_per_time: Option<usize>,
+    wall_clock_budget: Option<Duration>,
+    context_pool: usize,
+    time_unit: Option<TimeUnit>,
+    clock_mode: ClockMode,
+    time_comparison: TimeComparison,
+    superdense: bool,
+    track_causality: bool,
+    profile: bool,
+    seed: u64,
+    antithetic: bool,
+    hooks: Vec<EventHook>,
+    world: WorldState,
+    clocks: HashMap<String, Clock>,
+    #[cfg(feature = "chrono")]
+    epoch: Option<crate::Epoch>,
+}
+
+impl EventSchedulerBuilder {
+    /// Creates a builder holding the default configuration.
+    pub fn new() -> Self {
+        EventSchedulerBuilder {
+            start_time: 0.0,
+            queue_backend: QueueBackend::default(),
+            label_index: false,
+            logging: true,
+            warm_up: 0.0,
+            max_events_per_time: None,
+            wall_clock_budget: None,
+            context_pool: crate::pool::DEFAULT_POOL_SIZE,
+            time_unit: None,
+            clock_mode: ClockMode::Float,
+            time_comparison: TimeComparison::Exact,
+            superdense: false,
+            track_causality: false,
+            profile: false,
+            seed: DEFAULT_SEED,
+            antithetic: false,
+            hooks: Vec::new(),
+            world: WorldState::new(),
+            clocks: HashMap::new(),
+            #[cfg(feature = "chrono")]
+            epoch: None,
+        }
+    }
+
+    /// Sets the initial simulation time. Defaults to `0.0`.
+    pub fn start_time(mut self, start_time: f64) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    /// Sets the priority-queue implementation used for pending events.
+    pub fn queue_backend(mut self, backend: QueueBackend) -> Self {
+        self.queue_backend = backend;
+        self
+    }
+
+    /// Keeps an index of pending events by label, which makes
+    /// [`EventScheduler::cancel_label`] and the label queries of [`EventQueue`] fast in large
+    /// queues. Defaults to off.
+    pub fn label_index(mut self, enabled: bool) -> Self {
+        self.label_index = enabled;
+        self
+    }
+
+    /// Enables or disables the event log. Defaults to enabled.
+    pub fn logging(mut self, enabled: bool) -> Self {
+        self.logging = enabled;
+        self
+    }
+
+    /// Records which event caused each scheduled event in the scheduler's
+    /// [`crate::CausalityGraph`]. Defaults to off.
+    pub fn track_causality(mut self, enabled: bool) -> Self {
+        self.track_causality = enabled;
+        self
+    }
+
+    /// Times the action of every executed event, per label, in the scheduler's
+    /// [`crate::Profiler`]. Defaults to off.
+    pub fn profile(mut self, enabled: bool) -> Self {
+        self.profile = enabled;
+        self
+    }
+
+    /// Sets a warm-up period: events executed before this time are not logged.
+    pub fn warm_up(mut self, warm_up: f64) -> Self {
+        self.warm_up = warm_up;
+        self
+    }
+
+    /// Limits how many events may run at a single timestamp, to catch zero-delay cascades.
+    /// Defaults to unlimited.
+    pub fn max_events_per_time(mut self, limit: usize) -> Self {
+        self.max_events_per_time = Some(limit);
+        self
+    }
+
+    /// Limits how much wall-clock time each run may take. A run that exceeds the budget
+    /// returns after the event in progress with [`crate::StopReason::WallClockBudget`].
+    /// Defaults to unlimited.
+    pub fn wall_clock_budget(mut self, budget: Duration) -> Self {
+        self.wall_clock_budget = Some(budget);
+        self
+    }
+
+    /// Sets how many emptied context maps the scheduler keeps for reuse, see
+    /// [`EventScheduler::pooled_context`]. Defaults to 64; zero disables pooling.
+    pub fn context_pool(mut self, capacity: usize) -> Self {
+        self.context_pool = capacity;
+        self
+    }
+
+    /// Sets what one unit of simulation time represents, so that delays can be given as
+    /// durations such as `5.minutes()`. Defaults to none.
+    pub fn time_unit(mut self, unit: TimeUnit) -> Self {
+        self.time_unit = Some(unit);
+        self
+    }
+
+    /// Sets how delays are added to the current time. Defaults to [`ClockMode::Float`]; use
+    /// [`ClockMode::Ticks`] for long horizons where floating-point drift matters.
+    pub fn clock_mode(mut self, mode: ClockMode) -> Self {
+        self.clock_mode = mode;
+        self
+    }
+
+    /// Sets the tolerance used to decide when a horizon is reached and which events are
+    /// simultaneous. Defaults to [`TimeComparison::Exact`].
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, TimeComparison};
+    ///
+    /// // A time meant to be the horizon, but accumulated with rounding error.
+    /// let time = 0.3 - 1e-12;
+    /// let mut exact = EventScheduler::new();
+    /// exact.timeout(time, None, None);
+    /// assert_eq!(exact.run_until_max_time(0.3).len(), 1);
+    ///
+    /// let mut tolerant = EventScheduler::builder().time_comparison(TimeComparison::Tolerance(1e-9)).build();
+    /// tolerant.timeout(time, None, None);
+    /// assert!(tolerant.run_until_max_time(0.3).is_empty());
+    /// ```
+    pub fn time_comparison(mut self, comparison: TimeComparison) -> Self {
+        self.time_comparison = comparison;
+        self
+    }
+
+    /// Orders events by superdense time, see [`crate::SuperdenseTime`]. Defaults to `false`.
+    pub fn superdense_time(mut self, enabled: bool) -> Self {
+        self.superdense = enabled;
+        self
+    }
+
+    /// Seeds the scheduler's random number generator. Defaults to [`DEFAULT_SEED`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Makes the scheduler's generator and streams antithetic, mirroring every draw of a
+    /// scheduler built with the same seed. Defaults to `false`.
+    pub fn antithetic(mut self, antithetic: bool) -> Self {
+        self.antithetic = antithetic;
+        self
+    }
+
+    /// Registers a hook called after every executed event with the event and its result.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::builder()
+    ///     .hook(Box::new(|s: &EventScheduler, event: &ScheduledAction, _result: &Option<String>| {
+    ///         println!("{} ran at {}", event.time, s.current_time);
+    ///     }))
+    ///     .build();
+    /// scheduler.timeout(1.0, None, None);
+    /// scheduler.run_until_max_time(5.0);
+    /// ```
+    pub fn hook(mut self, hook: EventHook) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Sets the model's shared state, available to actions through [`EventScheduler::state_mut`].
+    pub fn state<T: 'static>(mut self, state: T) -> Self {
+        self.world.set(state);
+        self
+    }
+
+    /// Registers a local clock, see [`EventScheduler::timeout_local`].
+    pub fn clock(mut self, name: impl Into<String>, clock: Clock) -> Self {
+        self.clocks.insert(name.into(), clock);
+        self
+    }
+
+    /// Anchors simulation time to calendar datetimes. Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn epoch(mut self, epoch: crate::Epoch) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Builds the configured `EventScheduler`.
+    pub fn build(self) -> EventScheduler {
+        let mut event_queue = EventQueue::new(self.queue_backend);
+        event_queue.set_label_index(self.label_index);
+        EventScheduler {
+            current_time: self.start_time,
+            event_queue,
+            event_log: Vec::new(),
+            logging: self.logging,
+            warm_up: self.warm_up,
+            max_events_per_time: self.max_events_per_time,
+            wall_clock_budget: self.wall_clock_budget,
+            time_unit: self.time_unit,
+            clock_mode: self.clock_mode,
+            time_comparison: self.time_comparison,
+            rng: if self.antithetic { SimRng::new(self.seed).antithetic() } else { SimRng::new(self.seed) },
+            streams: if self.antithetic { RngStreams::new(self.seed).antithetic() } else { RngStreams::new(self.seed) },
+            activities: ActivityLog::new(),
+            entities: EntityTracker::new(),
+            event_graph: EventGraph::new(),
+            causality: CausalityGraph::tracking(self.track_causality),
+            tag_metrics: TagMetrics::new(self.start_time),
+            profiler: Profiler::enabled(self.profile),
+            world: self.world,
+            blackboard: Blackboard::new(),
+            cells: Cells::new(),
+            actions: ActionRegistry::new(),
+            clocks: self.clocks,
+            #[cfg(feature = "chrono")]
+            epoch: self.epoch,
+            hooks: self.hooks,
+            current_label: None,
+            current_event: None,
+            events_at_time: 0,
+            debug: Default::default(),
+            continuous: Vec::new(),
+            counters: Default::default(),
+            context_pool: crate::pool::ContextPool::new(self.context_pool),
+            coalesced: HashMap::new(),
+            conditions: Default::default(),
+            pause_requested: false,
+            stop_reason: None,
+            invalid_event: None,
+            external_calls: Default::default(),
+            two_phase: Default::default(),
+            interventions: Vec::new(),
+            concurrent: HashMap::new(),
+            classes: Default::default(),
+            min_delays: Default::default(),
+            superdense: self.superdense,
+            microstep: 0,
+        }
+    }
+}
+
+impl Default for EventSchedulerBuilder {
+    fn default() -> Self {
+        EventSchedulerBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventScheduler, ScheduledAction};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_warm_up_and_logging() {
+        let mut scheduler = EventScheduler::builder().warm_up(3.0).build();
+        scheduler.timeout(1.0, None, None);
+        scheduler.timeout(4.0, None, None);
+        assert_eq!(scheduler.run_until_max_time(10.0).len(), 1);
+
+        let mut quiet = EventScheduler::builder().logging(false).build();
+        quiet.timeout(1.0, None, None);
+        assert!(quiet.run_until_max_time(10.0).is_empty());
+    }
+
+    #[test]
+    fn test_hooks_see_every_event() {
+        let seen = Rc::new(Cell::new(0));
+        let counter = seen.clone();
+        let mut scheduler = EventScheduler::builder()
+            .logging(false)
+            .hook(Box::new(move |_: &EventScheduler, _: &ScheduledAction, _: &Option<String>| {
+                counter.set(counter.get() + 1)
+            }))
+            .build();
+        scheduler.timeout(1.0, None, None);
+        scheduler.timeout(2.0, None, None);
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(seen.get(), 2);
+    }
+
+    #[test]
+    fn test_seed_controls_rng() {
+        let mut a = EventScheduler::builder().seed(9).build();
+        let mut b = EventScheduler::builder().seed(9).build();
+        assert_eq!(a.rng.next_u64(), b.rng.next_u64());
+    }
+}
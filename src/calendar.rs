@@ -0,0 +1,185 @@
+//! # Availability Calendars
+//!
+//! A [`Calendar`] describes recurring availability windows, such as opening hours or work
+//! shifts, in units of simulation time. It can be queried directly or used to drive on/off
+//! events through the scheduler.
+
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The shared callback notified of availability changes.
+type ChangeHandler = Rc<RefCell<dyn FnMut(&mut EventScheduler, bool)>>;
+
+/// A set of availability windows repeating with a fixed period.
+///
+/// Windows are half-open intervals `[start, end)` measured from the start of each period.
+///
+/// # Example
+/// ```
+/// use desru::Calendar;
+///
+/// // Open 9 to 17 on weekdays, with simulation time measured in hours.
+/// let hours = Calendar::weekdays(9.0, 17.0, 24.0);
+/// assert!(hours.is_open(10.0));           // Day 0, 10:00
+/// assert!(!hours.is_open(18.0));          // Day 0, 18:00
+/// assert!(!hours.is_open(5.0 * 24.0 + 10.0)); // Day 5 is a weekend day
+/// assert_eq!(hours.next_change(18.0), Some(24.0 + 9.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calendar {
+    period: f64,
+    windows: Vec<(f64, f64)>,
+}
+
+impl Calendar {
+    /// Creates a calendar with the given period and no availability windows.
+    ///
+    /// # Panics
+    /// Panics if `period` is not positive and finite.
+    pub fn new(period: f64) -> Self {
+        assert!(period.is_finite() && period > 0.0, "calendar period must be positive");
+        Calendar { period, windows: Vec::new() }
+    }
+
+    /// Creates a weekly calendar open from `open` to `close` on the first five days.
+    ///
+    /// # Parameters
+    /// - `open`: Opening time as an offset from the start of each day.
+    /// - `close`: Closing time as an offset from the start of each day.
+    /// - `day_length`: Length of one day in simulation time units (e.g. `24.0` for hours).
+    pub fn weekdays(open: f64, close: f64, day_length: f64) -> Self {
+        let mut calendar = Calendar::new(7.0 * day_length);
+        for day in 0..5 {
+            let start = day as f64 * day_length;
+            calendar = calendar.window(start + open, start + close);
+        }
+        calendar
+    }
+
+    /// Adds an availability window `[start, end)` within the period. Overlapping windows are merged.
+    ///
+    /// # Panics
+    /// Panics if the window is empty or does not lie within `[0, period]`.
+    pub fn window(mut self, start: f64, end: f64) -> Self {
+        assert!(0.0 <= start && start < end && end <= self.period, "window must lie within the period");
+        self.windows.push((start, end));
+        self.windows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = Vec::with_capacity(self.windows.len());
+        for (start, end) in self.windows.drain(..) {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.windows = merged;
+        self
+    }
+
+    /// Returns the length of one repetition.
+    pub fn period(&self) -> f64 {
+        self.period
+    }
+
+    /// Returns the merged availability windows.
+    pub fn windows(&self) -> &[(f64, f64)] {
+        &self.windows
+    }
+
+    fn always_open(&self) -> bool {
+        self.windows == [(0.0, self.period)]
+    }
+
+    /// Returns `true` if `time` falls within an availability window.
+    pub fn is_open(&self, time: f64) -> bool {
+        let phase = time.rem_euclid(self.period);
+        self.windows.iter().any(|&(start, end)| start <= phase && phase < end)
+    }
+
+    /// Returns the first time strictly after `time` at which availability changes, or `None`
+    /// if the calendar is always open or always closed.
+    pub fn next_change(&self, time: f64) -> Option<f64> {
+        if self.windows.is_empty() || self.always_open() {
+            return None;
+        }
+        let phase = time.rem_euclid(self.period);
+        let base = time - phase;
+        let boundaries = self.windows.iter().flat_map(|&(start, end)| [start, end]);
+        // A window ending at the period boundary continues into one starting at zero.
+        let wraps = self.windows[0].0 == 0.0 && self.windows[self.windows.len() - 1].1 == self.period;
+        boundaries
+            .filter(|&b| !(wraps && (b == 0.0 || b == self.period)))
+            .flat_map(|b| [b, b + self.period])
+            .filter(|&b| b > phase)
+            .min_by(f64::total_cmp)
+            .map(|b| base + b)
+    }
+
+    /// Drives availability changes through the scheduler.
+    ///
+    /// An event at the current time reports the initial state, followed by an event at every
+    /// subsequent opening or closing. Each event calls `on_change` with `true` when opening and
+    /// `false` when closing.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Calendar, EventScheduler};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let changes = Rc::new(RefCell::new(Vec::new()));
+    /// let record = changes.clone();
+    /// let mut scheduler = EventScheduler::new();
+    /// Calendar::new(24.0).window(9.0, 17.0).drive(&mut scheduler, move |s, open| {
+    ///     record.borrow_mut().push((s.current_time, open));
+    /// });
+    /// scheduler.run_until_max_time(30.0);
+    /// assert_eq!(*changes.borrow(), vec![(0.0, false), (9.0, true), (17.0, false)]);
+    /// ```
+    pub fn drive<F>(&self, scheduler: &mut EventScheduler, on_change: F)
+    where
+        F: FnMut(&mut EventScheduler, bool) + 'static,
+    {
+        let on_change: ChangeHandler = Rc::new(RefCell::new(on_change));
+        scheduler.schedule(change_event(self.clone(), scheduler.current_time, on_change));
+    }
+}
+
+/// Builds the event that reports the state at `time` and schedules the following change.
+fn change_event(calendar: Calendar, time: f64, on_change: ChangeHandler) -> ScheduledAction {
+    ScheduledAction::new(
+        time,
+        Some(Box::new(move |scheduler: &mut EventScheduler| {
+            let open = calendar.is_open(scheduler.current_time);
+            (on_change.borrow_mut())(scheduler, open);
+            if let Some(next) = calendar.next_change(scheduler.current_time) {
+                let event = change_event(calendar.clone(), next, on_change.clone());
+                scheduler.schedule(event);
+            }
+            None
+        })),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merging_and_wrapping() {
+        let calendar = Calendar::new(24.0).window(20.0, 24.0).window(0.0, 6.0).window(5.0, 7.0);
+        assert_eq!(calendar.windows(), &[(0.0, 7.0), (20.0, 24.0)]);
+        assert!(calendar.is_open(23.0) && calendar.is_open(24.5));
+        // The night shift runs 20:00 to 07:00 without a change at midnight.
+        assert_eq!(calendar.next_change(21.0), Some(31.0));
+        assert_eq!(calendar.next_change(8.0), Some(20.0));
+    }
+
+    #[test]
+    fn test_degenerate_calendars() {
+        assert_eq!(Calendar::new(10.0).next_change(3.0), None);
+        assert_eq!(Calendar::new(10.0).window(0.0, 10.0).next_change(3.0), None);
+        assert!(Calendar::new(10.0).window(0.0, 10.0).is_open(123.4));
+    }
+}
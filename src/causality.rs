@@ -0,0 +1,206 @@
+//! # Causality
+//!
+//! The [`EventGraph`](crate::EventGraph) relates event labels; a [`CausalityGraph`] relates
+//! individual events, to answer "why did this event happen?" in a complex model. With
+//! tracking on, every scheduled event is recorded with its cause: the event that was running
+//! when it was scheduled, or the event it names with [`ScheduledAction::with_cause`], such as
+//! the arrival a departure belongs to when the departure is scheduled by a server. Events
+//! scheduled during set-up have no cause.
+//!
+//! Tracking records every event of the run, so it is off unless enabled with
+//! [`crate::EventSchedulerBuilder::track_causality`] or [`CausalityGraph::set_tracking`]. The
+//! graph can be exported in Graphviz DOT format or as JSON.
+
+use crate::csv::{json_number, json_string};
+use crate::graph::dot_id;
+use crate::{EventId, ScheduledAction};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// One event in a [`CausalityGraph`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CausalNode {
+    pub id: EventId,
+    pub label: Option<String>,
+    /// The event that caused this one, if any.
+    pub cause: Option<EventId>,
+    /// The time at which the event was scheduled.
+    pub scheduled_at: f64,
+    /// The time for which the event was scheduled.
+    pub time: f64,
+    /// Whether the event has run.
+    pub executed: bool,
+}
+
+/// Which event caused which, for every event scheduled while tracking was on.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduledAction};
+///
+/// let mut scheduler = EventScheduler::builder().track_causality(true).build();
+/// let order = scheduler.schedule(ScheduledAction::at(1.0).with_label("order").with_action(|s| {
+///     s.schedule(ScheduledAction::at(s.current_time + 2.0).with_label("ship").with_action(|s| {
+///         s.schedule(ScheduledAction::at(s.current_time + 3.0).with_label("deliver"));
+///         None
+///     }));
+///     None
+/// }));
+/// scheduler.run_until_max_time(10.0);
+///
+/// let causality = &scheduler.causality;
+/// let deliver = causality.nodes().find(|node| node.label.as_deref() == Some("deliver")).unwrap().id;
+/// let why: Vec<_> = causality.chain(deliver).iter().filter_map(|node| node.label.as_deref()).collect();
+/// assert_eq!(why, ["deliver", "ship", "order"]);
+/// assert_eq!(causality.effects(order).len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CausalityGraph {
+    tracking: bool,
+    nodes: BTreeMap<u64, CausalNode>,
+}
+
+impl CausalityGraph {
+    /// Creates an empty graph that is not tracking.
+    pub fn new() -> Self {
+        CausalityGraph::default()
+    }
+
+    /// Returns `true` if scheduled events are being recorded.
+    pub fn is_tracking(&self) -> bool {
+        self.tracking
+    }
+
+    /// Turns recording of scheduled events on or off. Events already recorded are kept.
+    pub fn set_tracking(&mut self, tracking: bool) {
+        self.tracking = tracking;
+    }
+
+    /// Returns the recorded event with id `id`.
+    pub fn node(&self, id: EventId) -> Option<&CausalNode> {
+        self.nodes.get(&id.0)
+    }
+
+    /// Returns every recorded event, in the order they were scheduled.
+    pub fn nodes(&self) -> impl Iterator<Item = &CausalNode> {
+        self.nodes.values()
+    }
+
+    /// Returns the number of recorded events.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if no events have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the event `id` followed by its cause, the cause's cause, and so on back to an
+    /// event without a recorded cause.
+    pub fn chain(&self, id: EventId) -> Vec<&CausalNode> {
+        let mut chain = Vec::new();
+        let mut next = self.node(id);
+        while let Some(node) = next {
+            chain.push(node);
+            next = node.cause.and_then(|cause| self.node(cause));
+        }
+        chain
+    }
+
+    /// Returns the events that `id` caused, in the order they were scheduled.
+    pub fn effects(&self, id: EventId) -> Vec<&CausalNode> {
+        self.nodes.values().filter(|node| node.cause == Some(id)).collect()
+    }
+
+    /// Writes the graph in Graphviz DOT format, one node per event labeled with its label and
+    /// time. Events that never ran are dashed.
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "digraph causality {{")?;
+        for node in self.nodes.values() {
+            let name = format!("{} @ {}", node.label.as_deref().unwrap_or("#"), node.time);
+            let style = if node.executed { "" } else { ", style=dashed" };
+            writeln!(writer, "    {} [label={}{}];", node.id.0, dot_id(&name), style)?;
+        }
+        for node in self.nodes.values() {
+            if let Some(cause) = node.cause {
+                writeln!(writer, "    {} -> {};", cause.0, node.id.0)?;
+            }
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Writes the graph as a JSON array with one object per event.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "[")?;
+        for (i, node) in self.nodes.values().enumerate() {
+            let separator = if i + 1 < self.nodes.len() { "," } else { "" };
+            writeln!(
+                writer,
+                "  {{\"id\": {}, \"label\": {}, \"cause\": {}, \"scheduled_at\": {}, \"time\": {}, \"executed\": {}}}{}",
+                node.id.0,
+                node.label.as_deref().map_or("null".to_string(), json_string),
+                node.cause.map_or("null".to_string(), |cause| cause.0.to_string()),
+                json_number(Some(node.scheduled_at)),
+                json_number(Some(node.time)),
+                node.executed,
+                separator
+            )?;
+        }
+        writeln!(writer, "]")
+    }
+
+    /// Creates an empty graph, tracking if `tracking` is `true`.
+    pub(crate) fn tracking(tracking: bool) -> Self {
+        CausalityGraph { tracking, nodes: BTreeMap::new() }
+    }
+
+    /// Records a newly scheduled event.
+    pub(crate) fn record(&mut self, node: CausalNode) {
+        self.nodes.insert(node.id.0, node);
+    }
+
+    /// Records that an event has run.
+    pub(crate) fn mark_executed(&mut self, id: EventId) {
+        if let Some(node) = self.nodes.get_mut(&id.0) {
+            node.executed = true;
+        }
+    }
+}
+
+impl ScheduledAction {
+    /// Declares the event that caused this one, overriding the event running when it is
+    /// scheduled, for the scheduler's [`CausalityGraph`].
+    pub fn with_cause(mut self, cause: EventId) -> Self {
+        self.cause = Some(cause);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventScheduler, ScheduledAction};
+
+    #[test]
+    fn test_declared_causes_and_exports() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(0.5).with_label("untracked"));
+        scheduler.causality.set_tracking(true);
+        let arrival = scheduler.schedule(ScheduledAction::at(1.0).with_label("arrival"));
+        scheduler.schedule(ScheduledAction::at(2.0).with_label("departure").with_cause(arrival));
+        scheduler.run_until_max_time(1.5);
+
+        let mut dot = Vec::new();
+        scheduler.causality.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("2 [label=\"arrival @ 1\"];"));
+        assert!(dot.contains("3 [label=\"departure @ 2\", style=dashed];"));
+        assert!(dot.contains("2 -> 3;"));
+
+        let mut json = Vec::new();
+        scheduler.causality.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().count(), 4);
+        assert!(json.contains("\"id\": 3, \"label\": \"departure\", \"cause\": 2"));
+    }
+}
@@ -0,0 +1,179 @@
+//! # Channels
+//!
+//! A [`Channel`] is a mailbox between parts of a model. Sent messages arrive at the current
+//! time or after a delay; a receiver registered with [`Channel::receive`] is suspended until a
+//! message is available and then called with it, in the order receivers were registered.
+
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// The continuation of a suspended receiver.
+type Receiver<T> = Box<dyn FnOnce(&mut EventScheduler, T)>;
+
+/// Identifies a pending receive, so that it can be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ReceiveId(pub u64);
+
+struct ChannelState<T> {
+    messages: VecDeque<T>,
+    receivers: VecDeque<(ReceiveId, Receiver<T>)>,
+    next_id: u64,
+}
+
+/// A simulated mailbox carrying messages of type `T`.
+///
+/// Clones refer to the same mailbox, so senders and receivers each keep a clone.
+///
+/// # Example
+/// ```
+/// use desru::{Channel, EventScheduler};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let received = Rc::new(RefCell::new(Vec::new()));
+/// let mut scheduler = EventScheduler::new();
+/// let link: Channel<&str> = Channel::new();
+///
+/// // The receiver waits from time 0 until the message arrives at time 3.
+/// let record = received.clone();
+/// link.receive(&mut scheduler, move |s, message| record.borrow_mut().push((s.current_time, message)));
+/// link.send_after(&mut scheduler, "hello", 3.0);
+/// scheduler.run_until_max_time(10.0);
+/// assert_eq!(*received.borrow(), vec![(3.0, "hello")]);
+/// ```
+pub struct Channel<T> {
+    state: Rc<RefCell<ChannelState<T>>>,
+}
+
+impl<T> Clone for Channel<T> {
+    fn clone(&self) -> Self {
+        Channel { state: self.state.clone() }
+    }
+}
+
+impl<T: 'static> Channel<T> {
+    /// Creates an empty channel.
+    pub fn new() -> Self {
+        Channel { state: Rc::new(RefCell::new(ChannelState { messages: VecDeque::new(), receivers: VecDeque::new(), next_id: 0 })) }
+    }
+
+    /// Sends a message that arrives at the current time.
+    pub fn send(&self, scheduler: &mut EventScheduler, message: T) {
+        self.send_after(scheduler, message, 0.0);
+    }
+
+    /// Sends a message that arrives after `delay`.
+    pub fn send_after(&self, scheduler: &mut EventScheduler, message: T, delay: f64) {
+        let channel = self.clone();
+        let mut message = Some(message);
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(delay)).with_action(move |s| {
+            if let Some(message) = message.take() {
+                channel.arrive(s, message);
+            }
+            None
+        }));
+    }
+
+    /// Waits for the next message. If one is already waiting, `on_message` is called with it
+    /// by an event at the current time; otherwise it is called when one arrives.
+    pub fn receive<F>(&self, scheduler: &mut EventScheduler, on_message: F) -> ReceiveId
+    where
+        F: FnOnce(&mut EventScheduler, T) + 'static,
+    {
+        let mut state = self.state.borrow_mut();
+        let id = ReceiveId(state.next_id);
+        state.next_id += 1;
+        match state.messages.pop_front() {
+            Some(message) => {
+                let mut delivery = Some((on_message, message));
+                scheduler.schedule(ScheduledAction::at(scheduler.current_time).with_action(move |s| {
+                    if let Some((on_message, message)) = delivery.take() {
+                        on_message(s, message);
+                    }
+                    None
+                }));
+            }
+            None => state.receivers.push_back((id, Box::new(on_message))),
+        }
+        id
+    }
+
+    /// Cancels a receive that is still waiting for a message.
+    ///
+    /// # Returns
+    /// `true` if the receive was waiting.
+    pub fn cancel_receive(&self, id: ReceiveId) -> bool {
+        let mut state = self.state.borrow_mut();
+        let before = state.receivers.len();
+        state.receivers.retain(|(waiting, _)| *waiting != id);
+        state.receivers.len() != before
+    }
+
+    /// Takes the oldest waiting message without suspending.
+    pub fn try_receive(&self) -> Option<T> {
+        self.state.borrow_mut().messages.pop_front()
+    }
+
+    /// Returns the number of messages that have arrived but not been received.
+    pub fn len(&self) -> usize {
+        self.state.borrow().messages.len()
+    }
+
+    /// Returns `true` if no messages are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of receivers waiting for a message.
+    pub fn waiting_receivers(&self) -> usize {
+        self.state.borrow().receivers.len()
+    }
+
+    fn arrive(&self, scheduler: &mut EventScheduler, message: T) {
+        let receiver = {
+            let mut state = self.state.borrow_mut();
+            match state.receivers.pop_front() {
+                Some((_, receiver)) => receiver,
+                None => {
+                    state.messages.push_back(message);
+                    return;
+                }
+            }
+        };
+        receiver(scheduler, message);
+    }
+}
+
+impl<T: 'static> Default for Channel<T> {
+    fn default() -> Self {
+        Channel::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffered_messages_and_cancelled_receivers() {
+        let mut scheduler = EventScheduler::new();
+        let channel = Channel::new();
+        let got = Rc::new(RefCell::new(Vec::new()));
+
+        let cancelled = channel.receive(&mut scheduler, |_, _: u32| panic!("cancelled receiver was called"));
+        assert!(channel.cancel_receive(cancelled));
+        channel.send(&mut scheduler, 1);
+        channel.send_after(&mut scheduler, 2, 1.0);
+        scheduler.run_until_max_time(5.0);
+        assert_eq!(channel.len(), 2);
+
+        let record = got.clone();
+        channel.receive(&mut scheduler, move |s, m| record.borrow_mut().push((s.current_time, m)));
+        assert_eq!(channel.try_receive(), Some(2));
+        scheduler.run_until_max_time(5.0);
+        assert_eq!(*got.borrow(), vec![(1.0, 1)]);
+        assert!(channel.is_empty());
+    }
+}
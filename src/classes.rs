@@ -0,0 +1,191 @@
+//! # Event Classes
+//!
+//! When several events are due at the same instant, the scheduler normally runs them by
+//! priority and then in the order they were scheduled. For research on scheduling policies,
+//! events can instead be put in named classes with [`EventScheduler::schedule_in_class`], and
+//! a [`ClassPolicy`] set with [`EventScheduler::set_class_policy`] decides which class is
+//! served next among events that are otherwise tied, with the same time and priority.
+//!
+//! Events in the same class are served in the order they were scheduled. Events without a
+//! class, or in a class the policy does not name, are served after the named classes. Time
+//! order always comes first: a policy never runs an event before an earlier one. The batches
+//! of [`EventScheduler::pop_simultaneous`], and so of timestep, two-phase and concurrent runs,
+//! are put in the same order.
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::collections::{BTreeMap, HashMap};
+
+/// How the scheduler chooses between tied events of different classes, see
+/// [`EventScheduler::set_class_policy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassPolicy {
+    /// Serves the classes in the order listed: an event of a class runs only when no tied
+    /// event of an earlier class is waiting.
+    StrictPriority(Vec<String>),
+    /// Serves the classes in turn, each up to its weight of consecutive tied events before
+    /// the next class with waiting events has its turn. A class with weight zero is served
+    /// only when no other named class is waiting.
+    WeightedRoundRobin(Vec<(String, u32)>),
+}
+
+/// The classes of pending events, the policy, and how many events each class has had served.
+#[derive(Debug, Default)]
+pub(crate) struct EventClasses {
+    classes: HashMap<u64, String>,
+    policy: Option<ClassPolicy>,
+    turn: usize,
+    credit: u32,
+    served: BTreeMap<String, u64>,
+}
+
+impl EventClasses {
+    /// Forgets the class of a cancelled event.
+    pub(crate) fn remove(&mut self, id: EventId) {
+        self.classes.remove(&id.0);
+    }
+
+    /// Returns the position in `tied` of the event to run next, where `tied` lists the ids of
+    /// the tied events in the order they would otherwise run.
+    fn select(&mut self, tied: &[u64]) -> usize {
+        let tied: Vec<Option<&str>> = tied.iter().map(|seq| self.classes.get(seq).map(String::as_str)).collect();
+        match &self.policy {
+            None => 0,
+            Some(ClassPolicy::StrictPriority(order)) => {
+                let rank = |class: Option<&str>| class.and_then(|class| order.iter().position(|named| named == class)).unwrap_or(order.len());
+                (0..tied.len()).min_by_key(|&i| rank(tied[i])).unwrap_or(0)
+            }
+            Some(ClassPolicy::WeightedRoundRobin(weights)) => {
+                let waiting = |class: &str| tied.iter().position(|tied| *tied == Some(class));
+                for _ in 0..=weights.len() {
+                    let (class, weight) = &weights[self.turn];
+                    if let Some(i) = waiting(class).filter(|_| self.credit < *weight) {
+                        self.credit += 1;
+                        return i;
+                    }
+                    self.turn = (self.turn + 1) % weights.len();
+                    self.credit = 0;
+                }
+                weights.iter().find_map(|(class, _)| waiting(class)).unwrap_or(0)
+            }
+        }
+    }
+}
+
+impl EventScheduler {
+    /// Schedules an event in the named class.
+    ///
+    /// # Returns
+    /// The id of the event, as for [`EventScheduler::schedule`].
+    pub fn schedule_in_class(&mut self, class: impl Into<String>, event: ScheduledAction) -> EventId {
+        let id = self.schedule(event);
+        if id != EventId(0) {
+            self.classes.classes.insert(id.0, class.into());
+        }
+        id
+    }
+
+    /// Sets how tied events of different classes are served, replacing the previous policy.
+    ///
+    /// # Panics
+    /// Panics if a weighted round robin names no classes.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{ClassPolicy, EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.set_class_policy(ClassPolicy::WeightedRoundRobin(vec![("gold".to_string(), 2), ("bronze".to_string(), 1)]));
+    /// for _ in 0..3 {
+    ///     scheduler.schedule_in_class("bronze", ScheduledAction::at(1.0).with_label("bronze"));
+    ///     scheduler.schedule_in_class("gold", ScheduledAction::at(1.0).with_label("gold"));
+    /// }
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_label("unclassed"));
+    ///
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// let order: Vec<_> = log.iter().map(|record| record.label.as_deref().unwrap()).collect();
+    /// assert_eq!(order, ["gold", "gold", "bronze", "gold", "bronze", "bronze", "unclassed"]);
+    /// assert_eq!(scheduler.served_by_class(), [("bronze".to_string(), 3), ("gold".to_string(), 3)]);
+    /// ```
+    pub fn set_class_policy(&mut self, policy: ClassPolicy) {
+        if let ClassPolicy::WeightedRoundRobin(weights) = &policy {
+            assert!(!weights.is_empty(), "a weighted round robin needs at least one class");
+        }
+        self.classes.policy = Some(policy);
+        self.classes.turn = 0;
+        self.classes.credit = 0;
+    }
+
+    /// Returns the class of a pending event, if it has one.
+    pub fn class_of(&self, id: EventId) -> Option<&str> {
+        self.classes.classes.get(&id.0).map(String::as_str)
+    }
+
+    /// Returns how many events of each class have been taken off the queue to run, sorted by
+    /// class.
+    pub fn served_by_class(&self) -> Vec<(String, u64)> {
+        self.classes.served.iter().map(|(class, count)| (class.clone(), *count)).collect()
+    }
+
+    /// Takes the next event to run off the queue, letting the class policy choose among the
+    /// events tied at the front. The tied events are staged in the queue, so that the rest of
+    /// the batch stays off the backend until it runs.
+    pub(crate) fn pop_next_event(&mut self) -> Option<ScheduledAction> {
+        let event = if self.classes.policy.is_some() {
+            let tied: Vec<u64> = self.event_queue.stage_ties().iter().map(|event| event.seq).collect();
+            let chosen = if tied.len() > 1 { self.classes.select(&tied) } else { 0 };
+            self.event_queue.take_staged(chosen)?
+        } else {
+            self.event_queue.pop()?
+        };
+        if let Some(class) = self.classes.classes.remove(&event.seq) {
+            *self.classes.served.entry(class).or_insert(0) += 1;
+        }
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict_priority_only_reorders_ties() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.set_class_policy(ClassPolicy::StrictPriority(vec!["urgent".to_string(), "routine".to_string()]));
+        scheduler.schedule_in_class("routine", ScheduledAction::at(1.0).with_label("routine 1"));
+        scheduler.schedule(ScheduledAction::at(1.0).with_label("unclassed"));
+        let cancelled = scheduler.schedule_in_class("urgent", ScheduledAction::at(1.0).with_label("cancelled"));
+        scheduler.schedule_in_class("urgent", ScheduledAction::at(1.0).with_label("urgent"));
+        scheduler.schedule_in_class("urgent", ScheduledAction::at(1.0).with_priority(1).with_label("urgent, low priority"));
+        scheduler.schedule_in_class("routine", ScheduledAction::at(0.5).with_label("earlier"));
+        assert_eq!(scheduler.class_of(cancelled), Some("urgent"));
+        scheduler.cancel(cancelled);
+        assert_eq!(scheduler.class_of(cancelled), None);
+
+        let log = scheduler.run_until_max_time(10.0);
+        let order: Vec<_> = log.iter().map(|record| record.label.as_deref().unwrap()).collect();
+        assert_eq!(order, ["earlier", "urgent", "routine 1", "unclassed", "urgent, low priority"]);
+    }
+
+    #[test]
+    fn test_staged_ties_see_events_scheduled_while_they_wait() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.set_class_policy(ClassPolicy::StrictPriority(vec!["urgent".to_string(), "routine".to_string()]));
+        scheduler.schedule_in_class("routine", ScheduledAction::at(1.0).with_label("routine 0").with_action(|s| {
+            s.schedule_in_class("urgent", ScheduledAction::at(1.0).with_label("urgent"));
+            s.schedule(ScheduledAction::at(1.0).with_priority(-1).with_label("ahead"));
+            Some(format!("cancelled {}", s.cancel_label("routine 999")))
+        }));
+        for i in 1..1000 {
+            scheduler.schedule_in_class("routine", ScheduledAction::at(1.0).with_label(format!("routine {}", i)));
+        }
+
+        let log = scheduler.run_until_max_time(10.0);
+        assert_eq!(log[0].result.as_deref(), Some("cancelled 1"));
+        let order: Vec<_> = log.iter().map(|record| record.label.clone().unwrap()).collect();
+        let expected: Vec<_> = ["routine 0", "ahead", "urgent"].into_iter().map(String::from).chain((1..999).map(|i| format!("routine {}", i))).collect();
+        assert_eq!(order, expected);
+        assert_eq!(scheduler.served_by_class(), [("routine".to_string(), 999), ("urgent".to_string(), 1)]);
+        assert!(scheduler.event_queue.is_empty());
+    }
+}
@@ -0,0 +1,363 @@
+//! # Command-Line Harness
+//!
+//! [`Cli`] turns a [`SimModel`] into a command-line program, so that a model's `main` is one
+//! line instead of hand-written argument parsing:
+//!
+//! ```no_run
+//! # use desru::{EventScheduler, SimModel};
+//! # struct Clinic;
+//! # impl SimModel for Clinic {
+//! #     type Output = Vec<(String, f64)>;
+//! #     fn init(&mut self, _: &mut EventScheduler) {}
+//! #     fn finalize(&mut self, _: &mut EventScheduler) -> Self::Output { Vec::new() }
+//! # }
+//! use desru::Cli;
+//! use std::process::ExitCode;
+//!
+//! fn main() -> ExitCode {
+//!     Cli::new("clinic").horizon(480.0).run(|_params| Clinic)
+//! }
+//! ```
+//!
+//! The program accepts `--horizon`, `--seed`, `--replications`, `--output`, `--format`
+//! (`table`, `csv`, or `json`), and `--config`, which reads scenarios from a
+//! [`ScenarioConfig`] file; flags given on the command line override the file.
+
+use crate::{ConfigError, Experiment, ExperimentResults, ScenarioConfig, SimConfig, SimModel};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// How a [`Cli`] writes its results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// The mean and standard deviation of every metric in every scenario.
+    #[default]
+    Table,
+    /// One row per scenario, replication, and metric, see [`ExperimentResults::write_csv`].
+    Csv,
+    /// The same rows as JSON, see [`ExperimentResults::write_json`].
+    Json,
+}
+
+/// The options given on a command line, see [`Cli::parse`]. Options not given are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliOptions {
+    pub horizon: Option<f64>,
+    pub seed: Option<u64>,
+    pub replications: Option<usize>,
+    pub output: Option<PathBuf>,
+    pub format: OutputFormat,
+    pub config: Option<PathBuf>,
+}
+
+/// Why a [`Cli`] did not produce results.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CliError {
+    /// `--help` was given; holds the usage text.
+    Help(String),
+    /// The arguments were invalid.
+    Usage(String),
+    /// The `--config` file could not be read.
+    Config(ConfigError),
+    /// The results could not be written.
+    Io(io::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help(usage) => f.write_str(usage),
+            CliError::Usage(message) => f.write_str(message),
+            CliError::Config(error) => write!(f, "{}", error),
+            CliError::Io(error) => write!(f, "cannot write results: {}", error),
+        }
+    }
+}
+
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CliError::Config(error) => Some(error),
+            CliError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A command-line program that runs replications of a model.
+///
+/// # Example
+/// ```
+/// use desru::{Cli, EventScheduler, SimModel};
+///
+/// struct Coin;
+///
+/// impl SimModel for Coin {
+///     type Output = Vec<(String, f64)>;
+///
+///     fn init(&mut self, _: &mut EventScheduler) {}
+///
+///     fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+///         vec![("heads".to_string(), (scheduler.rng.next_f64() < 0.5) as u8 as f64)]
+///     }
+/// }
+///
+/// let cli = Cli::new("coin").about("Flips a coin.");
+/// let mut out = Vec::new();
+/// let results = cli.execute(["--replications", "20", "--format", "csv"], |_| Coin, &mut out).unwrap();
+/// assert_eq!(results.rows().len(), 20);
+/// assert!(String::from_utf8(out).unwrap().starts_with("scenario,replication,seed,metric,value"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cli {
+    name: String,
+    about: Option<String>,
+    horizon: f64,
+    seed: u64,
+    replications: usize,
+}
+
+impl Cli {
+    /// Creates a harness for the program `name` with a horizon of `100.0`, the default seed,
+    /// and one replication.
+    pub fn new(name: impl Into<String>) -> Self {
+        Cli { name: name.into(), about: None, horizon: 100.0, seed: crate::DEFAULT_SEED, replications: 1 }
+    }
+
+    /// Sets the description shown by `--help`.
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.about = Some(about.into());
+        self
+    }
+
+    /// Sets the horizon used when neither `--horizon` nor a config file gives one.
+    pub fn horizon(mut self, horizon: f64) -> Self {
+        self.horizon = horizon;
+        self
+    }
+
+    /// Sets the seed used when neither `--seed` nor a config file gives one.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the replication count used when neither `--replications` nor a config file gives one.
+    pub fn replications(mut self, replications: usize) -> Self {
+        self.replications = replications;
+        self
+    }
+
+    /// Returns the `--help` text.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("Usage: {} [OPTIONS]\n", self.name);
+        if let Some(about) = &self.about {
+            usage.push_str(&format!("\n{}\n", about));
+        }
+        usage.push_str(&format!(
+            "\nOptions:\n  \
+             --horizon <TIME>        End each run at TIME [default: {}]\n  \
+             --seed <SEED>           Base seed for the replications [default: {}]\n  \
+             --replications <N>      Replications per scenario [default: {}]\n  \
+             --config <PATH>         Read scenarios from a TOML or YAML file\n  \
+             --output <PATH>         Write results to PATH instead of standard output\n  \
+             --format <FORMAT>       table, csv, or json [default: table]\n  \
+             --help                  Print this help\n",
+            self.horizon, self.seed, self.replications
+        ));
+        usage
+    }
+
+    /// Parses command-line arguments, not including the program name.
+    ///
+    /// # Errors
+    /// Returns [`CliError::Help`] for `--help` and [`CliError::Usage`] for unknown options and
+    /// invalid values.
+    pub fn parse<I, S>(&self, args: I) -> Result<CliOptions, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let arg = arg.as_ref();
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg, None),
+            };
+            if flag == "--help" || flag == "-h" {
+                return Err(CliError::Help(self.usage()));
+            }
+            let value = match inline.or_else(|| args.next().map(|v| v.as_ref().to_string())) {
+                Some(value) => value,
+                None => return Err(self.usage_error(format!("{} needs a value", flag))),
+            };
+            match flag {
+                "--horizon" => options.horizon = Some(self.parse_value(flag, &value)?),
+                "--seed" => options.seed = Some(self.parse_value(flag, &value)?),
+                "--replications" => options.replications = Some(self.parse_value(flag, &value)?),
+                "--output" => options.output = Some(PathBuf::from(value)),
+                "--config" => options.config = Some(PathBuf::from(value)),
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "table" => OutputFormat::Table,
+                        "csv" => OutputFormat::Csv,
+                        "json" => OutputFormat::Json,
+                        _ => return Err(self.usage_error(format!("unknown format `{}`", value))),
+                    }
+                }
+                _ => return Err(self.usage_error(format!("unknown option `{}`", flag))),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Parses `args`, runs the experiment they describe, and writes the results to the
+    /// `--output` file or to `out`.
+    ///
+    /// # Parameters
+    /// - `make_model`: Builds a fresh model for one run from the scenario's parameters, which
+    ///   are empty unless a config file defines scenarios.
+    ///
+    /// # Errors
+    /// As for [`Cli::parse`], and [`CliError::Config`] or [`CliError::Io`] if the config file
+    /// cannot be read or the results cannot be written.
+    pub fn execute<I, S, M, F, K, W>(&self, args: I, make_model: F, out: W) -> Result<ExperimentResults, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        F: Fn(&BTreeMap<String, f64>) -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+        W: Write,
+    {
+        let options = self.parse(args)?;
+        let mut scenarios = ScenarioConfig {
+            horizon: self.horizon,
+            seed: self.seed,
+            replications: self.replications,
+            warm_up: 0.0,
+            scenarios: vec![("default".to_string(), BTreeMap::new())],
+        };
+        if let Some(path) = &options.config {
+            scenarios = ScenarioConfig::load(path).map_err(CliError::Config)?;
+        }
+        let mut config = SimConfig::new(options.horizon.unwrap_or(scenarios.horizon)).with_seed(options.seed.unwrap_or(scenarios.seed));
+        config.warm_up = scenarios.warm_up;
+        let mut experiment = Experiment::new(config).replications(options.replications.unwrap_or(scenarios.replications));
+        for (name, parameters) in scenarios.scenarios {
+            experiment = experiment.scenario(name, parameters);
+        }
+        let results = experiment.run(make_model);
+        match &options.output {
+            Some(path) => {
+                let file = std::fs::File::create(path).map_err(CliError::Io)?;
+                write_results(&results, options.format, io::BufWriter::new(file))
+            }
+            None => write_results(&results, options.format, out),
+        }
+        .map_err(CliError::Io)?;
+        Ok(results)
+    }
+
+    /// Runs the program with the process's arguments, writing results to standard output and
+    /// errors to standard error.
+    ///
+    /// # Returns
+    /// [`ExitCode::SUCCESS`] on success or after `--help`, and exit code `2` otherwise.
+    pub fn run<M, F, K>(&self, make_model: F) -> ExitCode
+    where
+        F: Fn(&BTreeMap<String, f64>) -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        match self.execute(std::env::args().skip(1), make_model, io::stdout().lock()) {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(CliError::Help(usage)) => {
+                print!("{}", usage);
+                ExitCode::SUCCESS
+            }
+            Err(error) => {
+                eprintln!("{}: {}", self.name, error);
+                ExitCode::from(2)
+            }
+        }
+    }
+
+    fn parse_value<T: std::str::FromStr>(&self, flag: &str, value: &str) -> Result<T, CliError> {
+        value.parse().map_err(|_| self.usage_error(format!("invalid value `{}` for {}", value, flag)))
+    }
+
+    fn usage_error(&self, message: String) -> CliError {
+        CliError::Usage(format!("{}\n\n{}", message, self.usage()))
+    }
+}
+
+fn write_results<W: Write>(results: &ExperimentResults, format: OutputFormat, mut out: W) -> io::Result<()> {
+    match format {
+        OutputFormat::Csv => results.write_csv(out),
+        OutputFormat::Json => results.write_json(out),
+        OutputFormat::Table => {
+            writeln!(out, "{:<20} {:<20} {:>12} {:>12} {:>6}", "scenario", "metric", "mean", "std_dev", "n")?;
+            for scenario in results.scenarios() {
+                for metric in results.metrics() {
+                    let tally = results.tally(scenario, metric);
+                    if tally.count() > 0 {
+                        writeln!(out, "{:<20} {:<20} {:>12.4} {:>12.4} {:>6}", scenario, metric, tally.mean(), tally.std_dev(), tally.count())?;
+                    }
+                }
+            }
+            out.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventScheduler;
+
+    struct Elapsed;
+
+    impl SimModel for Elapsed {
+        type Output = [(&'static str, f64); 1];
+
+        fn init(&mut self, scheduler: &mut EventScheduler) {
+            scheduler.timeout(1.0, None, None);
+        }
+
+        fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+            [("rng", scheduler.rng.next_f64())]
+        }
+    }
+
+    #[test]
+    fn test_parse_flags_and_errors() {
+        let cli = Cli::new("model");
+        let options = cli.parse(["--horizon=50", "--seed", "3", "--format", "json"]).unwrap();
+        assert_eq!(options.horizon, Some(50.0));
+        assert_eq!(options.seed, Some(3));
+        assert_eq!(options.format, OutputFormat::Json);
+        assert!(matches!(cli.parse(["--help"]), Err(CliError::Help(_))));
+        assert!(matches!(cli.parse(["--seed"]), Err(CliError::Usage(m)) if m.starts_with("--seed needs a value")));
+        assert!(matches!(cli.parse(["--speed", "1"]), Err(CliError::Usage(m)) if m.starts_with("unknown option `--speed`")));
+    }
+
+    #[test]
+    fn test_table_output_summarises_replications() {
+        let mut out = Vec::new();
+        let results = Cli::new("model").replications(4).execute(["--seed", "11"], |_| Elapsed, &mut out).unwrap();
+        assert_eq!(results.rows().len(), 4);
+        let table = String::from_utf8(out).unwrap();
+        assert_eq!(table.lines().count(), 2);
+        assert!(table.lines().nth(1).unwrap().starts_with("default"));
+    }
+}
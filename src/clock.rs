@@ -0,0 +1,131 @@
+//! # Local Clocks
+//!
+//! Distributed systems rarely agree on the time. A [`Clock`] describes a subsystem's local
+//! clock relative to the scheduler's master clock: it may be offset and may run fast or slow.
+//! Named clocks are registered on the scheduler, and delays measured on a local clock, such as
+//! a node's timeout, are converted to master time when they are scheduled.
+
+use crate::{Action, Context, EventId, EventScheduler};
+
+/// A local clock reading `offset + (1 + drift) * t` at master time `t`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Clock {
+    /// The local reading at master time zero.
+    pub offset: f64,
+    /// How much faster than the master clock the local clock runs, e.g. `1e-4` for 100 ppm.
+    pub drift: f64,
+}
+
+impl Clock {
+    /// Creates a clock in step with the master clock.
+    pub fn new() -> Self {
+        Clock::default()
+    }
+
+    /// Sets the local reading at master time zero.
+    pub fn with_offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the relative rate error.
+    ///
+    /// # Panics
+    /// Panics if `drift` is not greater than `-1`, which would stop or reverse the clock.
+    pub fn with_drift(mut self, drift: f64) -> Self {
+        assert!(drift > -1.0, "clock drift must be greater than -1");
+        self.drift = drift;
+        self
+    }
+
+    /// Returns the local reading at master time `master`.
+    pub fn local_time(&self, master: f64) -> f64 {
+        self.offset + (1.0 + self.drift) * master
+    }
+
+    /// Returns the master time at which the local clock reads `local`.
+    pub fn master_time(&self, local: f64) -> f64 {
+        (local - self.offset) / (1.0 + self.drift)
+    }
+
+    /// Converts a delay measured on the local clock to master time.
+    pub fn master_delay(&self, local_delay: f64) -> f64 {
+        local_delay / (1.0 + self.drift)
+    }
+}
+
+impl EventScheduler {
+    /// Registers or replaces the local clock `name`.
+    pub fn add_clock(&mut self, name: impl Into<String>, clock: Clock) {
+        self.clocks.insert(name.into(), clock);
+    }
+
+    /// Returns the local clock `name`, if registered.
+    pub fn clock(&self, name: &str) -> Option<&Clock> {
+        self.clocks.get(name)
+    }
+
+    fn registered_clock(&self, name: &str) -> Clock {
+        *self.clocks.get(name).unwrap_or_else(|| panic!("no clock named {:?}", name))
+    }
+
+    /// Returns the current reading of local clock `name`.
+    ///
+    /// # Panics
+    /// Panics if no clock named `name` is registered.
+    pub fn local_time(&self, name: &str) -> f64 {
+        self.registered_clock(name).local_time(self.current_time)
+    }
+
+    /// Schedules an action after a delay measured on local clock `name`.
+    ///
+    /// # Panics
+    /// Panics if no clock named `name` is registered.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Clock, EventScheduler};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// // Node b's clock is 5 ahead and runs 25% fast.
+    /// scheduler.add_clock("b", Clock::new().with_offset(5.0).with_drift(0.25));
+    /// scheduler.timeout_local("b", 10.0, Some(Box::new(|s| {
+    ///     assert_eq!(s.local_time("b"), 15.0);
+    ///     None
+    /// })), None);
+    /// scheduler.run_until_max_time(100.0);
+    /// assert_eq!(scheduler.current_time, 8.0);
+    /// ```
+    pub fn timeout_local(&mut self, name: &str, delay: f64, action: Option<Action>, context: Option<Context>) -> EventId {
+        let delay = self.registered_clock(name).master_delay(delay);
+        self.timeout(delay, action, context)
+    }
+
+    /// Schedules an action for when local clock `name` reads `local`.
+    ///
+    /// # Panics
+    /// Panics if no clock named `name` is registered.
+    pub fn schedule_local(&mut self, name: &str, local: f64, action: Option<Action>, context: Option<Context>) -> EventId {
+        let time = self.registered_clock(name).master_time(local);
+        self.schedule(crate::ScheduledAction::new(time, action, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_round_trip() {
+        let clock = Clock::new().with_offset(-2.0).with_drift(-0.5);
+        assert_eq!(clock.local_time(10.0), 3.0);
+        assert_eq!(clock.master_time(3.0), 10.0);
+        assert_eq!(clock.master_delay(1.0), 2.0);
+
+        let mut scheduler = EventScheduler::new();
+        scheduler.add_clock("slow", clock);
+        scheduler.schedule_local("slow", 0.0, None, None);
+        scheduler.run_until_max_time(100.0);
+        assert_eq!(scheduler.current_time, 4.0);
+    }
+}
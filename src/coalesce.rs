@@ -0,0 +1,118 @@
+//! # Coalescing
+//!
+//! Timer-style events are often rescheduled before they fire, such as a retransmission timer
+//! reset by every acknowledgement. [`EventScheduler::schedule_coalesced`] keeps at most one
+//! pending instance per label, resolving duplicates with a [`DedupPolicy`].
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+
+/// The pending instance of a coalesced label and the order in which it will run.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Coalesced {
+    id: EventId,
+    time: f64,
+    microstep: u64,
+    priority: i64,
+}
+
+/// What to do when an event is scheduled under a label that already has a pending instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupPolicy {
+    /// Keep the pending instance and discard the new event.
+    Reject,
+    /// Cancel the pending instance and schedule the new event, debouncing the label.
+    #[default]
+    Replace,
+    /// Keep whichever instance would run first.
+    KeepEarliest,
+}
+
+impl EventScheduler {
+    /// Schedules a labeled event unless another event scheduled this way with the same label
+    /// is still pending, in which case `policy` decides which of the two remains.
+    ///
+    /// Events without a label are scheduled as usual.
+    ///
+    /// # Returns
+    /// The id of the instance left pending.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{DedupPolicy, EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// // Each keystroke pushes the autosave back, so only the last one triggers it.
+    /// for keystroke in [0.0, 1.0, 2.5] {
+    ///     scheduler.timeout(keystroke, Some(Box::new(|s| {
+    ///         let autosave = ScheduledAction::at(s.current_time + 2.0).with_action(|_| Some("saved".to_string()));
+    ///         s.schedule_coalesced(autosave.with_label("autosave"), DedupPolicy::Replace);
+    ///         None
+    ///     })), None);
+    /// }
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// let saves: Vec<f64> = log.iter().filter(|r| r.result.is_some()).map(|r| r.time).collect();
+    /// assert_eq!(saves, vec![4.5]);
+    /// ```
+    pub fn schedule_coalesced(&mut self, event: ScheduledAction, policy: DedupPolicy) -> EventId {
+        let Some(label) = event.label.clone() else {
+            return self.schedule(event);
+        };
+        let pending = self.coalesced.get(&label).copied().filter(|existing| self.is_pending(existing.id));
+        let microstep = self.microstep_at(event.time);
+        if let Some(existing) = pending {
+            let keep_existing = match policy {
+                DedupPolicy::Reject => true,
+                DedupPolicy::Replace => false,
+                // Ties keep the pending instance, which was scheduled first.
+                DedupPolicy::KeepEarliest => {
+                    (existing.time, existing.microstep, existing.priority) <= (event.time, microstep, event.priority)
+                }
+            };
+            if keep_existing {
+                return existing.id;
+            }
+            self.cancel(existing.id);
+        }
+        let (time, priority) = (event.time, event.priority);
+        let id = self.schedule(event);
+        self.coalesced.insert(label, Coalesced { id, time, microstep, priority });
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_and_keep_earliest() {
+        let mut scheduler = EventScheduler::new();
+        let first = scheduler.schedule_coalesced(ScheduledAction::at(5.0).with_label("t"), DedupPolicy::Reject);
+        assert_eq!(scheduler.schedule_coalesced(ScheduledAction::at(1.0).with_label("t"), DedupPolicy::Reject), first);
+        assert_eq!(scheduler.schedule_coalesced(ScheduledAction::at(9.0).with_label("t"), DedupPolicy::KeepEarliest), first);
+        let earlier = scheduler.schedule_coalesced(ScheduledAction::at(3.0).with_label("t"), DedupPolicy::KeepEarliest);
+        assert_ne!(earlier, first);
+        assert!(!scheduler.is_pending(first));
+        scheduler.schedule_coalesced(ScheduledAction::at(3.0), DedupPolicy::Reject);
+        assert_eq!(scheduler.event_queue.len(), 2);
+        scheduler.run_until_max_time(10.0);
+        assert_ne!(scheduler.schedule_coalesced(ScheduledAction::at(12.0).with_label("t"), DedupPolicy::Reject), earlier);
+    }
+
+    #[test]
+    fn test_keep_earliest_compares_microsteps_before_priorities() {
+        let mut scheduler = EventScheduler::builder().superdense_time(true).build();
+        scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+            s.schedule_coalesced(ScheduledAction::at(1.0).with_label("t").with_priority(5).with_action(|_| Some("pending".to_string())), DedupPolicy::KeepEarliest);
+            s.schedule(ScheduledAction::at(1.0).with_action(|s| {
+                // An urgent duplicate a microstep later still runs after the pending instance.
+                s.schedule_coalesced(ScheduledAction::at(1.0).with_label("t").with_priority(-10).with_action(|_| Some("duplicate".to_string())), DedupPolicy::KeepEarliest);
+                None
+            }));
+            None
+        }));
+        let log = scheduler.run_until_max_time(5.0);
+        let kept: Vec<_> = log.iter().filter_map(|r| r.result.as_deref()).collect();
+        assert_eq!(kept, ["pending"]);
+    }
+}
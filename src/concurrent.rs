@@ -0,0 +1,375 @@
+//! # Concurrent Events
+//!
+//! A model with many independent simultaneous events, such as a thousand sensors all
+//! sampling on the same tick, spends its time running them one after another. A
+//! [`ConcurrentEvent`] declares the named [`Cells`] it reads and writes, and
+//! [`EventScheduler::run_concurrent_until`] runs the concurrent events of each instant in waves
+//! of events whose declarations do not conflict: two events conflict if either writes a cell
+//! the other reads or writes. With the `rayon` feature a wave runs on rayon's work-stealing
+//! thread pool; without it, one event at a time. Either way the outcome is the same as running
+//! the events in order, because an event only ever runs after every earlier event it
+//! conflicts with.
+//!
+//! A concurrent event only sees the cells it declares, through a [`CellView`], so its work
+//! must be `Send` and cannot touch the scheduler. Ordinary events at the same instant run in
+//! their place in the order and can read and write the cells through `scheduler.cells`. Outside
+//! [`EventScheduler::run_concurrent_until`], concurrent events run one at a time like any other.
+
+use crate::{EventId, EventScheduler, LogPolicy, RunResult, ScheduledAction, SimError};
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// A shareable value stored in [`Cells`].
+type CellValue = Box<dyn Any + Send + Sync>;
+
+/// The work of a concurrent event.
+pub type ConcurrentWork = Box<dyn FnOnce(&mut CellView<'_>) -> Option<String> + Send>;
+
+/// Named values that concurrent events read and write.
+#[derive(Default)]
+pub struct Cells {
+    values: HashMap<String, CellValue>,
+}
+
+impl Cells {
+    /// Creates an empty set of cells.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value` in the cell `key`, replacing any previous value.
+    pub fn insert<T: Any + Send + Sync>(&mut self, key: impl Into<String>, value: T) {
+        self.values.insert(key.into(), Box::new(value));
+    }
+
+    /// Returns the value of the cell `key`, or `None` if it is empty or holds another type.
+    pub fn get<T: Any>(&self, key: &str) -> Option<&T> {
+        self.values.get(key)?.downcast_ref()
+    }
+
+    /// Returns the value of the cell `key` mutably, or `None` if it is empty or holds another
+    /// type.
+    pub fn get_mut<T: Any>(&mut self, key: &str) -> Option<&mut T> {
+        self.values.get_mut(key)?.downcast_mut()
+    }
+
+    /// Splits the cells into a view for each of `events`, which must not conflict.
+    fn views<'a>(&'a mut self, events: &[&Declaration], now: f64) -> Vec<CellView<'a>> {
+        let mut views: Vec<CellView<'a>> = events.iter().map(|_| CellView { now, reads: HashMap::new(), writes: HashMap::new() }).collect();
+        for (key, value) in self.values.iter_mut() {
+            if let Some(writer) = events.iter().position(|event| event.writes.contains(key)) {
+                views[writer].writes.insert(key, value.as_mut());
+                continue;
+            }
+            let value: &'a CellValue = value;
+            let shared: &'a (dyn Any + Send + Sync) = value.as_ref();
+            for (view, event) in views.iter_mut().zip(events) {
+                if event.reads.contains(key) {
+                    view.reads.insert(key, shared);
+                }
+            }
+        }
+        views
+    }
+}
+
+/// The cells a concurrent event declared, as it sees them while it runs.
+pub struct CellView<'a> {
+    now: f64,
+    reads: HashMap<&'a str, &'a (dyn Any + Send + Sync)>,
+    writes: HashMap<&'a str, &'a mut (dyn Any + Send + Sync)>,
+}
+
+impl CellView<'_> {
+    /// Returns the time of the event.
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// Returns the value of a cell the event reads or writes.
+    ///
+    /// # Panics
+    /// Panics if the event did not declare the cell, or the cell is empty or holds another
+    /// type.
+    pub fn get<T: Any>(&self, key: &str) -> &T {
+        let value: Option<&(dyn Any + Send + Sync)> = match self.writes.get(key) {
+            Some(value) => Some(&**value),
+            None => self.reads.get(key).copied(),
+        };
+        value.and_then(|value| value.downcast_ref()).unwrap_or_else(|| panic!("cell {:?} is not declared, empty, or not a {}", key, std::any::type_name::<T>()))
+    }
+
+    /// Returns the value of a cell the event writes.
+    ///
+    /// # Panics
+    /// Panics if the event did not declare that it writes the cell, or the cell is empty or
+    /// holds another type.
+    pub fn get_mut<T: Any>(&mut self, key: &str) -> &mut T {
+        self.writes
+            .get_mut(key)
+            .and_then(|value| value.downcast_mut())
+            .unwrap_or_else(|| panic!("cell {:?} is not declared as written, empty, or not a {}", key, std::any::type_name::<T>()))
+    }
+}
+
+/// The cells a concurrent event reads and writes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Declaration {
+    reads: BTreeSet<String>,
+    writes: BTreeSet<String>,
+}
+
+impl Declaration {
+    fn conflicts_with(&self, other: &Declaration) -> bool {
+        !self.writes.is_disjoint(&other.writes) || !self.writes.is_disjoint(&other.reads) || !self.reads.is_disjoint(&other.writes)
+    }
+}
+
+/// An event that declares the cells it reads and writes so that it can run in parallel with
+/// others at the same time.
+///
+/// # Example
+/// ```
+/// use desru::{ConcurrentEvent, EventScheduler};
+///
+/// let mut scheduler = EventScheduler::new();
+/// scheduler.cells.insert("gain", 2.0_f64);
+/// for sensor in 0..8 {
+///     let key = format!("sensor {}", sensor);
+///     scheduler.cells.insert(key.clone(), 0.0_f64);
+///     // Each sensor writes its own cell and only reads the shared gain, so all eight
+///     // readings at t = 1 can run at once.
+///     scheduler.schedule_concurrent(ConcurrentEvent::at(1.0).reads(["gain"]).writes([key.clone()]).with_work(move |cells| {
+///         let reading = *cells.get::<f64>("gain") * f64::from(sensor);
+///         *cells.get_mut::<f64>(&key) = reading;
+///         None
+///     }));
+/// }
+/// scheduler.run_concurrent_until(10.0);
+/// assert_eq!(scheduler.cells.get::<f64>("sensor 7"), Some(&14.0));
+/// ```
+pub struct ConcurrentEvent {
+    time: f64,
+    label: Option<String>,
+    declaration: Declaration,
+    work: ConcurrentWork,
+}
+
+impl ConcurrentEvent {
+    /// Creates an event at `time` that reads and writes nothing and does nothing.
+    pub fn at(time: f64) -> Self {
+        ConcurrentEvent { time, label: None, declaration: Declaration::default(), work: Box::new(|_| None) }
+    }
+
+    /// Sets the label of the event.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Declares cells the event reads.
+    pub fn reads<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.declaration.reads.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Declares cells the event writes, and may also read.
+    pub fn writes<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.declaration.writes.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the work the event does on the cells it declared.
+    pub fn with_work<F>(mut self, work: F) -> Self
+    where
+        F: FnOnce(&mut CellView<'_>) -> Option<String> + Send + 'static,
+    {
+        self.work = Box::new(work);
+        self
+    }
+
+    /// Returns `true` if the events cannot run at the same time: one writes a cell the other
+    /// reads or writes.
+    pub fn conflicts_with(&self, other: &ConcurrentEvent) -> bool {
+        self.declaration.conflicts_with(&other.declaration)
+    }
+}
+
+impl EventScheduler {
+    /// Schedules a concurrent event.
+    ///
+    /// # Returns
+    /// The id of the event, as for [`EventScheduler::schedule`].
+    pub fn schedule_concurrent(&mut self, event: ConcurrentEvent) -> EventId {
+        let own_id = Rc::new(Cell::new(EventId(0)));
+        let id = own_id.clone();
+        let mut placeholder = ScheduledAction::at(event.time).with_action(move |s| {
+            let event = s.concurrent.remove(&id.get().0)?;
+            let now = s.current_time;
+            let mut views = s.cells.views(&[&event.declaration], now);
+            (event.work)(&mut views[0])
+        });
+        placeholder.label = event.label.clone();
+        let event_id = self.schedule(placeholder);
+        if event_id == EventId(0) {
+            // The time was NaN, so the event was not queued and the run will fail.
+            return event_id;
+        }
+        own_id.set(event_id);
+        self.concurrent.insert(event_id.0, event);
+        event_id
+    }
+
+    /// Runs timestep by timestep until the next event would occur at or after `max_time`,
+    /// running the concurrent events of each timestep in waves of events that do not conflict.
+    ///
+    /// Events that a timestep schedules for the same time run in the next timestep.
+    ///
+    /// # Panics
+    /// Panics if `max_events_per_time` is exceeded or an event was scheduled at a NaN time; use
+    /// [`EventScheduler::try_run_concurrent_until`] to handle these as errors instead.
+    pub fn run_concurrent_until(&mut self, max_time: f64) -> RunResult<'_> {
+        self.try_run_concurrent_until(max_time).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Runs as [`EventScheduler::run_concurrent_until`] does, returning an error instead of
+    /// panicking when the run cannot continue.
+    ///
+    /// Like [`EventScheduler::try_run`], the run also stops when an action requests a pause
+    /// or the wall-clock budget runs out, after the timestep in which that happened.
+    ///
+    /// # Errors
+    /// Returns [`SimError::ZeroDelayCascade`] if `max_events_per_time` would be exceeded, and
+    /// [`SimError::InvalidEventTime`] if an event was scheduled at a NaN time.
+    pub fn try_run_concurrent_until(&mut self, max_time: f64) -> Result<RunResult<'_>, SimError> {
+        self.try_run_timesteps(max_time, |s, batch| {
+            let mut segment = Vec::new();
+            for event in batch {
+                if s.concurrent.contains_key(&event.seq) {
+                    segment.push(event);
+                    continue;
+                }
+                s.run_concurrent_segment(std::mem::take(&mut segment));
+                let (event, result) = s.execute(event);
+                s.log_or_recycle(event, result, &LogPolicy::Full);
+            }
+            s.run_concurrent_segment(segment);
+        })
+    }
+
+    /// Runs consecutive concurrent events of one timestep, in waves, and logs them in order.
+    fn run_concurrent_segment(&mut self, placeholders: Vec<ScheduledAction>) {
+        let mut events: Vec<ConcurrentEvent> = placeholders.iter().filter_map(|placeholder| self.concurrent.remove(&placeholder.seq)).collect();
+        // Each event runs one wave after the latest earlier event it conflicts with.
+        let mut waves: Vec<usize> = Vec::with_capacity(events.len());
+        for (i, event) in events.iter().enumerate() {
+            let wave = (0..i).filter(|&j| events[j].conflicts_with(event)).map(|j| waves[j] + 1).max().unwrap_or(0);
+            waves.push(wave);
+        }
+        let mut works: Vec<Option<ConcurrentWork>> = events.iter_mut().map(|event| Some(std::mem::replace(&mut event.work, Box::new(|_| None)))).collect();
+        let mut results: Vec<(Option<String>, Duration)> = vec![(None, Duration::ZERO); events.len()];
+        let now = self.current_time;
+        for wave in 0..waves.iter().max().map_or(0, |last| last + 1) {
+            let members: Vec<usize> = (0..events.len()).filter(|&i| waves[i] == wave).collect();
+            let declarations: Vec<&Declaration> = members.iter().map(|&i| &events[i].declaration).collect();
+            let views = self.cells.views(&declarations, now);
+            let jobs: Vec<(ConcurrentWork, CellView<'_>)> = members.iter().map(|&i| works[i].take().expect("each event runs once")).zip(views).collect();
+            for (&i, result) in members.iter().zip(run_jobs(jobs)) {
+                results[i] = result;
+            }
+        }
+        for (event, (result, elapsed)) in placeholders.into_iter().zip(results) {
+            if self.profiler.is_enabled() {
+                self.profiler.record(event.label.as_deref(), elapsed);
+            }
+            self.events_at_time += 1;
+            self.counters.executed += 1;
+            self.causality.mark_executed(EventId(event.seq));
+            self.run_hooks(&event, &result);
+            self.log_or_recycle(event, result, &LogPolicy::Full);
+        }
+    }
+}
+
+/// Runs the work of one wave, in parallel with the `rayon` feature, returning each result
+/// with the time the work took.
+fn run_jobs(jobs: Vec<(ConcurrentWork, CellView<'_>)>) -> Vec<(Option<String>, Duration)> {
+    let run = |(work, mut view): (ConcurrentWork, CellView<'_>)| {
+        let started = Instant::now();
+        let result = work(&mut view);
+        (result, started.elapsed())
+    };
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        jobs.into_par_iter().map(run).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        jobs.into_iter().map(run).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waves_keep_the_order_of_conflicting_events() {
+        let run = |concurrent: bool| {
+            let mut scheduler = EventScheduler::new();
+            scheduler.cells.insert("total", 1_i64);
+            for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+                scheduler.cells.insert(key, 0_i64);
+                // Each event copies the running total, then the total doubles and grows.
+                scheduler.schedule_concurrent(ConcurrentEvent::at(1.0).reads(["total"]).writes([key]).with_work(move |cells| {
+                    *cells.get_mut::<i64>(key) = *cells.get::<i64>("total");
+                    None
+                }));
+                scheduler.schedule_concurrent(ConcurrentEvent::at(1.0).writes(["total"]).with_label("grow").with_work(move |cells| {
+                    *cells.get_mut::<i64>("total") = *cells.get::<i64>("total") * 2 + i as i64;
+                    None
+                }));
+            }
+            scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+                *s.cells.get_mut::<i64>("total").unwrap() += 100;
+                None
+            }));
+            if concurrent {
+                assert_eq!(scheduler.run_concurrent_until(5.0).len(), 7);
+            } else {
+                scheduler.run_until_max_time(5.0);
+            }
+            assert!(scheduler.concurrent.is_empty());
+            ["a", "b", "c", "total"].map(|key| *scheduler.cells.get::<i64>(key).unwrap())
+        };
+        assert_eq!(run(true), [1, 2, 5, 112]);
+        assert_eq!(run(false), run(true));
+    }
+
+    #[test]
+    fn test_try_run_profiles_and_reports_cascades() {
+        let mut scheduler = EventScheduler::builder().profile(true).max_events_per_time(2).build();
+        scheduler.cells.insert("x", 0_i64);
+        for time in [1.0, 2.0, 2.0, 2.0] {
+            scheduler.schedule_concurrent(ConcurrentEvent::at(time).reads(["x"]).with_label("read").with_work(|_| None));
+        }
+        let error = scheduler.try_run_concurrent_until(5.0).unwrap_err();
+        assert_eq!(error, SimError::ZeroDelayCascade { time: 2.0, limit: 2 });
+        assert_eq!(scheduler.event_queue.len(), 3);
+        let profile = scheduler.profiler.labels();
+        assert_eq!((profile[0].label.as_deref(), profile[0].count), (Some("read"), 1));
+    }
+}
@@ -0,0 +1,123 @@
+//! # Wake-on-Condition
+//!
+//! Instead of polling shared state with recurring events, a model can register an action with
+//! [`EventScheduler::when`] to run as soon as a predicate over the scheduler holds. Predicates
+//! are evaluated whenever the [`crate::Blackboard`] is posted to, and on demand through
+//! [`EventScheduler::check_conditions`] after changing other shared state such as the world
+//! state. Each registration fires at most once.
+
+use crate::{Action, EventScheduler, ScheduledAction};
+
+/// A predicate over the scheduler's state.
+type Predicate = Box<dyn Fn(&EventScheduler) -> bool>;
+
+/// Identifies a registration made with [`EventScheduler::when`], so that it can be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConditionId(pub u64);
+
+#[derive(Default)]
+pub(crate) struct Conditions {
+    waiting: Vec<(ConditionId, Predicate, Action)>,
+    next_id: u64,
+}
+
+impl EventScheduler {
+    /// Runs `action` in an event at the current time once `predicate` first holds.
+    ///
+    /// The predicate is checked immediately, then after every [`EventScheduler::post`] and
+    /// every call to [`EventScheduler::check_conditions`].
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.when(
+    ///     |s| s.blackboard.get_as::<u32>("stock").is_some_and(|stock| stock < 10),
+    ///     |s| Some(format!("reorder at {}", s.current_time)),
+    /// );
+    /// for (t, stock) in [(1.0, 30), (2.0, 12), (3.0, 8), (4.0, 5)] {
+    ///     scheduler.timeout(t, Some(Box::new(move |s| {
+    ///         s.post("stock", stock);
+    ///         None
+    ///     })), None);
+    /// }
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// let reorders: Vec<_> = log.iter().filter_map(|r| r.result.as_deref()).collect();
+    /// assert_eq!(reorders, ["reorder at 3"]);
+    /// ```
+    pub fn when<P, F>(&mut self, predicate: P, action: F) -> ConditionId
+    where
+        P: Fn(&EventScheduler) -> bool + 'static,
+        F: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+    {
+        let id = ConditionId(self.conditions.next_id);
+        self.conditions.next_id += 1;
+        if predicate(self) {
+            self.schedule(ScheduledAction::at(self.current_time).with_action(action));
+        } else {
+            self.conditions.waiting.push((id, Box::new(predicate), Box::new(action)));
+        }
+        id
+    }
+
+    /// Cancels a registration that has not fired yet.
+    ///
+    /// # Returns
+    /// `true` if the registration was waiting.
+    pub fn cancel_when(&mut self, id: ConditionId) -> bool {
+        let before = self.conditions.waiting.len();
+        self.conditions.waiting.retain(|(waiting, _, _)| *waiting != id);
+        self.conditions.waiting.len() != before
+    }
+
+    /// Evaluates every waiting predicate and schedules the actions of those that hold, in the
+    /// order they were registered.
+    ///
+    /// # Returns
+    /// The number of actions scheduled.
+    pub fn check_conditions(&mut self) -> usize {
+        if self.conditions.waiting.is_empty() {
+            return 0;
+        }
+        let waiting = std::mem::take(&mut self.conditions.waiting);
+        let (ready, still_waiting): (Vec<_>, Vec<_>) = waiting.into_iter().partition(|(_, predicate, _)| predicate(self));
+        self.conditions.waiting = still_waiting;
+        let fired = ready.len();
+        for (_, _, action) in ready {
+            self.schedule(ScheduledAction::new(self.current_time, Some(action), None));
+        }
+        fired
+    }
+
+    /// Returns the number of registrations still waiting for their predicate.
+    pub fn waiting_conditions(&self) -> usize {
+        self.conditions.waiting.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Tank {
+        level: f64,
+    }
+
+    #[test]
+    fn test_world_state_checks_and_cancel() {
+        let mut scheduler = EventScheduler::builder().state(Tank::default()).build();
+        scheduler.when(|_| true, |_| Some("immediate".to_string()));
+        let full = scheduler.when(|s| s.state::<Tank>().level >= 1.0, |_| Some("full".to_string()));
+        let overflow = scheduler.when(|s| s.state::<Tank>().level > 2.0, |_| Some("overflow".to_string()));
+        assert_eq!(scheduler.waiting_conditions(), 2);
+
+        scheduler.state_mut::<Tank>().level = 1.5;
+        assert_eq!(scheduler.check_conditions(), 1);
+        assert!(!scheduler.cancel_when(full));
+        assert!(scheduler.cancel_when(overflow));
+        let results: Vec<_> = scheduler.run_until_max_time(1.0).iter().filter_map(|r| r.result.clone()).collect();
+        assert_eq!(results, ["immediate", "full"]);
+    }
+}
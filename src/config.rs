@@ -0,0 +1,306 @@
+//! # Scenario Files
+//!
+//! A [`ScenarioConfig`] holds the parameters of an experiment (the horizon, the base seed, the
+//! number of replications, and one table of numeric parameters per scenario, such as arrival
+//! rates and resource capacities) and turns them into an [`Experiment`] with
+//! [`ScenarioConfig::experiment`]. It can be read from TOML or YAML:
+//!
+//! ```toml
+//! horizon = 480.0
+//! seed = 42
+//! replications = 10
+//!
+//! [scenarios.baseline]
+//! arrival_rate = 0.5
+//! servers = 2
+//! ```
+//!
+//! ```yaml
+//! horizon: 480.0
+//! seed: 42
+//! replications: 10
+//! scenarios:
+//!   baseline:
+//!     arrival_rate: 0.5
+//!     servers: 2
+//! ```
+//!
+//! To keep the crate free of dependencies, only the subset of each format needed for such
+//! files is understood: comments, `key = value` pairs and `[a.b]` tables in TOML, and
+//! `key: value` pairs nested by indentation in YAML. Values must be numbers. Keys may be
+//! quoted, and a `#` inside quotes does not start a comment.
+
+use crate::{Experiment, SimConfig};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// An error reading a scenario file.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// A line could not be parsed.
+    Syntax { line: usize, message: String },
+    /// A required key is missing.
+    Missing(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "cannot read scenario file: {}", error),
+            ConfigError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigError::Missing(key) => write!(f, "missing required key `{}`", key),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The parameters of an experiment, as read from a scenario file.
+///
+/// # Example
+/// ```
+/// use desru::ScenarioConfig;
+///
+/// let config = ScenarioConfig::from_toml_str("
+///     horizon = 100
+///     replications = 5
+///
+///     [scenarios.slow]
+///     arrival_rate = 1.0
+///
+///     [scenarios.fast]
+///     arrival_rate = 2.0
+/// ").unwrap();
+/// assert_eq!(config.horizon, 100.0);
+/// assert_eq!(config.scenarios[1].0, "fast");
+///
+/// let experiment = config.experiment();
+/// assert_eq!(experiment.scenarios()[0].1["arrival_rate"], 1.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioConfig {
+    /// The time at which each run ends.
+    pub horizon: f64,
+    /// The base seed from which replication seeds are derived. Defaults to [`crate::DEFAULT_SEED`].
+    pub seed: u64,
+    /// Defaults to `1`; must be positive.
+    pub replications: usize,
+    /// Defaults to `0.0`.
+    pub warm_up: f64,
+    /// Each scenario's name and parameters, in file order. A file without scenarios yields a
+    /// single scenario named `default` with no parameters.
+    pub scenarios: Vec<(String, BTreeMap<String, f64>)>,
+}
+
+impl ScenarioConfig {
+    /// Reads a scenario file, choosing the format from its extension: `.yaml` and `.yml` are
+    /// read as YAML, anything else as TOML.
+    ///
+    /// # Errors
+    /// Returns [`ConfigError::Io`] if the file cannot be read, and otherwise as for
+    /// [`ScenarioConfig::from_toml_str`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => ScenarioConfig::from_yaml_str(&text),
+            _ => ScenarioConfig::from_toml_str(&text),
+        }
+    }
+
+    /// Parses a scenario file in TOML.
+    ///
+    /// # Errors
+    /// Returns [`ConfigError::Syntax`] for malformed lines, unknown keys, non-numeric values,
+    /// and zero replications, and [`ConfigError::Missing`] if `horizon` is not given.
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let mut pairs = Vec::new();
+        let mut table: Vec<String> = Vec::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.strip_suffix(']').ok_or_else(|| syntax(index, "unterminated table header"))?;
+                table = header.split('.').map(|part| unquote(part.trim()).to_string()).collect();
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| syntax(index, "expected `key = value`"))?;
+            let mut path = table.clone();
+            path.push(unquote(key.trim()).to_string());
+            pairs.push(Pair { line: index + 1, path, value: value.trim().to_string() });
+        }
+        ScenarioConfig::from_pairs(pairs)
+    }
+
+    /// Parses a scenario file in YAML.
+    ///
+    /// # Errors
+    /// As for [`ScenarioConfig::from_toml_str`].
+    pub fn from_yaml_str(text: &str) -> Result<Self, ConfigError> {
+        let mut pairs = Vec::new();
+        // The indentation and key of each open mapping.
+        let mut parents: Vec<(usize, String)> = Vec::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = strip_comment(raw);
+            if line.trim().is_empty() || line.trim() == "---" {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+            let (key, value) = line.trim().split_once(':').ok_or_else(|| syntax(index, "expected `key: value`"))?;
+            while parents.last().is_some_and(|(parent, _)| *parent >= indent) {
+                parents.pop();
+            }
+            let key = unquote(key.trim()).to_string();
+            if value.trim().is_empty() {
+                parents.push((indent, key));
+            } else {
+                let mut path: Vec<String> = parents.iter().map(|(_, parent)| parent.clone()).collect();
+                path.push(key);
+                pairs.push(Pair { line: index + 1, path, value: value.trim().to_string() });
+            }
+        }
+        ScenarioConfig::from_pairs(pairs)
+    }
+
+    /// Builds an experiment running every scenario for `replications` replications.
+    pub fn experiment(&self) -> Experiment<BTreeMap<String, f64>> {
+        let mut config = SimConfig::new(self.horizon).with_seed(self.seed);
+        config.warm_up = self.warm_up;
+        let mut experiment = Experiment::new(config).replications(self.replications);
+        for (name, parameters) in &self.scenarios {
+            experiment = experiment.scenario(name.clone(), parameters.clone());
+        }
+        experiment
+    }
+
+    fn from_pairs(pairs: Vec<Pair>) -> Result<Self, ConfigError> {
+        let mut horizon = None;
+        let mut config =
+            ScenarioConfig { horizon: f64::INFINITY, seed: crate::DEFAULT_SEED, replications: 1, warm_up: 0.0, scenarios: Vec::new() };
+        for pair in pairs {
+            let path: Vec<&str> = pair.path.iter().map(String::as_str).collect();
+            match path[..] {
+                ["horizon"] => horizon = Some(pair.number()?),
+                ["warm_up"] => config.warm_up = pair.number()?,
+                ["seed"] => config.seed = pair.integer()?,
+                ["replications"] => config.replications = pair.positive_integer()? as usize,
+                ["scenarios", name, parameter] => {
+                    let value = pair.number()?;
+                    let position = match config.scenarios.iter().position(|(existing, _)| existing == name) {
+                        Some(position) => position,
+                        None => {
+                            config.scenarios.push((name.to_string(), BTreeMap::new()));
+                            config.scenarios.len() - 1
+                        }
+                    };
+                    config.scenarios[position].1.insert(parameter.to_string(), value);
+                }
+                _ => return Err(ConfigError::Syntax { line: pair.line, message: format!("unknown key `{}`", pair.path.join(".")) }),
+            }
+        }
+        config.horizon = horizon.ok_or(ConfigError::Missing("horizon"))?;
+        if config.scenarios.is_empty() {
+            config.scenarios.push(("default".to_string(), BTreeMap::new()));
+        }
+        Ok(config)
+    }
+}
+
+/// A leaf value and the keys leading to it.
+struct Pair {
+    line: usize,
+    path: Vec<String>,
+    value: String,
+}
+
+impl Pair {
+    fn number(&self) -> Result<f64, ConfigError> {
+        unquote(&self.value).replace('_', "").parse().map_err(|_| self.error("a number"))
+    }
+
+    fn integer(&self) -> Result<u64, ConfigError> {
+        unquote(&self.value).replace('_', "").parse().map_err(|_| self.error("a non-negative integer"))
+    }
+
+    fn positive_integer(&self) -> Result<u64, ConfigError> {
+        self.integer().ok().filter(|&n| n > 0).ok_or_else(|| self.error("a positive integer"))
+    }
+
+    fn error(&self, expected: &str) -> ConfigError {
+        ConfigError::Syntax { line: self.line, message: format!("`{}` must be {}, found `{}`", self.path.join("."), expected, self.value) }
+    }
+}
+
+fn syntax(index: usize, message: &str) -> ConfigError {
+    ConfigError::Syntax { line: index + 1, message: message.to_string() }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_and_yaml_agree() {
+        let toml = ScenarioConfig::from_toml_str(
+            "horizon = 480.0 # minutes\nseed = 42\nreplications = 3\nwarm_up = 60\n\n[scenarios.peak]\narrival_rate = 0.9\nservers = 3\n",
+        )
+        .unwrap();
+        let yaml = ScenarioConfig::from_yaml_str(
+            "---\nhorizon: 480.0  # minutes\nseed: 42\nreplications: 3\nwarm_up: 60\nscenarios:\n  peak:\n    arrival_rate: 0.9\n    servers: 3\n",
+        )
+        .unwrap();
+        assert_eq!(toml, yaml);
+        assert_eq!(toml.scenarios[0].1["servers"], 3.0);
+        assert_eq!(toml.experiment().seed(0), Experiment::<()>::new(SimConfig::new(480.0).with_seed(42)).seed(0));
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let missing = ScenarioConfig::from_toml_str("seed = 1").unwrap_err();
+        assert_eq!(missing.to_string(), "missing required key `horizon`");
+        let typo = ScenarioConfig::from_yaml_str("horizon: 10\nreplicatons: 2\n").unwrap_err();
+        assert_eq!(typo.to_string(), "line 2: unknown key `replicatons`");
+        let bad = ScenarioConfig::from_toml_str("horizon = soon").unwrap_err();
+        assert!(matches!(bad, ConfigError::Syntax { line: 1, .. }));
+        let none = ScenarioConfig::from_toml_str("horizon = 10\nreplications = 0").unwrap_err();
+        assert_eq!(none.to_string(), "line 2: `replications` must be a positive integer, found `0`");
+    }
+
+    #[test]
+    fn test_hashes_inside_quotes_are_not_comments() {
+        let toml = ScenarioConfig::from_toml_str("horizon = 10\n[scenarios.\"dock #2\"] # night shift\nservers = 1\n").unwrap();
+        let yaml = ScenarioConfig::from_yaml_str("horizon: 10\nscenarios:\n  \"dock #2\": # night shift\n    servers: 1\n").unwrap();
+        assert_eq!(toml.scenarios[0].0, "dock #2");
+        assert_eq!(toml, yaml);
+    }
+}
@@ -0,0 +1,393 @@
+//! # Event Context
+//!
+//! Every event carries a [`Context`] of string key-value pairs. By default this is a
+//! `HashMap<String, String>`. Most events carry only a handful of pairs, for which a hash
+//! table is a heavy allocation, so enabling the `compact-context` feature switches `Context`
+//! to [`ContextMap`]: a small map that stores up to four pairs inline in the event and only
+//! moves to the heap beyond that. Both types support the same common map operations, so model
+//! code written against `Context` compiles either way.
+//!
+//! Values are stored as strings so that every context can be logged and exported, but
+//! [`ContextExt`] adds a [`ContextBuilder`] and typed accessors that convert through
+//! `Display` and `FromStr`, so model code need not format and parse by hand.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+use std::str::FromStr;
+
+/// The key-value context attached to an event.
+#[cfg(not(feature = "compact-context"))]
+pub type Context = HashMap<String, String>;
+
+/// The key-value context attached to an event.
+#[cfg(feature = "compact-context")]
+pub type Context = ContextMap;
+
+/// The number of pairs a [`ContextMap`] stores without allocating.
+const INLINE: usize = 4;
+
+#[derive(Clone)]
+enum Storage {
+    // Empty strings do not allocate, so unused inline slots are free.
+    Inline(usize, [(String, String); INLINE]),
+    Spilled(Vec<(String, String)>),
+}
+
+/// A small string map that stores up to four pairs inline.
+///
+/// Pairs are kept in insertion order, and lookups are linear scans, which beats hashing for
+/// the few pairs typical of an event context. Equality ignores order, as for `HashMap`.
+///
+/// # Example
+/// ```
+/// use desru::ContextMap;
+///
+/// let mut context = ContextMap::new();
+/// context.insert("station".to_string(), "A".to_string());
+/// context.insert("class".to_string(), "vip".to_string());
+/// assert_eq!(context.get("station"), Some(&"A".to_string()));
+/// assert_eq!(context.keys().collect::<Vec<_>>(), ["station", "class"]);
+/// assert_eq!(context.capacity(), 0);
+/// ```
+#[derive(Clone)]
+pub struct ContextMap {
+    storage: Storage,
+}
+
+impl ContextMap {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        ContextMap { storage: Storage::Inline(0, Default::default()) }
+    }
+
+    fn entries(&self) -> &[(String, String)] {
+        match &self.storage {
+            Storage::Inline(len, entries) => &entries[..*len],
+            Storage::Spilled(entries) => entries,
+        }
+    }
+
+    fn entries_mut(&mut self) -> &mut [(String, String)] {
+        match &mut self.storage {
+            Storage::Inline(len, entries) => &mut entries[..*len],
+            Storage::Spilled(entries) => entries,
+        }
+    }
+
+    /// Inserts a pair, returning the previous value of the key if it was present.
+    pub fn insert(&mut self, key: String, value: String) -> Option<String> {
+        if let Some(entry) = self.entries_mut().iter_mut().find(|(k, _)| *k == key) {
+            return Some(std::mem::replace(&mut entry.1, value));
+        }
+        match &mut self.storage {
+            Storage::Inline(len, entries) if *len < INLINE => {
+                entries[*len] = (key, value);
+                *len += 1;
+            }
+            Storage::Inline(_, entries) => {
+                let mut spilled = Vec::with_capacity(2 * INLINE);
+                spilled.extend(entries.iter_mut().map(std::mem::take));
+                spilled.push((key, value));
+                self.storage = Storage::Spilled(spilled);
+            }
+            Storage::Spilled(entries) => entries.push((key, value)),
+        }
+        None
+    }
+
+    /// Returns the value of a key.
+    pub fn get<Q: AsRef<str> + ?Sized>(&self, key: &Q) -> Option<&String> {
+        let key = key.as_ref();
+        self.entries().iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Returns a mutable reference to the value of a key.
+    pub fn get_mut<Q: AsRef<str> + ?Sized>(&mut self, key: &Q) -> Option<&mut String> {
+        let key = key.as_ref();
+        self.entries_mut().iter_mut().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Returns `true` if the map contains the key.
+    pub fn contains_key<Q: AsRef<str> + ?Sized>(&self, key: &Q) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes a key, returning its value if it was present.
+    pub fn remove<Q: AsRef<str> + ?Sized>(&mut self, key: &Q) -> Option<String> {
+        let key = key.as_ref();
+        let position = self.entries().iter().position(|(k, _)| k == key)?;
+        match &mut self.storage {
+            Storage::Inline(len, entries) => {
+                entries[position..*len].rotate_left(1);
+                *len -= 1;
+                Some(std::mem::take(&mut entries[*len]).1)
+            }
+            Storage::Spilled(entries) => Some(entries.remove(position).1),
+        }
+    }
+
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns `true` if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of pairs the heap storage can hold, or zero while the pairs are inline.
+    pub fn capacity(&self) -> usize {
+        match &self.storage {
+            Storage::Inline(..) => 0,
+            Storage::Spilled(entries) => entries.capacity(),
+        }
+    }
+
+    /// Removes every pair, keeping any heap storage for reuse.
+    pub fn clear(&mut self) {
+        match &mut self.storage {
+            Storage::Inline(len, entries) => {
+                entries[..*len].iter_mut().for_each(|entry| *entry = Default::default());
+                *len = 0;
+            }
+            Storage::Spilled(entries) => entries.clear(),
+        }
+    }
+
+    /// Iterates over the pairs in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.entries().iter().map(|(k, v)| (k, v))
+    }
+
+    /// Iterates over the keys in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries().iter().map(|(k, _)| k)
+    }
+
+    /// Iterates over the values in insertion order.
+    pub fn values(&self) -> impl Iterator<Item = &String> {
+        self.entries().iter().map(|(_, v)| v)
+    }
+}
+
+impl Default for ContextMap {
+    fn default() -> Self {
+        ContextMap::new()
+    }
+}
+
+impl PartialEq for ContextMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for ContextMap {}
+
+impl fmt::Debug for ContextMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<Q: AsRef<str> + ?Sized> Index<&Q> for ContextMap {
+    type Output = String;
+
+    fn index(&self, key: &Q) -> &String {
+        self.get(key).expect("key not found in context")
+    }
+}
+
+impl Extend<(String, String)> for ContextMap {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl FromIterator<(String, String)> for ContextMap {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(pairs: I) -> Self {
+        let mut map = ContextMap::new();
+        map.extend(pairs);
+        map
+    }
+}
+
+impl<const N: usize> From<[(String, String); N]> for ContextMap {
+    fn from(pairs: [(String, String); N]) -> Self {
+        pairs.into_iter().collect()
+    }
+}
+
+impl From<HashMap<String, String>> for ContextMap {
+    fn from(map: HashMap<String, String>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl IntoIterator for ContextMap {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.storage {
+            Storage::Inline(len, entries) => entries.into_iter().take(len).collect::<Vec<_>>().into_iter(),
+            Storage::Spilled(entries) => entries.into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a ContextMap {
+    type Item = (&'a String, &'a String);
+    type IntoIter = std::iter::Map<std::slice::Iter<'a, (String, String)>, fn(&'a (String, String)) -> (&'a String, &'a String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries().iter().map(|(k, v)| (k, v))
+    }
+}
+
+/// Builds a [`Context`] from typed values, see [`ContextExt::builder`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextBuilder {
+    context: Context,
+}
+
+impl ContextBuilder {
+    /// Adds a pair, storing `value` in its `Display` form.
+    pub fn insert(mut self, key: impl Into<String>, value: impl fmt::Display) -> Self {
+        self.context.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// Returns the built context.
+    pub fn build(self) -> Context {
+        self.context
+    }
+}
+
+/// Typed construction and access for event contexts.
+///
+/// Implemented for both `HashMap<String, String>` and [`ContextMap`], so it works whichever
+/// type [`Context`] is. The typed getter is named `get_as` because both maps already have an
+/// untyped `get`.
+///
+/// # Example
+/// ```
+/// use desru::{Context, ContextExt};
+///
+/// let mut context = Context::builder().insert("patient_id", 42).insert("class", "urgent").build();
+/// assert_eq!(context.get_as::<u64>("patient_id"), Some(42));
+/// assert_eq!(context.get_as::<u64>("class"), None);
+///
+/// context.set("priority", 1.5);
+/// assert_eq!(context.get_as::<f64>("priority"), Some(1.5));
+/// assert_eq!(context["priority"], "1.5");
+/// ```
+pub trait ContextExt {
+    /// Starts building a context.
+    fn builder() -> ContextBuilder {
+        ContextBuilder::default()
+    }
+
+    /// Inserts `value` in its `Display` form, returning the previous value of the key.
+    fn set(&mut self, key: impl Into<String>, value: impl fmt::Display) -> Option<String>;
+
+    /// Parses the value of `key` as a `T`.
+    ///
+    /// # Returns
+    /// `None` if the key is missing or its value does not parse.
+    fn get_as<T: FromStr>(&self, key: &str) -> Option<T>;
+}
+
+impl ContextExt for HashMap<String, String> {
+    fn set(&mut self, key: impl Into<String>, value: impl fmt::Display) -> Option<String> {
+        self.insert(key.into(), value.to_string())
+    }
+
+    fn get_as<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+}
+
+impl ContextExt for ContextMap {
+    fn set(&mut self, key: impl Into<String>, value: impl fmt::Display) -> Option<String> {
+        self.insert(key.into(), value.to_string())
+    }
+
+    fn get_as<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+}
+
+/// Formats the pairs of `context` as `key=value`, sorted and separated by commas, for display.
+pub(crate) fn display_pairs(context: &Context) -> String {
+    let mut pairs: Vec<String> = context.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+    pairs.sort();
+    pairs.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Class {
+        Routine,
+        Urgent,
+    }
+
+    impl fmt::Display for Class {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Class::Routine => "routine",
+                Class::Urgent => "urgent",
+            })
+        }
+    }
+
+    impl FromStr for Class {
+        type Err = ();
+
+        fn from_str(s: &str) -> Result<Self, ()> {
+            match s {
+                "routine" => Ok(Class::Routine),
+                "urgent" => Ok(Class::Urgent),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_round_trip() {
+        let context = Context::builder().insert("patient_id", 7u64).insert("class", Class::Urgent).build();
+        assert_eq!(context.get_as::<Class>("class"), Some(Class::Urgent));
+        assert_eq!(context.get_as::<Class>("patient_id"), None);
+        assert_eq!(context.get_as::<u64>("missing"), None);
+
+        let mut compact = ContextMap::new();
+        assert_eq!(compact.set("class", Class::Routine), None);
+        assert_eq!(compact.set("class", Class::Urgent), Some("routine".to_string()));
+        assert_eq!(compact.get_as::<Class>("class"), Some(Class::Urgent));
+    }
+
+    #[test]
+    fn test_spill_and_remove() {
+        let mut map: ContextMap = (0..6).map(|i| (format!("k{}", i), i.to_string())).collect();
+        assert_eq!(map.len(), 6);
+        assert!(map.capacity() >= 6);
+        assert_eq!(map.insert("k2".to_string(), "two".to_string()), Some("2".to_string()));
+        assert_eq!(map.remove("k0"), Some("0".to_string()));
+        assert_eq!(map["k2"], "two");
+
+        let mut small = ContextMap::from([("a".to_string(), "1".to_string()), ("b".to_string(), "2".to_string())]);
+        assert_eq!(small.remove("a"), Some("1".to_string()));
+        assert_eq!(small.keys().collect::<Vec<_>>(), ["b"]);
+        let reordered = ContextMap::from([("b".to_string(), "2".to_string())]);
+        assert_eq!(small, reordered);
+        small.clear();
+        assert!(small.is_empty() && small.get("b").is_none());
+    }
+}
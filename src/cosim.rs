@@ -0,0 +1,247 @@
+//! # Federated Co-Simulation
+//!
+//! A [`Federation`] runs several simulators together in the style of HLA time management.
+//! Each member implements [`CoSimulation`]: it requests an advance to the time of its next
+//! event, promises through its lookahead not to send messages earlier than a lower bound, and
+//! is granted an advance only up to the lowest bound of the others (the lower bound on time
+//! stamps, LBTS). Messages sent in between are delivered to every other member before it
+//! moves again, so no member ever receives a message timestamped in its past.
+//!
+//! A [`Federate`] wraps an [`EventScheduler`] as a member. Its actions send messages through
+//! an [`Outbox`], and it receives messages by name: a message runs the action registered
+//! under its name with [`EventScheduler::register_action`], and is ignored if there is none.
+
+use crate::{Context, EventScheduler, SimError};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A timestamped message between members of a [`Federation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FederateMessage {
+    pub time: f64,
+    pub name: String,
+    pub context: Context,
+}
+
+/// A simulator that can take part in a [`Federation`].
+pub trait CoSimulation {
+    /// Returns the member's current time.
+    fn time(&self) -> f64;
+
+    /// Returns how far ahead of the events it runs the member schedules its messages.
+    fn lookahead(&self) -> f64;
+
+    /// Returns the time the member would like to advance to: that of its next event, or
+    /// infinity if it has none.
+    fn request_advance(&self) -> f64;
+
+    /// Returns the earliest time at which the member could still send a message.
+    fn lower_bound(&self) -> f64 {
+        self.request_advance().max(self.time()) + self.lookahead()
+    }
+
+    /// Accepts a message from another member.
+    ///
+    /// # Errors
+    /// Returns [`SimError::CausalityViolation`] if the message is timestamped before the
+    /// member's current time.
+    fn receive(&mut self, message: FederateMessage) -> Result<(), SimError>;
+
+    /// Advances to `time`, which no message still to come can precede.
+    ///
+    /// # Returns
+    /// The messages sent while advancing.
+    ///
+    /// # Errors
+    /// Returns [`SimError::Interrupted`] if the member stopped short of `time`, leaving its
+    /// clock where it stopped.
+    fn grant(&mut self, time: f64) -> Result<Vec<FederateMessage>, SimError>;
+}
+
+/// The state shared by the clones of an [`Outbox`].
+struct OutboxState {
+    lookahead: f64,
+    messages: Vec<FederateMessage>,
+}
+
+/// The handle through which a [`Federate`]'s actions send messages. Every clone refers to the
+/// same outbox.
+pub struct Outbox {
+    state: Rc<RefCell<OutboxState>>,
+}
+
+impl Clone for Outbox {
+    fn clone(&self) -> Self {
+        Outbox { state: self.state.clone() }
+    }
+}
+
+impl Outbox {
+    /// Sends a message to the other members of the federation, to be received at `time`.
+    ///
+    /// # Panics
+    /// Panics if `time` is earlier than the current time plus the federate's lookahead,
+    /// which would break the promise the federation relies on.
+    pub fn send(&self, scheduler: &EventScheduler, time: f64, name: impl Into<String>, context: Context) {
+        let mut state = self.state.borrow_mut();
+        let earliest = scheduler.time_after_at_least(state.lookahead);
+        assert!(time >= earliest, "message at time {} violates the lookahead; the earliest allowed is {}", time, earliest);
+        state.messages.push(FederateMessage { time, name: name.into(), context });
+    }
+}
+
+/// An [`EventScheduler`] taking part in a [`Federation`].
+pub struct Federate {
+    pub scheduler: EventScheduler,
+    outbox: Outbox,
+}
+
+impl Federate {
+    /// Wraps `scheduler` as a federate whose messages are sent at least `lookahead` after
+    /// the event sending them.
+    pub fn new(scheduler: EventScheduler, lookahead: f64) -> Self {
+        let outbox = Outbox { state: Rc::new(RefCell::new(OutboxState { lookahead, messages: Vec::new() })) };
+        Federate { scheduler, outbox }
+    }
+
+    /// Returns a handle for the federate's actions to send messages through.
+    pub fn outbox(&self) -> Outbox {
+        self.outbox.clone()
+    }
+}
+
+impl CoSimulation for Federate {
+    fn time(&self) -> f64 {
+        self.scheduler.current_time
+    }
+
+    fn lookahead(&self) -> f64 {
+        self.outbox.state.borrow().lookahead
+    }
+
+    /// Returns the scheduler's [`EventScheduler::lookahead`], so that minimum delays and
+    /// zero crossings are taken into account.
+    fn request_advance(&self) -> f64 {
+        self.scheduler.lookahead()
+    }
+
+    fn receive(&mut self, message: FederateMessage) -> Result<(), SimError> {
+        if message.time < self.scheduler.current_time {
+            return Err(SimError::CausalityViolation { time: message.time, now: self.scheduler.current_time });
+        }
+        if self.scheduler.actions.get(&message.name).is_some() {
+            self.scheduler.schedule_named(message.time, &message.name, message.context)?;
+        }
+        Ok(())
+    }
+
+    fn grant(&mut self, time: f64) -> Result<Vec<FederateMessage>, SimError> {
+        self.scheduler.advance_to(time)?;
+        Ok(std::mem::take(&mut self.outbox.state.borrow_mut().messages))
+    }
+}
+
+/// Simulators run together under conservative time management.
+///
+/// # Example
+/// ```
+/// use desru::{Context, CoSimulation, EventScheduler, Federate, Federation};
+///
+/// // A ping federate sends a message one time unit after each of its ticks.
+/// let mut ping = Federate::new(EventScheduler::new(), 1.0);
+/// let outbox = ping.outbox();
+/// ping.scheduler.register_action("tick", move |s, _| {
+///     outbox.send(s, s.current_time + 1.0, "ping", Context::new());
+///     let next = s.current_time + 3.0;
+///     s.schedule_named(next, "tick", Context::new()).unwrap();
+///     None
+/// });
+/// ping.scheduler.schedule_named(0.0, "tick", Context::new()).unwrap();
+///
+/// // A pong federate records when each message arrives.
+/// let mut pong = Federate::new(EventScheduler::new(), 1.0);
+/// pong.scheduler.register_action("ping", |s, _| Some(format!("ping at {}", s.current_time)));
+///
+/// Federation::new().join(&mut ping).join(&mut pong).run_until(10.0).unwrap();
+/// let arrivals: Vec<f64> = pong.scheduler.event_log.iter().map(|record| record.time).collect();
+/// assert_eq!(arrivals, [1.0, 4.0, 7.0]);
+/// assert_eq!(pong.time(), 10.0);
+/// ```
+#[derive(Default)]
+pub struct Federation<'a> {
+    members: Vec<&'a mut dyn CoSimulation>,
+}
+
+impl<'a> Federation<'a> {
+    /// Creates a federation without members.
+    pub fn new() -> Self {
+        Federation::default()
+    }
+
+    /// Adds a member.
+    pub fn join(mut self, member: &'a mut dyn CoSimulation) -> Self {
+        self.members.push(member);
+        self
+    }
+
+    /// Advances every member to `horizon`, granting each in turn as far as the lower bounds
+    /// of the others allow and delivering the messages it sends.
+    ///
+    /// # Errors
+    /// Returns [`SimError::FederationDeadlock`] if no member can advance, which happens when
+    /// members with zero lookahead wait on each other, and otherwise any error a member
+    /// returns.
+    pub fn run_until(&mut self, horizon: f64) -> Result<(), SimError> {
+        while self.members.iter().any(|member| member.time() < horizon) {
+            let mut advanced = false;
+            for index in 0..self.members.len() {
+                let bound = self
+                    .members
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, member)| member.lower_bound())
+                    .fold(horizon, f64::min);
+                if bound <= self.members[index].time() {
+                    continue;
+                }
+                let messages = self.members[index].grant(bound)?;
+                advanced = true;
+                for message in messages {
+                    for (other, member) in self.members.iter_mut().enumerate() {
+                        if other != index {
+                            member.receive(message.clone())?;
+                        }
+                    }
+                }
+            }
+            if !advanced {
+                let time = self.members.iter().map(|member| member.time()).fold(f64::INFINITY, f64::min);
+                return Err(SimError::FederationDeadlock { time });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_lookahead_members_deadlock() {
+        let mut a = Federate::new(EventScheduler::new(), 0.0);
+        let mut b = Federate::new(EventScheduler::new(), 0.0);
+        a.scheduler.timeout(0.0, None, None);
+        b.scheduler.timeout(0.0, None, None);
+        let error = Federation::new().join(&mut a).join(&mut b).run_until(5.0).unwrap_err();
+        assert_eq!(error, SimError::FederationDeadlock { time: 0.0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "violates the lookahead")]
+    fn test_sending_inside_the_lookahead_panics() {
+        let federate = Federate::new(EventScheduler::new(), 2.0);
+        federate.outbox().send(&federate.scheduler, 1.0, "early", Context::new());
+    }
+}
@@ -0,0 +1,186 @@
+//! # Calendar Time
+//!
+//! Optional mapping between simulation time and calendar datetimes, enabled with the `chrono`
+//! feature. An [`Epoch`] anchors simulation time `0.0` to a [`NaiveDateTime`] and fixes how much
+//! wall-calendar time one unit of simulation time represents.
+
+use crate::csv::csv_field;
+use crate::{Action, EventScheduler};
+use chrono::{NaiveDateTime, TimeDelta};
+use std::io::{self, Write};
+
+/// Maps simulation time onto calendar datetimes.
+///
+/// # Example
+/// ```
+/// use chrono::{NaiveDate, TimeDelta};
+/// use desru::Epoch;
+///
+/// let origin = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+/// let epoch = Epoch::new(origin, TimeDelta::hours(1));
+/// let nine_am = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+/// assert_eq!(epoch.to_sim_time(nine_am), 9.0);
+/// assert_eq!(epoch.to_datetime(9.0), nine_am);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Epoch {
+    pub origin: NaiveDateTime,
+    pub unit: TimeDelta,
+}
+
+impl Epoch {
+    /// Creates a new epoch.
+    ///
+    /// # Parameters
+    /// - `origin`: The datetime corresponding to simulation time `0.0`.
+    /// - `unit`: The calendar duration of one unit of simulation time.
+    ///
+    /// # Panics
+    /// Panics if `unit` is not a positive duration.
+    pub fn new(origin: NaiveDateTime, unit: TimeDelta) -> Self {
+        assert!(unit > TimeDelta::zero(), "epoch unit must be positive");
+        Epoch { origin, unit }
+    }
+
+    /// Converts a simulation time into a datetime, rounded to the nearest nanosecond.
+    ///
+    /// # Panics
+    /// Panics if the datetime is outside the range chrono can represent; use
+    /// [`Epoch::checked_to_datetime`] to handle this instead.
+    pub fn to_datetime(&self, time: f64) -> NaiveDateTime {
+        self.checked_to_datetime(time)
+            .unwrap_or_else(|| panic!("simulation time {} is outside the range of calendar datetimes", time))
+    }
+
+    /// Converts a simulation time into a datetime, rounded to the nearest nanosecond.
+    ///
+    /// # Returns
+    /// `None` if `time` is not finite or the datetime is outside the range chrono can represent.
+    pub fn checked_to_datetime(&self, time: f64) -> Option<NaiveDateTime> {
+        let nanos = (time * nanoseconds(self.unit)).round();
+        let seconds = (nanos / 1e9).floor();
+        if seconds.is_nan() || seconds.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let elapsed = TimeDelta::try_seconds(seconds as i64)?.checked_add(&TimeDelta::nanoseconds((nanos - seconds * 1e9) as i64))?;
+        self.origin.checked_add_signed(elapsed)
+    }
+
+    /// Converts a datetime into simulation time.
+    pub fn to_sim_time(&self, datetime: NaiveDateTime) -> f64 {
+        nanoseconds(datetime - self.origin) / nanoseconds(self.unit)
+    }
+}
+
+/// Returns the length of a duration in nanoseconds. Unlike [`TimeDelta::num_nanoseconds`],
+/// this covers every duration, at the cost of precision beyond about 104 days.
+fn nanoseconds(delta: TimeDelta) -> f64 {
+    delta.num_seconds() as f64 * 1e9 + delta.subsec_nanos() as f64
+}
+
+impl EventScheduler {
+    fn require_epoch(&self) -> Epoch {
+        self.epoch.expect("no epoch configured; use EventSchedulerBuilder::epoch")
+    }
+
+    /// Returns the current simulation time as a datetime, if an epoch is configured and the
+    /// datetime can be represented.
+    pub fn current_datetime(&self) -> Option<NaiveDateTime> {
+        self.epoch.and_then(|epoch| epoch.checked_to_datetime(self.current_time))
+    }
+
+    /// Schedules an event at the simulation time corresponding to a datetime.
+    ///
+    /// # Parameters
+    /// - `datetime`: When the event should occur.
+    /// - `action`: The action to be executed (optional).
+    /// - `context`: Additional context for the event (optional).
+    ///
+    /// # Returns
+    /// The [`crate::EventId`] of the scheduled event.
+    ///
+    /// # Panics
+    /// Panics if the scheduler has no epoch.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{NaiveDate, TimeDelta};
+    /// use desru::{Epoch, EventScheduler};
+    ///
+    /// let monday = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    /// let mut scheduler = EventScheduler::builder()
+    ///     .epoch(Epoch::new(monday.and_hms_opt(0, 0, 0).unwrap(), TimeDelta::minutes(1)))
+    ///     .build();
+    /// scheduler.schedule_at_datetime(monday.and_hms_opt(9, 0, 0).unwrap(),
+    ///                                Some(Box::new(|_| Some("Doors open".to_string()))),
+    ///                                None);
+    /// assert_eq!(scheduler.event_queue.peek().map(|e| e.time), Some(540.0));
+    /// ```
+    pub fn schedule_at_datetime(&mut self, datetime: NaiveDateTime, action: Option<Action>, context: Option<crate::Context>) -> crate::EventId {
+        let time = self.require_epoch().to_sim_time(datetime);
+        self.schedule(crate::ScheduledAction::new(time, action, context))
+    }
+
+    /// Writes the event log as CSV with a calendar timestamp column.
+    ///
+    /// Columns are `time`, `timestamp` (ISO 8601, or empty for times outside the calendar's
+    /// range), `result`, and `context` (as `key=value` pairs separated by `;`, sorted by key).
+    ///
+    /// # Panics
+    /// Panics if the scheduler has no epoch.
+    pub fn write_timestamped_log<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let epoch = self.require_epoch();
+        writeln!(writer, "time,timestamp,result,context")?;
+        for record in &self.event_log {
+            let mut pairs: Vec<_> = record.context.iter().collect();
+            pairs.sort();
+            let context: Vec<String> = pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                record.time,
+                epoch.checked_to_datetime(record.time).map_or(String::new(), |datetime| datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+                csv_field(record.result.as_deref().unwrap_or("")),
+                csv_field(&context.join(";")),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_timestamped_log() {
+        let origin = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(8, 0, 0).unwrap();
+        let mut scheduler = EventScheduler::builder()
+            .epoch(Epoch::new(origin, TimeDelta::minutes(1)))
+            .build();
+        scheduler.timeout(90.0, Some(Box::new(|_| Some("a,b".to_string()))), None);
+        scheduler.run_until_max_time(100.0);
+        assert_eq!(scheduler.current_datetime(), Some(origin + TimeDelta::minutes(90)));
+
+        let mut out = Vec::new();
+        scheduler.write_timestamped_log(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().nth(1), Some("90,2024-03-01T09:30:00,\"a,b\","));
+    }
+
+    #[test]
+    fn test_conversions_beyond_the_nanosecond_range() {
+        let origin = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let epoch = Epoch::new(origin, TimeDelta::days(400 * 365));
+        let later = origin + TimeDelta::days(200 * 365);
+        assert_eq!(epoch.to_sim_time(later), 0.5);
+        assert_eq!(epoch.checked_to_datetime(0.5), Some(later));
+        assert_eq!(epoch.checked_to_datetime(1e9), None);
+        assert_eq!(epoch.checked_to_datetime(f64::NAN), None);
+
+        let mut scheduler = EventScheduler::builder().epoch(epoch).build();
+        scheduler.current_time = 1e9;
+        assert_eq!(scheduler.current_datetime(), None);
+    }
+}
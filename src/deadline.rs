@@ -0,0 +1,80 @@
+//! # Deadlines
+//!
+//! A common pattern races an operation against a timeout: whichever happens first wins and
+//! the other must be cancelled. [`EventScheduler::with_deadline`] schedules both sides and
+//! cancels the loser automatically, so neither side needs to track the other's event id.
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// The two events of a race set up by [`EventScheduler::with_deadline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Race {
+    pub main: EventId,
+    pub timeout: EventId,
+}
+
+impl EventScheduler {
+    /// Schedules `main` after `delay` and `on_timeout` after `deadline`, both measured from now.
+    /// Whichever runs first cancels the other. If both fall at the same time and priority,
+    /// `main` wins.
+    ///
+    /// Either event can also be cancelled by id through the returned [`Race`], for example to
+    /// abandon the operation without a timeout.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let race = scheduler.with_deadline(
+    ///     7.0, |_| Some("reply".to_string()),
+    ///     5.0, |_| Some("timed out".to_string()),
+    /// );
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!(log.len(), 1);
+    /// assert_eq!(log[0].result.as_deref(), Some("timed out"));
+    /// assert_eq!(log[0].id, race.timeout);
+    /// ```
+    pub fn with_deadline<M, T>(&mut self, delay: f64, mut main: M, deadline: f64, mut on_timeout: T) -> Race
+    where
+        M: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+        T: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+    {
+        let main_id = Rc::new(Cell::new(None));
+        let timeout_id = Rc::new(Cell::new(None));
+        let loser = timeout_id.clone();
+        let main = self.schedule(ScheduledAction::at(self.time_after(delay)).with_action(move |s| {
+            if let Some(id) = loser.get() {
+                s.cancel(id);
+            }
+            main(s)
+        }));
+        let loser = main_id.clone();
+        let timeout = self.schedule(ScheduledAction::at(self.time_after(deadline)).with_action(move |s| {
+            if let Some(id) = loser.get() {
+                s.cancel(id);
+            }
+            on_timeout(s)
+        }));
+        main_id.set(Some(main));
+        timeout_id.set(Some(timeout));
+        Race { main, timeout }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_main_wins_ties() {
+        let mut scheduler = EventScheduler::new();
+        let race = scheduler.with_deadline(3.0, |_| Some("main".to_string()), 3.0, |_| Some("timeout".to_string()));
+        let log = scheduler.run_until_max_time(10.0);
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].id, race.main);
+        assert!(!scheduler.is_pending(race.timeout));
+    }
+}
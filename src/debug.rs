@@ -0,0 +1,171 @@
+//! # Debugging
+//!
+//! A debug run executes events like [`EventScheduler::try_run`] but pauses before any event
+//! matching a [`Breakpoint`]. While paused, the event that triggered the breakpoint is still at
+//! the front of the queue, and the clock, queue, log, and model state can all be inspected
+//! through the scheduler. Calling [`EventScheduler::debug_run`] again continues from the
+//! paused event; [`EventScheduler::step`] runs one event at a time.
+
+use crate::{Context, EventId, EventScheduler, ScheduledAction, SimError, StopCondition};
+use std::fmt;
+
+/// A predicate over an event's context.
+pub type ContextPredicate = Box<dyn Fn(&Context) -> bool>;
+
+/// A condition on the next event that pauses a debug run.
+pub enum Breakpoint {
+    /// Pauses before events scheduled within `[start, end]`.
+    TimeRange { start: f64, end: f64 },
+    /// Pauses before events with this label.
+    Label(String),
+    /// Pauses before events whose context satisfies the predicate.
+    Context(ContextPredicate),
+}
+
+impl Breakpoint {
+    /// Returns `true` if the breakpoint applies to `event`.
+    pub fn matches(&self, event: &ScheduledAction) -> bool {
+        match self {
+            Breakpoint::TimeRange { start, end } => *start <= event.time && event.time <= *end,
+            Breakpoint::Label(label) => event.label.as_deref() == Some(label.as_str()),
+            Breakpoint::Context(predicate) => predicate(&event.context),
+        }
+    }
+}
+
+impl fmt::Debug for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::TimeRange { start, end } => f.debug_struct("TimeRange").field("start", start).field("end", end).finish(),
+            Breakpoint::Label(label) => f.debug_tuple("Label").field(label).finish(),
+            Breakpoint::Context(_) => f.debug_tuple("Context").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Identifies a breakpoint added with [`EventScheduler::add_breakpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BreakpointId(pub u64);
+
+/// Why a debug run returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugStop {
+    /// The next event matches a breakpoint and has not run yet.
+    Breakpoint { breakpoint: BreakpointId, event: EventId, time: f64 },
+    /// The stop condition was met.
+    Stopped,
+    /// No events are left.
+    Exhausted,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DebugState {
+    breakpoints: Vec<(BreakpointId, Breakpoint)>,
+    next_id: u64,
+    resume: Option<EventId>,
+}
+
+impl EventScheduler {
+    /// Adds a breakpoint for debug runs.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+        let id = BreakpointId(self.debug.next_id);
+        self.debug.next_id += 1;
+        self.debug.breakpoints.push((id, breakpoint));
+        id
+    }
+
+    /// Removes a breakpoint.
+    ///
+    /// # Returns
+    /// `true` if the breakpoint existed.
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> bool {
+        let before = self.debug.breakpoints.len();
+        self.debug.breakpoints.retain(|(existing, _)| *existing != id);
+        self.debug.breakpoints.len() != before
+    }
+
+    /// Runs the next event and logs it as [`EventScheduler::run`] would.
+    ///
+    /// # Returns
+    /// `true` if an event ran, `false` if the queue was empty.
+    ///
+    /// # Errors
+    /// Returns [`SimError::ZeroDelayCascade`] if `max_events_per_time` is exceeded.
+    pub fn step(&mut self) -> Result<bool, SimError> {
+        let Some((event, result)) = self.execute_next()? else {
+            return Ok(false);
+        };
+        self.log_or_recycle(event, result, &crate::LogPolicy::Full);
+        Ok(true)
+    }
+
+    /// Runs until the stop condition is met, the queue empties, or the next event matches a
+    /// breakpoint.
+    ///
+    /// If the previous debug run paused at a breakpoint, the paused event runs first without
+    /// triggering it again.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Breakpoint, DebugStop, EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// for t in [1.0, 2.0, 3.0] {
+    ///     scheduler.schedule(ScheduledAction::at(t).with_label(if t == 2.0 { "departure" } else { "arrival" }));
+    /// }
+    /// scheduler.add_breakpoint(Breakpoint::Label("departure".to_string()));
+    ///
+    /// let stop = scheduler.debug_run(Box::new(|_| false)).unwrap();
+    /// assert!(matches!(stop, DebugStop::Breakpoint { time, .. } if time == 2.0));
+    /// assert_eq!(scheduler.current_time, 1.0);
+    /// assert_eq!(scheduler.event_queue.peek().and_then(|e| e.label.as_deref()), Some("departure"));
+    ///
+    /// assert_eq!(scheduler.debug_run(Box::new(|_| false)).unwrap(), DebugStop::Exhausted);
+    /// assert_eq!(scheduler.current_time, 3.0);
+    /// ```
+    pub fn debug_run(&mut self, stop: StopCondition) -> Result<DebugStop, SimError> {
+        let mut resume = self.debug.resume.take();
+        loop {
+            if stop(self) {
+                return Ok(DebugStop::Stopped);
+            }
+            let Some(next) = self.event_queue.peek() else {
+                return Ok(DebugStop::Exhausted);
+            };
+            let id = EventId(next.seq);
+            if resume.take() != Some(id) {
+                if let Some((breakpoint, _)) = self.debug.breakpoints.iter().find(|(_, b)| b.matches(next)) {
+                    self.debug.resume = Some(id);
+                    return Ok(DebugStop::Breakpoint { breakpoint: *breakpoint, event: id, time: next.time });
+                }
+            }
+            self.step()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_and_time_breakpoints() {
+        let mut scheduler = EventScheduler::new();
+        let mut vip = Context::new();
+        vip.insert("class".to_string(), "vip".to_string());
+        scheduler.schedule(ScheduledAction::at(1.0));
+        scheduler.schedule(ScheduledAction::at(2.0).with_context(vip));
+        scheduler.schedule(ScheduledAction::at(5.0));
+        let by_context = scheduler.add_breakpoint(Breakpoint::Context(Box::new(|c| c.get("class").is_some_and(|v| v == "vip"))));
+        let by_time = scheduler.add_breakpoint(Breakpoint::TimeRange { start: 4.0, end: 6.0 });
+
+        let first = scheduler.debug_run(Box::new(|_| false)).unwrap();
+        assert!(matches!(first, DebugStop::Breakpoint { breakpoint, .. } if breakpoint == by_context));
+        let second = scheduler.debug_run(Box::new(|_| false)).unwrap();
+        assert!(matches!(second, DebugStop::Breakpoint { breakpoint, .. } if breakpoint == by_time));
+        assert!(scheduler.remove_breakpoint(by_time));
+        assert!(scheduler.step().unwrap());
+        assert!(!scheduler.step().unwrap());
+        assert_eq!(scheduler.event_log.len(), 3);
+    }
+}
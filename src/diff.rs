@@ -0,0 +1,176 @@
+//! # Run Comparison
+//!
+//! [`diff_logs`] compares two event logs, such as those of a model before and after a
+//! refactoring, or of a port and the SimPy model it reproduces. Events are aligned by time
+//! and label; ids are ignored, since two implementations rarely schedule in the same order.
+//! Aligned events whose results or contexts differ, and events present in only one log, are
+//! reported in time order, so the first entry of a [`LogDiff`] is where the runs diverge.
+
+use crate::EventRecord;
+use std::fmt;
+
+/// One way in which two logs differ.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogDifference {
+    /// An event that only the left log contains.
+    OnlyLeft(EventRecord),
+    /// An event that only the right log contains.
+    OnlyRight(EventRecord),
+    /// Events at the same time with the same label whose results or contexts differ.
+    Changed { left: Box<EventRecord>, right: Box<EventRecord> },
+}
+
+impl LogDifference {
+    /// Returns the time at which the difference occurs.
+    pub fn time(&self) -> f64 {
+        match self {
+            LogDifference::OnlyLeft(record) | LogDifference::OnlyRight(record) => record.time,
+            LogDifference::Changed { left, .. } => left.time,
+        }
+    }
+}
+
+impl fmt::Display for LogDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = |record: &EventRecord| record.label.clone().unwrap_or_else(|| "-".to_string());
+        match self {
+            LogDifference::OnlyLeft(record) => write!(f, "- {} {} {:?}", record.time, label(record), record.result),
+            LogDifference::OnlyRight(record) => write!(f, "+ {} {} {:?}", record.time, label(record), record.result),
+            LogDifference::Changed { left, right } => {
+                write!(f, "~ {} {} {:?} -> {:?}", left.time, label(left), left.result, right.result)?;
+                if left.context != right.context {
+                    write!(f, " (context differs)")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of [`diff_logs`].
+///
+/// Its `Display` form lists one difference per line, marked `-` for events only on the left,
+/// `+` for events only on the right, and `~` for changed events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogDiff {
+    pub differences: Vec<LogDifference>,
+    /// How many events were aligned with an identical counterpart.
+    pub matched: usize,
+}
+
+impl LogDiff {
+    /// Returns `true` if the logs agree.
+    pub fn is_identical(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Returns the earliest difference, where the runs diverge.
+    pub fn first_divergence(&self) -> Option<&LogDifference> {
+        self.differences.first()
+    }
+}
+
+impl fmt::Display for LogDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_identical() {
+            return writeln!(f, "identical ({} events)", self.matched);
+        }
+        writeln!(f, "{} differences, {} events matched", self.differences.len(), self.matched)?;
+        for difference in &self.differences {
+            writeln!(f, "{}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two time-ordered logs, treating times within `tolerance` of each other as equal.
+///
+/// Within a group of events at the same time, each event on the left is aligned with the
+/// first unaligned event on the right with the same label, so the order in which
+/// simultaneous events ran does not matter.
+///
+/// # Example
+/// ```
+/// use desru::{diff_logs, EventScheduler, LogDifference, ScheduledAction};
+///
+/// let run = |service: f64| {
+///     let mut scheduler = EventScheduler::new();
+///     scheduler.schedule(ScheduledAction::at(0.0).with_label("arrive"));
+///     scheduler.schedule(ScheduledAction::at(service).with_label("depart"));
+///     scheduler.run_until_max_time(10.0).log.to_vec()
+/// };
+/// let diff = diff_logs(&run(2.0), &run(3.0), 1e-9);
+/// assert_eq!(diff.matched, 1);
+/// assert!(matches!(diff.first_divergence(), Some(LogDifference::OnlyLeft(record)) if record.time == 2.0));
+/// assert_eq!(diff.to_string(), "2 differences, 1 events matched\n- 2 depart None\n+ 3 depart None\n");
+/// ```
+pub fn diff_logs(left: &[EventRecord], right: &[EventRecord], tolerance: f64) -> LogDiff {
+    let mut diff = LogDiff { differences: Vec::new(), matched: 0 };
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        let (l, r) = (&left[i], &right[j]);
+        if l.time < r.time - tolerance {
+            diff.differences.push(LogDifference::OnlyLeft(l.clone()));
+            i += 1;
+        } else if r.time < l.time - tolerance {
+            diff.differences.push(LogDifference::OnlyRight(r.clone()));
+            j += 1;
+        } else {
+            let time = l.time;
+            let left_end = group_end(left, i, time, tolerance);
+            let right_end = group_end(right, j, time, tolerance);
+            let mut used = vec![false; right_end - j];
+            for l in &left[i..left_end] {
+                let found = (0..used.len()).find(|&k| !used[k] && right[j + k].label == l.label);
+                match found {
+                    Some(k) => {
+                        used[k] = true;
+                        let r = &right[j + k];
+                        if l.result == r.result && l.context == r.context {
+                            diff.matched += 1;
+                        } else {
+                            diff.differences.push(LogDifference::Changed { left: Box::new(l.clone()), right: Box::new(r.clone()) });
+                        }
+                    }
+                    None => diff.differences.push(LogDifference::OnlyLeft(l.clone())),
+                }
+            }
+            for (k, used) in used.into_iter().enumerate() {
+                if !used {
+                    diff.differences.push(LogDifference::OnlyRight(right[j + k].clone()));
+                }
+            }
+            i = left_end;
+            j = right_end;
+        }
+    }
+    diff.differences.extend(left[i..].iter().cloned().map(LogDifference::OnlyLeft));
+    diff.differences.extend(right[j..].iter().cloned().map(LogDifference::OnlyRight));
+    diff
+}
+
+/// Returns the end of the run of events starting at `start` within `tolerance` of `time`.
+fn group_end(log: &[EventRecord], start: usize, time: f64, tolerance: f64) -> usize {
+    log[start..].iter().position(|record| (record.time - time).abs() > tolerance).map_or(log.len(), |n| start + n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, EventId};
+
+    fn record(id: u64, time: f64, label: &str, result: &str) -> EventRecord {
+        EventRecord { id: EventId(id), time, label: Some(label.to_string()), context: Context::new(), result: Some(result.to_string()) }
+    }
+
+    #[test]
+    fn test_same_time_events_align_by_label_regardless_of_order() {
+        let left = [record(1, 1.0, "a", "x"), record(2, 1.0, "b", "y"), record(3, 2.0, "c", "z")];
+        let right = [record(7, 1.0, "b", "y"), record(8, 1.0 + 1e-12, "a", "x"), record(9, 2.0, "c", "w")];
+        let diff = diff_logs(&left, &right, 1e-9);
+        assert_eq!(diff.matched, 2);
+        assert_eq!(diff.differences.len(), 1);
+        assert!(matches!(diff.first_divergence(), Some(LogDifference::Changed { right, .. }) if right.result.as_deref() == Some("w")));
+        assert!(diff_logs(&left, &left, 0.0).is_identical());
+    }
+}
@@ -0,0 +1,115 @@
+//! # Queue Disciplines
+//!
+//! A [`QueueDiscipline`] decides which waiting request a [`crate::Resource`] serves next. The
+//! built-in disciplines cover the classic orders; implement the trait for anything else.
+
+use crate::resource::RequestId;
+use crate::SimRng;
+
+/// A read-only view of a request waiting for a resource.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueuedRequest {
+    pub id: RequestId,
+    pub priority: i64,
+    /// A user-supplied sort key, such as an expected processing time. Defaults to `0.0`.
+    pub key: f64,
+    pub requested_at: f64,
+}
+
+/// Chooses the next request to serve from a resource's wait queue.
+///
+/// # Example
+/// ```
+/// use desru::{QueueDiscipline, QueuedRequest, Resource, SimRng};
+///
+/// /// Serves the request with the largest key first.
+/// struct LongestFirst;
+///
+/// impl QueueDiscipline for LongestFirst {
+///     fn select(&mut self, waiting: &[QueuedRequest], _rng: &mut SimRng) -> usize {
+///         (0..waiting.len()).max_by(|&a, &b| waiting[a].key.total_cmp(&waiting[b].key)).unwrap()
+///     }
+/// }
+///
+/// let resource = Resource::new(1).with_discipline(LongestFirst);
+/// ```
+pub trait QueueDiscipline {
+    /// Returns the position in `waiting` of the request to serve next.
+    ///
+    /// `waiting` is never empty and is ordered by arrival, oldest first.
+    fn select(&mut self, waiting: &[QueuedRequest], rng: &mut SimRng) -> usize;
+}
+
+/// First in, first out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+impl QueueDiscipline for Fifo {
+    fn select(&mut self, _waiting: &[QueuedRequest], _rng: &mut SimRng) -> usize {
+        0
+    }
+}
+
+/// Last in, first out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lifo;
+
+impl QueueDiscipline for Lifo {
+    fn select(&mut self, waiting: &[QueuedRequest], _rng: &mut SimRng) -> usize {
+        waiting.len() - 1
+    }
+}
+
+/// Lowest priority value first, FIFO among equal priorities. The default discipline.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Priority;
+
+impl QueueDiscipline for Priority {
+    fn select(&mut self, waiting: &[QueuedRequest], _rng: &mut SimRng) -> usize {
+        (0..waiting.len()).min_by_key(|&i| waiting[i].priority).unwrap_or(0)
+    }
+}
+
+/// Uniformly random order, drawn from the scheduler's random number generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomOrder;
+
+impl QueueDiscipline for RandomOrder {
+    fn select(&mut self, waiting: &[QueuedRequest], rng: &mut SimRng) -> usize {
+        rng.gen_index(waiting.len())
+    }
+}
+
+/// Smallest key first, FIFO among equal keys. With the key set to the expected service time
+/// this is the shortest-processing-time rule.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShortestProcessingTime;
+
+impl QueueDiscipline for ShortestProcessingTime {
+    fn select(&mut self, waiting: &[QueuedRequest], _rng: &mut SimRng) -> usize {
+        (0..waiting.len()).min_by(|&a, &b| waiting[a].key.total_cmp(&waiting[b].key)).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(keys: &[(i64, f64)]) -> Vec<QueuedRequest> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, &(priority, key))| QueuedRequest { id: RequestId(i as u64), priority, key, requested_at: i as f64 })
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_disciplines() {
+        let waiting = queue(&[(2, 3.0), (1, 5.0), (1, 1.0), (3, 1.0)]);
+        let mut rng = SimRng::new(0);
+        assert_eq!(Fifo.select(&waiting, &mut rng), 0);
+        assert_eq!(Lifo.select(&waiting, &mut rng), 3);
+        assert_eq!(Priority.select(&waiting, &mut rng), 1);
+        assert_eq!(ShortestProcessingTime.select(&waiting, &mut rng), 2);
+        assert!(RandomOrder.select(&waiting, &mut rng) < 4);
+    }
+}
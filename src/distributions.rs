@@ -0,0 +1,432 @@
+//! # Distributions
+//!
+//! Common distributions for interarrival, service, and repair times, sharing the
+//! [`Distribution`] trait so that models can swap one for another without rewriting the
+//! closures that draw from them. Every distribution draws from a [`SimRng`] it is given, so
+//! the same distribution can be sampled from the scheduler's generator, from a named stream,
+//! or from an antithetic generator. [`sampler`] adapts a distribution into the closure taken by
+//! [`crate::Source`] and [`crate::Server`], drawing from a named stream so that each source of
+//! randomness is seeded independently.
+//!
+//! Distributions with a closed-form inverse are sampled by inversion, one uniform per draw,
+//! which keeps common and antithetic random numbers effective.
+
+use crate::{EventScheduler, SimRng};
+
+/// A distribution of non-negative durations.
+pub trait Distribution {
+    /// Draws one value.
+    fn sample(&self, rng: &mut SimRng) -> f64;
+
+    /// Returns the mean of the distribution.
+    fn mean(&self) -> f64;
+}
+
+impl<D: Distribution + ?Sized> Distribution for Box<D> {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        (**self).sample(rng)
+    }
+
+    fn mean(&self) -> f64 {
+        (**self).mean()
+    }
+}
+
+/// Returns a closure drawing from `distribution` on the scheduler's stream named `stream`,
+/// in the form taken by [`crate::Source::new`] and [`crate::Server::new`].
+///
+/// # Example
+/// ```
+/// use desru::{sampler, EventScheduler, Exponential, Queue, Server, Sink, Source};
+///
+/// let mut scheduler = EventScheduler::builder().seed(11).build();
+/// let arrivals = Source::new(sampler(Exponential::new(1.0), "arrivals")).with_limit(200);
+/// let line = Queue::new();
+/// let teller = Server::new(1, sampler(Exponential::new(2.0), "service"));
+/// let exit = Sink::new();
+/// arrivals.connect(&line);
+/// line.connect(&teller);
+/// teller.connect(&exit);
+///
+/// arrivals.start(&mut scheduler);
+/// scheduler.run_until_max_time(1_000.0);
+/// assert_eq!(exit.count(), 200);
+/// ```
+pub fn sampler<D>(distribution: D, stream: &str) -> impl FnMut(&mut EventScheduler) -> f64
+where
+    D: Distribution + 'static,
+{
+    let stream = stream.to_string();
+    move |scheduler| distribution.sample(scheduler.stream(&stream))
+}
+
+/// Draws an exponential variate with unit rate by inversion.
+fn unit_exponential(rng: &mut SimRng) -> f64 {
+    -(1.0 - rng.next_f64()).ln()
+}
+
+/// Draws a standard normal variate with the Box-Muller transform.
+fn standard_normal(rng: &mut SimRng) -> f64 {
+    let (u, v) = (1.0 - rng.next_f64(), rng.next_f64());
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+/// The exponential distribution, for memoryless durations such as Poisson interarrival times.
+///
+/// # Example
+/// ```
+/// use desru::{Distribution, Exponential, SimRng};
+///
+/// let service = Exponential::new(4.0);
+/// assert_eq!(service.mean(), 0.25);
+/// assert!(service.sample(&mut SimRng::new(1)) >= 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exponential {
+    rate: f64,
+}
+
+impl Exponential {
+    /// Creates an exponential distribution with the given rate.
+    ///
+    /// # Panics
+    /// Panics if `rate` is not positive and finite.
+    pub fn new(rate: f64) -> Self {
+        assert!(rate > 0.0 && rate.is_finite(), "the exponential rate must be positive and finite");
+        Exponential { rate }
+    }
+}
+
+impl Distribution for Exponential {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        unit_exponential(rng) / self.rate
+    }
+
+    fn mean(&self) -> f64 {
+        1.0 / self.rate
+    }
+}
+
+/// The Erlang distribution: the sum of `shape` exponential phases with the given rate each,
+/// for multi-stage service.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Erlang {
+    shape: u32,
+    rate: f64,
+}
+
+impl Erlang {
+    /// Creates an Erlang distribution of `shape` phases, each with rate `rate`.
+    ///
+    /// # Panics
+    /// Panics if `shape` is zero or `rate` is not positive and finite.
+    pub fn new(shape: u32, rate: f64) -> Self {
+        assert!(shape > 0, "the Erlang shape must be positive");
+        assert!(rate > 0.0 && rate.is_finite(), "the Erlang rate must be positive and finite");
+        Erlang { shape, rate }
+    }
+}
+
+impl Distribution for Erlang {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        (0..self.shape).map(|_| unit_exponential(rng)).sum::<f64>() / self.rate
+    }
+
+    fn mean(&self) -> f64 {
+        f64::from(self.shape) / self.rate
+    }
+}
+
+/// The lognormal distribution, whose logarithm is normal with mean `mu` and standard
+/// deviation `sigma`; a common fit for repair and task times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogNormal {
+    mu: f64,
+    sigma: f64,
+}
+
+impl LogNormal {
+    /// Creates a lognormal distribution from the parameters of the underlying normal.
+    ///
+    /// # Panics
+    /// Panics if `sigma` is negative or either parameter is not finite.
+    pub fn new(mu: f64, sigma: f64) -> Self {
+        assert!(mu.is_finite() && sigma.is_finite() && sigma >= 0.0, "lognormal parameters must be finite with a non-negative sigma");
+        LogNormal { mu, sigma }
+    }
+
+    /// Creates a lognormal distribution with the given mean and standard deviation of the
+    /// values themselves, which are usually what is measured.
+    ///
+    /// # Panics
+    /// Panics if `mean` is not positive or `sd` is negative.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Distribution, LogNormal};
+    ///
+    /// let repair = LogNormal::from_mean_sd(3.0, 1.5);
+    /// assert!((repair.mean() - 3.0).abs() < 1e-12);
+    /// ```
+    pub fn from_mean_sd(mean: f64, sd: f64) -> Self {
+        assert!(mean > 0.0 && sd >= 0.0, "a lognormal needs a positive mean and a non-negative standard deviation");
+        let variance = (1.0 + (sd / mean).powi(2)).ln();
+        LogNormal::new(mean.ln() - variance / 2.0, variance.sqrt())
+    }
+}
+
+impl Distribution for LogNormal {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        (self.mu + self.sigma * standard_normal(rng)).exp()
+    }
+
+    fn mean(&self) -> f64 {
+        (self.mu + self.sigma * self.sigma / 2.0).exp()
+    }
+}
+
+/// The Weibull distribution, for lifetimes whose failure rate rises (`shape > 1`) or falls
+/// (`shape < 1`) with age.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weibull {
+    shape: f64,
+    scale: f64,
+}
+
+impl Weibull {
+    /// Creates a Weibull distribution with the given shape and scale.
+    ///
+    /// # Panics
+    /// Panics if either parameter is not positive and finite.
+    pub fn new(shape: f64, scale: f64) -> Self {
+        assert!(shape > 0.0 && shape.is_finite() && scale > 0.0 && scale.is_finite(), "Weibull parameters must be positive and finite");
+        Weibull { shape, scale }
+    }
+}
+
+impl Distribution for Weibull {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        self.scale * unit_exponential(rng).powf(1.0 / self.shape)
+    }
+
+    fn mean(&self) -> f64 {
+        self.scale * crate::analysis::ln_gamma(1.0 + 1.0 / self.shape).exp()
+    }
+}
+
+/// Resamples observed values, each equally likely, so a trace of measured durations can drive
+/// a model directly.
+///
+/// # Example
+/// ```
+/// use desru::{Distribution, Empirical, SimRng};
+///
+/// let observed = Empirical::new(vec![2.0, 3.0, 7.0]);
+/// assert_eq!(observed.mean(), 4.0);
+/// assert!([2.0, 3.0, 7.0].contains(&observed.sample(&mut SimRng::new(5))));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Empirical {
+    values: Vec<f64>,
+}
+
+impl Empirical {
+    /// Creates a distribution over the observed `values`.
+    ///
+    /// # Panics
+    /// Panics if `values` is empty or contains a value that is not finite.
+    pub fn new(values: Vec<f64>) -> Self {
+        assert!(!values.is_empty(), "an empirical distribution needs at least one value");
+        assert!(values.iter().all(|v| v.is_finite()), "empirical values must be finite");
+        Empirical { values }
+    }
+
+    /// Returns the observed values.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+}
+
+impl Distribution for Empirical {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        self.values[rng.gen_index(self.values.len())]
+    }
+
+    fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+}
+
+/// A kernel density estimate of observed durations: resamples an observation and adds
+/// Gaussian noise of standard deviation `bandwidth`, so that draws fill the gaps between the
+/// observations instead of repeating them.
+///
+/// Draws that would be negative are reflected at zero, so the distribution stays on the
+/// non-negative durations it models.
+///
+/// # Example
+/// ```
+/// use desru::{read_column, Distribution, KernelDensity, SimRng};
+///
+/// let csv = "minutes\n4.0\n5.5\n6.0\n9.0\n";
+/// let service = KernelDensity::new(read_column(csv.as_bytes(), "minutes").unwrap());
+/// assert!(service.bandwidth() > 0.0);
+/// assert!(service.sample(&mut SimRng::new(8)) >= 0.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelDensity {
+    values: Vec<f64>,
+    bandwidth: f64,
+}
+
+impl KernelDensity {
+    /// Creates an estimate whose bandwidth is chosen by Silverman's rule of thumb,
+    /// `0.9 min(sd, IQR / 1.34) n^(-1/5)`.
+    ///
+    /// # Panics
+    /// Panics if `values` is empty or contains a value that is not finite.
+    pub fn new(values: Vec<f64>) -> Self {
+        assert!(!values.is_empty(), "a kernel density estimate needs at least one value");
+        assert!(values.iter().all(|v| v.is_finite()), "kernel density values must be finite");
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let sd = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0)).sqrt();
+        let mut sorted = values.clone();
+        sorted.sort_by(f64::total_cmp);
+        let quantile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        let iqr = quantile(0.75) - quantile(0.25);
+        let spread = if iqr > 0.0 { sd.min(iqr / 1.34) } else { sd };
+        KernelDensity { values, bandwidth: 0.9 * spread * n.powf(-0.2) }
+    }
+
+    /// Replaces the bandwidth.
+    ///
+    /// # Panics
+    /// Panics if `bandwidth` is negative or not finite.
+    pub fn with_bandwidth(mut self, bandwidth: f64) -> Self {
+        assert!(bandwidth >= 0.0 && bandwidth.is_finite(), "the bandwidth must be finite and non-negative");
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    /// Returns the standard deviation of the kernel.
+    pub fn bandwidth(&self) -> f64 {
+        self.bandwidth
+    }
+}
+
+impl Distribution for KernelDensity {
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        let value = self.values[rng.gen_index(self.values.len())];
+        (value + self.bandwidth * standard_normal(rng)).abs()
+    }
+
+    fn mean(&self) -> f64 {
+        let h = self.bandwidth;
+        let reflected = |v: f64| {
+            if h == 0.0 {
+                return v.abs();
+            }
+            // The mean of |v + hZ|, the folded normal.
+            let z = v / h;
+            v * (1.0 - 2.0 * normal_cdf(-z)) + 2.0 * h * (-z * z / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt()
+        };
+        self.values.iter().map(|&v| reflected(v)).sum::<f64>() / self.values.len() as f64
+    }
+}
+
+/// The standard normal distribution function, accurate to about `1e-7`.
+fn normal_cdf(x: f64) -> f64 {
+    // Abramowitz and Stegun 7.1.26 for erfc(|x| / sqrt(2)).
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs() / std::f64::consts::SQRT_2);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let tail = poly * (-x * x / 2.0).exp() / 2.0;
+    if x < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+/// A weighted mixture of distributions, for populations made of distinct classes, such as
+/// quick and complex jobs.
+///
+/// # Example
+/// ```
+/// use desru::{Distribution, Exponential, Mixture, SimRng};
+///
+/// // 90% of calls are quick, 10% take much longer.
+/// let calls = Mixture::new().with(0.9, Exponential::new(1.0)).with(0.1, Exponential::new(0.1));
+/// assert!((calls.mean() - 1.9).abs() < 1e-12);
+/// assert!(calls.sample(&mut SimRng::new(2)) >= 0.0);
+/// ```
+#[derive(Default)]
+pub struct Mixture {
+    components: Vec<(f64, Box<dyn Distribution>)>,
+}
+
+impl Mixture {
+    /// Creates a mixture with no components.
+    pub fn new() -> Self {
+        Mixture::default()
+    }
+
+    /// Adds a component drawn with relative weight `weight`.
+    ///
+    /// # Panics
+    /// Panics if `weight` is negative or not finite.
+    pub fn with(mut self, weight: f64, distribution: impl Distribution + 'static) -> Self {
+        assert!(weight >= 0.0 && weight.is_finite(), "mixture weights must be finite and non-negative");
+        self.components.push((weight, Box::new(distribution)));
+        self
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.components.iter().map(|(weight, _)| weight).sum()
+    }
+}
+
+impl Distribution for Mixture {
+    /// # Panics
+    /// Panics if the mixture has no component with positive weight.
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        let total = self.total_weight();
+        assert!(total > 0.0, "a mixture needs a component with positive weight");
+        let mut draw = rng.next_f64() * total;
+        let last = self.components.iter().rposition(|(weight, _)| *weight > 0.0).unwrap();
+        let chosen = self.components[..last].iter().position(|(weight, _)| {
+            draw -= weight;
+            draw < 0.0
+        });
+        self.components[chosen.unwrap_or(last)].1.sample(rng)
+    }
+
+    fn mean(&self) -> f64 {
+        self.components.iter().map(|(weight, d)| weight * d.mean()).sum::<f64>() / self.total_weight()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_means_match_distribution_means() {
+        let distributions: Vec<Box<dyn Distribution>> = vec![
+            Box::new(Exponential::new(2.0)),
+            Box::new(Erlang::new(3, 1.5)),
+            Box::new(LogNormal::from_mean_sd(2.0, 0.5)),
+            Box::new(Weibull::new(1.5, 2.0)),
+            Box::new(Empirical::new(vec![1.0, 2.0, 6.0])),
+            Box::new(KernelDensity::new(vec![0.2, 0.5, 1.0, 3.0]).with_bandwidth(0.6)),
+            Box::new(Mixture::new().with(1.0, Exponential::new(1.0)).with(3.0, Erlang::new(2, 1.0))),
+        ];
+        let mut rng = SimRng::new(17);
+        for distribution in &distributions {
+            let n = 50_000;
+            let mean = (0..n).map(|_| distribution.sample(&mut rng)).sum::<f64>() / n as f64;
+            assert!((mean - distribution.mean()).abs() < 0.03 * distribution.mean(), "{} vs {}", mean, distribution.mean());
+        }
+        assert!((Weibull::new(1.0, 2.0).mean() - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,131 @@
+//! # Driving a Run
+//!
+//! [`EventScheduler::drive`] turns a run into an iterator of [`EventRecord`]s, yielded one at
+//! a time as events execute. Nothing runs until the next record is requested, so a `for` loop
+//! can stream results, check them as they arrive, and `break` to leave the rest of the run
+//! pending.
+//!
+//! Yielded records are handed to the caller instead of being added to `event_log`.
+
+use crate::{EventRecord, EventScheduler, SimError, StopCondition, StopReason};
+
+/// An iterator over the events of a run, created by [`EventScheduler::drive`].
+pub struct Drive<'a> {
+    scheduler: &'a mut EventScheduler,
+    stop: StopCondition,
+    finished: bool,
+}
+
+impl<'a> Drive<'a> {
+    /// Returns the scheduler being driven, to inspect between records.
+    pub fn scheduler(&self) -> &EventScheduler {
+        self.scheduler
+    }
+
+    /// Returns the next record, or the error that ended the run.
+    ///
+    /// After an error or the end of the run, returns `None`.
+    ///
+    /// # Errors
+    /// As for [`EventScheduler::try_run`].
+    pub fn try_next(&mut self) -> Option<Result<EventRecord, SimError>> {
+        if self.finished {
+            return None;
+        }
+        let reason = if (self.stop)(self.scheduler) {
+            StopReason::Condition
+        } else {
+            match self.scheduler.execute_next() {
+                Ok(Some((event, result))) => {
+                    if std::mem::take(&mut self.scheduler.pause_requested) {
+                        self.finish(StopReason::Paused);
+                    }
+                    return Some(Ok(EventRecord::new(event, result)));
+                }
+                Ok(None) => StopReason::QueueEmpty,
+                Err(error) => {
+                    self.finish(StopReason::Error(error.clone()));
+                    return Some(Err(error));
+                }
+            }
+        };
+        self.finish(reason);
+        None
+    }
+
+    fn finish(&mut self, reason: StopReason) {
+        self.finished = true;
+        self.scheduler.stop_reason = Some(reason);
+    }
+}
+
+impl Iterator for Drive<'_> {
+    type Item = EventRecord;
+
+    /// Runs the next event and returns its record.
+    ///
+    /// # Panics
+    /// Panics if the run fails; use [`Drive::try_next`] to handle this as an error instead.
+    fn next(&mut self) -> Option<EventRecord> {
+        self.try_next().map(|record| record.unwrap_or_else(|error| panic!("{}", error)))
+    }
+}
+
+impl EventScheduler {
+    /// Returns an iterator that runs one event per item until `stop` is met, the queue
+    /// empties, or an action requests a pause, yielding the record of each executed event.
+    ///
+    /// Records are yielded whether or not logging is on, and are not added to `event_log`.
+    /// When the iterator ends, [`EventScheduler::stop_reason`] tells why; if it is dropped
+    /// early, the remaining events stay pending.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// for t in 1..=100 {
+    ///     scheduler.schedule(ScheduledAction::at(f64::from(t)).with_label("reading").with_action(move |_| Some((t * t).to_string())));
+    /// }
+    ///
+    /// // Stop at the first reading above 50, without running the rest.
+    /// let mut first_high = None;
+    /// for record in scheduler.drive(Box::new(|_| false)) {
+    ///     if record.result.as_deref().is_some_and(|r| r.parse::<u32>().unwrap() > 50) {
+    ///         first_high = Some(record.time);
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(first_high, Some(8.0));
+    /// assert_eq!(scheduler.event_queue.len(), 92);
+    /// assert!(scheduler.event_log.is_empty());
+    /// ```
+    pub fn drive(&mut self, stop: StopCondition) -> Drive<'_> {
+        self.pause_requested = false;
+        Drive { scheduler: self, stop, finished: false }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventScheduler, ScheduledAction, SimError, StopReason};
+
+    #[test]
+    fn test_drive_ends_with_the_stop_reason() {
+        let mut scheduler = EventScheduler::builder().max_events_per_time(2).build();
+        for t in [1.0, 2.0, 3.0, 3.0, 3.0] {
+            scheduler.schedule(ScheduledAction::at(t));
+        }
+        let mut drive = scheduler.drive(Box::new(|s| s.current_time >= 2.0));
+        let times: Vec<f64> = drive.by_ref().map(|record| record.time).collect();
+        assert_eq!(times, [1.0, 2.0]);
+        assert_eq!(drive.scheduler().stop_reason(), Some(&StopReason::Condition));
+
+        let mut drive = scheduler.drive(Box::new(|_| false));
+        assert!(drive.try_next().unwrap().is_ok());
+        assert!(drive.try_next().unwrap().is_ok());
+        assert_eq!(drive.try_next().unwrap().unwrap_err(), SimError::ZeroDelayCascade { time: 3.0, limit: 2 });
+        assert!(drive.try_next().is_none());
+        assert_eq!(scheduler.event_queue.len(), 1);
+    }
+}
@@ -0,0 +1,114 @@
+//! # Embedding
+//!
+//! When the scheduler is driven from another runtime, such as JavaScript in a browser, some
+//! actions live in the host and cannot be Rust closures. An external event scheduled with
+//! [`EventScheduler::schedule_external`] carries only a name and a context; when it comes due,
+//! [`EventScheduler::run_until_external`] stops and hands it to the host as an
+//! [`ExternalCall`]. The host runs its callback, which may schedule further events, and calls
+//! `run_until_external` again to continue. Log entries are streamed back incrementally with
+//! [`EventScheduler::log_since`], so a host can render a run while it progresses.
+//!
+//! This loop is the interface a WebAssembly build would expose to JavaScript. The crate does
+//! not provide that build: it has no `wasm` feature and does not depend on `wasm-bindgen`, so
+//! the JavaScript glue belongs in a separate crate.
+
+use crate::{Context, EventId, EventRecord, EventScheduler, ScheduledAction};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// An external event that has come due, see [`EventScheduler::run_until_external`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalCall {
+    pub id: EventId,
+    pub time: f64,
+    pub name: String,
+    pub context: Context,
+}
+
+impl EventScheduler {
+    /// Schedules an event at `time` whose action is run by the host, labeled with `name`.
+    pub fn schedule_external(&mut self, time: f64, name: impl Into<String>, context: Context) -> EventId {
+        let name = name.into();
+        let mut call = Some((name.clone(), context.clone()));
+        // The id is only known once the event is queued.
+        let own_id = Rc::new(Cell::new(EventId(0)));
+        let id = own_id.clone();
+        let event_id = self.schedule(ScheduledAction::at(time).with_label(name).with_context(context).with_action(move |s| {
+            if let Some((name, context)) = call.take() {
+                s.external_calls.push_back(ExternalCall { id: id.get(), time: s.current_time, name, context });
+            }
+            None
+        }));
+        own_id.set(event_id);
+        event_id
+    }
+
+    /// Runs events until an external event comes due or the next event would occur at or
+    /// after `max_time`.
+    ///
+    /// # Returns
+    /// The external call for the host to handle, or `None` once the horizon is reached, no
+    /// events are left, or the run paused or used up its wall-clock budget.
+    ///
+    /// # Panics
+    /// Panics if `max_events_per_time` is exceeded or an event was scheduled at a NaN time.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Context, EventScheduler};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule_external(1.0, "render", Context::new());
+    /// let mut handled = Vec::new();
+    /// let mut cursor = 0;
+    /// while let Some(call) = scheduler.run_until_external(5.0) {
+    ///     // The host's callback: draw a frame, then ask for the next one.
+    ///     handled.push((call.name, call.time));
+    ///     let next = scheduler.current_time + 1.0;
+    ///     scheduler.schedule_external(next, "render", Context::new());
+    ///     assert_eq!(scheduler.log_since(&mut cursor).len(), 1);
+    /// }
+    /// assert_eq!(handled.len(), 4);
+    /// assert_eq!(handled[3], ("render".to_string(), 4.0));
+    /// ```
+    pub fn run_until_external(&mut self, max_time: f64) -> Option<ExternalCall> {
+        let horizon = crate::stop_at_max_time_factory(max_time);
+        let stop = |s: &EventScheduler| !s.external_calls.is_empty() || horizon(s);
+        self.try_run_observed(stop, &crate::LogPolicy::Full, |_, _, _| {}).unwrap_or_else(|error| panic!("{}", error));
+        self.external_calls.pop_front()
+    }
+
+    /// Returns the log entries recorded since `cursor` and advances it past them.
+    ///
+    /// Start with a cursor of `0` to stream the whole log.
+    pub fn log_since(&self, cursor: &mut usize) -> &[EventRecord] {
+        let start = (*cursor).min(self.event_log.len());
+        *cursor = self.event_log.len();
+        &self.event_log[start..]
+    }
+}
+
+/// External calls that have come due but not been handed to the host.
+pub(crate) type ExternalCalls = VecDeque<ExternalCall>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_events_run_between_external_calls() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.timeout(1.0, Some(Box::new(|_| Some("rust".to_string()))), None);
+        let id = scheduler.schedule_external(2.0, "host", Context::from([("frame".to_string(), "1".to_string())]));
+        let late = scheduler.schedule_external(9.0, "late", Context::new());
+
+        let call = scheduler.run_until_external(5.0).unwrap();
+        assert_eq!((call.id, call.time, call.context["frame"].as_str()), (id, 2.0, "1"));
+        let mut cursor = 0;
+        assert_eq!(scheduler.log_since(&mut cursor).len(), 2);
+        assert!(scheduler.log_since(&mut cursor).is_empty());
+        assert_eq!(scheduler.run_until_external(5.0), None);
+        assert!(scheduler.is_pending(late));
+    }
+}
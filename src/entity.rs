@@ -0,0 +1,156 @@
+//! # Entities
+//!
+//! An [`Entity`] is something that flows through a model, such as a customer, a job, or a
+//! packet. The scheduler's [`EntityTracker`] hands out entity ids and records the time of each
+//! entity's lifecycle [`Milestone`]s, from which waiting and cycle times follow directly.
+//!
+//! Milestones are recorded by the queueing network blocks, [`crate::Server`] and [`crate::Sink`],
+//! which know the entity they handle, and by models calling [`EventScheduler::record_milestone`]. A
+//! [`crate::Resource`] request does not name an entity, so a model built on resources records
+//! [`Milestone::StartedService`] in its grant callback and [`Milestone::Departed`] when it
+//! releases the unit.
+
+use crate::stats::Tally;
+use crate::EventScheduler;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Uniquely identifies an entity within one scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(pub u64);
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entity-{}", self.0)
+    }
+}
+
+/// An entity and the simulation time at which it was created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entity {
+    pub id: EntityId,
+    pub created: f64,
+}
+
+/// A point in an entity's lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Milestone {
+    Created,
+    StartedService,
+    Departed,
+    /// A model-specific milestone.
+    Custom(String),
+}
+
+/// Records the milestones of every entity created through the scheduler.
+#[derive(Debug, Clone, Default)]
+pub struct EntityTracker {
+    next_id: u64,
+    milestones: HashMap<EntityId, Vec<(Milestone, f64)>>,
+}
+
+impl EntityTracker {
+    /// Creates an empty tracker.
+    pub fn new() -> Self {
+        EntityTracker::default()
+    }
+
+    /// Returns the number of entities created.
+    pub fn count(&self) -> usize {
+        self.next_id as usize
+    }
+
+    /// Returns the recorded milestones of an entity in the order they occurred.
+    pub fn milestones(&self, id: EntityId) -> &[(Milestone, f64)] {
+        self.milestones.get(&id).map_or(&[], |m| m.as_slice())
+    }
+
+    /// Returns the time at which an entity first reached `milestone`.
+    pub fn time_of(&self, id: EntityId, milestone: &Milestone) -> Option<f64> {
+        self.milestones(id).iter().find(|(m, _)| m == milestone).map(|&(_, t)| t)
+    }
+
+    /// Returns the time between two milestones for every entity that reached both, in
+    /// order of entity id.
+    pub fn durations(&self, from: &Milestone, to: &Milestone) -> Tally {
+        let mut ids: Vec<_> = self.milestones.keys().copied().collect();
+        ids.sort();
+        let mut tally = Tally::new();
+        for id in ids {
+            if let (Some(start), Some(end)) = (self.time_of(id, from), self.time_of(id, to)) {
+                tally.record(end - start);
+            }
+        }
+        tally
+    }
+
+    /// Returns the time from creation to the start of service for every entity served.
+    pub fn waiting_times(&self) -> Tally {
+        self.durations(&Milestone::Created, &Milestone::StartedService)
+    }
+
+    /// Returns the time from creation to departure for every departed entity.
+    pub fn cycle_times(&self) -> Tally {
+        self.durations(&Milestone::Created, &Milestone::Departed)
+    }
+}
+
+impl EventScheduler {
+    /// Creates a new entity at the current time and records its [`Milestone::Created`] milestone.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, Milestone};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let customer = scheduler.create_entity();
+    /// scheduler.timeout(2.0, Some(Box::new(move |s| {
+    ///     s.record_milestone(customer.id, Milestone::StartedService);
+    ///     None
+    /// })), None);
+    /// scheduler.timeout(5.0, Some(Box::new(move |s| {
+    ///     s.record_milestone(customer.id, Milestone::Departed);
+    ///     None
+    /// })), None);
+    /// scheduler.run_until_max_time(10.0);
+    ///
+    /// assert_eq!(scheduler.entities.waiting_times().values(), &[2.0]);
+    /// assert_eq!(scheduler.entities.cycle_times().values(), &[5.0]);
+    /// ```
+    pub fn create_entity(&mut self) -> Entity {
+        let id = EntityId(self.entities.next_id);
+        self.entities.next_id += 1;
+        self.entities.milestones.insert(id, vec![(Milestone::Created, self.current_time)]);
+        Entity { id, created: self.current_time }
+    }
+
+    /// Records that an entity reached `milestone` at the current time.
+    pub fn record_milestone(&mut self, id: EntityId, milestone: Milestone) {
+        self.entities.milestones.entry(id).or_default().push((milestone, self.current_time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_milestones_and_durations() {
+        let mut scheduler = EventScheduler::new();
+        let a = scheduler.create_entity();
+        scheduler.current_time = 1.0;
+        let b = scheduler.create_entity();
+        scheduler.current_time = 3.0;
+        scheduler.record_milestone(b.id, Milestone::Custom("triaged".to_string()));
+        scheduler.record_milestone(a.id, Milestone::Departed);
+
+        assert_eq!(scheduler.entities.count(), 2);
+        assert_eq!(b.created, 1.0);
+        assert_eq!(scheduler.entities.milestones(b.id).len(), 2);
+        assert_eq!(scheduler.entities.cycle_times().values(), &[3.0]);
+        assert_eq!(
+            scheduler.entities.durations(&Milestone::Created, &Milestone::Custom("triaged".to_string())).values(),
+            &[2.0]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! # Errors
+//!
+//! [`SimError`] describes the ways a simulation run can fail.
+
+use crate::context::display_pairs;
+use crate::Context;
+use std::error::Error;
+use std::fmt;
+
+/// An error raised while running a simulation.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SimError {
+    /// More events than the configured limit ran at a single timestamp, which usually means
+    /// events keep scheduling each other with zero delay and time can never advance.
+    ZeroDelayCascade {
+        /// The timestamp at which the limit was reached.
+        time: f64,
+        /// The configured maximum number of events per timestamp.
+        limit: usize,
+    },
+    /// An event was scheduled by the name of an action that was never registered.
+    UnknownAction {
+        name: String,
+    },
+    /// The clock was asked to move, or an event to be scheduled, before the current time.
+    CausalityViolation {
+        /// The requested time.
+        time: f64,
+        /// The current time.
+        now: f64,
+    },
+    /// The clock was asked to move to a time that cannot be ordered, such as NaN.
+    InvalidAdvance {
+        /// The requested time.
+        time: f64,
+    },
+    /// A run that was to move the clock to `time` stopped early, because an action requested a
+    /// pause or the wall-clock budget ran out. The clock stays at `now`, where the run stopped.
+    Interrupted {
+        /// The time the run was to reach.
+        time: f64,
+        /// The current time.
+        now: f64,
+    },
+    /// No member of a federation could advance, because each was waiting on the others.
+    FederationDeadlock {
+        /// The earliest time among the members.
+        time: f64,
+    },
+    /// An event was scheduled at a time that cannot be ordered, such as NaN from a division by
+    /// zero in a delay. The event is not queued.
+    InvalidEventTime {
+        time: f64,
+        /// The label of the event.
+        label: Option<String>,
+        /// The context of the event, boxed to keep the error small.
+        context: Box<Context>,
+    },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::ZeroDelayCascade { time, limit } => write!(
+                f,
+                "more than {} events scheduled at time {}; possible unbounded zero-delay cascade",
+                limit, time
+            ),
+            SimError::UnknownAction { name } => write!(f, "no action registered under the name `{}`", name),
+            SimError::CausalityViolation { time, now } => write!(f, "time {} is before the current time {}", time, now),
+            SimError::InvalidAdvance { time } => write!(f, "the clock cannot be moved to time {}", time),
+            SimError::Interrupted { time, now } => write!(f, "the run stopped at time {} before reaching time {}", now, time),
+            SimError::FederationDeadlock { time } => write!(f, "federation deadlocked at time {}; no member can advance", time),
+            SimError::InvalidEventTime { time, label, context } => {
+                write!(f, "event `{}` was scheduled at time {}, which cannot be ordered", label.as_deref().unwrap_or("unlabeled"), time)?;
+                if !context.is_empty() {
+                    write!(f, " (context: {})", display_pairs(context))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for SimError {}
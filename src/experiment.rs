@@ -0,0 +1,307 @@
+//! # Experiments
+//!
+//! An [`Experiment`] runs replications of a [`SimModel`] under each of several scenarios and
+//! collects the metrics each run reports into a tidy [`ExperimentResults`] table with one row
+//! per scenario, replication, and metric.
+//!
+//! Replication `r` uses the same seed in every scenario, so scenarios are compared under common
+//! random numbers. Seeds are hashed from the configuration's seed unless another
+//! [`SeedStrategy`] is chosen. With the `rayon` feature, [`Experiment::run_parallel`] spreads the runs
+//! across threads and returns exactly the same table as [`Experiment::run`].
+
+use crate::csv::{csv_field, json_number, json_string};
+use crate::{SeedStrategy, SimConfig, SimModel, Simulation, Tally};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// A set of scenarios, each run for the same number of replications.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Experiment, ScheduledAction, SimConfig, SimModel};
+///
+/// // Counts arrivals of a Poisson process with the given rate.
+/// struct Arrivals {
+///     rate: f64,
+///     count: f64,
+/// }
+///
+/// impl SimModel for Arrivals {
+///     type Output = Vec<(String, f64)>;
+///
+///     fn init(&mut self, scheduler: &mut EventScheduler) {
+///         scheduler.schedule(ScheduledAction::at(0.0).with_label("arrival"));
+///     }
+///
+///     fn on_event(&mut self, scheduler: &mut EventScheduler, _: &ScheduledAction, _: &Option<String>) {
+///         self.count += 1.0;
+///         let gap = -(1.0 - scheduler.rng.next_f64()).ln() / self.rate;
+///         scheduler.schedule(ScheduledAction::at(scheduler.current_time + gap).with_label("arrival"));
+///     }
+///
+///     fn finalize(&mut self, _: &mut EventScheduler) -> Self::Output {
+///         vec![("arrivals".to_string(), self.count)]
+///     }
+/// }
+///
+/// let experiment = Experiment::grid(SimConfig::new(100.0), &[("rate", &[1.0, 2.0])]).replications(10);
+/// let results = experiment.run(|params| Arrivals { rate: params["rate"], count: 0.0 });
+/// assert_eq!(results.rows().len(), 20);
+/// let slow = results.tally("rate=1", "arrivals").mean();
+/// let fast = results.tally("rate=2", "arrivals").mean();
+/// assert!(fast > slow);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Experiment<P> {
+    config: SimConfig,
+    scenarios: Vec<(String, P)>,
+    replications: usize,
+    seeds: SeedStrategy,
+}
+
+impl<P> Experiment<P> {
+    /// Creates an experiment with no scenarios and one replication per scenario.
+    ///
+    /// # Parameters
+    /// - `config`: The configuration shared by every run. Its seed is the base from which the
+    ///   replication seeds are derived.
+    pub fn new(config: SimConfig) -> Self {
+        let seeds = SeedStrategy::Hashed(config.seed);
+        Experiment { config, scenarios: Vec::new(), replications: 1, seeds }
+    }
+
+    /// Adds a named scenario with its parameters.
+    pub fn scenario(mut self, name: impl Into<String>, parameters: P) -> Self {
+        self.scenarios.push((name.into(), parameters));
+        self
+    }
+
+    /// Sets the number of replications run for each scenario.
+    pub fn replications(mut self, replications: usize) -> Self {
+        self.replications = replications;
+        self
+    }
+
+    /// Sets how replication seeds are chosen.
+    pub fn seed_strategy(mut self, seeds: SeedStrategy) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    /// Returns the scenarios in the order they were added.
+    pub fn scenarios(&self) -> &[(String, P)] {
+        &self.scenarios
+    }
+
+    /// Returns the seed used by replication `replication` of every scenario.
+    pub fn seed(&self, replication: usize) -> u64 {
+        self.seeds.seed(replication)
+    }
+
+    /// Runs every replication of every scenario, one after another.
+    ///
+    /// # Parameters
+    /// - `make_model`: Builds a fresh model for one run from the scenario's parameters.
+    pub fn run<M, F, K>(&self, make_model: F) -> ExperimentResults
+    where
+        F: Fn(&P) -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        let rows = (0..self.scenarios.len() * self.replications).flat_map(|run| self.run_one(run, &make_model)).collect();
+        ExperimentResults { rows }
+    }
+
+    /// Runs every replication of every scenario in parallel. Requires the `rayon` feature.
+    ///
+    /// Each run builds its own scheduler and model on the thread that executes it, so only the
+    /// parameters, the model factory, and the metrics need to cross threads.
+    #[cfg(feature = "rayon")]
+    pub fn run_parallel<M, F, K>(&self, make_model: F) -> ExperimentResults
+    where
+        P: Sync,
+        F: Fn(&P) -> M + Sync,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        use rayon::prelude::*;
+
+        let runs: Vec<Vec<ExperimentRow>> =
+            (0..self.scenarios.len() * self.replications).into_par_iter().map(|run| self.run_one(run, &make_model)).collect();
+        ExperimentResults { rows: runs.into_iter().flatten().collect() }
+    }
+
+    fn run_one<M, F, K>(&self, run: usize, make_model: &F) -> Vec<ExperimentRow>
+    where
+        F: Fn(&P) -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        let (name, parameters) = &self.scenarios[run / self.replications];
+        let replication = run % self.replications;
+        let seed = self.seed(replication);
+        let config = self.config.clone().with_seed(seed);
+        let output = Simulation::run(&mut make_model(parameters), &config);
+        output
+            .into_iter()
+            .map(|(metric, value)| ExperimentRow { scenario: name.clone(), replication, seed, metric: metric.into(), value })
+            .collect()
+    }
+}
+
+impl Experiment<BTreeMap<String, f64>> {
+    /// Creates an experiment with one scenario for every combination of factor levels.
+    ///
+    /// Scenarios are named like `servers=2,rate=0.5`, with factors in the order given.
+    ///
+    /// # Parameters
+    /// - `config`: The configuration shared by every run.
+    /// - `factors`: Each factor's name and the levels to sweep.
+    pub fn grid(config: SimConfig, factors: &[(&str, &[f64])]) -> Self {
+        let mut combinations: Vec<Vec<(&str, f64)>> = vec![Vec::new()];
+        for (name, levels) in factors {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|prefix| {
+                    levels.iter().map(move |&level| {
+                        let mut combination = prefix.clone();
+                        combination.push((name, level));
+                        combination
+                    })
+                })
+                .collect();
+        }
+        let mut experiment = Experiment::new(config);
+        for combination in combinations {
+            let label: Vec<String> = combination.iter().map(|(name, level)| format!("{}={}", name, level)).collect();
+            let parameters = combination.iter().map(|(name, level)| (name.to_string(), *level)).collect();
+            experiment = experiment.scenario(label.join(","), parameters);
+        }
+        experiment
+    }
+}
+
+/// One metric reported by one run of an experiment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentRow {
+    pub scenario: String,
+    pub replication: usize,
+    pub seed: u64,
+    pub metric: String,
+    pub value: f64,
+}
+
+/// The metrics reported by every run of an experiment, ordered by scenario, then replication.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExperimentResults {
+    rows: Vec<ExperimentRow>,
+}
+
+impl ExperimentResults {
+    /// Returns every row of the table.
+    pub fn rows(&self) -> &[ExperimentRow] {
+        &self.rows
+    }
+
+    /// Collects the values of one metric across the replications of one scenario.
+    pub fn tally(&self, scenario: &str, metric: &str) -> Tally {
+        let mut tally = Tally::new();
+        for row in self.rows.iter().filter(|row| row.scenario == scenario && row.metric == metric) {
+            tally.record(row.value);
+        }
+        tally
+    }
+
+    /// Writes the table as CSV with columns `scenario,replication,seed,metric,value`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "scenario,replication,seed,metric,value")?;
+        for row in &self.rows {
+            writeln!(writer, "{},{},{},{},{}", csv_field(&row.scenario), row.replication, row.seed, csv_field(&row.metric), row.value)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the table as a JSON array with one object per row.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "[")?;
+        for (i, row) in self.rows.iter().enumerate() {
+            let separator = if i + 1 < self.rows.len() { "," } else { "" };
+            writeln!(
+                writer,
+                "  {{\"scenario\": {}, \"replication\": {}, \"seed\": {}, \"metric\": {}, \"value\": {}}}{}",
+                json_string(&row.scenario),
+                row.replication,
+                row.seed,
+                json_string(&row.metric),
+                json_number(Some(row.value)),
+                separator
+            )?;
+        }
+        writeln!(writer, "]")
+    }
+
+    /// Returns the distinct metric names in the order they first appear.
+    pub fn metrics(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for row in &self.rows {
+            if !names.contains(&row.metric.as_str()) {
+                names.push(&row.metric);
+            }
+        }
+        names
+    }
+
+    /// Returns the distinct scenario names in order.
+    pub fn scenarios(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for row in &self.rows {
+            if names.last() != Some(&row.scenario.as_str()) {
+                names.push(&row.scenario);
+            }
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventScheduler;
+
+    struct Draw;
+
+    impl SimModel for Draw {
+        type Output = [(&'static str, f64); 1];
+
+        fn init(&mut self, _: &mut EventScheduler) {}
+
+        fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+            [("draw", scheduler.rng.next_f64())]
+        }
+    }
+
+    #[test]
+    fn test_common_random_numbers_across_scenarios() {
+        let experiment = Experiment::new(SimConfig::new(1.0)).scenario("a", ()).scenario("b", ()).replications(3);
+        let results = experiment.run(|_| Draw);
+        let a = results.tally("a", "draw");
+        let b = results.tally("b", "draw");
+        assert_eq!(a.values(), b.values());
+        assert_ne!(a.values()[0], a.values()[1]);
+
+        let mut csv = Vec::new();
+        results.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 7);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let experiment = Experiment::grid(SimConfig::new(1.0), &[("x", &[1.0, 2.0]), ("y", &[3.0])]).replications(4);
+        assert_eq!(experiment.scenarios()[1].0, "x=2,y=3");
+        assert_eq!(experiment.run(|_| Draw), experiment.run_parallel(|_| Draw));
+    }
+}
@@ -0,0 +1,206 @@
+//! # C Interface
+//!
+//! With the `ffi` feature, the crate exports a small `extern "C"` API so that it can serve as
+//! the discrete-event kernel of a C or C++ tool. Build a linkable library with
+//! `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`) and declare:
+//!
+//! ```c
+//! typedef struct DesruScheduler DesruScheduler;
+//! typedef void (*DesruCallback)(DesruScheduler *scheduler, void *user_data);
+//! typedef struct { uint64_t id; double time; } DesruLogEntry;
+//!
+//! DesruScheduler *desru_scheduler_new(uint64_t seed);
+//! void desru_scheduler_free(DesruScheduler *scheduler);
+//! double desru_now(const DesruScheduler *scheduler);
+//! uint64_t desru_schedule(DesruScheduler *scheduler, double time, DesruCallback callback, void *user_data);
+//! bool desru_cancel(DesruScheduler *scheduler, uint64_t id);
+//! int32_t desru_run_until(DesruScheduler *scheduler, double max_time, size_t *log_len);
+//! bool desru_log_poll(const DesruScheduler *scheduler, size_t *cursor, DesruLogEntry *entry);
+//! ```
+//!
+//! Callbacks receive the scheduler they run on and may schedule and cancel events through it;
+//! `user_data` is passed through untouched and remains owned by the caller.
+//!
+//! `desru_run_until` returns `DESRU_OK` (0), `DESRU_SIM_ERROR` (1) if the run failed, for
+//! example on an event scheduled at a NaN time, or `DESRU_PANIC` (2) if the run panicked.
+//! Panics are caught, since unwinding into C aborts the host process.
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+
+/// The status of a run that completed.
+pub const DESRU_OK: i32 = 0;
+/// The status of a run that failed with a [`crate::SimError`].
+pub const DESRU_SIM_ERROR: i32 = 1;
+/// The status of a run that panicked. The scheduler may be left part way through an event and
+/// should only be freed.
+pub const DESRU_PANIC: i32 = 2;
+
+/// A C callback run as an event action.
+pub type DesruCallback = extern "C" fn(scheduler: *mut EventScheduler, user_data: *mut c_void);
+
+/// A log entry returned by [`desru_log_poll`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DesruLogEntry {
+    pub id: u64,
+    pub time: f64,
+}
+
+/// Creates a scheduler seeded with `seed`. Free it with [`desru_scheduler_free`].
+#[no_mangle]
+pub extern "C" fn desru_scheduler_new(seed: u64) -> *mut EventScheduler {
+    Box::into_raw(Box::new(EventScheduler::builder().seed(seed).build()))
+}
+
+/// Frees a scheduler created by [`desru_scheduler_new`]. Null is ignored.
+///
+/// # Safety
+/// `scheduler` must be null or a pointer returned by `desru_scheduler_new` that has not been
+/// freed, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn desru_scheduler_free(scheduler: *mut EventScheduler) {
+    if !scheduler.is_null() {
+        drop(Box::from_raw(scheduler));
+    }
+}
+
+/// Returns the scheduler's current time.
+///
+/// # Safety
+/// `scheduler` must be a live pointer returned by `desru_scheduler_new`.
+#[no_mangle]
+pub unsafe extern "C" fn desru_now(scheduler: *const EventScheduler) -> f64 {
+    (*scheduler).current_time
+}
+
+/// Schedules `callback(scheduler, user_data)` to run at `time`.
+///
+/// # Returns
+/// The event's id, for [`desru_cancel`].
+///
+/// # Safety
+/// `scheduler` must be a live pointer returned by `desru_scheduler_new`, and `user_data` must
+/// stay valid until the callback has run or been cancelled.
+#[no_mangle]
+pub unsafe extern "C" fn desru_schedule(scheduler: *mut EventScheduler, time: f64, callback: DesruCallback, user_data: *mut c_void) -> u64 {
+    let event = ScheduledAction::at(time).with_action(move |s| {
+        callback(s as *mut EventScheduler, user_data);
+        None
+    });
+    (*scheduler).schedule(event).0
+}
+
+/// Cancels a pending event.
+///
+/// # Returns
+/// `true` if the event was pending.
+///
+/// # Safety
+/// `scheduler` must be a live pointer returned by `desru_scheduler_new`.
+#[no_mangle]
+pub unsafe extern "C" fn desru_cancel(scheduler: *mut EventScheduler, id: u64) -> bool {
+    (*scheduler).cancel(EventId(id))
+}
+
+/// Runs events until the next event would occur at or after `max_time`, and writes the
+/// number of log entries after the run to `*log_len` unless it is null.
+///
+/// # Returns
+/// [`DESRU_OK`], [`DESRU_SIM_ERROR`], or [`DESRU_PANIC`].
+///
+/// # Safety
+/// `scheduler` must be a live pointer returned by `desru_scheduler_new`, and must not be
+/// running already: callbacks may schedule and cancel, but not run. `log_len` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn desru_run_until(scheduler: *mut EventScheduler, max_time: f64, log_len: *mut usize) -> i32 {
+    let scheduler = &mut *scheduler;
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| scheduler.try_run(crate::stop_at_max_time_factory(max_time), None).map(|_| ())));
+    if !log_len.is_null() {
+        *log_len = scheduler.event_log.len();
+    }
+    match outcome {
+        Ok(Ok(())) => DESRU_OK,
+        Ok(Err(_)) => DESRU_SIM_ERROR,
+        Err(_) => DESRU_PANIC,
+    }
+}
+
+/// Copies the log entry at `*cursor` into `*entry` and advances the cursor.
+///
+/// # Returns
+/// `false`, leaving `*entry` untouched, once the cursor has reached the end of the log.
+///
+/// # Safety
+/// `scheduler` must be a live pointer returned by `desru_scheduler_new`, and `cursor` and
+/// `entry` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn desru_log_poll(scheduler: *const EventScheduler, cursor: *mut usize, entry: *mut DesruLogEntry) -> bool {
+    let scheduler = &*scheduler;
+    let Some(record) = scheduler.event_log.get(*cursor) else {
+        return false;
+    };
+    *entry = DesruLogEntry { id: record.id.0, time: record.time };
+    *cursor += 1;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn ping(scheduler: *mut EventScheduler, user_data: *mut c_void) {
+        unsafe {
+            let count = &mut *(user_data as *mut u32);
+            *count += 1;
+            let next = desru_now(scheduler) + 1.0;
+            desru_schedule(scheduler, next, ping, user_data);
+        }
+    }
+
+    #[test]
+    fn test_callbacks_reschedule_through_the_c_api() {
+        let mut count = 0_u32;
+        unsafe {
+            let scheduler = desru_scheduler_new(1);
+            desru_schedule(scheduler, 0.0, ping, &mut count as *mut u32 as *mut c_void);
+            let never = desru_schedule(scheduler, 100.0, ping, std::ptr::null_mut());
+            assert!(desru_cancel(scheduler, never));
+            let mut log_len = 0;
+            assert_eq!(desru_run_until(scheduler, 3.5, &mut log_len), DESRU_OK);
+            assert_eq!(log_len, 4);
+
+            let (mut cursor, mut entry) = (0, DesruLogEntry::default());
+            let mut times = Vec::new();
+            while desru_log_poll(scheduler, &mut cursor, &mut entry) {
+                times.push(entry.time);
+            }
+            desru_scheduler_free(scheduler);
+            assert_eq!(times, [0.0, 1.0, 2.0, 3.0]);
+        }
+        assert_eq!(count, 4);
+    }
+
+    extern "C" fn nan_delay(scheduler: *mut EventScheduler, _: *mut c_void) {
+        unsafe {
+            desru_schedule(scheduler, f64::NAN, nan_delay, std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_run_errors_become_status_codes() {
+        unsafe {
+            let scheduler = desru_scheduler_new(1);
+            desru_schedule(scheduler, 1.0, nan_delay, std::ptr::null_mut());
+            let mut log_len = 0;
+            assert_eq!(desru_run_until(scheduler, 10.0, &mut log_len), DESRU_SIM_ERROR);
+            assert_eq!(log_len, 1);
+
+            (*scheduler).schedule(ScheduledAction::at(2.0).with_action(|_| panic!("model bug")));
+            assert_eq!(desru_run_until(scheduler, 10.0, std::ptr::null_mut()), DESRU_PANIC);
+            desru_scheduler_free(scheduler);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! # Event-Flow Graphs
+//!
+//! While a simulation runs, the scheduler records which labeled events schedule which other
+//! labeled events. The resulting [`EventGraph`] describes the structure of the model as
+//! observed in the run, and can be exported as a Mermaid state diagram or a Graphviz DOT graph.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// Statistics for one edge of the event-flow graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EdgeStats {
+    /// How many times the source event scheduled the target event.
+    pub count: u64,
+    /// The sum of the delays between the source's execution and the target's scheduled time.
+    pub total_delay: f64,
+}
+
+impl EdgeStats {
+    /// Returns the average delay along this edge.
+    pub fn mean_delay(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_delay / self.count as f64
+        }
+    }
+}
+
+/// The observed "schedules" relation between event labels.
+///
+/// Labeled events scheduled from outside any event, such as during model set-up, are recorded
+/// as entry points.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduledAction};
+///
+/// fn park(s: &mut EventScheduler) {
+///     let event = ScheduledAction::new(s.current_time + 5.0, Some(Box::new(|s| { drive(s); None })), None);
+///     s.schedule(event.with_label("Drive"));
+/// }
+///
+/// fn drive(s: &mut EventScheduler) {
+///     let event = ScheduledAction::new(s.current_time + 2.0, Some(Box::new(|s| { park(s); None })), None);
+///     s.schedule(event.with_label("Park"));
+/// }
+///
+/// let mut scheduler = EventScheduler::new();
+/// scheduler.schedule(ScheduledAction::new(0.0, Some(Box::new(|s| { park(s); None })), None).with_label("Park"));
+/// scheduler.run_until_max_time(15.0);
+///
+/// let mut diagram = Vec::new();
+/// scheduler.event_graph.write_mermaid(&mut diagram).unwrap();
+/// let diagram = String::from_utf8(diagram).unwrap();
+/// assert!(diagram.contains("[*] --> Park"));
+/// assert!(diagram.contains("Park --> Drive: 5"));
+/// assert!(diagram.contains("Drive --> Park: 2"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventGraph {
+    entries: BTreeSet<String>,
+    edges: BTreeMap<String, BTreeMap<String, EdgeStats>>,
+}
+
+impl EventGraph {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        EventGraph::default()
+    }
+
+    /// Records that an event labeled `to` was scheduled, `delay` ahead, by one labeled `from`,
+    /// or from outside any labeled event if `from` is `None`.
+    pub fn record(&mut self, from: Option<&str>, to: &str, delay: f64) {
+        let Some(from) = from else {
+            if !self.entries.contains(to) {
+                self.entries.insert(to.to_string());
+            }
+            return;
+        };
+        let targets = match self.edges.get_mut(from) {
+            Some(targets) => targets,
+            None => self.edges.entry(from.to_string()).or_default(),
+        };
+        let stats = match targets.get_mut(to) {
+            Some(stats) => stats,
+            None => targets.entry(to.to_string()).or_default(),
+        };
+        stats.count += 1;
+        stats.total_delay += delay;
+    }
+
+    /// Returns the labels scheduled from outside any labeled event.
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Returns every `(from, to, stats)` edge, sorted by label.
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str, &EdgeStats)> {
+        self.edges
+            .iter()
+            .flat_map(|(from, targets)| targets.iter().map(move |(to, stats)| (from.as_str(), to.as_str(), stats)))
+    }
+
+    /// Writes the graph as a Mermaid state diagram, annotating edges with their mean delay.
+    pub fn write_mermaid<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "stateDiagram-v2")?;
+        for entry in self.entries() {
+            writeln!(writer, "    [*] --> {}", mermaid_id(entry))?;
+        }
+        for (from, to, stats) in self.edges() {
+            writeln!(writer, "    {} --> {}: {}", mermaid_id(from), mermaid_id(to), stats.mean_delay())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the graph in Graphviz DOT format, labeling edges with count and mean delay.
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "digraph events {{")?;
+        if !self.entries.is_empty() {
+            writeln!(writer, "    \"[*]\" [shape=point];")?;
+        }
+        for entry in self.entries() {
+            writeln!(writer, "    \"[*]\" -> {};", dot_id(entry))?;
+        }
+        for (from, to, stats) in self.edges() {
+            writeln!(
+                writer,
+                "    {} -> {} [label=\"n={} mean={}\"];",
+                dot_id(from),
+                dot_id(to),
+                stats.count,
+                stats.mean_delay()
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+/// Mermaid state ids cannot contain whitespace or punctuation.
+fn mermaid_id(label: &str) -> String {
+    label.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect()
+}
+
+pub(crate) fn dot_id(label: &str) -> String {
+    format!("\"{}\"", label.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_export() {
+        let mut graph = EventGraph::new();
+        graph.record(None, "arrival", 0.0);
+        graph.record(Some("arrival"), "arrival", 1.0);
+        graph.record(Some("arrival"), "arrival", 3.0);
+        graph.record(Some("arrival"), "end of \"service\"", 4.0);
+        let mut out = Vec::new();
+        graph.write_dot(&mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("\"[*]\" -> \"arrival\";"));
+        assert!(dot.contains("\"arrival\" -> \"arrival\" [label=\"n=2 mean=2\"];"));
+        assert!(dot.contains("\"arrival\" -> \"end of \\\"service\\\"\""));
+    }
+}
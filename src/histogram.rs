@@ -0,0 +1,414 @@
+//! # Histograms
+//!
+//! A [`Histogram`] collects the distribution of observations such as waiting times or queue
+//! lengths. Fixed histograms count observations into equal-width bins over a known range in
+//! constant memory; automatic histograms keep the observations and choose their bins when
+//! asked. Quantiles can be tracked in constant memory with the P² estimator, [`P2Quantile`].
+
+use crate::csv::json_number;
+use std::io::{self, Write};
+
+/// One bin of a histogram, covering `[lower, upper)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Binning {
+    Fixed { low: f64, high: f64, counts: Vec<u64>, underflow: u64, overflow: u64 },
+    Auto { values: Vec<f64> },
+}
+
+/// Collects the distribution of a stream of observations.
+///
+/// # Example
+/// ```
+/// use desru::Histogram;
+///
+/// let mut waits = Histogram::fixed(0.0, 10.0, 5).with_quantile(0.5);
+/// for w in [0.5, 1.0, 2.5, 3.0, 3.5, 9.0, 12.0] {
+///     waits.record(w);
+/// }
+/// let bins = waits.bins();
+/// assert_eq!(bins[0].count, 2);
+/// assert_eq!(bins[1].count, 3);
+/// assert_eq!(waits.overflow(), 1);
+/// let median = waits.quantile(0.5).unwrap();
+/// assert!((median - 3.0).abs() < 1.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    binning: Binning,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    estimators: Vec<P2Quantile>,
+}
+
+impl Histogram {
+    /// Creates a histogram with `bins` equal-width bins over `[low, high)`. Observations outside
+    /// the range are counted as underflow or overflow.
+    ///
+    /// # Panics
+    /// Panics if `bins` is zero or the range is empty.
+    pub fn fixed(low: f64, high: f64, bins: usize) -> Self {
+        assert!(bins > 0, "a histogram needs at least one bin");
+        assert!(high > low, "histogram range must be non-empty");
+        Histogram::with_binning(Binning::Fixed { low, high, counts: vec![0; bins], underflow: 0, overflow: 0 })
+    }
+
+    /// Creates a histogram that keeps every observation and chooses bins from the data with the
+    /// Freedman–Diaconis rule, falling back to Sturges' rule when the data have no spread.
+    pub fn auto() -> Self {
+        Histogram::with_binning(Binning::Auto { values: Vec::new() })
+    }
+
+    fn with_binning(binning: Binning) -> Self {
+        Histogram { binning, count: 0, sum: 0.0, min: f64::INFINITY, max: f64::NEG_INFINITY, estimators: Vec::new() }
+    }
+
+    /// Tracks the `p`-quantile with a [`P2Quantile`] estimator.
+    pub fn with_quantile(mut self, p: f64) -> Self {
+        self.estimators.push(P2Quantile::new(p));
+        self
+    }
+
+    /// Records an observation.
+    pub fn record(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        for estimator in &mut self.estimators {
+            estimator.record(value);
+        }
+        match &mut self.binning {
+            Binning::Fixed { low, high, counts, underflow, overflow } => {
+                if value < *low {
+                    *underflow += 1;
+                } else if value >= *high {
+                    *overflow += 1;
+                } else {
+                    let last = counts.len() - 1;
+                    let index = ((value - *low) / (*high - *low) * counts.len() as f64) as usize;
+                    counts[index.min(last)] += 1;
+                }
+            }
+            Binning::Auto { values } => values.push(value),
+        }
+    }
+
+    /// Returns the number of observations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean observation, or `0.0` if there are none.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    /// Returns the smallest observation.
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Returns the largest observation.
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Returns the number of observations below the range of a fixed histogram.
+    pub fn underflow(&self) -> u64 {
+        match self.binning {
+            Binning::Fixed { underflow, .. } => underflow,
+            Binning::Auto { .. } => 0,
+        }
+    }
+
+    /// Returns the number of observations at or above the range of a fixed histogram.
+    pub fn overflow(&self) -> u64 {
+        match self.binning {
+            Binning::Fixed { overflow, .. } => overflow,
+            Binning::Auto { .. } => 0,
+        }
+    }
+
+    /// Returns the bins in ascending order. The last bin of an automatic histogram also
+    /// includes the largest observation.
+    pub fn bins(&self) -> Vec<Bin> {
+        match &self.binning {
+            Binning::Fixed { low, high, counts, .. } => {
+                let width = (high - low) / counts.len() as f64;
+                counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &count)| Bin { lower: low + i as f64 * width, upper: low + (i + 1) as f64 * width, count })
+                    .collect()
+            }
+            Binning::Auto { values } => auto_bins(values),
+        }
+    }
+
+    /// Estimates the `p`-quantile.
+    ///
+    /// Automatic histograms compute it exactly. Fixed histograms use a tracked estimator for
+    /// `p` if there is one, and otherwise interpolate within the bins.
+    ///
+    /// # Returns
+    /// The estimate, or `None` if there are no observations.
+    pub fn quantile(&self, p: f64) -> Option<f64> {
+        assert!((0.0..=1.0).contains(&p), "quantile probability must be in [0, 1]");
+        if self.count == 0 {
+            return None;
+        }
+        if let Binning::Auto { values } = &self.binning {
+            let mut sorted = values.clone();
+            sorted.sort_by(f64::total_cmp);
+            return Some(interpolated_quantile(&sorted, p));
+        }
+        if let Some(estimator) = self.estimators.iter().find(|e| (e.p - p).abs() < 1e-12) {
+            return estimator.estimate();
+        }
+        let bins = self.bins();
+        let mut remaining = p * self.count as f64 - self.underflow() as f64;
+        if remaining <= 0.0 {
+            return Some(self.min);
+        }
+        for bin in &bins {
+            if remaining <= bin.count as f64 {
+                return Some(bin.lower + (bin.upper - bin.lower) * remaining / bin.count as f64);
+            }
+            remaining -= bin.count as f64;
+        }
+        Some(self.max)
+    }
+
+    /// Writes the bins as CSV with columns `lower,upper,count`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "lower,upper,count")?;
+        for bin in self.bins() {
+            writeln!(writer, "{},{},{}", bin.lower, bin.upper, bin.count)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the histogram as a JSON object with its summary statistics, tracked quantiles,
+    /// and bins. Undefined statistics are written as `null`.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "{{\"count\":{},\"mean\":{},\"min\":{},\"max\":{},\"underflow\":{},\"overflow\":{},\"quantiles\":[",
+            self.count,
+            json_number(self.min().map(|_| self.mean())),
+            json_number(self.min()),
+            json_number(self.max()),
+            self.underflow(),
+            self.overflow()
+        )?;
+        for (i, estimator) in self.estimators.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(writer, "{}{{\"p\":{},\"value\":{}}}", separator, estimator.p, json_number(estimator.estimate()))?;
+        }
+        write!(writer, "],\"bins\":[")?;
+        for (i, bin) in self.bins().iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(writer, "{}{{\"lower\":{},\"upper\":{},\"count\":{}}}", separator, bin.lower, bin.upper, bin.count)?;
+        }
+        writeln!(writer, "]}}")
+    }
+}
+
+fn auto_bins(values: &[f64]) -> Vec<Bin> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let (low, high) = (sorted[0], sorted[sorted.len() - 1]);
+    if high == low {
+        return vec![Bin { lower: low, upper: high, count: sorted.len() as u64 }];
+    }
+    let n = sorted.len() as f64;
+    let iqr = interpolated_quantile(&sorted, 0.75) - interpolated_quantile(&sorted, 0.25);
+    let bins = if iqr > 0.0 {
+        ((high - low) / (2.0 * iqr / n.cbrt())).ceil() as usize
+    } else {
+        n.log2().ceil() as usize + 1
+    };
+    let bins = bins.clamp(1, 10_000);
+    let width = (high - low) / bins as f64;
+    let mut counts = vec![0; bins];
+    for value in sorted {
+        counts[(((value - low) / width) as usize).min(bins - 1)] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| Bin { lower: low + i as f64 * width, upper: low + (i + 1) as f64 * width, count })
+        .collect()
+}
+
+/// Linear interpolation between order statistics of sorted, non-empty data.
+fn interpolated_quantile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    let above = rank.ceil() as usize;
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+/// Estimates one quantile of a stream in constant memory with the P² algorithm
+/// (Jain & Chlamtac, 1985).
+///
+/// # Example
+/// ```
+/// use desru::{P2Quantile, SimRng};
+///
+/// let mut median = P2Quantile::new(0.5);
+/// let mut rng = SimRng::new(1);
+/// for _ in 0..10_000 {
+///     median.record(rng.gen_range(0.0, 100.0));
+/// }
+/// assert!((median.estimate().unwrap() - 50.0).abs() < 2.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct P2Quantile {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    /// Creates an estimator of the `p`-quantile.
+    ///
+    /// # Panics
+    /// Panics if `p` is not within `[0, 1]`.
+    pub fn new(p: f64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "quantile probability must be in [0, 1]");
+        P2Quantile {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    /// Returns the probability whose quantile is estimated.
+    pub fn p(&self) -> f64 {
+        self.p
+    }
+
+    /// Records an observation.
+    pub fn record(&mut self, value: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+        let q = &mut self.heights;
+        let cell = if value < q[0] {
+            q[0] = value;
+            0
+        } else if value >= q[4] {
+            q[4] = value;
+            3
+        } else {
+            (0..4).find(|&i| value < q[i + 1]).unwrap_or(3)
+        };
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+        for i in 1..4 {
+            let n = &mut self.positions;
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let s = d.signum();
+                let parabolic = q[i]
+                    + s / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + s) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - s) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if s > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + s * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += s;
+            }
+        }
+    }
+
+    /// Returns the current estimate, or `None` if nothing has been recorded. With fewer than
+    /// five observations the estimate is exact.
+    pub fn estimate(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            1..=4 => {
+                let mut sorted = self.heights[..self.count].to_vec();
+                sorted.sort_by(f64::total_cmp);
+                Some(interpolated_quantile(&sorted, self.p))
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimRng;
+
+    #[test]
+    fn test_p2_tracks_exponential_tail() {
+        let mut rng = SimRng::new(8);
+        let mut histogram = Histogram::auto().with_quantile(0.9);
+        let mut estimator = P2Quantile::new(0.9);
+        for _ in 0..20_000 {
+            let x = -(1.0 - rng.next_f64()).ln();
+            histogram.record(x);
+            estimator.record(x);
+        }
+        let exact = histogram.quantile(0.9).unwrap();
+        assert!((exact - 10f64.ln()).abs() < 0.05);
+        assert!((estimator.estimate().unwrap() - exact).abs() < 0.05);
+        let bins = histogram.bins();
+        assert_eq!(bins.iter().map(|b| b.count).sum::<u64>(), 20_000);
+        assert!(bins.len() > 10);
+    }
+
+    #[test]
+    fn test_json_export() {
+        let mut histogram = Histogram::fixed(0.0, 2.0, 2);
+        let mut out = Vec::new();
+        histogram.write_json(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("{\"count\":0,\"mean\":null,\"min\":null"));
+
+        histogram.record(-1.0);
+        histogram.record(1.5);
+        let mut out = Vec::new();
+        histogram.write_json(&mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.contains("\"underflow\":1"));
+        assert!(json.contains("{\"lower\":1,\"upper\":2,\"count\":1}"));
+    }
+}
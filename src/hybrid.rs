@@ -0,0 +1,271 @@
+//! # Hybrid Simulation
+//!
+//! A [`Continuous`] system carries a continuous state, such as a tank level or a position,
+//! that evolves between discrete events according to a user-supplied integrator. Once attached
+//! to a scheduler, the system is advanced up to the time of each event before that event runs,
+//! so event actions always see the state at the current time.
+//!
+//! Zero-crossing functions detect state events: when a guard changes sign between two events,
+//! the crossing time is located by bisection and a handler runs as an event at that time,
+//! before any other event scheduled then. The state is only advanced towards pending events,
+//! so a model with no other events should schedule one at the end of its horizon.
+
+use crate::{Action, EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Integrator<S> = Box<dyn FnMut(f64, &mut S)>;
+type Guard<S> = Box<dyn Fn(&S) -> f64>;
+type CrossingHandler = Rc<RefCell<dyn FnMut(&mut EventScheduler)>>;
+
+struct ContinuousState<S> {
+    state: S,
+    time: f64,
+    max_step: f64,
+    integrator: Integrator<S>,
+    crossings: Vec<(Guard<S>, CrossingHandler)>,
+    // The result of the last probe: where it stopped, which guard crossed there, and the state.
+    probe: Option<(f64, Option<usize>, S)>,
+}
+
+/// A continuous state advanced between events.
+///
+/// # Example
+/// ```
+/// use desru::{Continuous, EventScheduler, ScheduledAction};
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// // A ball dropped from 10 m; the state is (height, velocity).
+/// let ball = Continuous::new((10.0, 0.0), |dt, (h, v): &mut (f64, f64)| {
+///     *v -= 9.81 * dt;
+///     *h += *v * dt;
+/// })
+/// .max_step(0.001);
+///
+/// let landed = Rc::new(Cell::new(0.0));
+/// let record = landed.clone();
+/// let ball = ball.zero_crossing(|(h, _)| *h, move |s| record.set(s.current_time));
+///
+/// let mut scheduler = EventScheduler::new();
+/// ball.attach(&mut scheduler);
+/// scheduler.schedule(ScheduledAction::at(5.0));
+/// scheduler.run_until_max_time(10.0);
+/// assert!((landed.get() - (2.0 * 10.0 / 9.81_f64).sqrt()).abs() < 0.01);
+/// ```
+pub struct Continuous<S> {
+    inner: Rc<RefCell<ContinuousState<S>>>,
+}
+
+impl<S> Clone for Continuous<S> {
+    fn clone(&self) -> Self {
+        Continuous { inner: self.inner.clone() }
+    }
+}
+
+impl<S: Clone + 'static> Continuous<S> {
+    /// Creates a continuous system.
+    ///
+    /// # Parameters
+    /// - `state`: The initial state.
+    /// - `integrator`: Advances the state by a time step `dt`, as in `on_advance(dt, &mut state)`.
+    pub fn new(state: S, integrator: impl FnMut(f64, &mut S) + 'static) -> Self {
+        Continuous {
+            inner: Rc::new(RefCell::new(ContinuousState {
+                state,
+                time: 0.0,
+                max_step: f64::INFINITY,
+                integrator: Box::new(integrator),
+                crossings: Vec::new(),
+                probe: None,
+            })),
+        }
+    }
+
+    /// Limits the step passed to the integrator. Defaults to unlimited, so the state is
+    /// advanced from one event to the next in a single step.
+    pub fn max_step(self, max_step: f64) -> Self {
+        assert!(max_step > 0.0, "max_step must be positive");
+        self.inner.borrow_mut().max_step = max_step;
+        self
+    }
+
+    /// Adds a state event: `handler` runs as an event whenever `guard` changes sign.
+    pub fn zero_crossing(self, guard: impl Fn(&S) -> f64 + 'static, handler: impl FnMut(&mut EventScheduler) + 'static) -> Self {
+        self.inner.borrow_mut().crossings.push((Box::new(guard), Rc::new(RefCell::new(handler))));
+        self
+    }
+
+    /// Starts advancing the system alongside `scheduler`, from its current time.
+    pub fn attach(&self, scheduler: &mut EventScheduler) {
+        self.inner.borrow_mut().time = scheduler.current_time;
+        scheduler.continuous.push(Box::new(self.clone()));
+    }
+
+    /// Returns a copy of the current state.
+    pub fn state(&self) -> S {
+        self.inner.borrow().state.clone()
+    }
+
+    /// Modifies the state, for example from an event action.
+    pub fn with_state<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        let mut inner = self.inner.borrow_mut();
+        inner.probe = None;
+        f(&mut inner.state)
+    }
+
+    /// Returns the time up to which the state has been advanced.
+    pub fn time(&self) -> f64 {
+        self.inner.borrow().time
+    }
+}
+
+/// The scheduler's view of a continuous system.
+pub(crate) trait ContinuousSystem {
+    /// Integrates a copy of the state towards `to`, returning the first crossing time, if any.
+    fn probe(&mut self, to: f64) -> Option<f64>;
+
+    /// Advances the state to `to`, returning the handler to run if a guard crosses zero there.
+    fn advance(&mut self, to: f64) -> Option<Action>;
+
+    /// Returns `true` if the system has zero-crossing guards, and so can raise events.
+    fn has_crossings(&self) -> bool;
+}
+
+fn crossed(before: f64, after: f64) -> bool {
+    before != 0.0 && (after == 0.0 || (before < 0.0) != (after < 0.0))
+}
+
+impl<S: Clone + 'static> ContinuousSystem for Continuous<S> {
+    fn has_crossings(&self) -> bool {
+        !self.inner.borrow().crossings.is_empty()
+    }
+
+    fn probe(&mut self, to: f64) -> Option<f64> {
+        let inner = &mut *self.inner.borrow_mut();
+        let mut time = inner.time;
+        let mut state = inner.state.clone();
+        while time < to {
+            let step = inner.max_step.min(to - time);
+            let before = state.clone();
+            let guards: Vec<f64> = inner.crossings.iter().map(|(guard, _)| guard(&before)).collect();
+            (inner.integrator)(step, &mut state);
+            let mut earliest: Option<(f64, usize, S)> = None;
+            for (index, (guard, _)) in inner.crossings.iter().enumerate() {
+                if !crossed(guards[index], guard(&state)) {
+                    continue;
+                }
+                // Bisect for the smallest step after which the guard has crossed.
+                let (mut low, mut high, mut at_high) = (0.0, step, state.clone());
+                for _ in 0..60 {
+                    let mid = (low + high) / 2.0;
+                    let mut trial = before.clone();
+                    (inner.integrator)(mid, &mut trial);
+                    if crossed(guards[index], guard(&trial)) {
+                        high = mid;
+                        at_high = trial;
+                    } else {
+                        low = mid;
+                    }
+                }
+                if earliest.as_ref().is_none_or(|(offset, _, _)| high < *offset) {
+                    earliest = Some((high, index, at_high));
+                }
+            }
+            if let Some((offset, index, state)) = earliest {
+                let crossing = time + offset;
+                inner.probe = Some((crossing, Some(index), state));
+                return Some(crossing);
+            }
+            time += step;
+        }
+        inner.probe = Some((to, None, state));
+        None
+    }
+
+    fn advance(&mut self, to: f64) -> Option<Action> {
+        let inner = &mut *self.inner.borrow_mut();
+        match inner.probe.take() {
+            Some((time, crossing, state)) if time == to => {
+                inner.state = state;
+                inner.time = to;
+                let handler = inner.crossings[crossing?].1.clone();
+                Some(Box::new(move |scheduler| {
+                    (handler.borrow_mut())(scheduler);
+                    None
+                }))
+            }
+            _ => {
+                while inner.time < to {
+                    let step = inner.max_step.min(to - inner.time);
+                    (inner.integrator)(step, &mut inner.state);
+                    inner.time += step;
+                }
+                inner.time = to;
+                None
+            }
+        }
+    }
+}
+
+impl EventScheduler {
+    /// Advances every continuous system to `to`, or to the earliest zero crossing before it.
+    ///
+    /// # Returns
+    /// `true` if a crossing handler was scheduled, so the front of the queue has changed.
+    pub(crate) fn advance_continuous(&mut self, to: f64) -> bool {
+        if self.continuous.is_empty() {
+            return false;
+        }
+        let mut systems = std::mem::take(&mut self.continuous);
+        let target = systems.iter_mut().filter_map(|system| system.probe(to)).fold(to, f64::min);
+        let mut scheduled = false;
+        for system in systems.iter_mut() {
+            if let Some(action) = system.advance(target) {
+                self.schedule(ScheduledAction::new(target, Some(action), None).with_priority(i64::MIN));
+                scheduled = true;
+            }
+        }
+        self.continuous.append(&mut systems);
+        scheduled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bouncing_ball_reverses_at_each_crossing() {
+        let ball = Continuous::new((1.0_f64, 0.0_f64), |dt, (h, v): &mut (f64, f64)| {
+            *v -= 9.81 * dt;
+            *h += *v * dt;
+        })
+        .max_step(0.0005);
+        let bounces = Rc::new(RefCell::new(Vec::new()));
+        let (handle, record) = (ball.clone(), bounces.clone());
+        let ball = ball.zero_crossing(
+            |(h, _)| *h,
+            move |s| {
+                record.borrow_mut().push(s.current_time);
+                handle.with_state(|(h, v)| {
+                    *h = 0.0;
+                    *v *= -0.5;
+                });
+            },
+        );
+
+        let mut scheduler = EventScheduler::new();
+        ball.attach(&mut scheduler);
+        scheduler.schedule(ScheduledAction::at(1.0));
+        scheduler.run_until_max_time(2.0);
+
+        let bounces = bounces.borrow();
+        let first = (2.0 / 9.81_f64).sqrt();
+        assert_eq!(bounces.len(), 2);
+        assert!((bounces[0] - first).abs() < 0.01);
+        // Half the speed means half the flight time up and down again.
+        assert!((bounces[1] - 2.0 * first).abs() < 0.02);
+        assert_eq!(ball.time(), 1.0);
+    }
+}
@@ -0,0 +1,122 @@
+//! # Queue Introspection
+//!
+//! [`EventScheduler::pending_events`] takes an ordered snapshot of the event queue and
+//! [`EventScheduler::dump_queue`] prints it, for debugging complex schedules.
+
+use crate::context::display_pairs;
+use crate::{Context, EventId, EventScheduler};
+use std::fmt;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+/// A snapshot of a pending event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEvent {
+    pub id: EventId,
+    pub time: f64,
+    pub priority: i64,
+    pub label: Option<String>,
+    pub tags: BTreeSet<String>,
+    pub context: Context,
+    pub active: bool,
+}
+
+impl fmt::Display for PendingEvent {
+    /// Formats the event as its id, label, and time, followed by its context if it has any.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} at {}", self.id, self.label.as_deref().unwrap_or("unlabeled"), self.time)?;
+        if !self.context.is_empty() {
+            write!(f, " ({})", display_pairs(&self.context))?;
+        }
+        if !self.active {
+            write!(f, " [inactive]")?;
+        }
+        Ok(())
+    }
+}
+
+impl EventScheduler {
+    /// Returns a snapshot of the pending events in the order they will run.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule(ScheduledAction::at(3.0).with_label("b"));
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_label("a"));
+    /// let labels: Vec<_> = scheduler.pending_events().into_iter().filter_map(|e| e.label).collect();
+    /// assert_eq!(labels, ["a", "b"]);
+    /// ```
+    pub fn pending_events(&self) -> Vec<PendingEvent> {
+        let mut events: Vec<_> = self.event_queue.iter().collect();
+        // Events that run first compare greatest.
+        events.sort_by(|a, b| b.cmp(a));
+        events
+            .into_iter()
+            .map(|event| PendingEvent {
+                id: EventId(event.seq),
+                time: event.time,
+                priority: event.priority,
+                label: event.label.clone(),
+                tags: event.tags.clone(),
+                context: event.context.clone(),
+                active: event.active,
+            })
+            .collect()
+    }
+
+    /// Writes the pending events as a table, one per line in the order they will run.
+    ///
+    /// Context entries are sorted by key. Inactive events are marked in the `active` column.
+    pub fn dump_queue<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let time = match self.time_unit {
+            Some(unit) => format!("time ({})", unit),
+            None => "time".to_string(),
+        };
+        writeln!(writer, "{:>12} {:>8} {:>8} {:<16} {:<6} context", time, "priority", "id", "label", "active")?;
+        for event in self.pending_events() {
+            let mut pairs: Vec<_> = event.context.iter().collect();
+            pairs.sort();
+            let context: Vec<String> = pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            writeln!(
+                writer,
+                "{:>12} {:>8} {:>8} {:<16} {:<6} {}",
+                event.time,
+                event.priority,
+                event.id.0,
+                event.label.as_deref().unwrap_or("-"),
+                event.active,
+                context.join(";")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, EventScheduler, ScheduledAction};
+
+    #[test]
+    fn test_dump_orders_by_time_then_priority() {
+        let mut scheduler = EventScheduler::new();
+        let mut context = Context::new();
+        context.insert("z".to_string(), "1".to_string());
+        context.insert("a".to_string(), "2".to_string());
+        scheduler.schedule(ScheduledAction::at(2.0).with_label("late"));
+        scheduler.schedule(ScheduledAction::at(1.0).with_label("normal").with_context(context));
+        let urgent = scheduler.schedule(ScheduledAction::at(1.0).with_label("urgent").with_priority(-1));
+
+        let pending = scheduler.pending_events();
+        assert_eq!(pending[0].id, urgent);
+        let mut out = Vec::new();
+        scheduler.dump_queue(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("urgent"));
+        assert!(lines[2].ends_with("a=2;z=1"));
+        assert!(lines[3].contains("late"));
+    }
+}
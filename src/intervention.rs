@@ -0,0 +1,152 @@
+//! # Interventions
+//!
+//! A what-if study often changes a run from outside partway through: close a ward at day 30,
+//! add a server when the queue passes some length. An intervention injected with
+//! [`EventScheduler::inject_at`] is a control event that runs before every model event at its
+//! time, whatever their priority or the order they were scheduled in, so the model sees the
+//! change from the start of that instant.
+//!
+//! Pending interventions are kept in their own queue, which can be listed with
+//! [`EventScheduler::interventions`] and edited while a run is paused: cancelled with
+//! [`EventScheduler::cancel`] or moved with [`EventScheduler::reschedule_intervention`].
+
+use crate::{EventId, EventScheduler, PendingEvent, ScheduledAction};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// The pending interventions, in the order they were injected.
+pub(crate) type Interventions = Vec<(EventId, ScheduledAction)>;
+
+impl EventScheduler {
+    /// Injects an intervention labeled `"intervention"` that runs `action` at `time`, before
+    /// any model event at that time.
+    ///
+    /// # Panics
+    /// Panics if `time` is before the current time.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.world.set(1_u32);
+    /// // A model event at t = 5 with the most urgent priority still sees the new capacity.
+    /// scheduler.schedule(ScheduledAction::at(5.0).with_priority(i64::MIN).with_action(|s| Some(format!("capacity {}", s.state::<u32>()))));
+    /// scheduler.inject_at(5.0, |s| {
+    ///     *s.state_mut::<u32>() = 3;
+    ///     Some("opened two more desks".to_string())
+    /// });
+    ///
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!(log[0].label.as_deref(), Some("intervention"));
+    /// assert_eq!(log[1].result.as_deref(), Some("capacity 3"));
+    /// ```
+    pub fn inject_at<F>(&mut self, time: f64, action: F) -> EventId
+    where
+        F: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+    {
+        self.inject(ScheduledAction::at(time).with_label("intervention").with_action(action))
+    }
+
+    /// Injects `event` as an intervention, keeping its label, context, and tags. Its priority
+    /// is ignored: interventions at the same time run in the order they were injected.
+    ///
+    /// # Panics
+    /// Panics if the event's time is before the current time.
+    pub fn inject(&mut self, event: ScheduledAction) -> EventId {
+        assert!(event.time >= self.current_time, "cannot inject an intervention at {}, before the current time {}", event.time, self.current_time);
+        // A placeholder in the event queue makes stop conditions, debugging, and queue
+        // snapshots see the intervention; it runs the intervention itself only if a loop
+        // other than `execute_next` pops it.
+        let mut placeholder = ScheduledAction::at(event.time).with_priority(i64::MIN).with_context(event.context.clone());
+        placeholder.label = event.label.clone();
+        placeholder.tags = event.tags.clone();
+        let own_id = Rc::new(Cell::new(EventId(0)));
+        let placeholder_id = own_id.clone();
+        let id = self.event_queue.push(placeholder.with_action(move |s| s.take_intervention(placeholder_id.get()).and_then(|mut event| event.run(s))));
+        own_id.set(id);
+        self.interventions.push((id, event));
+        id
+    }
+
+    /// Returns the pending interventions in the order they will run.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_label("pause").with_action(|s| {
+    ///     s.request_pause();
+    ///     None
+    /// }));
+    /// let early = scheduler.inject_at(4.0, |_| Some("early".to_string()));
+    /// let late = scheduler.inject_at(8.0, |_| Some("late".to_string()));
+    /// scheduler.run_until_max_time(10.0);
+    ///
+    /// // While paused, drop one intervention and bring the other forward.
+    /// scheduler.cancel(early);
+    /// let moved = scheduler.reschedule_intervention(late, 2.0).unwrap();
+    /// let pending = scheduler.interventions();
+    /// assert_eq!((pending.len(), pending[0].id, pending[0].time), (1, moved, 2.0));
+    ///
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!((log[1].time, log[1].result.as_deref()), (2.0, Some("late")));
+    /// ```
+    pub fn interventions(&self) -> Vec<PendingEvent> {
+        self.pending_events().into_iter().filter(|event| self.interventions.iter().any(|(id, _)| *id == event.id)).collect()
+    }
+
+    /// Moves a pending intervention to `time`.
+    ///
+    /// # Returns
+    /// The intervention's new id, or `None` if it is not pending.
+    ///
+    /// # Panics
+    /// Panics if `time` is before the current time.
+    pub fn reschedule_intervention(&mut self, id: EventId, time: f64) -> Option<EventId> {
+        let mut event = self.take_intervention(id)?;
+        event.time = time;
+        Some(self.inject(event))
+    }
+
+    /// Removes a pending intervention and its placeholder.
+    pub(crate) fn take_intervention(&mut self, id: EventId) -> Option<ScheduledAction> {
+        let index = self.interventions.iter().position(|(pending, _)| *pending == id)?;
+        self.event_queue.discard(id);
+        let (_, mut event) = self.interventions.remove(index);
+        event.seq = id.0;
+        Some(event)
+    }
+
+    /// Removes the first intervention due by `next_time`, the time of the next event.
+    pub(crate) fn take_due_intervention(&mut self, next_time: f64) -> Option<ScheduledAction> {
+        let due = self
+            .interventions
+            .iter()
+            .filter(|(_, event)| event.time <= next_time || self.time_comparison.same(event.time, next_time))
+            .min_by(|a, b| a.1.time.total_cmp(&b.1.time).then(a.0.0.cmp(&b.0.0)))
+            .map(|(id, _)| *id)?;
+        self.take_intervention(due)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventScheduler, ScheduledAction};
+
+    #[test]
+    fn test_interventions_precede_immediate_events_and_run_in_injection_order() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+            s.schedule_now(|_| Some("immediate".to_string()));
+            s.inject_at(1.0, |_| Some("second".to_string()));
+            None
+        }));
+        scheduler.inject_at(1.0, |_| Some("first".to_string()));
+        let results: Vec<_> = scheduler.run_until_max_time(5.0).iter().filter_map(|record| record.result.clone()).collect();
+        assert_eq!(results, ["first", "second", "immediate"]);
+        assert!(scheduler.interventions().is_empty());
+        assert_eq!(scheduler.metrics().events_cancelled, 0);
+    }
+}
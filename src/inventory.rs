@@ -0,0 +1,286 @@
+//! # Inventory
+//!
+//! An [`Inventory`] holds stock that is depleted by demand and replenished by orders, which
+//! arrive after a lead time. Stock is reviewed continuously under an [`InventoryPolicy`]:
+//! whenever the inventory position (stock on hand, minus backorders, plus stock on order)
+//! falls to the reorder point, an order is placed. Demand that cannot be met from stock is
+//! backordered and filled by later deliveries. Holding, backorder, and ordering costs are
+//! accumulated as the simulation runs.
+
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A reorder-point policy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryPolicy {
+    /// The (s, S) policy: at or below `reorder_point`, order up to `order_up_to`.
+    MinMax { reorder_point: f64, order_up_to: f64 },
+    /// The (R, Q) policy: at or below `reorder_point`, order multiples of `quantity` until the
+    /// position is back above it.
+    ReorderQuantity { reorder_point: f64, quantity: f64 },
+}
+
+impl InventoryPolicy {
+    /// Returns the amount to order at the given inventory position, or zero.
+    pub fn order_quantity(&self, position: f64) -> f64 {
+        match *self {
+            InventoryPolicy::MinMax { reorder_point, order_up_to } if position <= reorder_point => order_up_to - position,
+            InventoryPolicy::ReorderQuantity { reorder_point, quantity } if position <= reorder_point => {
+                ((reorder_point - position) / quantity).floor().max(0.0) * quantity + quantity
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Cost rates accumulated by an [`Inventory`]. All default to zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InventoryCosts {
+    /// Cost per unit of stock on hand per time unit.
+    pub holding: f64,
+    /// Cost per backordered unit per time unit.
+    pub backorder: f64,
+    /// Fixed cost per order placed.
+    pub ordering: f64,
+    /// Cost per unit ordered.
+    pub per_unit: f64,
+}
+
+/// Draws an order's lead time.
+type LeadTimeFn = Box<dyn FnMut(&mut EventScheduler) -> f64>;
+
+struct InventoryState {
+    net: f64,
+    on_order: f64,
+    policy: InventoryPolicy,
+    lead_time: LeadTimeFn,
+    costs: InventoryCosts,
+    start: Option<f64>,
+    last_time: f64,
+    holding_area: f64,
+    backorder_area: f64,
+    orders: usize,
+    ordered: f64,
+    demanded: f64,
+    filled: f64,
+}
+
+impl InventoryState {
+    /// Accumulates the stock and backorder areas up to `now`.
+    fn advance(&mut self, now: f64) {
+        match self.start {
+            None => self.start = Some(now),
+            Some(_) => {
+                let elapsed = now - self.last_time;
+                self.holding_area += self.net.max(0.0) * elapsed;
+                self.backorder_area += (-self.net).max(0.0) * elapsed;
+            }
+        }
+        self.last_time = now;
+    }
+}
+
+/// A stock point under continuous review.
+///
+/// Clones share one stock level, so demand events and reports can each hold a clone.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Inventory, InventoryCosts, InventoryPolicy};
+///
+/// let mut scheduler = EventScheduler::new();
+/// let policy = InventoryPolicy::MinMax { reorder_point: 3.0, order_up_to: 10.0 };
+/// let costs = InventoryCosts { holding: 1.0, backorder: 5.0, ordering: 20.0, per_unit: 0.0 };
+/// let store = Inventory::new(10.0, policy, |_| 2.0).with_costs(costs);
+/// store.start(&scheduler);
+/// for (time, quantity) in [(1.0, 4.0), (2.0, 4.0), (3.0, 3.0)] {
+///     let store = store.clone();
+///     scheduler.timeout(time, Some(Box::new(move |s| {
+///         store.demand(s, quantity);
+///         None
+///     })), None);
+/// }
+/// scheduler.run_until_max_time(5.0);
+///
+/// // Falling to 2 at time 2 orders 8, which arrives at 4 and fills the backordered unit.
+/// assert_eq!(store.orders(), 1);
+/// assert_eq!(store.on_hand(), 7.0);
+/// assert_eq!(store.fill_rate(), 10.0 / 11.0);
+/// // Holding 10 + 6 + 2 + 0 + 7, one unit backordered for 1, and one order.
+/// assert_eq!(store.total_cost(5.0), 25.0 + 5.0 + 20.0);
+/// ```
+pub struct Inventory {
+    state: Rc<RefCell<InventoryState>>,
+}
+
+impl Clone for Inventory {
+    fn clone(&self) -> Self {
+        Inventory { state: self.state.clone() }
+    }
+}
+
+impl Inventory {
+    /// Creates an inventory holding `initial` units, with lead times drawn from `lead_time`.
+    pub fn new<F>(initial: f64, policy: InventoryPolicy, lead_time: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler) -> f64 + 'static,
+    {
+        Inventory {
+            state: Rc::new(RefCell::new(InventoryState {
+                net: initial,
+                on_order: 0.0,
+                policy,
+                lead_time: Box::new(lead_time),
+                costs: InventoryCosts::default(),
+                start: None,
+                last_time: 0.0,
+                holding_area: 0.0,
+                backorder_area: 0.0,
+                orders: 0,
+                ordered: 0.0,
+                demanded: 0.0,
+                filled: 0.0,
+            })),
+        }
+    }
+
+    /// Sets the cost rates.
+    pub fn with_costs(self, costs: InventoryCosts) -> Self {
+        self.state.borrow_mut().costs = costs;
+        self
+    }
+
+    /// Starts accumulating holding and backorder costs at the current time. Otherwise they are
+    /// measured from the first demand.
+    pub fn start(&self, scheduler: &EventScheduler) {
+        self.state.borrow_mut().advance(scheduler.current_time);
+    }
+
+    /// Removes `quantity` units, backordering whatever is not in stock, and places an order
+    /// if the policy calls for one.
+    ///
+    /// # Returns
+    /// The number of units filled immediately.
+    pub fn demand(&self, scheduler: &mut EventScheduler, quantity: f64) -> f64 {
+        let filled = {
+            let mut state = self.state.borrow_mut();
+            state.advance(scheduler.current_time);
+            let filled = quantity.min(state.net.max(0.0));
+            state.net -= quantity;
+            state.demanded += quantity;
+            state.filled += filled;
+            filled
+        };
+        self.review(scheduler);
+        filled
+    }
+
+    /// Places an order if the inventory position is at or below the reorder point.
+    fn review(&self, scheduler: &mut EventScheduler) {
+        let (quantity, lead_time) = {
+            let mut state = self.state.borrow_mut();
+            let quantity = state.policy.order_quantity(state.net + state.on_order);
+            if quantity <= 0.0 {
+                return;
+            }
+            state.on_order += quantity;
+            state.orders += 1;
+            state.ordered += quantity;
+            (quantity, (state.lead_time)(scheduler))
+        };
+        let inventory = self.clone();
+        scheduler.schedule(ScheduledAction::at(scheduler.time_after(lead_time)).with_action(move |s| {
+            inventory.receive(s, quantity);
+            None
+        }));
+    }
+
+    fn receive(&self, scheduler: &mut EventScheduler, quantity: f64) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.advance(scheduler.current_time);
+            state.net += quantity;
+            state.on_order -= quantity;
+        }
+        self.review(scheduler);
+    }
+
+    /// Returns the stock on hand.
+    pub fn on_hand(&self) -> f64 {
+        self.state.borrow().net.max(0.0)
+    }
+
+    /// Returns the number of backordered units.
+    pub fn backorders(&self) -> f64 {
+        (-self.state.borrow().net).max(0.0)
+    }
+
+    /// Returns the stock on order but not yet delivered.
+    pub fn on_order(&self) -> f64 {
+        self.state.borrow().on_order
+    }
+
+    /// Returns the inventory position: on hand, minus backorders, plus on order.
+    pub fn position(&self) -> f64 {
+        let state = self.state.borrow();
+        state.net + state.on_order
+    }
+
+    /// Returns the number of orders placed.
+    pub fn orders(&self) -> usize {
+        self.state.borrow().orders
+    }
+
+    /// Returns the fraction of demand filled immediately from stock, or `1.0` with no demand.
+    pub fn fill_rate(&self) -> f64 {
+        let state = self.state.borrow();
+        if state.demanded == 0.0 {
+            1.0
+        } else {
+            state.filled / state.demanded
+        }
+    }
+
+    /// Returns the time-average stock on hand up to `now`.
+    pub fn average_on_hand(&self, now: f64) -> f64 {
+        let state = self.state.borrow();
+        match state.start {
+            Some(start) if now > start => (state.holding_area + state.net.max(0.0) * (now - state.last_time)) / (now - start),
+            _ => state.net.max(0.0),
+        }
+    }
+
+    /// Returns the holding, backorder, and ordering costs accumulated up to `now`.
+    pub fn total_cost(&self, now: f64) -> f64 {
+        let state = self.state.borrow();
+        let elapsed = if state.start.is_some() { now - state.last_time } else { 0.0 };
+        let holding = state.holding_area + state.net.max(0.0) * elapsed;
+        let backorder = state.backorder_area + (-state.net).max(0.0) * elapsed;
+        let costs = state.costs;
+        costs.holding * holding
+            + costs.backorder * backorder
+            + costs.ordering * state.orders as f64
+            + costs.per_unit * state.ordered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_quantity_multiples() {
+        let policy = InventoryPolicy::ReorderQuantity { reorder_point: 5.0, quantity: 4.0 };
+        assert_eq!(policy.order_quantity(6.0), 0.0);
+        assert_eq!(policy.order_quantity(5.0), 4.0);
+        assert_eq!(policy.order_quantity(-4.0), 12.0);
+
+        let mut scheduler = EventScheduler::new();
+        let depot = Inventory::new(6.0, policy, |_| 1.0);
+        assert_eq!(depot.demand(&mut scheduler, 10.0), 6.0);
+        assert_eq!((depot.backorders(), depot.on_order()), (4.0, 12.0));
+        scheduler.run_until_max_time(5.0);
+        assert_eq!((depot.on_hand(), depot.position(), depot.orders()), (8.0, 8.0, 1));
+    }
+}
@@ -6,7 +6,7 @@
 //!
 //! The core components of the framework are:
 //! 
-//! - [`Event`]: A struct representing a single event in the simulation, holding its scheduled time, an action, and context.
+//! - [`ScheduledAction`]: A struct representing a single event in the simulation, holding its scheduled time, an action, and context.
 //! - [`EventScheduler`]: A struct that manages the execution of events, prioritizing those scheduled to run earlier.
 //!
 //! ## Key Features
@@ -21,13 +21,13 @@
 //! Below is a simple example demonstrating how to create an event, schedule it in the `EventScheduler`, and run the simulation.
 //!
 //! ```rust
-//! use desru::{Event, EventScheduler};
+//! use desru::{EventScheduler, ScheduledAction};
 //!
 //! fn main() {
 //!     let mut scheduler = EventScheduler::new();
-//!     let mut event = Event::new(0.0,
-//!                                Some(Box::new(|scheduler| Some("Executed".to_string()))),
-//!                                None);
+//!     let mut event = ScheduledAction::new(0.0,
+//!                                          Some(Box::new(|scheduler| Some("Executed".to_string()))),
+//!                                          None);
 //!     scheduler.schedule(event);
 //!     scheduler.run_until_max_time(10.0);
 //! }
@@ -43,7 +43,7 @@
 //! In this example we implement the same process, but using `desru`.
 //!
 //!```rust
-//!use desru::{Event, EventScheduler};
+//!use desru::{EventScheduler, ScheduledAction};
 //!
 //!fn car(scheduler: &mut EventScheduler) {
 //!    // Start by parking, which repeats in a loop
@@ -53,13 +53,13 @@
 //!    let trip_duration = 2.0;
 //!
 //!    // Schedule event to start driving after parking
-//!    scheduler.schedule(Event::new(
+//!    scheduler.schedule(ScheduledAction::new(
 //!        scheduler.current_time + parking_duration,
 //!        Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!            println!("Start driving at {}", scheduler.current_time);
 //!
 //!            // Schedule event to start parking after driving
-//!            scheduler.schedule(Event::new(
+//!            scheduler.schedule(ScheduledAction::new(
 //!                scheduler.current_time + trip_duration,
 //!                Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!                    car(scheduler); // Recurse to repeat the cycle
@@ -97,7 +97,7 @@
 //! events scheduled within a single function. However, we can refactor the code using multiple functions that schedule each other. Further, for this simple example at least, we don't need to keep defining the durations to the same constant values in every function call so we can also define those as global constants. I think it is easier to read this way:
 //!
 //!```rust
-//! use desru::{Event, EventScheduler};
+//! use desru::{EventScheduler, ScheduledAction};
 //!
 //! const MAX_TIME: f64 = 15.0;
 //! const PARK_DURATION: f64 =  5.0;
@@ -109,7 +109,7 @@
 //!
 //!fn park(scheduler: &mut EventScheduler) {
 //!    println!("Start parking at {}", scheduler.current_time);
-//!    scheduler.schedule(Event::new(
+//!    scheduler.schedule(ScheduledAction::new(
 //!        scheduler.current_time + PARK_DURATION,
 //!        Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!            drive(scheduler);
@@ -121,7 +121,7 @@
 //!
 //!fn drive(scheduler: &mut EventScheduler) {
 //!    println!("Start driving at {}", scheduler.current_time);
-//!    scheduler.schedule(Event::new(
+//!    scheduler.schedule(ScheduledAction::new(
 //!        scheduler.current_time + DRIVE_DURATION,
 //!        Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!            park(scheduler); // Return to parking
@@ -152,7 +152,7 @@
 //! While Rust does not have classes in the usual sense, it does support its own flavor of [OOP](https://doc.rust-lang.org/beta/book/ch17-00-oop.html). While Rust's OOP does not have inheritance, for which Rust has some useful alternatives, we do not need it to implement an object-oriented implementation of SimPy's example.
 //!
 //! ```rust
-//! use desru::{Event, EventScheduler};
+//! use desru::{EventScheduler, ScheduledAction};
 //!
 //! const CHARGE_DURATION: f64 = 5.0;
 //! const TRIP_DURATION: f64 = 2.0;
@@ -170,7 +170,7 @@
 //!
 //!    fn start(&mut self) {
 //!        // Start the car process
-//!        self.scheduler.schedule(Event::new(
+//!        self.scheduler.schedule(ScheduledAction::new(
 //!            self.scheduler.current_time,
 //!            Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!                let mut car_instance = Car { scheduler };  // Create a car instance with a mutable reference
@@ -186,7 +186,7 @@
 //!        println!("Start parking and charging at {}", self.scheduler.current_time);
 //!
 //!        // Schedule the charge process
-//!        self.scheduler.schedule(Event::new(
+//!        self.scheduler.schedule(ScheduledAction::new(
 //!            self.scheduler.current_time + CHARGE_DURATION,
 //!            Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!                let mut car_instance = Car { scheduler };
@@ -202,7 +202,7 @@
 //!        println!("Start driving at {}", self.scheduler.current_time);
 //!
 //!        // Schedule the next run cycle (parking and charging) after the trip duration
-//!        self.scheduler.schedule(Event::new(
+//!        self.scheduler.schedule(ScheduledAction::new(
 //!            self.scheduler.current_time + TRIP_DURATION,
 //!            Some(Box::new(move |scheduler: &mut EventScheduler| {
 //!                let mut car_instance = Car { scheduler };
@@ -227,7 +227,7 @@
 //!```
 //!
 //! ## Core Structs
-//! - [`Event`]: Defines the core event object used to represent scheduled actions.
+//! - [`ScheduledAction`]: Defines the core event object used to represent scheduled actions.
 //! - [`EventScheduler`]: Manages the execution of events over simulated time.
 //!
 //! ## Customization
@@ -241,8 +241,10 @@
 //! - **Efficient:** Using a priority queue to ensure events are executed in the correct order.
 //!
 //! ## Design Non-Goals
-//! This framework is only for the very most core components for DES, and will not provide
-//! implementations of simulation tools.
+//! The scheduler and its event queue are the core of the crate, and the tools built on them,
+//! such as [`Simulation`] and its models, are layered on top: the core never depends on them,
+//! and none of them is needed to schedule and run events. The crate does not aim to be a
+//! modelling language or a graphical simulation environment.
 //!
 //!
 //! ## Future Directions
@@ -255,6 +257,10 @@
 //! This crate provides essential components for event-driven simulations in Rust. Starting
 //! with events and a scheduler, and abstractions that provide weak coupling with state, this crate
 //! can be used to implement most conceivable discrete event simulations.
+//!
+//! Public types implement `Debug`, `Default` where they have an empty value, and `Display`
+//! where they describe events. The crate has no `serde` feature; data leaves a run through
+//! its CSV, JSON and SQL writers.
 
 ///////////////////////////////////
 // CONTENTS:                    //
@@ -270,15 +276,214 @@
 ///////////////
 
 use simple_mermaid::mermaid;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::cmp::Ordering;
 use std::fmt;
+use std::time::Duration;
+
+mod activity;
+mod agent;
+mod analysis;
+mod assertions;
+mod background;
+mod batch;
+mod blackboard;
+mod blocks;
+mod breakdown;
+mod builder;
+mod calendar;
+mod causality;
+mod channel;
+mod classes;
+mod cli;
+mod clock;
+mod coalesce;
+mod concurrent;
+mod condition;
+mod config;
+mod context;
+mod cosim;
+mod csv;
+#[cfg(feature = "chrono")]
+mod datetime;
+mod deadline;
+mod debug;
+mod diff;
+mod discipline;
+mod distributions;
+mod drive;
+mod embed;
+mod entity;
+mod error;
+mod experiment;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod graph;
+mod histogram;
+mod hybrid;
+mod inspect;
+mod intervention;
+mod inventory;
+mod lookahead;
+mod macros;
+mod markov;
+mod memory;
+mod metrics;
+mod mock;
+mod model;
+mod network;
+mod nhpp;
+mod optimize;
+mod petri;
+mod plot;
+mod pool;
+mod profile;
+mod queue;
+mod rate_limit;
+mod registry;
+mod report;
+mod resource;
+mod rng;
+mod rollback;
+mod routing;
+mod sensitivity;
+mod server;
+mod sim_event;
+mod simpy;
+mod sink;
+mod splitting;
+mod state_machine;
+mod stats;
+mod superdense;
+mod tags;
+mod testing;
+mod timestep;
+mod timewarp;
+mod trace;
+mod two_phase;
+mod units;
+mod world;
+
+pub use activity::{Activity, ActivityLog};
+pub use agent::{Agent, AgentContext, AgentId, AgentManager};
+pub use analysis::{batch_means, confidence_interval, student_t_quantile, welch_moving_average, ConfidenceInterval};
+pub use assertions::{EventAssertion, LogAssertions};
+pub use background::BackgroundSink;
+pub use batch::Batcher;
+pub use blackboard::{Blackboard, BlackboardEntry, WatchId};
+pub use blocks::{Block, Queue, Server, Sink, Source};
+pub use breakdown::Breakdown;
+pub use builder::EventSchedulerBuilder;
+pub use calendar::Calendar;
+pub use causality::{CausalNode, CausalityGraph};
+pub use channel::{Channel, ReceiveId};
+pub use classes::ClassPolicy;
+pub use cli::{Cli, CliError, CliOptions, OutputFormat};
+pub use clock::Clock;
+pub use coalesce::DedupPolicy;
+pub use concurrent::{CellView, Cells, ConcurrentEvent, ConcurrentWork};
+pub use condition::ConditionId;
+pub use config::{ConfigError, ScenarioConfig};
+pub use context::{Context, ContextBuilder, ContextExt, ContextMap};
+pub use cosim::{CoSimulation, Federate, FederateMessage, Federation, Outbox};
+#[cfg(feature = "chrono")]
+pub use datetime::Epoch;
+pub use deadline::Race;
+pub use debug::{Breakpoint, BreakpointId, ContextPredicate, DebugStop};
+pub use diff::{diff_logs, LogDiff, LogDifference};
+pub use discipline::{Fifo, Lifo, Priority, QueueDiscipline, QueuedRequest, RandomOrder, ShortestProcessingTime};
+pub use distributions::{sampler, Distribution, Empirical, Erlang, Exponential, KernelDensity, LogNormal, Mixture, Weibull};
+pub use drive::Drive;
+pub use embed::ExternalCall;
+pub use entity::{Entity, EntityId, EntityTracker, Milestone};
+pub use error::SimError;
+pub use experiment::{Experiment, ExperimentResults, ExperimentRow};
+#[cfg(feature = "ffi")]
+pub use ffi::{desru_cancel, desru_log_poll, desru_now, desru_run_until, desru_schedule, desru_scheduler_free, desru_scheduler_new, DesruCallback, DesruLogEntry};
+pub use graph::{EdgeStats, EventGraph};
+pub use histogram::{Bin, Histogram, P2Quantile};
+pub use hybrid::Continuous;
+pub use inspect::PendingEvent;
+pub use inventory::{Inventory, InventoryCosts, InventoryPolicy};
+pub use markov::MarkovChain;
+pub use memory::{memory_hook, MemoryFootprint};
+pub use metrics::SchedulerMetrics;
+pub use mock::{MockScheduler, ScheduleIntent, Scheduler};
+pub use model::{SimConfig, SimModel, Simulation};
+pub use network::{Link, Network, NodeId};
+pub use nhpp::Nhpp;
+pub use optimize::{optimize, Evaluation, GridSearch, NelderMead, OptimizationResult, Parameters, RandomSearch, SearchStrategy};
+pub use petri::PetriNet;
+pub use plot::{sample_hook, Sample, Trajectories};
+pub use pool::PoolStats;
+pub use profile::{LabelProfile, Profiler};
+pub use queue::{EventId, EventQueue, QueueBackend};
+pub use rate_limit::RateLimiter;
+pub use registry::{ActionRegistry, NamedAction};
+pub use report::{RunResult, StopReason};
+pub use resource::{Grant, GrantFn, PreemptFn, Preempted, PreemptionMode, RequestId, Resource, ResourceStats};
+pub use rng::{RngStreams, SeedAudit, SeedSequence, SeedStrategy, SimRng, DEFAULT_SEED};
+pub use rollback::{Checkpoint, TimeTravel};
+pub use routing::{routing_matrix, Probabilities, RandomRoute, RoundRobin, Router, RoutingPolicy, ShortestQueue};
+pub use sensitivity::{Sensitivity, SensitivityResults, SensitivityRow};
+pub use sim_event::SimEvent;
+pub use simpy::{Environment, Yield};
+pub use sink::{CsvSink, JsonLinesSink, LogSink, SqlSink, StreamSink};
+pub use splitting::{Splitting, SplittingEstimate};
+pub use state_machine::{DelayFn, StateHook, StateMachine};
+pub use stats::{Monitored, Tally};
+pub use superdense::SuperdenseTime;
+pub use tags::TagMetrics;
+pub use testing::{check_property, invariant_hook, Counterexample, EventSpec, Scenario, ScheduleSpec};
+pub use timewarp::{LogicalProcess, TimeWarp, TimeWarpStats, WarpMessage, WarpOutbox};
+pub use trace::{load_column, read_column, read_csv_trace, read_json_lines_trace, TraceError, TraceRecord};
+pub use two_phase::TwoPhaseEvent;
+pub use units::{ClockMode, IntoSimTime, SimDuration, TimeComparison, TimeUnit, TimeUnits};
+pub use world::WorldState;
+
+/// The closure executed when an event is triggered.
+pub type Action = Box<dyn FnMut(&mut EventScheduler) -> Option<String>>;
+
+/// A predicate over the scheduler that returns `true` when a run should stop.
+pub type StopCondition = Box<dyn Fn(&EventScheduler) -> bool>;
+
+/// A predicate deciding whether an executed event and its result are logged.
+pub type LogFilter = Box<dyn Fn(&ScheduledAction, &Option<String>) -> bool>;
+
+/// A callback invoked after every executed event, see [`EventSchedulerBuilder::hook`].
+pub type EventHook = Box<dyn FnMut(&EventScheduler, &ScheduledAction, &Option<String>)>;
+
+/// How much of a run to record in the event log, see [`EventScheduler::run_with_policy`].
+///
+/// Logging is still subject to the scheduler's `logging` flag and `warm_up` time.
+pub enum LogPolicy {
+    /// Record nothing, for runs that only need final statistics.
+    Off,
+    /// Record only events whose action returned a result, with their context cleared.
+    ResultsOnly,
+    /// Record the events accepted by a filter.
+    Filtered(LogFilter),
+    /// Record every executed event.
+    Full,
+}
+
+impl LogPolicy {
+    /// Returns `true` if an executed event should be recorded.
+    fn accepts(&self, event: &ScheduledAction, result: &Option<String>) -> bool {
+        match self {
+            LogPolicy::Off => false,
+            LogPolicy::ResultsOnly => result.is_some(),
+            LogPolicy::Filtered(filter) => filter(event, result),
+            LogPolicy::Full => true,
+        }
+    }
+}
 
 /////////////////////////////
 // $1 DEFINE EVENT STRUCT //
 ///////////////////////////
 
-/// Represents an event in the simulation.
+/// An action scheduled to run at a point in simulated time.
 ///
 /// Each event has a scheduled time (`time`), an associated action (`action`) 
 /// that will be executed when the event occurs, and a `context` for storing 
@@ -290,115 +495,297 @@ use std::fmt;
 ///   It returns an `Option<String>` to optionally pass a result when executed.
 /// - `context`: A map containing any extra contextual information as key-value pairs (both as `String`).
 /// - `active`: A boolean indicating if the event is active. If false, the event will not run.
-pub struct Event {
+/// - `entity`: The entity this event concerns, if any.
+/// - `label`: A name for the kind of event, such as `"arrival"`, used in diagnostics and exports.
+/// - `priority`: Orders events scheduled for the same time; lower values run first. Defaults to `0`.
+/// - `tags`: Tags counted per executed event in the scheduler's `tag_metrics`.
+pub struct ScheduledAction {
     pub time: f64,
-    pub action: Box<dyn FnMut(&mut EventScheduler) -> Option<String>>,
-    pub context: HashMap<String, String>,
+    pub action: Action,
+    pub context: Context,
     pub active: bool,
+    pub entity: Option<Entity>,
+    pub label: Option<String>,
+    pub priority: i64,
+    pub tags: BTreeSet<String>,
+    pub(crate) seq: u64,
+    pub(crate) microstep: u64,
+    pub(crate) chain: VecDeque<(f64, Action)>,
+    pub(crate) cause: Option<EventId>,
     }
 
+/// The former name of [`ScheduledAction`].
+///
+/// `Event` named the scheduled callback, which confused it with the completion events that
+/// process-interaction models wait on; those are now [`SimEvent`]s. The alias keeps existing
+/// models compiling and may be deprecated in a future release.
+///
+/// # Example
+/// ```
+/// use desru::{Event, ScheduledAction};
+///
+/// let event: ScheduledAction = Event::at(2.0).with_label("legacy");
+/// assert_eq!(event.time, 2.0);
+/// ```
+pub type Event = ScheduledAction;
+
 // Implement debug for using {:?}
-impl fmt::Debug for Event {
+impl fmt::Debug for ScheduledAction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Event")
+        f.debug_struct("ScheduledAction")
          .field("time", &self.time)
          .field("active", &self.active)
          .field("context", &self.context)
+         .field("entity", &self.entity)
+         .field("label", &self.label)
+         .field("priority", &self.priority)
+         .field("tags", &self.tags)
+         .field("chained", &self.chain.len())
          .finish()
     }
 }
 
-// Implement Clone manually for Event
-impl Clone for Event {
+// Implement Clone manually for ScheduledAction
+impl Clone for ScheduledAction {
     /// Creates a clone of the event.
     ///
     /// **Note**: The action closure is not cloned, since closures cannot be cloned. A placeholder
-    /// action that returns `None` is used in the cloned event. The `context` and other fields are 
-    /// copied as usual.
+    /// action that returns `None` is used in the cloned event, and actions chained with
+    /// [`ScheduledAction::then`] are dropped. The `context` and other fields are copied as usual.
     fn clone(&self) -> Self {
-        Event {
+        ScheduledAction {
             time: self.time,
             action: Box::new(|_| None), // Placeholder action for clone.
             context: self.context.clone(),
             active: self.active,
+            entity: self.entity,
+            label: self.label.clone(),
+            priority: self.priority,
+            tags: self.tags.clone(),
+            seq: self.seq,
+            microstep: self.microstep,
+            chain: VecDeque::new(),
+            cause: self.cause,
             }
         }
     }
 
-// Implement Event methods
-impl Event {
-    /// Creates a new `Event` with the given time, action, and context.
+// Implement ScheduledAction methods
+impl ScheduledAction {
+    /// Creates a new `ScheduledAction` with the given time, action, and context.
     ///
     /// # Parameters
     /// - `time`: The time when the event should be executed.
     /// - `action`: An optional closure representing the event's task. Defaults to a no-op (returns `None`).
-    /// - `context`: An optional `HashMap` of context information. Defaults to an empty map.
+    /// - `context`: An optional [`Context`] of key-value information. Defaults to an empty map.
     ///
     /// # Returns
-    /// A new `Event` instance.
+    /// A new `ScheduledAction` instance.
     ///
     /// # Example
     /// ```
-    /// use desru::{Event};
+    /// use desru::{ScheduledAction};
     ///
-    /// let event = Event::new(5.0, None, None);
+    /// let event = ScheduledAction::new(5.0, None, None);
     /// assert_eq!(event.time, 5.0);
     /// ```
-    pub fn new(time: f64, action: Option<Box<dyn FnMut(&mut EventScheduler) -> Option<String>>>, context: Option<HashMap<String, String>>) -> Self {
-        Event {
+    pub fn new(time: f64, action: Option<Action>, context: Option<Context>) -> Self {
+        ScheduledAction {
             time,
             action: action.unwrap_or_else(|| Box::new(|_| None)),
             context: context.unwrap_or_default(),
             active: true,
+            entity: None,
+            label: None,
+            priority: 0,
+            tags: BTreeSet::new(),
+            seq: 0,
+            microstep: 0,
+            chain: VecDeque::new(),
+            cause: None,
             }
     }
 
+    /// Creates a no-op `ScheduledAction` at the given time, to be customized with the `with_*` methods.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Context, EventScheduler, ScheduledAction};
+    ///
+    /// let event = ScheduledAction::at(3.0)
+    ///     .with_action(|s| Some(format!("ran at {}", s.current_time)))
+    ///     .with_context(Context::from([("station".to_string(), "A".to_string())]))
+    ///     .with_priority(-1);
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule(event);
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!(log[0].result, Some("ran at 3".to_string()));
+    /// ```
+    pub fn at(time: f64) -> Self {
+        ScheduledAction::new(time, None, None)
+    }
+
+    /// Sets the action run when the event is triggered.
+    pub fn with_action<F>(mut self, action: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+    {
+        self.action = Box::new(action);
+        self
+    }
+
+    /// Sets the event's context.
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Sets the priority used to order events scheduled for the same time; lower values run first.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_action(|_| Some("routine".to_string())));
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_action(|_| Some("urgent".to_string())).with_priority(-10));
+    /// let log = scheduler.run_until_max_time(5.0);
+    /// assert_eq!(log[0].result, Some("urgent".to_string()));
+    /// ```
+    pub fn with_priority(mut self, priority: i64) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Attaches an entity to the event.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let job = scheduler.create_entity();
+    /// let event = ScheduledAction::new(1.0, None, None).with_entity(job);
+    /// assert_eq!(event.entity.map(|e| e.id), Some(job.id));
+    /// ```
+    pub fn with_entity(mut self, entity: Entity) -> Self {
+        self.entity = Some(entity);
+        self
+    }
+
+    /// Labels the event, for example `"arrival"` or `"departure"`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::ScheduledAction;
+    ///
+    /// let event = ScheduledAction::new(1.0, None, None).with_label("arrival");
+    /// assert_eq!(event.label.as_deref(), Some("arrival"));
+    /// ```
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Adds a tag to the event.
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    /// Chains a follow-up action to run `delay` time units after this event's action.
+    ///
+    /// Each link is scheduled only when the previous one has executed, carrying the event's
+    /// context and entity along. Deactivating a link stops the rest of the chain.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule(
+    ///     ScheduledAction::new(0.0, Some(Box::new(|s| { println!("park at {}", s.current_time); None })), None)
+    ///         .then(5.0, |s| { println!("drive at {}", s.current_time); None })
+    ///         .then(2.0, |s| Some(format!("parked again at {}", s.current_time))),
+    /// );
+    /// let log = scheduler.run_until_max_time(15.0);
+    /// assert_eq!(log.last().unwrap().result, Some("parked again at 7".to_string()));
+    /// ```
+    pub fn then<F>(mut self, delay: f64, action: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+    {
+        self.chain.push_back((delay, Box::new(action)));
+        self
+    }
+
     /// Executes the action of the event if it is active.
     ///
+    /// If the event has actions chained with [`ScheduledAction::then`], the next link is scheduled.
+    ///
     /// # Returns
     /// - `Some(String)`: The result of the action if the event is active and the action produces a result.
     /// - `None`: If the event is inactive or the action produces no result.
     ///
     /// # Example
     /// ```
-    /// use desru::{Event, EventScheduler};
+    /// use desru::{EventScheduler, ScheduledAction};
     ///
     /// let mut scheduler = EventScheduler::new();
-    /// let mut event = Event::new(0.0,
-    ///                            Some(Box::new(|scheduler| Some("Executed".to_string()))),
-    ///                            None);
+    /// let mut event = ScheduledAction::new(0.0,
+    ///                                      Some(Box::new(|scheduler| Some("Executed".to_string()))),
+    ///                                      None);
     /// assert_eq!(event.run(&mut scheduler), Some("Executed".to_string()));
     /// ```
     pub fn run(&mut self, scheduler: &mut EventScheduler) -> Option<String> {
         if self.active {
-           (self.action)(scheduler)
+            let result = (self.action)(scheduler);
+            if let Some((delay, action)) = self.chain.pop_front() {
+                let mut next = ScheduledAction::new(scheduler.time_after(delay), Some(action), Some(self.context.clone()));
+                next.entity = self.entity;
+                next.chain = std::mem::take(&mut self.chain);
+                scheduler.schedule(next);
+            }
+            result
         } else {
             None
         }
     }
 
     /// Sets the event to be active.
-    pub fn activate(&mut self) -> () {
+    pub fn activate(&mut self) {
         self.active = true;
     }
 
     /// Sets the event to be inactive.
-    pub fn deactivate(&mut self) -> () {
+    pub fn deactivate(&mut self) {
         self.active = false;
     }
 }
 
-// Implement ordering traits for Event to use in BinaryHeap
-impl PartialEq for Event {
-    /// Checks if two events are equal based on their scheduled time.
+// Implement ordering traits for ScheduledAction to use in BinaryHeap
+impl PartialEq for ScheduledAction {
+    /// Checks if two events are equal based on their scheduled time and scheduling order.
     fn eq(&self, other: &Self) -> bool {
-        self.time == other.time
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-impl Eq for Event {}
+impl Eq for ScheduledAction {}
 
-impl PartialOrd for Event {
+impl fmt::Display for ScheduledAction {
+    /// Formats the event as its label and time, followed by its context if it has any.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.label.as_deref().unwrap_or("unlabeled"), self.time)?;
+        if !self.context.is_empty() {
+            write!(f, " ({})", context::display_pairs(&self.context))?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialOrd for ScheduledAction {
     /// Compares two events based on their time, in reverse order, for use in a max-heap.
     ///
     /// This allows events with earlier times to be processed first.
@@ -407,13 +794,18 @@ impl PartialOrd for Event {
     }
 }
 
-impl Ord for Event {
+impl Ord for ScheduledAction {
     /// Defines the ordering between two events.
     ///
     /// The event with the earlier time has higher priority, enabling
-    /// the `BinaryHeap` to act as a priority queue.
+    /// the `BinaryHeap` to act as a priority queue. Events scheduled for the
+    /// same time run by microstep, which is always zero unless superdense time is enabled,
+    /// then by ascending `priority`, then in the order they were scheduled.
     fn cmp(&self, other: &Self) -> Ordering {
-        other.time.partial_cmp(&self.time).unwrap()
+        other.time.total_cmp(&self.time)
+            .then_with(|| other.microstep.cmp(&self.microstep))
+            .then_with(|| other.priority.cmp(&self.priority))
+            .then_with(|| other.seq.cmp(&self.seq))
     }
 }
 
@@ -421,19 +813,125 @@ impl Ord for Event {
 // $2 DEFINE EVENT SCHEDULER //
 //////////////////////////////
 
+/// A logged event: what ran, when, and what it returned.
+///
+/// Records are built by moving the relevant fields out of the executed [`ScheduledAction`], so logging
+/// never clones its context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord {
+    pub id: EventId,
+    pub time: f64,
+    pub label: Option<String>,
+    pub context: Context,
+    pub result: Option<String>,
+}
+
+impl fmt::Display for EventRecord {
+    /// Formats the record as its id, label, and time, followed by its context and result if
+    /// it has them.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} at {}", self.id, self.label.as_deref().unwrap_or("unlabeled"), self.time)?;
+        if !self.context.is_empty() {
+            write!(f, " ({})", context::display_pairs(&self.context))?;
+        }
+        if let Some(result) = &self.result {
+            write!(f, " -> {}", result)?;
+        }
+        Ok(())
+    }
+}
+
+impl EventRecord {
+    /// Converts an executed event and its result into a record.
+    pub fn new(event: ScheduledAction, result: Option<String>) -> Self {
+        EventRecord { id: EventId(event.seq), time: event.time, label: event.label, context: event.context, result }
+    }
+}
+
 /// Manages and schedules events using a priority queue.
 ///
 /// The `EventScheduler` executes events based on their scheduled time, maintaining an event log
 /// and allowing for conditional execution (e.g., stop after a certain time or when certain criteria are met).
 ///
+/// Use [`EventScheduler::builder`] to configure options other than the defaults.
+///
+/// # Ordering Guarantees
+/// Events run in order of time. Events scheduled for the same time run by ascending
+/// `priority`, and events with equal time and priority run in the order they were scheduled,
+/// so an event scheduled with zero delay runs after every same-time event already pending with
+/// the same priority. Set `max_events_per_time` to catch zero-delay cascades that would
+/// otherwise keep the clock from ever advancing.
+///
 /// # Fields
 /// - `current_time`: The current time in the simulation, updated as events are processed.
-/// - `event_queue`: A binary heap used as a priority queue for storing scheduled events.
-/// - `event_log`: A log that stores all events executed and their results.
+/// - `event_queue`: A priority queue for storing scheduled events.
+/// - `event_log`: A log that stores a record of each event executed and its result.
+/// - `logging`: Whether executed events are recorded in `event_log`.
+/// - `warm_up`: Events executed before this time are not recorded in `event_log`.
+/// - `max_events_per_time`: The most events allowed to run at a single timestamp, if limited.
+/// - `wall_clock_budget`: The most wall-clock time a single run may take, if limited.
+/// - `time_unit`: What one unit of simulation time represents, if configured.
+/// - `clock_mode`: How delays are added to the current time, see [`ClockMode`].
+/// - `time_comparison`: The tolerance for comparing times, see [`TimeComparison`].
+/// - `rng`: The random number generator shared by the model.
+/// - `streams`: Named random number streams, for common random numbers across scenarios.
+/// - `activities`: Activities recorded with [`EventScheduler::begin_activity`] and [`EventScheduler::end_activity`].
+/// - `entities`: Lifecycle milestones of the entities created with [`EventScheduler::create_entity`].
+/// - `event_graph`: Which labeled events scheduled which others, observed as the run proceeds.
+/// - `tag_metrics`: How many executed events carried each tag.
+/// - `profiler`: How long the actions of each event label took, when enabled, see [`Profiler`].
+/// - `world`: The model's shared state, accessed with [`EventScheduler::state`] and [`EventScheduler::state_mut`].
+/// - `blackboard`: Shared, time-stamped key-value data, see [`Blackboard`].
+/// - `actions`: Actions that can be scheduled by name, see [`EventScheduler::schedule_named`].
+/// - `clocks`: Named local clocks, see [`Clock`].
+/// - `epoch`: The calendar datetime mapping, available with the `chrono` feature.
 pub struct EventScheduler {
     pub current_time: f64,
-    pub event_queue: BinaryHeap<Event>,
-    pub event_log: Vec<(Event, Option<String>)>,
+    pub event_queue: EventQueue,
+    pub event_log: Vec<EventRecord>,
+    pub logging: bool,
+    pub warm_up: f64,
+    pub max_events_per_time: Option<usize>,
+    pub wall_clock_budget: Option<Duration>,
+    pub time_unit: Option<TimeUnit>,
+    pub clock_mode: ClockMode,
+    pub time_comparison: TimeComparison,
+    pub rng: SimRng,
+    pub streams: RngStreams,
+    pub activities: ActivityLog,
+    pub entities: EntityTracker,
+    pub event_graph: EventGraph,
+    pub causality: CausalityGraph,
+    pub tag_metrics: TagMetrics,
+    pub profiler: Profiler,
+    pub world: WorldState,
+    pub blackboard: Blackboard,
+    pub cells: Cells,
+    pub actions: ActionRegistry,
+    pub clocks: HashMap<String, Clock>,
+    #[cfg(feature = "chrono")]
+    pub epoch: Option<Epoch>,
+    pub(crate) hooks: Vec<EventHook>,
+    pub(crate) current_label: Option<String>,
+    pub(crate) current_event: Option<EventId>,
+    pub(crate) events_at_time: usize,
+    pub(crate) debug: debug::DebugState,
+    pub(crate) continuous: Vec<Box<dyn hybrid::ContinuousSystem>>,
+    pub(crate) counters: metrics::RunCounters,
+    pub(crate) context_pool: pool::ContextPool,
+    pub(crate) coalesced: HashMap<String, coalesce::Coalesced>,
+    pub(crate) conditions: condition::Conditions,
+    pub(crate) pause_requested: bool,
+    pub(crate) stop_reason: Option<StopReason>,
+    pub(crate) invalid_event: Option<SimError>,
+    pub(crate) external_calls: embed::ExternalCalls,
+    pub(crate) two_phase: two_phase::TwoPhaseEvents,
+    pub(crate) interventions: intervention::Interventions,
+    pub(crate) concurrent: HashMap<u64, ConcurrentEvent>,
+    pub(crate) classes: classes::EventClasses,
+    pub(crate) min_delays: lookahead::MinDelays,
+    pub(crate) superdense: bool,
+    pub(crate) microstep: u64,
 }
 
 // Implement EventScheduler methods
@@ -451,11 +949,20 @@ impl EventScheduler {
     /// assert_eq!(scheduler.current_time, 0.0);
     /// ```
     pub fn new() -> Self {
-        EventScheduler {
-            current_time: 0.0,
-            event_queue: BinaryHeap::new(),
-            event_log: Vec::new(),
-        }
+        EventSchedulerBuilder::new().build()
+    }
+
+    /// Returns a builder for configuring a new `EventScheduler`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let scheduler = EventScheduler::builder().start_time(5.0).seed(1).build();
+    /// assert_eq!(scheduler.current_time, 5.0);
+    /// ```
+    pub fn builder() -> EventSchedulerBuilder {
+        EventSchedulerBuilder::new()
     }
 
     /// Schedules a new event by adding it to the event queue.
@@ -463,37 +970,163 @@ impl EventScheduler {
     /// # Parameters
     /// - `event`: The event to be scheduled.
     ///
+    /// # Returns
+    /// The [`EventId`] of the scheduled event, which can be passed to [`EventScheduler::cancel`].
+    /// An event at a NaN time is not queued and gets `EventId(0)`, which no event has; the
+    /// next step of a run then fails with [`SimError::InvalidEventTime`].
+    ///
     /// # Example
     /// ```
-    /// use desru::{Event, EventScheduler};
+    /// use desru::{EventScheduler, ScheduledAction};
     ///
     /// let mut scheduler = EventScheduler::new();
-    /// let event = Event::new(5.0, None, None);
+    /// let event = ScheduledAction::new(5.0, None, None);
     /// scheduler.schedule(event);
     /// ```
-    pub fn schedule(&mut self, event: Event) {
-        self.event_queue.push(event);
+    pub fn schedule(&mut self, mut event: ScheduledAction) -> EventId {
+        if event.time.is_nan() {
+            // NaN cannot be compared with other times, so the event is held back and the run
+            // fails with a description of it at its next step.
+            let error = SimError::InvalidEventTime { time: event.time, label: event.label, context: Box::new(event.context) };
+            self.invalid_event.get_or_insert(error);
+            return EventId(0);
+        }
+        event.microstep = self.microstep_at(event.time);
+        if let Some(label) = &event.label {
+            self.event_graph.record(self.current_label.as_deref(), label, event.time - self.current_time);
+        }
+        if !self.causality.is_tracking() {
+            return self.event_queue.push(event);
+        }
+        let (label, time, cause) = (event.label.clone(), event.time, event.cause.or(self.current_event));
+        let id = self.event_queue.push(event);
+        self.causality.record(CausalNode { id, label, cause, scheduled_at: self.current_time, time, executed: false });
+        id
+    }
+
+    /// Cancels a pending event.
+    ///
+    /// # Returns
+    /// `true` if the event was pending, `false` if it had already run or been cancelled.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let reminder = scheduler.timeout(5.0, Some(Box::new(|_| Some("Reminder".to_string()))), None);
+    /// assert!(scheduler.is_pending(reminder));
+    /// assert!(scheduler.cancel(reminder));
+    /// assert!(scheduler.run_until_max_time(10.0).is_empty());
+    /// ```
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        self.two_phase.remove(&id.0);
+        self.concurrent.remove(&id.0);
+        self.classes.remove(id);
+        self.interventions.retain(|(pending, _)| *pending != id);
+        self.event_queue.cancel(id)
+    }
+
+    /// Cancels every pending event labeled `label`.
+    ///
+    /// # Returns
+    /// The number of events cancelled.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::builder().label_index(true).build();
+    /// for t in [1.0, 2.0, 3.0] {
+    ///     scheduler.schedule(ScheduledAction::at(t).with_label("reminder"));
+    /// }
+    /// scheduler.schedule(ScheduledAction::at(2.5).with_label("arrival"));
+    /// assert_eq!(scheduler.cancel_label("reminder"), 3);
+    /// assert_eq!(scheduler.event_queue.len(), 1);
+    /// ```
+    pub fn cancel_label(&mut self, label: &str) -> usize {
+        let ids = self.event_queue.ids_with_label(label);
+        ids.into_iter().filter(|&id| self.cancel(id)).count()
+    }
+
+    /// Returns `true` if the event is still waiting to run.
+    pub fn is_pending(&self, id: EventId) -> bool {
+        self.event_queue.contains(id)
+    }
+
+    /// Returns the named random number stream, creating it on first use.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::builder().seed(4).build();
+    /// let service = scheduler.stream("service").gen_range(1.0, 2.0);
+    /// assert!((1.0..2.0).contains(&service));
+    /// ```
+    pub fn stream(&mut self, name: &str) -> &mut SimRng {
+        self.streams.stream(name)
+    }
+
+    /// Returns the seed the scheduler was built with and the seeds of the streams used so far.
+    pub fn seed_audit(&self) -> SeedAudit {
+        SeedAudit::of(&self.streams)
     }
 
     /// Schedules a timeout event to be executed after a specified delay.
     ///
     /// # Parameters
-    /// - `delay`: The amount of time after which the event should occur.
+    /// - `delay`: The amount of time after which the event should occur, in simulation time
+    ///   units or as a [`SimDuration`].
     /// - `action`: The action to be executed (optional).
     /// - `context`: Additional context for the event (optional).
     ///
+    /// # Returns
+    /// The [`EventId`] of the timeout event.
+    ///
     /// # Example
     /// ```
     /// use desru::EventScheduler;
     ///
     /// let mut scheduler = EventScheduler::new();
-    /// scheduler.timeout(10.0,
-    ///                   Some(Box::new(|_| Some("Timeout event".to_string()))),
-    ///                   None);
+    /// let id = scheduler.timeout(10.0,
+    ///                            Some(Box::new(|_| Some("Timeout event".to_string()))),
+    ///                            None);
+    /// assert!(scheduler.is_pending(id));
+    /// ```
+    pub fn timeout(&mut self, delay: impl IntoSimTime, action: Option<Action>, context: Option<Context>) -> EventId {
+        let event = ScheduledAction::new(self.time_after(self.delay(delay)), action, context);
+        self.schedule(event)
+    }
+
+    /// Schedules an action to run at the current time, before any other event already
+    /// scheduled for this time.
+    ///
+    /// Immediate events use the most urgent priority, `i64::MIN`, so they run before time
+    /// advances; several immediate events run in the order they were scheduled.
+    ///
+    /// # Returns
+    /// The [`EventId`] of the immediate event.
+    ///
+    /// # Example
     /// ```
-    pub fn timeout(&mut self, delay: f64, action: Option<Box<dyn FnMut(&mut EventScheduler) -> Option<String>>>, context: Option<HashMap<String, String>>) {
-        let event = Event::new(self.current_time + delay, action, context);
-        self.schedule(event);
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+    ///     s.schedule(ScheduledAction::at(1.0).with_action(|_| Some("later".to_string())));
+    ///     s.schedule_now(|_| Some("next".to_string()));
+    ///     None
+    /// }));
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!(log[1].result.as_deref(), Some("next"));
+    /// assert_eq!(log[2].result.as_deref(), Some("later"));
+    /// ```
+    pub fn schedule_now<F>(&mut self, action: F) -> EventId
+    where
+        F: FnMut(&mut EventScheduler) -> Option<String> + 'static,
+    {
+        self.schedule(ScheduledAction::at(self.current_time).with_action(action).with_priority(i64::MIN))
     }
 
     /// Runs the event scheduler until a stop condition is met.
@@ -503,11 +1136,12 @@ impl EventScheduler {
     /// - `log_filter`: An optional closure that determines whether to log an event. Defaults to logging all events.
     ///
     /// # Returns
-    /// A vector of executed events along with their results.
+    /// A [`RunResult`] holding the event log, a record of every logged event so far including
+    /// earlier runs, and summaries of the run.
     ///
     /// # Example
     /// ```
-    /// use desru::{Event, EventScheduler};
+    /// use desru::{EventScheduler, ScheduledAction};
     ///
     /// let mut scheduler = EventScheduler::new();
     /// scheduler.timeout(5.0,
@@ -516,20 +1150,189 @@ impl EventScheduler {
     /// let stop_fn = Box::new(|s: &EventScheduler| s.current_time >= 10.0);
     /// scheduler.run(stop_fn, None);
     /// ```
-    pub fn run(&mut self, stop: Box<dyn Fn(&Self) -> bool>, log_filter: Option<Box<dyn Fn(&Event, &Option<String>) -> bool>>)  -> Vec<(Event, Option<String>)> {
-        let log_filter = log_filter.unwrap_or_else(|| Box::new(|_, _| true));
-        while !stop(self) {
-            if let Some(mut event) = self.event_queue.pop() {
-                self.current_time = event.time;
-                let event_result = event.run(self);
-                if log_filter(&event, &event_result) {
-                    self.event_log.push((event, event_result));
-                }
-            } else {
-                break;
+    ///
+    /// # Panics
+    /// Panics if `max_events_per_time` is exceeded or an event was scheduled at a NaN time; use
+    /// [`EventScheduler::try_run`] to handle these as errors instead.
+    pub fn run(&mut self, stop: StopCondition, log_filter: Option<LogFilter>) -> RunResult<'_> {
+        self.try_run(stop, log_filter).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Runs the event scheduler until a stop condition is met, returning an error instead of
+    /// panicking when the run cannot continue.
+    ///
+    /// # Errors
+    /// Returns [`SimError::ZeroDelayCascade`] if more than `max_events_per_time` events would run
+    /// at one timestamp. The offending event is left in the queue.
+    ///
+    /// Returns [`SimError::InvalidEventTime`] if an event was scheduled at a NaN time. The
+    /// offending event is dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, SimError};
+    ///
+    /// fn ping(s: &mut EventScheduler) -> Option<String> {
+    ///     s.schedule_now(ping);
+    ///     None
+    /// }
+    ///
+    /// let mut scheduler = EventScheduler::builder().max_events_per_time(1000).build();
+    /// scheduler.schedule_now(ping);
+    /// let error = scheduler.try_run(Box::new(|_| false), None).unwrap_err();
+    /// assert_eq!(error, SimError::ZeroDelayCascade { time: 0.0, limit: 1000 });
+    /// ```
+    pub fn try_run(&mut self, stop: StopCondition, log_filter: Option<LogFilter>) -> Result<RunResult<'_>, SimError> {
+        let policy = match log_filter {
+            Some(filter) => LogPolicy::Filtered(filter),
+            None => LogPolicy::Full,
+        };
+        self.try_run_with_policy(stop, policy)
+    }
+
+    /// Runs the event scheduler until a stop condition is met, logging according to `policy`.
+    ///
+    /// The returned [`RunResult`] holds whatever the policy recorded.
+    ///
+    /// # Panics
+    /// Panics if `max_events_per_time` is exceeded or an event was scheduled at a NaN time; use
+    /// [`EventScheduler::try_run_with_policy`] to handle these as errors instead.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, LogPolicy};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.timeout(1.0, None, None);
+    /// scheduler.timeout(2.0, Some(Box::new(|_| Some("done".to_string()))), None);
+    /// scheduler.run_with_policy(Box::new(|s| s.current_time >= 10.0), LogPolicy::ResultsOnly);
+    /// assert_eq!(scheduler.event_log.len(), 1);
+    /// assert_eq!(scheduler.event_log[0].result.as_deref(), Some("done"));
+    /// ```
+    pub fn run_with_policy(&mut self, stop: StopCondition, policy: LogPolicy) -> RunResult<'_> {
+        self.try_run_with_policy(stop, policy).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Runs the event scheduler until a stop condition is met, logging according to `policy`
+    /// and returning an error instead of panicking when the run cannot continue.
+    ///
+    /// # Errors
+    /// As for [`EventScheduler::try_run`].
+    pub fn try_run_with_policy(&mut self, stop: StopCondition, policy: LogPolicy) -> Result<RunResult<'_>, SimError> {
+        let reason = self.try_run_observed(stop, &policy, |_, _, _| {})?;
+        Ok(RunResult::new(self, reason))
+    }
+
+    /// Runs until `stop` returns `true`, passing each executed event and its result to
+    /// `on_event` before it is logged. The run pauses, keeps to the wall-clock budget, and
+    /// records its stop reason and wall time as every run does.
+    pub(crate) fn try_run_observed<S, F>(&mut self, stop: S, policy: &LogPolicy, mut on_event: F) -> Result<StopReason, SimError>
+    where
+        S: Fn(&EventScheduler) -> bool,
+        F: FnMut(&mut EventScheduler, &ScheduledAction, &Option<String>),
+    {
+        let started = std::time::Instant::now();
+        let result = self.run_loop(stop, policy, started, &mut on_event);
+        self.counters.wall_time += started.elapsed();
+        self.stop_reason = Some(match &result {
+            Ok(reason) => reason.clone(),
+            Err(error) => StopReason::Error(error.clone()),
+        });
+        result
+    }
+
+    fn run_loop<S, F>(&mut self, stop: S, policy: &LogPolicy, started: std::time::Instant, on_event: &mut F) -> Result<StopReason, SimError>
+    where
+        S: Fn(&EventScheduler) -> bool,
+        F: FnMut(&mut EventScheduler, &ScheduledAction, &Option<String>),
+    {
+        self.pause_requested = false;
+        loop {
+            if stop(self) {
+                return Ok(StopReason::Condition);
+            }
+            let Some((event, event_result)) = self.execute_next()? else {
+                return Ok(StopReason::QueueEmpty);
+            };
+            on_event(self, &event, &event_result);
+            self.log_or_recycle(event, event_result, policy);
+            if std::mem::take(&mut self.pause_requested) {
+                return Ok(StopReason::Paused);
+            }
+            if self.wall_clock_budget.is_some_and(|budget| started.elapsed() >= budget) {
+                return Ok(StopReason::WallClockBudget);
             }
         }
-        self.event_log.clone()
+    }
+
+    /// Records an executed event if logging is on and `policy` accepts it, returning whatever
+    /// is not recorded to the context pool.
+    pub(crate) fn log_or_recycle(&mut self, mut event: ScheduledAction, result: Option<String>, policy: &LogPolicy) {
+        if self.should_log() && policy.accepts(&event, &result) {
+            if let LogPolicy::ResultsOnly = policy {
+                self.context_pool.put(std::mem::take(&mut event.context));
+            }
+            self.event_log.push(EventRecord::new(event, result));
+        } else {
+            self.context_pool.put(event.context);
+        }
+    }
+
+    /// Pops and runs the next event, calling the hooks but not logging it.
+    pub(crate) fn execute_next(&mut self) -> Result<Option<(ScheduledAction, Option<String>)>, SimError> {
+        if let Some(error) = self.invalid_event.take() {
+            return Err(error);
+        }
+        let next_time = loop {
+            let Some(next_time) = self.event_queue.peek().map(|e| e.time) else {
+                return Ok(None);
+            };
+            if !self.advance_continuous(next_time) {
+                break next_time;
+            }
+        };
+        if !self.time_comparison.same(next_time, self.current_time) {
+            self.events_at_time = 0;
+        } else if let Some(limit) = self.max_events_per_time {
+            if self.events_at_time >= limit {
+                return Err(SimError::ZeroDelayCascade { time: next_time, limit });
+            }
+        }
+        if let Some(intervention) = self.take_due_intervention(next_time) {
+            return Ok(Some(self.execute(intervention)));
+        }
+        let Some(event) = self.pop_next_event() else {
+            return Ok(None);
+        };
+        Ok(Some(self.execute(event)))
+    }
+
+    /// Runs an event that has been taken off the queue, calling the hooks but not logging it.
+    pub(crate) fn execute(&mut self, mut event: ScheduledAction) -> (ScheduledAction, Option<String>) {
+        self.events_at_time += 1;
+        self.counters.executed += 1;
+        self.current_time = event.time;
+        self.microstep = event.microstep;
+        self.current_label = event.label.take();
+        self.current_event = Some(EventId(event.seq));
+        self.causality.mark_executed(EventId(event.seq));
+        let started = self.profiler.is_enabled().then(std::time::Instant::now);
+        let event_result = event.run(self);
+        self.current_event = None;
+        event.label = self.current_label.take();
+        if let Some(started) = started {
+            self.profiler.record(event.label.as_deref(), started.elapsed());
+        }
+        for tag in &event.tags {
+            self.tag_metrics.record(tag);
+        }
+        self.run_hooks(&event, &event_result);
+        (event, event_result)
+    }
+
+    /// Returns `true` if an event executed now should be recorded in the log.
+    pub(crate) fn should_log(&self) -> bool {
+        self.logging && self.current_time >= self.warm_up
     }
 
     /// Runs the event scheduler until a specified maximum time is reached.
@@ -540,11 +1343,11 @@ impl EventScheduler {
     /// - `max_time`: The maximum simulation time.
     ///
     /// # Returns
-    /// A vector of executed events along with their results.
+    /// The run's [`RunResult`], as for [`EventScheduler::run`].
     ///
     /// # Example
     /// ```
-    /// use desru::{Event, EventScheduler};
+    /// use desru::{EventScheduler, ScheduledAction};
     ///
     /// let mut scheduler = EventScheduler::new();
     /// scheduler.timeout(5.0,
@@ -552,9 +1355,27 @@ impl EventScheduler {
     ///                   None);
     /// scheduler.run_until_max_time(10.0);
     /// ```
-    pub fn run_until_max_time(&mut self, max_time: f64) -> Vec<(Event, Option<String>)> {
+    pub fn run_until_max_time(&mut self, max_time: f64) -> RunResult<'_> {
         self.run(Box::new(stop_at_max_time_factory(max_time)), None)
     }
+
+    /// Calls every registered hook with the event that just ran and its result.
+    fn run_hooks(&mut self, event: &ScheduledAction, result: &Option<String>) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook(self, event, result);
+        }
+        self.hooks = hooks;
+    }
+}
+
+impl Default for EventScheduler {
+    fn default() -> Self {
+        EventScheduler::new()
+    }
 }
 
 /////////////////////////
@@ -568,11 +1389,13 @@ impl EventScheduler {
 /// - `max_time`: The maximum simulation time.
 ///
 /// # Returns
-/// A closure that returns `true` when the scheduler's current tim
-fn stop_at_max_time_factory(max_time: f64) -> Box<dyn Fn(&EventScheduler) -> bool> {
+/// A closure that returns `true` when the scheduler's current time, or the time of the next
+/// pending event, reaches `max_time`.
+fn stop_at_max_time_factory(max_time: f64) -> StopCondition {
     Box::new(move |scheduler: &EventScheduler| {
-        scheduler.current_time >= max_time
-        || scheduler.event_queue.peek().map_or(true, |event| event.time >= max_time)
+        let comparison = scheduler.time_comparison;
+        comparison.reached(scheduler.current_time, max_time)
+            || scheduler.event_queue.peek().is_some_and(|event| comparison.reached(event.time, max_time))
     })
 }
 
@@ -583,7 +1406,6 @@ fn stop_at_max_time_factory(max_time: f64) -> Box<dyn Fn(&EventScheduler) -> boo
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
 
     #[test]
     fn test_event_run() {
@@ -607,7 +1429,7 @@ mod tests {
     #[test]
     fn test_event_cloning() {
         let mut _scheduler = EventScheduler::new();
-        let mut context = HashMap::new();
+        let mut context = Context::new();
         context.insert("key".to_string(), "value".to_string());
         let original_event = Event::new(5.0, Some(Box::new(|_scheduler| Some("Executed".to_string()))), Some(context));
 
@@ -654,4 +1476,81 @@ mod tests {
         
         assert_eq!(executed_events.len(), 1); // Event A should execute
     }
+
+    #[test]
+    fn test_event_chaining() {
+        let mut scheduler = EventScheduler::new();
+        let mut context = Context::new();
+        context.insert("car".to_string(), "1".to_string());
+        let event = ScheduledAction::new(1.0, None, Some(context))
+            .then(2.0, |s| Some(format!("second at {}", s.current_time)))
+            .then(3.0, |s| Some(format!("third at {}", s.current_time)));
+        scheduler.schedule(event);
+
+        let log = scheduler.run_until_max_time(100.0);
+        let results: Vec<_> = log.iter().map(|r| (r.time, r.result.clone())).collect();
+        assert_eq!(results, vec![
+            (1.0, None),
+            (3.0, Some("second at 3".to_string())),
+            (6.0, Some("third at 6".to_string())),
+        ]);
+        assert!(log.iter().all(|r| r.context.get("car") == Some(&"1".to_string())));
+    }
+
+    #[test]
+    fn test_cascade_limit_resets_when_time_advances() {
+        let mut scheduler = EventScheduler::builder().max_events_per_time(2).build();
+        for time in [0.0, 0.0, 1.0, 1.0] {
+            scheduler.schedule(ScheduledAction::at(time));
+        }
+        assert_eq!(scheduler.run_until_max_time(10.0).len(), 4);
+
+        scheduler.schedule(ScheduledAction::at(scheduler.current_time));
+        let result = scheduler.try_run(Box::new(|_| false), None);
+        assert_eq!(result.unwrap_err(), SimError::ZeroDelayCascade { time: 1.0, limit: 2 });
+        assert_eq!(scheduler.event_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_events_and_records_display() {
+        let mut scheduler = EventScheduler::default();
+        let event = ScheduledAction::at(2.5).with_label("arrival").with_context(Context::builder().insert("queue", "b").insert("customer", 7).build()).with_action(|_| Some("served".to_string()));
+        assert_eq!(event.to_string(), "arrival at 2.5 (customer=7, queue=b)");
+        let id = scheduler.schedule(event);
+        assert_eq!(scheduler.pending_events()[0].to_string(), format!("{} arrival at 2.5 (customer=7, queue=b)", id));
+        scheduler.run_until_max_time(5.0);
+        assert_eq!(scheduler.event_log[0].to_string(), format!("{} arrival at 2.5 (customer=7, queue=b) -> served", id));
+        assert_eq!(ScheduledAction::at(1.0).to_string(), "unlabeled at 1");
+    }
+
+    #[test]
+    fn test_nan_time_is_reported_with_the_event() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+            let rate = 0.0;
+            s.schedule(ScheduledAction::at(s.current_time + rate / rate).with_label("departure").with_context(Context::builder().insert("customer", 17).build()));
+            None
+        }));
+        scheduler.schedule(ScheduledAction::at(2.0));
+        let error = scheduler.try_run(Box::new(|_| false), None).unwrap_err();
+        assert_eq!(error.to_string(), "event `departure` was scheduled at time NaN, which cannot be ordered (context: customer=17)");
+        assert_eq!((scheduler.current_time, scheduler.event_queue.len()), (1.0, 1));
+        assert_eq!(scheduler.try_run(Box::new(|_| false), None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_log_policies() {
+        let mut context = Context::new();
+        context.insert("id".to_string(), "7".to_string());
+        let mut scheduler = EventScheduler::new();
+        scheduler.timeout(1.0, Some(Box::new(|_| Some("a".to_string()))), None);
+        scheduler.timeout(2.0, None, None);
+        scheduler.run_with_policy(Box::new(|s| s.current_time >= 1.0), LogPolicy::Off);
+        assert!(scheduler.event_log.is_empty());
+        scheduler.timeout(1.0, Some(Box::new(|_| Some("b".to_string()))), Some(context));
+        scheduler.run_with_policy(Box::new(|_| false), LogPolicy::ResultsOnly);
+        assert_eq!(scheduler.event_log.len(), 1);
+        assert!(scheduler.event_log[0].context.is_empty());
+        assert_eq!(scheduler.metrics().events_executed, 3);
+    }
 }
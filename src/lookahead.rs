@@ -0,0 +1,92 @@
+//! # Lookahead
+//!
+//! A conservative parallel engine or a co-simulation master may only advance other parts of a
+//! model up to the earliest time at which this scheduler could still run an event.
+//! [`EventScheduler::lookahead`] returns that bound. It is never later than the next pending
+//! event, and it takes two other sources of earlier events into account:
+//!
+//! - Inputs from outside the queue, such as a host or another simulator, whose promised
+//!   minimum delay is registered with [`EventScheduler::register_min_delay`]. An input
+//!   registered with delay `d` never schedules an event sooner than `d` after the current time.
+//! - Continuous systems with zero-crossing guards, which may raise an event at any time before
+//!   the next pending one. While one is attached, the bound is the current time.
+
+use crate::EventScheduler;
+use std::collections::BTreeMap;
+
+/// The promised minimum delays of the inputs, by name.
+pub(crate) type MinDelays = BTreeMap<String, f64>;
+
+impl EventScheduler {
+    /// Promises that the input named `source` never schedules an event sooner than `delay`
+    /// after the current time, replacing any earlier promise for it.
+    ///
+    /// # Panics
+    /// Panics if `delay` is negative or NaN.
+    pub fn register_min_delay(&mut self, source: impl Into<String>, delay: f64) {
+        assert!(delay >= 0.0, "a minimum delay must be non-negative, got {}", delay);
+        self.min_delays.insert(source.into(), delay);
+    }
+
+    /// Withdraws the promise for the input named `source`, returning its delay.
+    pub fn remove_min_delay(&mut self, source: &str) -> Option<f64> {
+        self.min_delays.remove(source)
+    }
+
+    /// Returns the registered inputs and their minimum delays, sorted by name.
+    pub fn min_delays(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.min_delays.iter().map(|(source, delay)| (source.as_str(), *delay))
+    }
+
+    /// Returns a lower bound on the time of the next event the scheduler could run: the
+    /// earliest of the next pending event and the current time plus each registered minimum
+    /// delay, or the current time while a continuous system with zero crossings is attached.
+    ///
+    /// Infinity means that no event can ever run.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// assert_eq!(scheduler.lookahead(), f64::INFINITY);
+    ///
+    /// scheduler.schedule(ScheduledAction::at(10.0));
+    /// assert_eq!(scheduler.lookahead(), 10.0);
+    ///
+    /// // Orders arrive from outside at least 4 time units after they are placed.
+    /// scheduler.register_min_delay("orders", 4.0);
+    /// assert_eq!(scheduler.lookahead(), 4.0);
+    /// scheduler.schedule(ScheduledAction::at(6.0));
+    /// scheduler.run_until_max_time(8.0);
+    /// assert_eq!(scheduler.lookahead(), 10.0);
+    /// ```
+    pub fn lookahead(&self) -> f64 {
+        if self.continuous.iter().any(|system| system.has_crossings()) {
+            return self.current_time;
+        }
+        let next = self.event_queue.peek().map_or(f64::INFINITY, |event| event.time);
+        // In ticks mode a delay may round down, so an input's event can land before the
+        // current time plus its delay.
+        self.min_delays.values().map(|delay| self.time_after(*delay).min(self.current_time + delay)).fold(next, f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Continuous, EventScheduler, ScheduledAction};
+
+    #[test]
+    fn test_zero_crossings_pin_the_bound_to_now() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(5.0));
+        Continuous::new(1.0_f64, |dt, x: &mut f64| *x += dt).attach(&mut scheduler);
+        assert_eq!(scheduler.lookahead(), 5.0);
+
+        Continuous::new(1.0_f64, |dt, x: &mut f64| *x -= dt).zero_crossing(|x| *x, |_| {}).attach(&mut scheduler);
+        scheduler.register_min_delay("host", 0.5);
+        assert_eq!(scheduler.lookahead(), 0.0);
+        assert_eq!(scheduler.remove_min_delay("host"), Some(0.5));
+        assert_eq!(scheduler.min_delays().count(), 0);
+    }
+}
@@ -0,0 +1,69 @@
+//! # Macros
+
+/// Schedules a block of code to run at an absolute time or after a delay.
+///
+/// The macro hides the `Some(Box::new(move |scheduler| { ...; None }))` boilerplate of
+/// [`crate::ScheduledAction::new`]. Inside the block, the scheduler identifier passed as the first
+/// argument refers to the scheduler running the event, so the block can schedule further
+/// events through it. Like any event action, the block captures its environment by move.
+///
+/// - `schedule!(scheduler, at time => { ... })` runs the block at `time`.
+/// - `schedule!(scheduler, after delay => { ... })` runs the block `delay` after the current time,
+///   where `delay` is a number of time units or a [`crate::SimDuration`].
+///
+/// # Example
+/// ```
+/// use desru::{schedule, EventScheduler};
+///
+/// fn park(scheduler: &mut EventScheduler) {
+///     println!("Start parking at {}", scheduler.current_time);
+///     schedule!(scheduler, after 5.0 => { drive(scheduler) });
+/// }
+///
+/// fn drive(scheduler: &mut EventScheduler) {
+///     println!("Start driving at {}", scheduler.current_time);
+///     schedule!(scheduler, after 2.0 => { park(scheduler) });
+/// }
+///
+/// let mut scheduler = EventScheduler::new();
+/// schedule!(scheduler, at 0.0 => { park(scheduler) });
+/// scheduler.run_until_max_time(15.0);
+/// assert_eq!(scheduler.current_time, 14.0);
+/// ```
+#[macro_export]
+macro_rules! schedule {
+    ($scheduler:ident, at $time:expr => $body:block) => {
+        $scheduler.schedule($crate::ScheduledAction::new(
+            $time,
+            Some(Box::new(move |$scheduler: &mut $crate::EventScheduler| {
+                $body;
+                None
+            })),
+            None,
+        ))
+    };
+    ($scheduler:ident, after $delay:expr => $body:block) => {{
+        let time = $scheduler.time_after($scheduler.delay($delay));
+        $crate::schedule!($scheduler, at time => $body)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EventScheduler;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_schedule_macro_captures_and_nests() {
+        let fired = Rc::new(Cell::new(0.0));
+        let record = fired.clone();
+        let mut scheduler = EventScheduler::new();
+        schedule!(scheduler, at 1.0 => {
+            let record = record.clone();
+            schedule!(scheduler, after 2.5 => { record.set(scheduler.current_time) });
+        });
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(fired.get(), 3.5);
+    }
+}
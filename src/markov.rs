@@ -0,0 +1,229 @@
+//! # Markov Chains
+//!
+//! A [`MarkovChain`] simulates a continuous-time Markov chain from its transition rates: on
+//! entering a state it waits an exponentially distributed holding time whose rate is the sum
+//! of the state's outgoing rates, then jumps to a successor chosen with probability
+//! proportional to its rate. Giving a state its own holding-time distribution with
+//! [`MarkovChain::holding_with`] turns the chain into a semi-Markov process; the successor is
+//! still chosen by the rates.
+//!
+//! The time spent in each state is accumulated as the chain runs, so long-run occupancy can
+//! be compared with the chain's stationary distribution without keeping the whole path.
+
+use crate::{DelayFn, EventScheduler, SimRng};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct StateInfo {
+    rates: Vec<(usize, f64)>,
+    holding: Option<DelayFn>,
+    time: f64,
+    visits: u64,
+}
+
+struct ChainState {
+    names: Vec<String>,
+    states: Vec<StateInfo>,
+    current: usize,
+    entered: f64,
+    jumps: u64,
+}
+
+impl ChainState {
+    fn index(&mut self, name: &str) -> usize {
+        if let Some(index) = self.names.iter().position(|n| n == name) {
+            return index;
+        }
+        self.names.push(name.to_string());
+        self.states.push(StateInfo::default());
+        self.names.len() - 1
+    }
+}
+
+/// A continuous-time Markov or semi-Markov chain whose jumps are fired by the scheduler.
+///
+/// States are created as they are named. A state without outgoing rates is absorbing.
+/// Clones follow the same trajectory rather than starting an independent chain.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, MarkovChain};
+///
+/// // A machine that fails at rate 1 and is repaired at rate 4 is up 80% of the time.
+/// let machine = MarkovChain::new("up").rate("up", "down", 1.0).rate("down", "up", 4.0);
+/// let mut scheduler = EventScheduler::builder().seed(7).build();
+/// machine.start(&mut scheduler);
+/// scheduler.run_until_max_time(10_000.0);
+///
+/// let fractions = machine.occupancy_fractions(10_000.0);
+/// assert!((fractions["up"] - 0.8).abs() < 0.02);
+/// ```
+#[derive(Clone)]
+pub struct MarkovChain {
+    state: Rc<RefCell<ChainState>>,
+}
+
+impl MarkovChain {
+    /// Creates a chain that will begin in `initial` once started.
+    pub fn new(initial: &str) -> Self {
+        let mut state = ChainState { names: Vec::new(), states: Vec::new(), current: 0, entered: 0.0, jumps: 0 };
+        state.current = state.index(initial);
+        MarkovChain { state: Rc::new(RefCell::new(state)) }
+    }
+
+    /// Creates a chain from a rate matrix, where `rates[i][j]` is the rate of jumping from
+    /// `states[i]` to `states[j]`. The diagonal is ignored, so a generator matrix can be
+    /// passed as is.
+    ///
+    /// # Panics
+    /// Panics if the matrix is not square with one row per state.
+    pub fn from_rate_matrix(states: &[&str], rates: &[Vec<f64>], initial: &str) -> Self {
+        assert!(rates.len() == states.len() && rates.iter().all(|row| row.len() == states.len()), "the rate matrix must have one row and column per state");
+        let mut chain = MarkovChain::new(initial);
+        for (i, row) in rates.iter().enumerate() {
+            for (j, &rate) in row.iter().enumerate() {
+                if i != j && rate > 0.0 {
+                    chain = chain.rate(states[i], states[j], rate);
+                }
+            }
+        }
+        chain
+    }
+
+    /// Declares a jump from `from` to `to` at `rate`.
+    ///
+    /// # Panics
+    /// Panics if `rate` is negative or not finite.
+    pub fn rate(self, from: &str, to: &str, rate: f64) -> Self {
+        assert!(rate.is_finite() && rate >= 0.0, "transition rates must be finite and non-negative");
+        {
+            let mut state = self.state.borrow_mut();
+            let (from, to) = (state.index(from), state.index(to));
+            state.states[from].rates.push((to, rate));
+        }
+        self
+    }
+
+    /// Replaces the exponential holding time of `state` with one drawn from `holding`, making
+    /// the chain semi-Markov.
+    pub fn holding_with<F>(self, state: &str, holding: F) -> Self
+    where
+        F: FnMut(&mut SimRng) -> f64 + 'static,
+    {
+        {
+            let mut chain = self.state.borrow_mut();
+            let index = chain.index(state);
+            chain.states[index].holding = Some(Box::new(holding));
+        }
+        self
+    }
+
+    /// Returns the name of the current state.
+    pub fn state(&self) -> String {
+        let state = self.state.borrow();
+        state.names[state.current].clone()
+    }
+
+    /// Returns how many jumps the chain has made.
+    pub fn jumps(&self) -> u64 {
+        self.state.borrow().jumps
+    }
+
+    /// Returns how many times each state has been entered, including the initial entry.
+    pub fn visits(&self) -> HashMap<String, u64> {
+        let state = self.state.borrow();
+        state.names.iter().cloned().zip(state.states.iter().map(|s| s.visits)).collect()
+    }
+
+    /// Returns the total time spent in each state up to `now`.
+    pub fn occupancy(&self, now: f64) -> HashMap<String, f64> {
+        let state = self.state.borrow();
+        let mut totals: HashMap<String, f64> = state.names.iter().cloned().zip(state.states.iter().map(|s| s.time)).collect();
+        *totals.get_mut(&state.names[state.current]).unwrap() += now - state.entered;
+        totals
+    }
+
+    /// Returns the fraction of the time up to `now` spent in each state.
+    pub fn occupancy_fractions(&self, now: f64) -> HashMap<String, f64> {
+        let totals = self.occupancy(now);
+        let elapsed: f64 = totals.values().sum();
+        totals.into_iter().map(|(name, time)| (name, if elapsed > 0.0 { time / elapsed } else { 0.0 })).collect()
+    }
+
+    /// Enters the initial state at the current time.
+    pub fn start(&self, scheduler: &mut EventScheduler) {
+        let initial = {
+            let mut state = self.state.borrow_mut();
+            state.entered = scheduler.current_time;
+            state.current
+        };
+        self.enter(scheduler, initial);
+    }
+
+    fn enter(&self, scheduler: &mut EventScheduler, to: usize) {
+        let holding = {
+            let mut chain = self.state.borrow_mut();
+            let now = scheduler.current_time;
+            let (from, entered) = (chain.current, chain.entered);
+            chain.states[from].time += now - entered;
+            chain.current = to;
+            chain.entered = now;
+            let state = &mut chain.states[to];
+            state.visits += 1;
+            let total: f64 = state.rates.iter().map(|(_, rate)| rate).sum();
+            if total <= 0.0 {
+                return;
+            }
+            match state.holding.as_mut() {
+                Some(holding) => holding(&mut scheduler.rng),
+                None => -(1.0 - scheduler.rng.next_f64()).ln() / total,
+            }
+        };
+        let chain = self.clone();
+        scheduler.timeout(
+            holding,
+            Some(Box::new(move |s: &mut EventScheduler| {
+                let (next, label) = {
+                    let mut state = chain.state.borrow_mut();
+                    let rates = &state.states[state.current].rates;
+                    let total: f64 = rates.iter().map(|(_, rate)| rate).sum();
+                    let mut draw = s.rng.next_f64() * total;
+                    let next = rates.iter().find(|(_, rate)| {
+                        draw -= rate;
+                        draw < 0.0
+                    });
+                    // Rounding can leave the draw just short of the total; take the last jump.
+                    let next = next.or(rates.iter().rev().find(|(_, rate)| *rate > 0.0)).unwrap().0;
+                    state.jumps += 1;
+                    (next, format!("{} -> {}", state.names[state.current], state.names[next]))
+                };
+                chain.enter(s, next);
+                Some(label)
+            })),
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semi_markov_holding_times_and_absorption() {
+        let chain = MarkovChain::from_rate_matrix(&["a", "b", "end"], &[vec![-2.0, 1.0, 1.0], vec![3.0, -3.0, 0.0], vec![0.0, 0.0, 0.0]], "a")
+            .holding_with("a", |_| 2.0)
+            .holding_with("b", |_| 1.0);
+        let mut scheduler = EventScheduler::builder().seed(3).build();
+        chain.start(&mut scheduler);
+        scheduler.run_until_max_time(1_000.0);
+        assert_eq!(chain.state(), "end");
+        let visits = chain.visits();
+        assert_eq!(chain.jumps(), visits["a"] + visits["b"]);
+        let occupancy = chain.occupancy(scheduler.current_time);
+        assert_eq!(occupancy["a"], 2.0 * visits["a"] as f64);
+        assert_eq!(occupancy["b"], visits["b"] as f64);
+    }
+}
@@ -0,0 +1,188 @@
+//! # Memory Footprint
+//!
+//! Very large runs are more often limited by memory than by time: a queue of millions of
+//! pending events, or a full event log of a long run, can outgrow a constrained machine.
+//! [`EventScheduler::memory_footprint`] estimates how many bytes the queue and the log hold,
+//! and [`memory_hook`] calls back when the estimate crosses a threshold, so a model can warn,
+//! switch off logging, or stop before it runs out.
+//!
+//! The estimate counts the events and records themselves and the strings they own, by
+//! capacity. It cannot see inside action closures, so the state they capture is not counted.
+
+use crate::{Context, EventHook, EventRecord, EventScheduler, ScheduledAction};
+use std::fmt;
+use std::mem::size_of;
+
+/// An estimate of the heap memory held by a scheduler, see
+/// [`EventScheduler::memory_footprint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryFootprint {
+    /// The number of pending events.
+    pub queued_events: usize,
+    /// The bytes held by the pending events.
+    pub queue_bytes: usize,
+    /// The number of records in the event log.
+    pub logged_events: usize,
+    /// The bytes held by the event log, including its spare capacity.
+    pub log_bytes: usize,
+}
+
+impl MemoryFootprint {
+    /// Returns the total bytes of the queue and the log.
+    pub fn total_bytes(&self) -> usize {
+        self.queue_bytes + self.log_bytes
+    }
+}
+
+impl fmt::Display for MemoryFootprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "queue: {} events, {}", self.queued_events, human_bytes(self.queue_bytes))?;
+        writeln!(f, "log:   {} events, {}", self.logged_events, human_bytes(self.log_bytes))?;
+        write!(f, "total: {}", human_bytes(self.total_bytes()))
+    }
+}
+
+impl EventScheduler {
+    /// Estimates the memory held by the event queue and the event log.
+    ///
+    /// The estimate visits every pending event and log record, so it takes time in proportion
+    /// to their number; call it every so often rather than after every event.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Context, EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// for t in 0..1000 {
+    ///     let context = Context::from([("customer".to_string(), t.to_string())]);
+    ///     scheduler.schedule(ScheduledAction::at(f64::from(t)).with_context(context));
+    /// }
+    /// let before = scheduler.memory_footprint();
+    /// scheduler.run_until_max_time(500.0);
+    /// let after = scheduler.memory_footprint();
+    ///
+    /// assert_eq!((after.queued_events, after.logged_events), (500, 500));
+    /// assert!(after.queue_bytes < before.queue_bytes);
+    /// println!("{}", after);
+    /// ```
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        let queue_bytes = self.event_queue.iter().map(event_bytes).sum();
+        let log_bytes = self.event_log.capacity() * size_of::<EventRecord>() + self.event_log.iter().map(record_heap_bytes).sum::<usize>();
+        MemoryFootprint { queued_events: self.event_queue.len(), queue_bytes, logged_events: self.event_log.len(), log_bytes }
+    }
+}
+
+/// Returns a hook that checks the scheduler's [`MemoryFootprint`] every `check_every` events
+/// and calls `on_cross` when its total first reaches `threshold_bytes`.
+///
+/// After the total falls back below the threshold, for example because logging was switched
+/// off and the log was cleared, the next crossing calls `on_cross` again.
+///
+/// # Panics
+/// Panics if `check_every` is zero.
+///
+/// # Example
+/// ```
+/// use desru::{memory_hook, EventScheduler};
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// let warned = Rc::new(Cell::new(None));
+/// let warning = warned.clone();
+/// let mut scheduler = EventScheduler::builder()
+///     .hook(memory_hook(64 * 1024, 100, move |s, footprint| {
+///         eprintln!("memory use at t = {}:\n{}", s.current_time, footprint);
+///         warning.set(Some(s.current_time));
+///     }))
+///     .build();
+/// for t in 0..10_000 {
+///     scheduler.timeout(f64::from(t), None, None);
+/// }
+/// scheduler.run_until_max_time(f64::INFINITY);
+/// assert!(warned.get().is_some());
+/// ```
+pub fn memory_hook<F>(threshold_bytes: usize, check_every: u64, mut on_cross: F) -> EventHook
+where
+    F: FnMut(&EventScheduler, &MemoryFootprint) + 'static,
+{
+    assert!(check_every > 0, "memory must be checked at least every event");
+    let mut events = 0;
+    let mut above = false;
+    Box::new(move |scheduler: &EventScheduler, _: &ScheduledAction, _: &Option<String>| {
+        events += 1;
+        if events % check_every != 0 {
+            return;
+        }
+        let footprint = scheduler.memory_footprint();
+        let now_above = footprint.total_bytes() >= threshold_bytes;
+        if now_above && !above {
+            on_cross(scheduler, &footprint);
+        }
+        above = now_above;
+    })
+}
+
+fn event_bytes(event: &ScheduledAction) -> usize {
+    size_of::<ScheduledAction>()
+        + context_heap_bytes(&event.context)
+        + event.label.as_ref().map_or(0, String::capacity)
+        + event.tags.iter().map(|tag| size_of::<String>() + tag.capacity()).sum::<usize>()
+        + event.chain.capacity() * size_of::<(f64, crate::Action)>()
+}
+
+fn record_heap_bytes(record: &EventRecord) -> usize {
+    context_heap_bytes(&record.context) + record.label.as_ref().map_or(0, String::capacity) + record.result.as_ref().map_or(0, String::capacity)
+}
+
+fn context_heap_bytes(context: &Context) -> usize {
+    context.capacity() * size_of::<(String, String)>() + context.iter().map(|(key, value)| key.capacity() + value.capacity()).sum::<usize>()
+}
+
+fn human_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_hook_fires_once_per_crossing() {
+        let crossings = Rc::new(RefCell::new(Vec::new()));
+        let seen = crossings.clone();
+        let threshold = 200 * size_of::<EventRecord>();
+        let mut scheduler = EventScheduler::builder()
+            .hook(memory_hook(threshold, 1, move |s, _| seen.borrow_mut().push(s.current_time)))
+            .build();
+        fn tick(s: &mut EventScheduler) -> Option<String> {
+            if s.current_time == 300.0 {
+                s.event_log = Vec::new();
+            }
+            s.schedule(ScheduledAction::at(s.current_time + 1.0).with_action(tick));
+            None
+        }
+        scheduler.schedule(ScheduledAction::at(0.0).with_action(tick));
+        scheduler.run_until_max_time(1000.0);
+
+        // Hooks run before an event is logged. The log's capacity first exceeds 200 records
+        // when it grows to 256 for its 129th record.
+        assert_eq!(*crossings.borrow(), [129.0, 429.0]);
+        assert_eq!(scheduler.memory_footprint().queued_events, 1);
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+    }
+}
@@ -0,0 +1,121 @@
+//! # Run Metrics
+//!
+//! [`EventScheduler::metrics`] summarises how a run went: how many events were executed and
+//! cancelled, how large the queue grew, and how fast the scheduler ran in wall-clock time.
+
+use crate::csv::json_number;
+use crate::EventScheduler;
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Counters the scheduler keeps while running.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RunCounters {
+    pub(crate) executed: u64,
+    pub(crate) wall_time: Duration,
+}
+
+/// A summary of a scheduler's activity, see [`EventScheduler::metrics`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SchedulerMetrics {
+    pub events_executed: u64,
+    pub events_cancelled: u64,
+    /// Events still waiting to run.
+    pub events_pending: usize,
+    /// The largest number of events pending at once.
+    pub max_queue_length: usize,
+    /// Wall-clock time spent in [`EventScheduler::try_run`] and the functions built on it.
+    pub wall_time: Duration,
+    /// Events executed per second of `wall_time`, or `0.0` if no time was measured.
+    pub events_per_second: f64,
+    pub current_time: f64,
+}
+
+impl SchedulerMetrics {
+    /// Writes the metrics as a JSON object, with `null` for non-finite times and rates.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "{{\"events_executed\": {}, \"events_cancelled\": {}, \"events_pending\": {}, \"max_queue_length\": {}, \
+             \"wall_time_seconds\": {}, \"events_per_second\": {}, \"current_time\": {}}}",
+            self.events_executed,
+            self.events_cancelled,
+            self.events_pending,
+            self.max_queue_length,
+            json_number(Some(self.wall_time.as_secs_f64())),
+            json_number(Some(self.events_per_second)),
+            json_number(Some(self.current_time))
+        )
+    }
+}
+
+impl fmt::Display for SchedulerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "current time:      {}", self.current_time)?;
+        writeln!(f, "events executed:   {}", self.events_executed)?;
+        writeln!(f, "events cancelled:  {}", self.events_cancelled)?;
+        writeln!(f, "events pending:    {}", self.events_pending)?;
+        writeln!(f, "max queue length:  {}", self.max_queue_length)?;
+        writeln!(f, "wall time:         {:.3?}", self.wall_time)?;
+        write!(f, "events per second: {:.0}", self.events_per_second)
+    }
+}
+
+impl EventScheduler {
+    /// Returns a summary of the scheduler's activity so far.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.timeout(1.0, None, None);
+    /// let late = scheduler.timeout(50.0, None, None);
+    /// scheduler.cancel(late);
+    /// scheduler.run_until_max_time(10.0);
+    ///
+    /// let metrics = scheduler.metrics();
+    /// assert_eq!(metrics.events_executed, 1);
+    /// assert_eq!(metrics.events_cancelled, 1);
+    /// assert_eq!(metrics.max_queue_length, 2);
+    /// println!("{}", metrics);
+    /// ```
+    pub fn metrics(&self) -> SchedulerMetrics {
+        let seconds = self.counters.wall_time.as_secs_f64();
+        SchedulerMetrics {
+            events_executed: self.counters.executed,
+            events_cancelled: self.event_queue.cancelled(),
+            events_pending: self.event_queue.len(),
+            max_queue_length: self.event_queue.max_len(),
+            wall_time: self.counters.wall_time,
+            events_per_second: if seconds > 0.0 { self.counters.executed as f64 / seconds } else { 0.0 },
+            current_time: self.current_time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_json() {
+        let mut scheduler = EventScheduler::new();
+        for t in 0..3 {
+            scheduler.timeout(t as f64, None, None);
+        }
+        scheduler.run_until_max_time(10.0);
+        let mut out = Vec::new();
+        scheduler.metrics().write_json(&mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.contains("\"events_executed\": 3"));
+        assert!(json.contains("\"current_time\": 2"));
+
+        scheduler.schedule(crate::ScheduledAction::at(f64::INFINITY));
+        scheduler.run(Box::new(|_| false), None);
+        let mut out = Vec::new();
+        scheduler.metrics().write_json(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\"current_time\": null}\n"));
+    }
+}
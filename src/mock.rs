@@ -0,0 +1,184 @@
+//! # Scheduler Trait and Test Double
+//!
+//! [`Scheduler`] is the scheduling interface of [`EventScheduler`]: reading the clock,
+//! scheduling and cancelling events. Library code that only needs this interface can be
+//! written against the trait and then unit tested with a [`MockScheduler`], which records what
+//! was scheduled instead of running it and whose clock the test advances by hand.
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+
+/// The scheduling operations shared by [`EventScheduler`] and [`MockScheduler`].
+pub trait Scheduler {
+    /// Returns the current simulation time.
+    fn now(&self) -> f64;
+
+    /// Schedules `event` at its own time.
+    fn schedule(&mut self, event: ScheduledAction) -> EventId;
+
+    /// Cancels a pending event, returning `true` if it was pending.
+    fn cancel(&mut self, id: EventId) -> bool;
+
+    /// Schedules `event` `delay` after the current time, overriding its time.
+    fn schedule_after(&mut self, delay: f64, mut event: ScheduledAction) -> EventId {
+        event.time = self.now() + delay;
+        self.schedule(event)
+    }
+}
+
+impl Scheduler for EventScheduler {
+    fn now(&self) -> f64 {
+        self.current_time
+    }
+
+    fn schedule(&mut self, event: ScheduledAction) -> EventId {
+        EventScheduler::schedule(self, event)
+    }
+
+    fn cancel(&mut self, id: EventId) -> bool {
+        EventScheduler::cancel(self, id)
+    }
+
+    fn schedule_after(&mut self, delay: f64, mut event: ScheduledAction) -> EventId {
+        event.time = self.time_after(delay);
+        EventScheduler::schedule(self, event)
+    }
+}
+
+/// A scheduling call recorded by a [`MockScheduler`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleIntent {
+    pub id: EventId,
+    pub time: f64,
+    pub label: Option<String>,
+    pub priority: i64,
+    /// The mock's clock when the call was made.
+    pub scheduled_at: f64,
+    pub cancelled: bool,
+}
+
+/// A [`Scheduler`] that records scheduling calls without running anything.
+///
+/// # Example
+/// ```
+/// use desru::{MockScheduler, ScheduledAction, Scheduler};
+///
+/// // Library code written against the trait.
+/// fn start_shift<S: Scheduler>(s: &mut S, length: f64) {
+///     s.schedule_after(length, ScheduledAction::at(0.0).with_label("shift end"));
+/// }
+///
+/// let mut mock = MockScheduler::new();
+/// mock.advance_to(8.0);
+/// start_shift(&mut mock, 4.0);
+/// let intent = &mock.intents()[0];
+/// assert_eq!((intent.time, intent.label.as_deref()), (12.0, Some("shift end")));
+///
+/// mock.advance_to(12.0);
+/// assert_eq!(mock.due().count(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockScheduler {
+    now: f64,
+    next_id: u64,
+    intents: Vec<ScheduleIntent>,
+    events: Vec<Option<ScheduledAction>>,
+}
+
+impl MockScheduler {
+    /// Creates a mock whose clock reads `0.0`.
+    pub fn new() -> Self {
+        MockScheduler::default()
+    }
+
+    /// Moves the clock to `time`.
+    ///
+    /// # Panics
+    /// Panics if `time` is earlier than the current time.
+    pub fn advance_to(&mut self, time: f64) {
+        assert!(time >= self.now, "cannot move the clock back from {} to {}", self.now, time);
+        self.now = time;
+    }
+
+    /// Moves the clock forward by `delay`.
+    pub fn advance_by(&mut self, delay: f64) {
+        self.advance_to(self.now + delay);
+    }
+
+    /// Returns every recorded scheduling call in the order it was made.
+    pub fn intents(&self) -> &[ScheduleIntent] {
+        &self.intents
+    }
+
+    /// Returns the uncancelled intents scheduled for the current time or earlier.
+    pub fn due(&self) -> impl Iterator<Item = &ScheduleIntent> {
+        self.intents.iter().filter(|intent| !intent.cancelled && intent.time <= self.now)
+    }
+
+    /// Returns the uncancelled intents scheduled after the current time.
+    pub fn pending(&self) -> impl Iterator<Item = &ScheduleIntent> {
+        self.intents.iter().filter(|intent| !intent.cancelled && intent.time > self.now)
+    }
+
+    /// Removes and returns a recorded event, for example to run its action against a real
+    /// [`EventScheduler`].
+    pub fn take_event(&mut self, id: EventId) -> Option<ScheduledAction> {
+        let index = self.intents.iter().position(|intent| intent.id == id)?;
+        self.events[index].take()
+    }
+}
+
+impl Scheduler for MockScheduler {
+    fn now(&self) -> f64 {
+        self.now
+    }
+
+    fn schedule(&mut self, event: ScheduledAction) -> EventId {
+        self.next_id += 1;
+        let id = EventId(self.next_id);
+        self.intents.push(ScheduleIntent {
+            id,
+            time: event.time,
+            label: event.label.clone(),
+            priority: event.priority,
+            scheduled_at: self.now,
+            cancelled: false,
+        });
+        self.events.push(Some(event));
+        id
+    }
+
+    fn cancel(&mut self, id: EventId) -> bool {
+        match self.intents.iter_mut().find(|intent| intent.id == id) {
+            Some(intent) if !intent.cancelled && intent.time >= self.now => {
+                intent.cancelled = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder<S: Scheduler>(s: &mut S) -> EventId {
+        s.schedule_after(5.0, ScheduledAction::at(0.0).with_action(|_| Some("remind".to_string())))
+    }
+
+    #[test]
+    fn test_mock_and_real_schedulers_agree() {
+        let mut mock = MockScheduler::new();
+        let id = reminder(&mut mock);
+        assert_eq!(mock.pending().count(), 1);
+        assert!(mock.cancel(id));
+        assert!(!mock.cancel(id));
+        assert_eq!(mock.pending().count(), 0);
+
+        let mut real = EventScheduler::new();
+        let mut event = mock.take_event(id).unwrap();
+        assert_eq!(event.run(&mut real).as_deref(), Some("remind"));
+        reminder(&mut real);
+        assert_eq!(real.run_until_max_time(10.0)[0].time, 5.0);
+    }
+}
@@ -0,0 +1,351 @@
+//! # Models
+//!
+//! Larger models can implement [`SimModel`] instead of wiring closures by hand. The model
+//! schedules its initial events in [`SimModel::init`], reacts to every executed event in
+//! [`SimModel::on_event`], and produces its results in [`SimModel::finalize`].
+//! [`Simulation::run`] builds a scheduler from a [`SimConfig`] and drives the model through a
+//! single run, and [`Simulation::run_replications`] runs independent replications and merges
+//! the metrics they report.
+
+use crate::{EventScheduler, QueueBackend, ScheduledAction, SeedStrategy, Tally, DEFAULT_SEED};
+use std::collections::BTreeMap;
+
+/// A simulation model driven by [`Simulation::run`].
+///
+/// Events scheduled with a label are a convenient way to dispatch in `on_event`, since the
+/// model itself cannot be captured by the events' actions.
+pub trait SimModel {
+    /// The results of one run.
+    type Output;
+
+    /// Schedules the model's initial events.
+    fn init(&mut self, scheduler: &mut EventScheduler);
+
+    /// Called after each event is executed, with the event and its result.
+    fn on_event(&mut self, scheduler: &mut EventScheduler, event: &ScheduledAction, result: &Option<String>) {
+        let _ = (scheduler, event, result);
+    }
+
+    /// Called once the run has ended to collect its results.
+    fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output;
+}
+
+/// The configuration of a single simulation run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimConfig {
+    /// The run ends when the next event would occur at or after this time.
+    pub max_time: f64,
+    pub seed: u64,
+    pub warm_up: f64,
+    pub logging: bool,
+    pub queue_backend: QueueBackend,
+    pub max_events_per_time: Option<usize>,
+    /// Whether the run's random numbers mirror those of a non-antithetic run with the same seed.
+    pub antithetic: bool,
+}
+
+impl SimConfig {
+    /// Creates a configuration for a run ending at `max_time`, otherwise using the
+    /// scheduler defaults.
+    pub fn new(max_time: f64) -> Self {
+        SimConfig {
+            max_time,
+            seed: DEFAULT_SEED,
+            warm_up: 0.0,
+            logging: true,
+            queue_backend: QueueBackend::default(),
+            max_events_per_time: None,
+            antithetic: false,
+        }
+    }
+
+    /// Sets the seed of the run's random number generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Builds a scheduler configured for this run.
+    pub fn scheduler(&self) -> EventScheduler {
+        let mut builder = EventScheduler::builder()
+            .seed(self.seed)
+            .warm_up(self.warm_up)
+            .logging(self.logging)
+            .queue_backend(self.queue_backend)
+            .antithetic(self.antithetic);
+        if let Some(limit) = self.max_events_per_time {
+            builder = builder.max_events_per_time(limit);
+        }
+        builder.build()
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig::new(f64::INFINITY)
+    }
+}
+
+/// Drives [`SimModel`]s through simulation runs.
+pub struct Simulation;
+
+impl Simulation {
+    /// Runs `model` once under `config` and returns its results.
+    ///
+    /// The run ends early if an action requests a pause or the wall-clock budget runs out, and
+    /// the model is then finalized as usual.
+    ///
+    /// # Panics
+    /// Panics if the run exceeds `config.max_events_per_time` or an event was scheduled at a
+    /// NaN time.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction, SimConfig, SimModel, Simulation};
+    ///
+    /// struct Arrivals {
+    ///     count: u32,
+    /// }
+    ///
+    /// impl SimModel for Arrivals {
+    ///     type Output = u32;
+    ///
+    ///     fn init(&mut self, scheduler: &mut EventScheduler) {
+    ///         scheduler.schedule(ScheduledAction::at(1.0).with_label("arrival"));
+    ///     }
+    ///
+    ///     fn on_event(&mut self, scheduler: &mut EventScheduler, event: &ScheduledAction, _: &Option<String>) {
+    ///         if event.label.as_deref() == Some("arrival") {
+    ///             self.count += 1;
+    ///             let gap = scheduler.rng.gen_range(0.5, 1.5);
+    ///             scheduler.schedule(ScheduledAction::at(scheduler.current_time + gap).with_label("arrival"));
+    ///         }
+    ///     }
+    ///
+    ///     fn finalize(&mut self, _: &mut EventScheduler) -> u32 {
+    ///         self.count
+    ///     }
+    /// }
+    ///
+    /// let count = Simulation::run(&mut Arrivals { count: 0 }, &SimConfig::new(100.0));
+    /// assert!(count > 50 && count < 200);
+    /// ```
+    pub fn run<M: SimModel>(model: &mut M, config: &SimConfig) -> M::Output {
+        let mut scheduler = config.scheduler();
+        model.init(&mut scheduler);
+        let stop = crate::stop_at_max_time_factory(config.max_time);
+        scheduler
+            .try_run_observed(stop, &crate::LogPolicy::Full, |scheduler, event, result| model.on_event(scheduler, event, result))
+            .unwrap_or_else(|error| panic!("{}", error));
+        model.finalize(&mut scheduler)
+    }
+
+    /// Runs `n` independent replications, one after another, and merges the metrics they
+    /// report.
+    ///
+    /// # Parameters
+    /// - `make_model`: Builds a fresh model for each replication.
+    /// - `config`: The configuration of every run, apart from its seed.
+    /// - `n`: The number of replications.
+    /// - `seeds`: How the seed of each replication is chosen.
+    ///
+    /// # Returns
+    /// A [`Tally`] per metric name, holding one value per replication in replication order.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, SeedStrategy, SimConfig, SimModel, Simulation};
+    ///
+    /// struct Coin;
+    ///
+    /// impl SimModel for Coin {
+    ///     type Output = Vec<(&'static str, f64)>;
+    ///
+    ///     fn init(&mut self, _: &mut EventScheduler) {}
+    ///
+    ///     fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+    ///         vec![("heads", (scheduler.rng.next_f64() < 0.5) as u8 as f64)]
+    ///     }
+    /// }
+    ///
+    /// let metrics = Simulation::run_replications(|| Coin, &SimConfig::default(), 1000, &SeedStrategy::Sequential(1));
+    /// let heads = &metrics["heads"];
+    /// assert_eq!(heads.count(), 1000);
+    /// assert!((heads.mean() - 0.5).abs() < 0.1);
+    /// ```
+    pub fn run_replications<M, F, K>(make_model: F, config: &SimConfig, n: usize, seeds: &SeedStrategy) -> BTreeMap<String, Tally>
+    where
+        F: Fn() -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        merge_metrics((0..n).map(|replication| Simulation::replicate(&make_model, config, seeds, replication)))
+    }
+
+    /// Runs `n` pairs of antithetic replications and merges the metrics they report.
+    ///
+    /// Each pair runs once with the pair's seed and once with the antithetic twin of that seed,
+    /// and counts as a single observation: the average of the two runs. When a metric responds
+    /// monotonically to the random numbers, the two runs are negatively correlated and the
+    /// averages vary less than independent replications would.
+    ///
+    /// # Returns
+    /// A [`Tally`] per metric name, holding one pair average per pair in pair order.
+    pub fn run_antithetic_pairs<M, F, K>(make_model: F, config: &SimConfig, n: usize, seeds: &SeedStrategy) -> BTreeMap<String, Tally>
+    where
+        F: Fn() -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        let antithetic = SimConfig { antithetic: !config.antithetic, ..config.clone() };
+        merge_metrics((0..n).map(|pair| {
+            let first: BTreeMap<String, f64> = Simulation::replicate(&make_model, config, seeds, pair)
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect();
+            let mut averages = Vec::new();
+            for (name, value) in Simulation::replicate(&make_model, &antithetic, seeds, pair) {
+                let name: String = name.into();
+                if let Some(first) = first.get(&name) {
+                    averages.push((name, (first + value) / 2.0));
+                }
+            }
+            averages
+        }))
+    }
+
+    /// Runs `n` independent replications across threads and merges the metrics they report.
+    /// Requires the `rayon` feature.
+    ///
+    /// Each replication builds its own scheduler and model on the thread that runs it, with the
+    /// same seeds as [`Simulation::run_replications`], which it matches exactly.
+    #[cfg(feature = "rayon")]
+    pub fn run_replications_parallel<M, F, K>(make_model: F, config: &SimConfig, n: usize, seeds: &SeedStrategy) -> BTreeMap<String, Tally>
+    where
+        F: Fn() -> M + Sync,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String> + Send,
+    {
+        use rayon::prelude::*;
+
+        let outputs: Vec<Vec<(K, f64)>> = (0..n)
+            .into_par_iter()
+            .map(|replication| Simulation::replicate(&make_model, config, seeds, replication))
+            .collect();
+        merge_metrics(outputs)
+    }
+
+    fn replicate<M, F, K>(make_model: &F, config: &SimConfig, seeds: &SeedStrategy, replication: usize) -> Vec<(K, f64)>
+    where
+        F: Fn() -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+    {
+        let config = config.clone().with_seed(seeds.seed(replication));
+        Simulation::run(&mut make_model(), &config).into_iter().collect()
+    }
+}
+
+/// Merges per-replication metrics, in replication order, into one tally per metric.
+fn merge_metrics<K: Into<String>>(outputs: impl IntoIterator<Item = Vec<(K, f64)>>) -> BTreeMap<String, Tally> {
+    let mut metrics: BTreeMap<String, Tally> = BTreeMap::new();
+    for output in outputs {
+        for (name, value) in output {
+            metrics.entry(name.into()).or_default().record(value);
+        }
+    }
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl SimModel for Echo {
+        type Output = Vec<(f64, Option<String>)>;
+
+        fn init(&mut self, scheduler: &mut EventScheduler) {
+            scheduler.timeout(1.0, Some(Box::new(|_| Some("first".to_string()))), None);
+            scheduler.timeout(3.0, Some(Box::new(|_| Some("second".to_string()))), None);
+            scheduler.timeout(9.0, None, None);
+        }
+
+        fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+            scheduler.event_log.iter().map(|r| (r.time, r.result.clone())).collect()
+        }
+    }
+
+    #[test]
+    fn test_run_respects_config() {
+        let config = SimConfig { warm_up: 2.0, ..SimConfig::new(5.0) };
+        assert_eq!(Simulation::run(&mut Echo, &config), vec![(3.0, Some("second".to_string()))]);
+    }
+
+    struct Pausing;
+
+    impl SimModel for Pausing {
+        type Output = (Option<crate::StopReason>, u64, bool);
+
+        fn init(&mut self, scheduler: &mut EventScheduler) {
+            Echo.init(scheduler);
+        }
+
+        fn on_event(&mut self, scheduler: &mut EventScheduler, _: &ScheduledAction, result: &Option<String>) {
+            if result.as_deref() == Some("second") {
+                scheduler.request_pause();
+            }
+        }
+
+        fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+            let metrics = scheduler.metrics();
+            (scheduler.stop_reason().cloned(), metrics.events_executed, metrics.wall_time > std::time::Duration::ZERO)
+        }
+    }
+
+    #[test]
+    fn test_run_pauses_and_records_its_wall_time() {
+        assert_eq!(Simulation::run(&mut Pausing, &SimConfig::new(100.0)), (Some(crate::StopReason::Paused), 2, true));
+    }
+
+    struct Draw;
+
+    impl SimModel for Draw {
+        type Output = [(&'static str, f64); 1];
+
+        fn init(&mut self, _: &mut EventScheduler) {}
+
+        fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+            [("draw", scheduler.rng.next_f64())]
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_replications_match_sequential() {
+        let config = SimConfig::default();
+        let seeds = SeedStrategy::Hashed(5);
+        let sequential = Simulation::run_replications(|| Draw, &config, 16, &seeds);
+        let parallel = Simulation::run_replications_parallel(|| Draw, &config, 16, &seeds);
+        assert_eq!(sequential["draw"].values(), parallel["draw"].values());
+    }
+
+    #[test]
+    fn test_antithetic_pairs_cancel_uniform_draws() {
+        let pairs = Simulation::run_antithetic_pairs(|| Draw, &SimConfig::default(), 10, &SeedStrategy::default());
+        assert!(pairs["draw"].values().iter().all(|mean| (mean - 0.5).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_replications_use_seed_strategy() {
+        let seeds = SeedStrategy::Explicit(vec![3, 3, 4]);
+        let draws = Simulation::run_replications(|| Draw, &SimConfig::default(), 3, &seeds);
+        let values = draws["draw"].values();
+        assert_eq!(values[0], values[1]);
+        assert_ne!(values[1], values[2]);
+    }
+}
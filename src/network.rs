@@ -0,0 +1,235 @@
+//! # Networks
+//!
+//! A [`Network`] connects named nodes with directed [`Link`]s. Sending a payload over a link
+//! schedules its delivery automatically: the payload waits for the link to finish any earlier
+//! transmissions, takes `size / bandwidth` to transmit, and then arrives `latency` later at the
+//! receiving node's handler.
+
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Identifies a node within its network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node-{}", self.0)
+    }
+}
+
+/// The parameters of a directed link between two nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link {
+    /// The propagation delay from the end of transmission to arrival.
+    pub latency: f64,
+    /// The payload size transmitted per time unit, or `None` for instantaneous transmission.
+    pub bandwidth: Option<f64>,
+}
+
+impl Link {
+    /// Creates a link with the given latency and unlimited bandwidth.
+    pub fn new(latency: f64) -> Self {
+        Link { latency, bandwidth: None }
+    }
+
+    /// Sets the bandwidth, in payload size per time unit.
+    pub fn with_bandwidth(mut self, bandwidth: f64) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    /// Returns the time needed to transmit a payload of `size`.
+    pub fn transmission_time(&self, size: f64) -> f64 {
+        match self.bandwidth {
+            Some(bandwidth) => size / bandwidth,
+            None => 0.0,
+        }
+    }
+}
+
+/// Called with the sending node and the payload when a payload arrives at a node.
+type NodeHandler<P> = Box<dyn FnMut(&mut EventScheduler, NodeId, P)>;
+
+struct Node<P> {
+    name: String,
+    // Taken out while the handler runs, so that it can use the network.
+    handler: Option<NodeHandler<P>>,
+}
+
+struct LinkState {
+    link: Link,
+    free_at: f64,
+    sent: u64,
+}
+
+struct NetworkState<P> {
+    nodes: Vec<Node<P>>,
+    links: BTreeMap<(NodeId, NodeId), LinkState>,
+}
+
+/// A topology of nodes and links carrying payloads of type `P`.
+///
+/// Clones share the topology and the links' state, so node handlers can capture a clone to
+/// forward or answer payloads.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Link, Network};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let arrivals = Rc::new(RefCell::new(Vec::new()));
+/// let network: Network<&str> = Network::new();
+/// let client = network.add_node("client", |_, _, _| {});
+/// let record = arrivals.clone();
+/// let server = network.add_node("server", move |s, _, payload| record.borrow_mut().push((s.current_time, payload)));
+/// network.connect(client, server, Link::new(1.0).with_bandwidth(10.0));
+///
+/// let mut scheduler = EventScheduler::new();
+/// // 20 units take 2 to transmit; the second payload queues behind the first.
+/// network.send(&mut scheduler, client, server, "first", 20.0);
+/// network.send(&mut scheduler, client, server, "second", 10.0);
+/// scheduler.run_until_max_time(10.0);
+/// assert_eq!(*arrivals.borrow(), vec![(3.0, "first"), (4.0, "second")]);
+/// ```
+pub struct Network<P> {
+    state: Rc<RefCell<NetworkState<P>>>,
+}
+
+impl<P> Clone for Network<P> {
+    fn clone(&self) -> Self {
+        Network { state: self.state.clone() }
+    }
+}
+
+impl<P: 'static> Network<P> {
+    /// Creates an empty network.
+    pub fn new() -> Self {
+        Network { state: Rc::new(RefCell::new(NetworkState { nodes: Vec::new(), links: BTreeMap::new() })) }
+    }
+
+    /// Adds a node whose `handler` is called with the sender and payload of each arrival.
+    pub fn add_node<F>(&self, name: impl Into<String>, handler: F) -> NodeId
+    where
+        F: FnMut(&mut EventScheduler, NodeId, P) + 'static,
+    {
+        let mut state = self.state.borrow_mut();
+        state.nodes.push(Node { name: name.into(), handler: Some(Box::new(handler)) });
+        NodeId(state.nodes.len() - 1)
+    }
+
+    /// Returns the name of a node.
+    ///
+    /// # Panics
+    /// Panics if the node is not part of this network.
+    pub fn name(&self, node: NodeId) -> String {
+        self.state.borrow().nodes[node.0].name.clone()
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.state.borrow().nodes.len()
+    }
+
+    /// Returns `true` if the network has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds or replaces the directed link from `from` to `to`.
+    pub fn connect(&self, from: NodeId, to: NodeId, link: Link) {
+        self.state.borrow_mut().links.insert((from, to), LinkState { link, free_at: f64::NEG_INFINITY, sent: 0 });
+    }
+
+    /// Adds or replaces links in both directions, each with its own transmission queue.
+    pub fn connect_both(&self, a: NodeId, b: NodeId, link: Link) {
+        self.connect(a, b, link);
+        self.connect(b, a, link);
+    }
+
+    /// Returns the link from `from` to `to`, if there is one.
+    pub fn link(&self, from: NodeId, to: NodeId) -> Option<Link> {
+        self.state.borrow().links.get(&(from, to)).map(|state| state.link)
+    }
+
+    /// Returns the nodes that `node` has links to.
+    pub fn neighbors(&self, node: NodeId) -> Vec<NodeId> {
+        self.state.borrow().links.keys().filter(|(from, _)| *from == node).map(|&(_, to)| to).collect()
+    }
+
+    /// Returns how many payloads have been sent over the link from `from` to `to`.
+    pub fn sent(&self, from: NodeId, to: NodeId) -> u64 {
+        self.state.borrow().links.get(&(from, to)).map_or(0, |state| state.sent)
+    }
+
+    /// Sends a payload of the given `size` over the link from `from` to `to`.
+    ///
+    /// # Returns
+    /// The time at which the payload will arrive, or `None` if there is no such link.
+    pub fn send(&self, scheduler: &mut EventScheduler, from: NodeId, to: NodeId, payload: P, size: f64) -> Option<f64> {
+        let arrival = {
+            let mut state = self.state.borrow_mut();
+            let link = state.links.get_mut(&(from, to))?;
+            let start = link.free_at.max(scheduler.current_time);
+            link.free_at = start + link.link.transmission_time(size);
+            link.sent += 1;
+            link.free_at + link.link.latency
+        };
+        let network = self.clone();
+        let mut payload = Some(payload);
+        scheduler.schedule(ScheduledAction::at(arrival).with_action(move |s| {
+            if let Some(payload) = payload.take() {
+                network.deliver(s, from, to, payload);
+            }
+            None
+        }));
+        Some(arrival)
+    }
+
+    fn deliver(&self, scheduler: &mut EventScheduler, from: NodeId, to: NodeId, payload: P) {
+        let handler = self.state.borrow_mut().nodes[to.0].handler.take();
+        if let Some(mut handler) = handler {
+            handler(scheduler, from, payload);
+            self.state.borrow_mut().nodes[to.0].handler = Some(handler);
+        }
+    }
+}
+
+impl<P: 'static> Default for Network<P> {
+    fn default() -> Self {
+        Network::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarding_over_two_hops() {
+        let network: Network<u32> = Network::new();
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let a = network.add_node("a", |_, _, _| {});
+        let forward = network.clone();
+        let b = network.add_node("b", move |s, _, hops| {
+            forward.send(s, NodeId(1), NodeId(2), hops + 1, 0.0);
+        });
+        let record = received.clone();
+        let c = network.add_node("c", move |s, from, hops| record.borrow_mut().push((s.current_time, from, hops)));
+        network.connect(a, b, Link::new(1.5));
+        network.connect_both(b, c, Link::new(2.0));
+
+        let mut scheduler = EventScheduler::new();
+        assert_eq!(network.send(&mut scheduler, a, c, 0, 0.0), None);
+        assert_eq!(network.send(&mut scheduler, a, b, 0, 0.0), Some(1.5));
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(*received.borrow(), vec![(3.5, b, 1)]);
+        assert_eq!(network.neighbors(b), vec![c]);
+        assert_eq!(network.sent(b, c), 1);
+        assert_eq!(network.name(c), "c");
+    }
+}
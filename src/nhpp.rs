@@ -0,0 +1,168 @@
+//! # Time-Varying Arrivals
+//!
+//! Arrivals to most service systems follow the time of day: a lunchtime peak at a café, a
+//! Monday morning surge at a help desk. A [`Nhpp`] is a nonhomogeneous Poisson process whose
+//! rate varies with time, given either as a piecewise-constant schedule or as a function
+//! `λ(t)`. Arrival times are drawn by thinning (Lewis and Shedler): candidates are drawn from a
+//! homogeneous process at an upper bound of the rate, and a candidate at time `t` is kept with
+//! probability `λ(t)` over the bound.
+
+use crate::{EventScheduler, SimRng};
+
+/// A nonhomogeneous Poisson arrival process.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Nhpp, Sink, Source};
+///
+/// // A help desk open from 8 to 17, busiest in the morning, repeating every 24 hours.
+/// let calls = Nhpp::piecewise(&[(0.0, 0.0), (8.0, 12.0), (12.0, 6.0), (17.0, 0.0)]).cyclic(24.0);
+/// let mut scheduler = EventScheduler::builder().seed(5).build();
+/// let arrivals = Source::new(calls.sampler("calls"));
+/// let desk = Sink::new();
+/// arrivals.connect(&desk);
+/// arrivals.start(&mut scheduler);
+/// scheduler.run_until_max_time(24.0 * 30.0);
+///
+/// // 4 * 12 + 5 * 6 = 78 calls a day on average.
+/// let per_day = desk.count() as f64 / 30.0;
+/// assert!((per_day - 78.0).abs() < 5.0);
+/// ```
+pub struct Nhpp {
+    rate: Rate,
+    bound: f64,
+    period: Option<f64>,
+}
+
+enum Rate {
+    /// Segment start times and the rate from each start to the next.
+    Piecewise(Vec<(f64, f64)>),
+    Function(Box<dyn Fn(f64) -> f64>),
+}
+
+impl Nhpp {
+    /// Creates a process with a piecewise-constant rate, given as `(start, rate)` pairs: the
+    /// rate is `rate` from `start` until the next segment's start, and the last rate holds
+    /// indefinitely. Before the first start the rate is zero.
+    ///
+    /// # Panics
+    /// Panics if there are no segments, the starts are not increasing, or a rate is negative
+    /// or not finite.
+    pub fn piecewise(segments: &[(f64, f64)]) -> Self {
+        assert!(!segments.is_empty(), "a piecewise rate needs at least one segment");
+        assert!(segments.windows(2).all(|w| w[0].0 < w[1].0), "segment starts must be increasing");
+        assert!(segments.iter().all(|(_, rate)| rate.is_finite() && *rate >= 0.0), "rates must be finite and non-negative");
+        let bound = segments.iter().map(|(_, rate)| *rate).fold(0.0, f64::max);
+        Nhpp { rate: Rate::Piecewise(segments.to_vec()), bound, period: None }
+    }
+
+    /// Creates a process with rate `rate(t)`, which must never exceed `bound`. The closer the
+    /// bound is to the peak rate, the fewer candidates are rejected.
+    ///
+    /// # Panics
+    /// Panics if `bound` is negative or not finite, and when sampling if `rate` returns a value
+    /// outside `[0, bound]`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Nhpp, SimRng};
+    ///
+    /// let rush = Nhpp::new(|t: f64| 5.0 + 4.0 * (t / 3.0).sin(), 9.0);
+    /// let first = rush.next_arrival(0.0, &mut SimRng::new(2)).unwrap();
+    /// assert!(first > 0.0);
+    /// ```
+    pub fn new<F>(rate: F, bound: f64) -> Self
+    where
+        F: Fn(f64) -> f64 + 'static,
+    {
+        assert!(bound.is_finite() && bound >= 0.0, "the rate bound must be finite and non-negative");
+        Nhpp { rate: Rate::Function(Box::new(rate)), bound, period: None }
+    }
+
+    /// Repeats the rate every `period`, so that a daily or weekly profile need only be given
+    /// once: the rate at `t` is the rate at `t` modulo `period`.
+    ///
+    /// # Panics
+    /// Panics if `period` is not positive and finite.
+    pub fn cyclic(mut self, period: f64) -> Self {
+        assert!(period > 0.0 && period.is_finite(), "the period must be positive and finite");
+        self.period = Some(period);
+        self
+    }
+
+    /// Returns the arrival rate at time `t`.
+    pub fn rate(&self, t: f64) -> f64 {
+        let t = self.period.map_or(t, |period| t.rem_euclid(period));
+        match &self.rate {
+            Rate::Piecewise(segments) => segments.iter().rev().find(|(start, _)| *start <= t).map_or(0.0, |(_, rate)| *rate),
+            Rate::Function(rate) => rate(t),
+        }
+    }
+
+    /// Draws the first arrival after `now`.
+    ///
+    /// # Returns
+    /// The arrival time, or `None` if the rate is zero from `now` on, as for a piecewise rate
+    /// whose last segment has rate zero.
+    pub fn next_arrival(&self, now: f64, rng: &mut SimRng) -> Option<f64> {
+        if self.bound == 0.0 {
+            return None;
+        }
+        let mut t = now;
+        loop {
+            if self.is_exhausted(t) {
+                return None;
+            }
+            t += -(1.0 - rng.next_f64()).ln() / self.bound;
+            let rate = self.rate(t);
+            assert!((0.0..=self.bound).contains(&rate), "the arrival rate {} at {} is outside [0, {}]", rate, t, self.bound);
+            if rng.next_f64() * self.bound < rate {
+                return Some(t);
+            }
+        }
+    }
+
+    /// Returns a closure drawing interarrival times on the scheduler's stream named `stream`,
+    /// in the form taken by [`crate::Source::new`]. Once no further arrival can occur, it
+    /// returns infinity, which defers the next arrival past any horizon.
+    pub fn sampler(self, stream: &str) -> impl FnMut(&mut EventScheduler) -> f64 {
+        let stream = stream.to_string();
+        move |scheduler| {
+            let now = scheduler.current_time;
+            self.next_arrival(now, scheduler.stream(&stream)).map_or(f64::INFINITY, |t| t - now)
+        }
+    }
+
+    /// Returns `true` if a non-cyclic piecewise rate is zero from `t` on.
+    fn is_exhausted(&self, t: f64) -> bool {
+        match (&self.rate, self.period) {
+            (Rate::Piecewise(segments), None) => segments.last().is_some_and(|&(start, rate)| rate == 0.0 && t >= start),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thinning_follows_the_rate_and_stops_when_it_ends() {
+        let process = Nhpp::piecewise(&[(1.0, 2.0), (3.0, 8.0), (4.0, 0.0)]);
+        assert_eq!((process.rate(0.5), process.rate(3.5), process.rate(9.0)), (0.0, 8.0, 0.0));
+        let mut rng = SimRng::new(9);
+        let mut counts = [0.0; 2];
+        let runs = 2_000;
+        for _ in 0..runs {
+            let mut t = 0.0;
+            while let Some(next) = process.next_arrival(t, &mut rng) {
+                assert!((1.0..4.0).contains(&next));
+                counts[usize::from(next >= 3.0)] += 1.0;
+                t = next;
+            }
+        }
+        // Two time units at rate 2, then one at rate 8.
+        assert!((counts[0] / runs as f64 - 4.0).abs() < 0.2);
+        assert!((counts[1] / runs as f64 - 8.0).abs() < 0.3);
+    }
+}
@@ -0,0 +1,348 @@
+//! # Simulation-Based Optimization
+//!
+//! [`optimize`] searches scenario parameters, such as the number of servers or a reorder
+//! point, for the values that minimize an objective computed from an experiment's results.
+//! Each candidate is run as a one-scenario [`Experiment`] with the same replication seeds, so
+//! candidates are compared under common random numbers and differences in the objective
+//! reflect the parameters rather than the noise.
+//!
+//! The search is a [`SearchStrategy`] that proposes candidates and is told their objective
+//! values. [`GridSearch`], [`RandomSearch`], and [`NelderMead`] are provided; any other
+//! strategy, such as a domain-specific heuristic, implements the trait.
+
+use crate::{Experiment, ExperimentResults, SimConfig, SimModel, SimRng};
+use std::collections::BTreeMap;
+
+/// Scenario parameters, by name, as used by [`Experiment::grid`].
+pub type Parameters = BTreeMap<String, f64>;
+
+/// Proposes candidate parameters and learns from their objective values.
+pub trait SearchStrategy {
+    /// Returns the next candidate to evaluate, or `None` when the search is finished.
+    fn propose(&mut self) -> Option<Parameters>;
+
+    /// Receives the objective value of the candidate last proposed.
+    fn observe(&mut self, parameters: &Parameters, value: f64);
+}
+
+/// One evaluated candidate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub parameters: Parameters,
+    pub value: f64,
+}
+
+/// The outcome of [`optimize`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizationResult {
+    /// The candidate with the lowest objective value.
+    pub best: Evaluation,
+    /// Every candidate evaluated, in order.
+    pub evaluations: Vec<Evaluation>,
+}
+
+/// Searches for the parameters minimizing `objective`.
+///
+/// Each candidate proposed by `strategy` is run for `replications` replications of a model
+/// built by `make_model`, with seeds hashed from `config.seed` as for [`Experiment::new`], and
+/// scored by `objective` on the results. The candidate's scenario is named like the scenarios
+/// of [`Experiment::grid`].
+///
+/// # Panics
+/// Panics if the strategy proposes no candidate.
+///
+/// # Example
+/// ```
+/// use desru::{optimize, EventScheduler, GridSearch, SimConfig, SimModel};
+///
+/// // Staffing cost of 3 per server plus a waiting cost that falls with more servers.
+/// struct Staffing { servers: f64 }
+///
+/// impl SimModel for Staffing {
+///     type Output = [(&'static str, f64); 1];
+///     fn init(&mut self, _: &mut EventScheduler) {}
+///     fn finalize(&mut self, s: &mut EventScheduler) -> Self::Output {
+///         let waiting = 20.0 / self.servers * (0.9 + 0.2 * s.rng.next_f64());
+///         [("cost", 3.0 * self.servers + waiting)]
+///     }
+/// }
+///
+/// let search = GridSearch::new(&[("servers", &[1.0, 2.0, 3.0, 4.0, 5.0])]);
+/// let result = optimize(&SimConfig::new(1.0), 20, search, |p| Staffing { servers: p["servers"] }, |results| {
+///     results.rows().iter().map(|row| row.value).sum::<f64>() / results.rows().len() as f64
+/// });
+/// assert_eq!(result.best.parameters["servers"], 3.0);
+/// assert_eq!(result.evaluations.len(), 5);
+/// ```
+pub fn optimize<S, M, F, K, O>(config: &SimConfig, replications: usize, mut strategy: S, make_model: F, objective: O) -> OptimizationResult
+where
+    S: SearchStrategy,
+    F: Fn(&Parameters) -> M,
+    M: SimModel,
+    M::Output: IntoIterator<Item = (K, f64)>,
+    K: Into<String>,
+    O: Fn(&ExperimentResults) -> f64,
+{
+    let mut evaluations: Vec<Evaluation> = Vec::new();
+    while let Some(parameters) = strategy.propose() {
+        let label: Vec<String> = parameters.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        let results = Experiment::new(config.clone()).replications(replications).scenario(label.join(","), parameters.clone()).run(&make_model);
+        let value = objective(&results);
+        strategy.observe(&parameters, value);
+        evaluations.push(Evaluation { parameters, value });
+    }
+    let best = evaluations.iter().min_by(|a, b| a.value.total_cmp(&b.value)).cloned().expect("the search strategy proposed no candidates");
+    OptimizationResult { best, evaluations }
+}
+
+/// Evaluates every combination of factor levels.
+#[derive(Debug, Clone)]
+pub struct GridSearch {
+    candidates: std::vec::IntoIter<Parameters>,
+}
+
+impl GridSearch {
+    /// Creates a search over every combination of the given levels of each factor.
+    pub fn new(factors: &[(&str, &[f64])]) -> Self {
+        let config = SimConfig::new(0.0);
+        let grid = Experiment::grid(config, factors);
+        let candidates: Vec<Parameters> = grid.scenarios().iter().map(|(_, parameters)| parameters.clone()).collect();
+        GridSearch { candidates: candidates.into_iter() }
+    }
+}
+
+impl SearchStrategy for GridSearch {
+    fn propose(&mut self) -> Option<Parameters> {
+        self.candidates.next()
+    }
+
+    fn observe(&mut self, _: &Parameters, _: f64) {}
+}
+
+/// Evaluates candidates drawn uniformly from a box of parameter ranges.
+#[derive(Debug, Clone)]
+pub struct RandomSearch {
+    bounds: Vec<(String, f64, f64)>,
+    remaining: usize,
+    rng: SimRng,
+}
+
+impl RandomSearch {
+    /// Creates a search drawing `samples` candidates, each parameter uniform in `[low, high)`.
+    pub fn new(bounds: &[(&str, f64, f64)], samples: usize, seed: u64) -> Self {
+        let bounds = bounds.iter().map(|&(name, low, high)| (name.to_string(), low, high)).collect();
+        RandomSearch { bounds, remaining: samples, rng: SimRng::new(seed) }
+    }
+}
+
+impl SearchStrategy for RandomSearch {
+    fn propose(&mut self) -> Option<Parameters> {
+        self.remaining = self.remaining.checked_sub(1)?;
+        Some(self.bounds.iter().map(|(name, low, high)| (name.clone(), self.rng.gen_range(*low, *high))).collect())
+    }
+
+    fn observe(&mut self, _: &Parameters, _: f64) {}
+}
+
+/// The step of the Nelder-Mead method whose trial point is being evaluated.
+#[derive(Debug, Clone)]
+enum Phase {
+    Initial,
+    Reflect,
+    Expand { reflected: (Vec<f64>, f64) },
+    Contract { reflected: (Vec<f64>, f64) },
+    Shrink { next: usize },
+}
+
+/// The Nelder-Mead simplex method, for continuous parameters.
+///
+/// The search stops once the objective values at the simplex's vertices are within
+/// `tolerance` of each other or `max_evaluations` candidates have been evaluated. Noise in
+/// the objective can stall it, so enough replications should be run to make the objective
+/// smooth.
+///
+/// # Example
+/// ```
+/// use desru::{NelderMead, SearchStrategy};
+///
+/// let mut search = NelderMead::new(&[("x", 0.0), ("y", 0.0)], 1.0, 200);
+/// let mut last = None;
+/// while let Some(p) = search.propose() {
+///     let value = (p["x"] - 3.0).powi(2) + (p["y"] + 1.0).powi(2);
+///     search.observe(&p, value);
+///     last = Some(p);
+/// }
+/// let p = last.unwrap();
+/// assert!((p["x"] - 3.0).abs() < 0.01 && (p["y"] + 1.0).abs() < 0.01);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NelderMead {
+    names: Vec<String>,
+    start: Vec<f64>,
+    step: f64,
+    simplex: Vec<(Vec<f64>, f64)>,
+    phase: Phase,
+    pending: Vec<f64>,
+    evaluations: usize,
+    max_evaluations: usize,
+    tolerance: f64,
+}
+
+impl NelderMead {
+    /// Creates a search starting from `start`, with an initial simplex whose edges are `step`
+    /// long along each parameter.
+    ///
+    /// # Panics
+    /// Panics if `start` is empty.
+    pub fn new(start: &[(&str, f64)], step: f64, max_evaluations: usize) -> Self {
+        assert!(!start.is_empty(), "Nelder-Mead needs at least one parameter");
+        NelderMead {
+            names: start.iter().map(|(name, _)| name.to_string()).collect(),
+            start: start.iter().map(|(_, value)| *value).collect(),
+            step,
+            simplex: Vec::new(),
+            phase: Phase::Initial,
+            pending: Vec::new(),
+            evaluations: 0,
+            max_evaluations,
+            tolerance: 1e-8,
+        }
+    }
+
+    /// Sets the spread of objective values at which the search stops. Defaults to `1e-8`.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns the centroid of every vertex but the worst, which is last.
+    fn centroid(&self) -> Vec<f64> {
+        let n = self.names.len();
+        (0..n).map(|d| self.simplex[..n].iter().map(|(x, _)| x[d]).sum::<f64>() / n as f64).collect()
+    }
+
+    /// Returns `centroid + t (worst - centroid)`.
+    fn along(&self, t: f64) -> Vec<f64> {
+        let worst = &self.simplex[self.names.len()].0;
+        self.centroid().iter().zip(worst).map(|(c, w)| c + t * (w - c)).collect()
+    }
+
+    fn replace_worst(&mut self, vertex: (Vec<f64>, f64)) {
+        let n = self.names.len();
+        self.simplex[n] = vertex;
+        self.phase = Phase::Reflect;
+    }
+}
+
+impl SearchStrategy for NelderMead {
+    fn propose(&mut self) -> Option<Parameters> {
+        if self.evaluations >= self.max_evaluations {
+            return None;
+        }
+        let n = self.names.len();
+        let point = match &self.phase {
+            Phase::Initial => {
+                let mut point = self.start.clone();
+                if let Some(d) = self.simplex.len().checked_sub(1) {
+                    point[d] += self.step;
+                }
+                point
+            }
+            Phase::Reflect => {
+                self.simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+                if self.simplex[n].1 - self.simplex[0].1 <= self.tolerance {
+                    return None;
+                }
+                self.along(-1.0)
+            }
+            Phase::Expand { .. } => self.along(-2.0),
+            Phase::Contract { reflected } => {
+                if reflected.1 < self.simplex[n].1 {
+                    self.along(-0.5)
+                } else {
+                    self.along(0.5)
+                }
+            }
+            Phase::Shrink { next } => {
+                let best = &self.simplex[0].0;
+                best.iter().zip(&self.simplex[*next].0).map(|(b, x)| b + 0.5 * (x - b)).collect()
+            }
+        };
+        self.pending = point;
+        Some(self.names.iter().cloned().zip(self.pending.iter().copied()).collect())
+    }
+
+    fn observe(&mut self, _: &Parameters, value: f64) {
+        self.evaluations += 1;
+        let n = self.names.len();
+        let point = std::mem::take(&mut self.pending);
+        match std::mem::replace(&mut self.phase, Phase::Reflect) {
+            Phase::Initial => {
+                self.simplex.push((point, value));
+                if self.simplex.len() <= n {
+                    self.phase = Phase::Initial;
+                }
+            }
+            Phase::Reflect => {
+                if value < self.simplex[0].1 {
+                    self.phase = Phase::Expand { reflected: (point, value) };
+                } else if value < self.simplex[n - 1].1 {
+                    self.replace_worst((point, value));
+                } else {
+                    self.phase = Phase::Contract { reflected: (point, value) };
+                }
+            }
+            Phase::Expand { reflected } => {
+                if value < reflected.1 {
+                    self.replace_worst((point, value));
+                } else {
+                    self.replace_worst(reflected);
+                }
+            }
+            Phase::Contract { reflected } => {
+                if value < reflected.1.min(self.simplex[n].1) {
+                    self.replace_worst((point, value));
+                } else {
+                    self.phase = Phase::Shrink { next: 1 };
+                }
+            }
+            Phase::Shrink { next } => {
+                self.simplex[next] = (point, value);
+                if next < n {
+                    self.phase = Phase::Shrink { next: next + 1 };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventScheduler;
+
+    struct Noisy {
+        x: f64,
+    }
+
+    impl SimModel for Noisy {
+        type Output = [(&'static str, f64); 1];
+
+        fn init(&mut self, _: &mut EventScheduler) {}
+
+        fn finalize(&mut self, scheduler: &mut EventScheduler) -> Self::Output {
+            [("loss", (self.x - 2.0).powi(2) + scheduler.rng.next_f64())]
+        }
+    }
+
+    #[test]
+    fn test_strategies_find_the_minimum_under_common_random_numbers() {
+        let mean = |results: &ExperimentResults| results.tally(results.scenarios()[0], "loss").mean();
+        let config = SimConfig::new(1.0);
+        let random = optimize(&config, 5, RandomSearch::new(&[("x", -5.0, 5.0)], 100, 3), |p| Noisy { x: p["x"] }, mean);
+        assert_eq!(random.evaluations.len(), 100);
+        assert!((random.best.parameters["x"] - 2.0).abs() < 0.3);
+        let simplex = optimize(&config, 5, NelderMead::new(&[("x", -4.0)], 1.0, 100).tolerance(1e-10), |p| Noisy { x: p["x"] }, mean);
+        assert!((simplex.best.parameters["x"] - 2.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,188 @@
+//! # Live Plotting
+//!
+//! [`sample_hook`] observes a model variable, such as a queue length or a resource's
+//! utilization, after every event and sends `(series, time, value)` samples down a channel,
+//! where a plotting front end on another thread can draw them as the run progresses.
+//! [`Trajectories`] collects the samples into one step series per variable and renders them
+//! as CSV or as an SVG chart, so trajectories can be viewed after a run without a plotting
+//! dependency. The crate has no `plotters` feature; a front end that draws with plotters or
+//! another library reads the samples from the channel of [`sample_hook`].
+
+use crate::csv::csv_field;
+use crate::{EventHook, EventScheduler, ScheduledAction};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// A value of a named series at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub series: String,
+    pub time: f64,
+    pub value: f64,
+}
+
+/// Returns a hook that sends the value of `probe` as a sample of `series` whenever it changes.
+///
+/// The first event always produces a sample. Samples stop quietly once the receiver is
+/// dropped.
+///
+/// # Example
+/// ```
+/// use desru::{sample_hook, EventScheduler, Trajectories};
+/// use std::sync::mpsc;
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let mut scheduler = EventScheduler::builder()
+///     .state(0_u32)
+///     .hook(sample_hook("queue", |s| *s.state::<u32>() as f64, sender))
+///     .build();
+/// for time in [1.0, 2.0, 4.0] {
+///     scheduler.timeout(time, Some(Box::new(|s| {
+///         *s.state_mut::<u32>() += 1;
+///         None
+///     })), None);
+/// }
+/// scheduler.run_until_max_time(10.0);
+///
+/// let trajectories = Trajectories::collect(&receiver);
+/// assert_eq!(trajectories.series("queue"), [(1.0, 1.0), (2.0, 2.0), (4.0, 3.0)]);
+/// ```
+pub fn sample_hook<F>(series: impl Into<String>, probe: F, sender: Sender<Sample>) -> EventHook
+where
+    F: Fn(&EventScheduler) -> f64 + 'static,
+{
+    let series = series.into();
+    let mut last = None;
+    Box::new(move |scheduler: &EventScheduler, _: &ScheduledAction, _: &Option<String>| {
+        let value = probe(scheduler);
+        if last != Some(value) {
+            last = Some(value);
+            let _ = sender.send(Sample { series: series.clone(), time: scheduler.current_time, value });
+        }
+    })
+}
+
+/// The colors given to successive series in an SVG chart.
+const PALETTE: [&str; 6] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b"];
+
+/// Samples grouped into one time-ordered series per variable.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trajectories {
+    series: BTreeMap<String, Vec<(f64, f64)>>,
+}
+
+impl Trajectories {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Trajectories::default()
+    }
+
+    /// Collects every sample waiting in `receiver` without blocking.
+    pub fn collect(receiver: &Receiver<Sample>) -> Self {
+        let mut trajectories = Trajectories::new();
+        trajectories.extend(receiver.try_iter());
+        trajectories
+    }
+
+    /// Adds a sample.
+    pub fn push(&mut self, sample: Sample) {
+        self.series.entry(sample.series).or_default().push((sample.time, sample.value));
+    }
+
+    /// Returns the `(time, value)` points of a series, empty if it has no samples.
+    pub fn series(&self, name: &str) -> &[(f64, f64)] {
+        self.series.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the names of the series in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.series.keys().map(String::as_str)
+    }
+
+    /// Writes the samples as CSV with columns `series,time,value`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "series,time,value")?;
+        for (name, points) in &self.series {
+            for (time, value) in points {
+                writeln!(writer, "{},{},{}", csv_field(name), time, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the series as an SVG line chart of `width` by `height` pixels, drawing each as
+    /// a step function that holds its value until the next sample, with a legend.
+    pub fn write_svg<W: Write>(&self, mut writer: W, width: u32, height: u32) -> io::Result<()> {
+        let points = || self.series.values().flatten();
+        let (t0, t1) = bounds(points().map(|(time, _)| *time));
+        let (v0, v1) = bounds(points().map(|(_, value)| *value).chain([0.0]));
+        let margin = 40.0;
+        let (w, h) = (f64::from(width) - 2.0 * margin, f64::from(height) - 2.0 * margin);
+        let x = |time: f64| margin + (time - t0) / (t1 - t0) * w;
+        let y = |value: f64| margin + h - (value - v0) / (v1 - v0) * h;
+
+        writeln!(writer, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">", width, height)?;
+        writeln!(writer, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#999\"/>", margin, margin, w, h)?;
+        writeln!(writer, "<text x=\"{}\" y=\"{}\" font-size=\"10\">{}</text>", margin, f64::from(height) - margin / 2.0, t0)?;
+        writeln!(writer, "<text x=\"{}\" y=\"{}\" font-size=\"10\" text-anchor=\"end\">{}</text>", margin + w, f64::from(height) - margin / 2.0, t1)?;
+        writeln!(writer, "<text x=\"{}\" y=\"{}\" font-size=\"10\" text-anchor=\"end\">{}</text>", margin - 4.0, margin + h, v0)?;
+        writeln!(writer, "<text x=\"{}\" y=\"{}\" font-size=\"10\" text-anchor=\"end\">{}</text>", margin - 4.0, margin, v1)?;
+        for (index, (name, series)) in self.series.iter().enumerate() {
+            let color = PALETTE[index % PALETTE.len()];
+            let mut path = String::new();
+            for (step, (time, value)) in series.iter().enumerate() {
+                if step == 0 {
+                    path.push_str(&format!("M{:.2} {:.2}", x(*time), y(*value)));
+                } else {
+                    path.push_str(&format!(" H{:.2} V{:.2}", x(*time), y(*value)));
+                }
+            }
+            writeln!(writer, "<path d=\"{}\" fill=\"none\" stroke=\"{}\"/>", path, color)?;
+            let name = name.replace('&', "&amp;").replace('<', "&lt;");
+            writeln!(writer, "<text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"{}\">{}</text>", margin + 4.0, margin + 14.0 * (index + 1) as f64, color, name)?;
+        }
+        writeln!(writer, "</svg>")
+    }
+}
+
+impl Extend<Sample> for Trajectories {
+    fn extend<I: IntoIterator<Item = Sample>>(&mut self, samples: I) {
+        for sample in samples {
+            self.push(sample);
+        }
+    }
+}
+
+/// Returns the range of `values`, widened to be non-empty.
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| (low.min(v), high.max(v)));
+    if !low.is_finite() {
+        (0.0, 1.0)
+    } else if low == high {
+        (low, low + 1.0)
+    } else {
+        (low, high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_draws_one_step_path_per_series() {
+        let mut trajectories = Trajectories::new();
+        trajectories.extend([
+            Sample { series: "busy".to_string(), time: 0.0, value: 0.0 },
+            Sample { series: "busy".to_string(), time: 5.0, value: 2.0 },
+            Sample { series: "queue".to_string(), time: 10.0, value: 1.0 },
+        ]);
+        let mut svg = Vec::new();
+        trajectories.write_svg(&mut svg, 240, 180).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert_eq!(svg.matches("<path").count(), 2);
+        assert!(svg.contains("d=\"M40.00 140.00 H120.00 V40.00\""));
+        assert_eq!(trajectories.names().collect::<Vec<_>>(), ["busy", "queue"]);
+    }
+}
@@ -0,0 +1,118 @@
+//! # Context Pool
+//!
+//! High-throughput models schedule many short-lived events, each with its own context map.
+//! Once an event has run and is not being logged, the scheduler keeps its emptied map in a
+//! bounded pool, and [`EventScheduler::pooled_context`] hands it out again, so the map's
+//! allocation is reused instead of freed and reallocated. With the `compact-context` feature,
+//! only contexts that outgrew their inline storage are pooled. Event actions are closures of
+//! differing types, so their boxes cannot be pooled in the same way.
+
+use crate::{Context, EventScheduler};
+
+/// The number of context maps the pool keeps by default.
+pub(crate) const DEFAULT_POOL_SIZE: usize = 64;
+
+/// Counters describing how well the context pool is working, see [`EventScheduler::pool_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Requests served with a recycled map.
+    pub reused: u64,
+    /// Requests served with a new map because the pool was empty.
+    pub allocated: u64,
+    /// Maps returned to the pool.
+    pub returned: u64,
+    /// Maps freed because the pool was full.
+    pub discarded: u64,
+    /// Maps currently in the pool.
+    pub available: usize,
+    /// The most maps the pool will hold.
+    pub capacity: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ContextPool {
+    maps: Vec<Context>,
+    stats: PoolStats,
+}
+
+impl ContextPool {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ContextPool { maps: Vec::new(), stats: PoolStats { capacity, ..PoolStats::default() } }
+    }
+
+    /// Returns a map to the pool. Maps that never allocated are not worth keeping.
+    pub(crate) fn put(&mut self, mut map: Context) {
+        if map.capacity() == 0 {
+            return;
+        }
+        if self.maps.len() >= self.stats.capacity {
+            self.stats.discarded += 1;
+            return;
+        }
+        map.clear();
+        self.maps.push(map);
+        self.stats.returned += 1;
+    }
+
+    fn take(&mut self) -> Context {
+        match self.maps.pop() {
+            Some(map) => {
+                self.stats.reused += 1;
+                map
+            }
+            None => {
+                self.stats.allocated += 1;
+                Context::new()
+            }
+        }
+    }
+}
+
+impl EventScheduler {
+    /// Returns an empty context map, reusing the allocation of an executed event's map if one
+    /// is available.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// fn tick(s: &mut EventScheduler) -> Option<String> {
+    ///     let mut context = s.pooled_context();
+    ///     context.insert("kind".to_string(), "tick".to_string());
+    ///     s.schedule(ScheduledAction::at(s.current_time + 1.0).with_action(tick).with_context(context));
+    ///     None
+    /// }
+    ///
+    /// let mut scheduler = EventScheduler::builder().logging(false).build();
+    /// scheduler.schedule_now(tick);
+    /// scheduler.run_until_max_time(100.0);
+    /// let stats = scheduler.pool_stats();
+    /// assert_eq!(stats.allocated + stats.reused, 100);
+    /// ```
+    pub fn pooled_context(&mut self) -> Context {
+        self.context_pool.take()
+    }
+
+    /// Returns the context pool's counters.
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats { available: self.context_pool.maps.len(), ..self.context_pool.stats }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_is_bounded() {
+        let mut pool = ContextPool::new(1);
+        pool.put(Context::new());
+        // Enough pairs to leave a compact context's inline storage.
+        let used: Context = (0..5).map(|i| (i.to_string(), i.to_string())).collect();
+        pool.put(used.clone());
+        pool.put(used);
+        let map = pool.take();
+        assert!(map.is_empty() && map.capacity() > 0);
+        assert_eq!((pool.stats.returned, pool.stats.discarded, pool.stats.reused), (1, 1, 1));
+    }
+}
@@ -0,0 +1,158 @@
+//! # Profiling
+//!
+//! In a slow model, a handful of actions usually account for most of the running time. With
+//! profiling on, the scheduler's [`Profiler`] measures the wall-clock time each action takes
+//! and accumulates it per event label, and [`crate::RunResult`] reports the totals, most
+//! expensive first. The time spent in hooks and in the queue is not included.
+//!
+//! Timing every event costs two clock reads per event, so profiling is off unless enabled
+//! with [`crate::EventSchedulerBuilder::profile`] or [`Profiler::set_enabled`].
+
+use crate::csv::csv_field;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// The accumulated running time of the actions of one event label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelProfile {
+    /// The label, or `None` for unlabeled events.
+    pub label: Option<String>,
+    /// The number of events run.
+    pub count: u64,
+    /// The total time their actions took.
+    pub total: Duration,
+    /// The longest time one of their actions took.
+    pub max: Duration,
+}
+
+impl LabelProfile {
+    /// Returns the mean time an action took.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.count as f64)
+        }
+    }
+}
+
+/// Per-label timings of executed actions.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduledAction};
+///
+/// let mut scheduler = EventScheduler::builder().profile(true).build();
+/// for t in 1..=10 {
+///     scheduler.schedule(ScheduledAction::at(f64::from(t)).with_label("light"));
+/// }
+/// scheduler.schedule(ScheduledAction::at(5.0).with_label("heavy").with_action(|_| {
+///     std::thread::sleep(std::time::Duration::from_millis(5));
+///     None
+/// }));
+/// let result = scheduler.run_until_max_time(20.0);
+///
+/// let heaviest = &result.profile[0];
+/// assert_eq!((heaviest.label.as_deref(), heaviest.count), (Some("heavy"), 1));
+/// assert_eq!(result.profile[1].count, 10);
+/// println!("{}", result.report());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    enabled: bool,
+    labels: HashMap<Option<String>, LabelProfile>,
+}
+
+impl Profiler {
+    /// Creates an empty profiler that is not enabled.
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    /// Returns `true` if actions are being timed.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns timing of actions on or off. Timings already recorded are kept.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns the timings of every label, in descending order of total time.
+    pub fn labels(&self) -> Vec<LabelProfile> {
+        let mut labels: Vec<LabelProfile> = self.labels.values().cloned().collect();
+        labels.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.label.cmp(&b.label)));
+        labels
+    }
+
+    /// Discards the timings recorded so far.
+    pub fn clear(&mut self) {
+        self.labels.clear();
+    }
+
+    /// Writes the timings as CSV with the columns `label,count,total_seconds,mean_seconds,max_seconds`,
+    /// in descending order of total time.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "label,count,total_seconds,mean_seconds,max_seconds")?;
+        for profile in self.labels() {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                csv_field(profile.label.as_deref().unwrap_or("")),
+                profile.count,
+                profile.total.as_secs_f64(),
+                profile.mean().as_secs_f64(),
+                profile.max.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Creates an empty profiler, enabled if `enabled` is `true`.
+    pub(crate) fn enabled(enabled: bool) -> Self {
+        Profiler { enabled, labels: HashMap::new() }
+    }
+
+    /// Records that an action of the event labeled `label` took `elapsed`.
+    pub(crate) fn record(&mut self, label: Option<&str>, elapsed: Duration) {
+        let profile = self.labels.entry(label.map(str::to_string)).or_insert_with(|| LabelProfile {
+            label: label.map(str::to_string),
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        });
+        profile.count += 1;
+        profile.total += elapsed;
+        profile.max = profile.max.max(elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventScheduler, ScheduledAction};
+
+    #[test]
+    fn test_profiles_only_while_enabled() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(1.0).with_label("before"));
+        scheduler.schedule(ScheduledAction::at(2.0).with_label("a").with_action(|s| {
+            s.profiler.set_enabled(true);
+            None
+        }));
+        for t in [3.0, 4.0, 5.0] {
+            scheduler.schedule(ScheduledAction::at(t).with_label("b"));
+        }
+        scheduler.schedule(ScheduledAction::at(6.0));
+        scheduler.run_until_max_time(10.0);
+
+        let labels = scheduler.profiler.labels();
+        let mut counts: Vec<_> = labels.iter().map(|profile| (profile.label.as_deref(), profile.count)).collect();
+        counts.sort();
+        assert_eq!(counts, [(None, 1), (Some("b"), 3)]);
+        let mut csv = Vec::new();
+        scheduler.profiler.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 3);
+    }
+}
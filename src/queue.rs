@@ -0,0 +1,766 @@
+//! # Event Queue Backends
+//!
+//! The scheduler stores pending events in an [`EventQueue`], which dispatches to one of several
+//! priority-queue implementations selected through [`QueueBackend`]. Every backend pops events in
+//! the same order, so switching backends never changes the outcome of a simulation, only its
+//! performance: earliest time first, then lowest microstep (always zero unless superdense time is
+//! enabled), then lowest `priority`, then the order in which events were scheduled. A
+//! [`crate::ClassPolicy`] set on the scheduler may further reorder events tied on time, which the
+//! scheduler does as it takes them off the queue.
+//!
+//! The queue also assigns each pushed event its [`EventId`] and supports cancelling pending
+//! events by id.
+//!
+//! The backend can be changed while events are pending with [`EventQueue::set_backend`], which
+//! moves them to the new one. [`QueueBackend::Adaptive`] does so automatically as the queue
+//! grows and shrinks: a binary heap is faster for the small queues of most models, and a
+//! calendar queue for the very large ones of models with many concurrent entities.
+//!
+//! Queries by label, such as [`EventQueue::count_label`], scan the pending events unless the
+//! queue keeps a label index, enabled with [`EventQueue::set_label_index`]. The index costs an
+//! entry per labeled event and an update on every push, pop, and cancellation, and answers
+//! counts in `O(1)` and the earliest event of a label in `O(log n)`.
+
+use crate::ScheduledAction;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// A handle to a scheduled event, used to cancel it or check whether it is still pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(pub u64);
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event-{}", self.0)
+    }
+}
+
+/// The priority-queue implementation used to hold pending events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueBackend {
+    /// A binary heap. A good general-purpose choice with `O(log n)` push and pop.
+    #[default]
+    BinaryHeap,
+    /// A calendar queue (Brown, 1988) with self-adjusting bucket count and width. Offers
+    /// amortised `O(1)` push and pop when event times are spread fairly evenly.
+    Calendar,
+    /// A binary heap while the queue is small, switching to a calendar queue once more than
+    /// 4096 events are pending and back once fewer than 1024 remain.
+    Adaptive,
+}
+
+/// The queue length above which an adaptive queue switches to a calendar queue.
+const ADAPTIVE_TO_CALENDAR: usize = 4096;
+
+/// The queue length below which an adaptive queue switches back to a binary heap. Well below
+/// [`ADAPTIVE_TO_CALENDAR`], so that a queue hovering near one threshold does not switch on
+/// every event.
+const ADAPTIVE_TO_HEAP: usize = 1024;
+
+/// A priority queue of pending events, ordered so that the earliest event is popped first.
+///
+/// # Example
+/// ```
+/// use desru::{EventQueue, QueueBackend, ScheduledAction};
+///
+/// let mut queue = EventQueue::new(QueueBackend::Calendar);
+/// queue.push(ScheduledAction::new(3.0, None, None));
+/// queue.push(ScheduledAction::new(1.0, None, None));
+/// assert_eq!(queue.len(), 2);
+/// assert_eq!(queue.peek().map(|e| e.time), Some(1.0));
+/// assert_eq!(queue.pop().map(|e| e.time), Some(1.0));
+/// ```
+#[derive(Debug)]
+pub struct EventQueue {
+    inner: Backend,
+    // Ids of pending, uncancelled events. Cancelled events stay in the backend until they
+    // reach the front, but the front of the backend is always live.
+    live: HashSet<u64>,
+    // Tied events moved off the backend for the scheduler to choose between, in the order
+    // they would run. They are live, and run before the front of the backend unless it has
+    // been ordered ahead of them since.
+    staged: VecDeque<ScheduledAction>,
+    next_seq: u64,
+    cancelled: u64,
+    max_len: usize,
+    adaptive: bool,
+    switches: u64,
+    labels: Option<LabelIndex>,
+}
+
+#[derive(Debug)]
+enum Backend {
+    Heap(BinaryHeap<ScheduledAction>),
+    Calendar(CalendarQueue),
+}
+
+impl EventQueue {
+    /// Creates an empty queue using the given backend.
+    pub fn new(backend: QueueBackend) -> Self {
+        let inner = match backend {
+            QueueBackend::BinaryHeap | QueueBackend::Adaptive => Backend::Heap(BinaryHeap::new()),
+            QueueBackend::Calendar => Backend::Calendar(CalendarQueue::new()),
+        };
+        let adaptive = backend == QueueBackend::Adaptive;
+        EventQueue { inner, live: HashSet::new(), staged: VecDeque::new(), next_seq: 0, cancelled: 0, max_len: 0, adaptive, switches: 0, labels: None }
+    }
+
+    /// Returns the backend currently holding the events. For an adaptive queue, this is
+    /// [`QueueBackend::BinaryHeap`] or [`QueueBackend::Calendar`], whichever it uses now.
+    pub fn backend(&self) -> QueueBackend {
+        match self.inner {
+            Backend::Heap(_) => QueueBackend::BinaryHeap,
+            Backend::Calendar(_) => QueueBackend::Calendar,
+        }
+    }
+
+    /// Returns `true` if the queue switches backends by itself, see [`QueueBackend::Adaptive`].
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    /// Changes the backend, moving the pending events to the new one. Events pop in the same
+    /// order afterwards, and their ids stay valid.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventQueue, QueueBackend, ScheduledAction};
+    ///
+    /// let mut queue = EventQueue::new(QueueBackend::BinaryHeap);
+    /// let first = queue.push(ScheduledAction::new(1.0, None, None));
+    /// queue.push(ScheduledAction::new(2.0, None, None));
+    /// queue.set_backend(QueueBackend::Calendar);
+    /// assert_eq!(queue.backend(), QueueBackend::Calendar);
+    /// assert!(queue.contains(first));
+    /// assert_eq!(queue.pop().map(|e| e.time), Some(1.0));
+    /// ```
+    pub fn set_backend(&mut self, backend: QueueBackend) {
+        self.adaptive = backend == QueueBackend::Adaptive;
+        if self.adaptive {
+            self.adapt();
+        } else {
+            self.migrate(backend);
+        }
+    }
+
+    /// Returns how many times the pending events were moved to another backend.
+    pub fn backend_switches(&self) -> u64 {
+        self.switches
+    }
+
+    /// Starts or stops keeping an index of the pending events by label, which makes the
+    /// label queries fast. Starting it indexes the events already pending.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventQueue, ScheduledAction};
+    ///
+    /// let mut queue = EventQueue::default();
+    /// queue.set_label_index(true);
+    /// for t in [4.0, 2.0, 3.0] {
+    ///     queue.push(ScheduledAction::at(t).with_label("departure"));
+    /// }
+    /// queue.push(ScheduledAction::at(1.0).with_label("arrival"));
+    /// assert_eq!(queue.count_label("departure"), 3);
+    /// assert_eq!(queue.earliest_with_label("departure").map(|(_, time)| time), Some(2.0));
+    ///
+    /// queue.pop();
+    /// queue.pop();
+    /// assert_eq!(queue.earliest_with_label("departure").map(|(_, time)| time), Some(3.0));
+    /// assert_eq!(queue.count_label("arrival"), 0);
+    /// ```
+    pub fn set_label_index(&mut self, enabled: bool) {
+        if !enabled {
+            self.labels = None;
+        } else if self.labels.is_none() {
+            let mut labels = LabelIndex::default();
+            for event in self.iter() {
+                labels.insert(event);
+            }
+            self.labels = Some(labels);
+        }
+    }
+
+    /// Returns `true` if the queue keeps an index of pending events by label.
+    pub fn has_label_index(&self) -> bool {
+        self.labels.is_some()
+    }
+
+    /// Returns the number of pending events labeled `label`.
+    pub fn count_label(&self, label: &str) -> usize {
+        match &self.labels {
+            Some(labels) => labels.by_label.get(label).map_or(0, BTreeSet::len),
+            None => self.iter().filter(|event| event.label.as_deref() == Some(label)).count(),
+        }
+    }
+
+    /// Returns the id and time of the pending event labeled `label` that will run first.
+    pub fn earliest_with_label(&self, label: &str) -> Option<(EventId, f64)> {
+        match &self.labels {
+            Some(labels) => labels.by_label.get(label)?.first().map(|key| (EventId(key.seq), key.time)),
+            None => self
+                .iter()
+                .filter(|event| event.label.as_deref() == Some(label))
+                .max()
+                .map(|event| (EventId(event.seq), event.time)),
+        }
+    }
+
+    /// Returns the ids of the pending events labeled `label`, in the order they will run.
+    pub fn ids_with_label(&self, label: &str) -> Vec<EventId> {
+        match &self.labels {
+            Some(labels) => labels.by_label.get(label).map_or_else(Vec::new, |keys| keys.iter().map(|key| EventId(key.seq)).collect()),
+            None => {
+                let mut events: Vec<&ScheduledAction> = self.iter().filter(|event| event.label.as_deref() == Some(label)).collect();
+                events.sort_by(|a, b| b.cmp(a));
+                events.into_iter().map(|event| EventId(event.seq)).collect()
+            }
+        }
+    }
+
+    /// Adds an event to the queue, assigning it a new id.
+    ///
+    /// Events pushed at the same time and priority are popped in the order they were pushed.
+    pub fn push(&mut self, mut event: ScheduledAction) -> EventId {
+        self.next_seq += 1;
+        event.seq = self.next_seq;
+        self.live.insert(event.seq);
+        self.max_len = self.max_len.max(self.live.len());
+        if let Some(labels) = &mut self.labels {
+            labels.insert(&event);
+        }
+        self.backend_push(event);
+        self.adapt();
+        EventId(self.next_seq)
+    }
+
+    /// Removes and returns the earliest event, if any.
+    pub fn pop(&mut self) -> Option<ScheduledAction> {
+        let event = match self.staged.front() {
+            Some(staged) if self.backend_peek().is_none_or(|front| staged > front) => self.staged.pop_front(),
+            _ => self.backend_pop(),
+        };
+        if let Some(event) = &event {
+            self.live.remove(&event.seq);
+            if let Some(labels) = &mut self.labels {
+                labels.remove(event.seq);
+            }
+        }
+        self.purge();
+        self.adapt();
+        event
+    }
+
+    /// Returns a reference to the earliest event without removing it.
+    pub fn peek(&self) -> Option<&ScheduledAction> {
+        match (self.staged.front(), self.backend_peek()) {
+            (Some(staged), Some(front)) => Some(staged.max(front)),
+            (staged, front) => staged.or(front),
+        }
+    }
+
+    /// Cancels a pending event so that it is never popped.
+    ///
+    /// # Returns
+    /// `true` if the event was pending, `false` if it had already run or been cancelled.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventQueue, ScheduledAction};
+    ///
+    /// let mut queue = EventQueue::default();
+    /// let id = queue.push(ScheduledAction::new(1.0, None, None));
+    /// queue.push(ScheduledAction::new(2.0, None, None));
+    /// assert!(queue.cancel(id));
+    /// assert!(!queue.cancel(id));
+    /// assert_eq!(queue.len(), 1);
+    /// assert_eq!(queue.pop().map(|e| e.time), Some(2.0));
+    /// ```
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        let removed = self.discard(id);
+        if removed {
+            self.cancelled += 1;
+        }
+        removed
+    }
+
+    /// Removes a pending event without counting it as cancelled, for internal events that the
+    /// scheduler replaces rather than cancels.
+    pub(crate) fn discard(&mut self, id: EventId) -> bool {
+        let removed = self.live.remove(&id.0);
+        if removed {
+            if let Some(labels) = &mut self.labels {
+                labels.remove(id.0);
+            }
+            if let Some(index) = self.staged.iter().position(|event| event.seq == id.0) {
+                self.staged.remove(index);
+            }
+            self.purge();
+        }
+        removed
+    }
+
+    /// Returns `true` if the event is still pending.
+    pub fn contains(&self, id: EventId) -> bool {
+        self.live.contains(&id.0)
+    }
+
+    /// Returns the number of pending events.
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    fn backend_peek(&self) -> Option<&ScheduledAction> {
+        match &self.inner {
+            Backend::Heap(heap) => heap.peek(),
+            Backend::Calendar(calendar) => calendar.peek(),
+        }
+    }
+
+    fn backend_pop(&mut self) -> Option<ScheduledAction> {
+        match &mut self.inner {
+            Backend::Heap(heap) => heap.pop(),
+            Backend::Calendar(calendar) => calendar.pop(),
+        }
+    }
+
+    fn backend_push(&mut self, event: ScheduledAction) {
+        match &mut self.inner {
+            Backend::Heap(heap) => heap.push(event),
+            Backend::Calendar(calendar) => calendar.push(event),
+        }
+    }
+
+    /// Moves the events tied with the front of the queue, at the same time, microstep and
+    /// priority, off the backend and returns them in the order they would run.
+    ///
+    /// The events stay pending until taken with [`EventQueue::take_staged`], so that choosing
+    /// among a batch of `k` tied events costs each of them one trip through the backend rather
+    /// than one per event taken.
+    pub(crate) fn stage_ties(&mut self) -> &VecDeque<ScheduledAction> {
+        let ties = |a: &ScheduledAction, b: &ScheduledAction| a.time == b.time && a.microstep == b.microstep && a.priority == b.priority;
+        if let (Some(staged), Some(front)) = (self.staged.front(), self.backend_peek()) {
+            if front > staged && !ties(front, staged) {
+                // An earlier event was scheduled after the batch was staged.
+                for event in std::mem::take(&mut self.staged) {
+                    self.backend_push(event);
+                }
+            }
+        }
+        while let Some(front) = self.backend_peek() {
+            if self.staged.front().is_some_and(|staged| !ties(front, staged)) {
+                break;
+            }
+            let event = self.backend_pop().expect("the backend has a front event");
+            let index = self.staged.partition_point(|staged| *staged > event);
+            self.staged.insert(index, event);
+            self.purge();
+        }
+        &self.staged
+    }
+
+    /// Removes and returns the event at `index` among those returned by
+    /// [`EventQueue::stage_ties`].
+    pub(crate) fn take_staged(&mut self, index: usize) -> Option<ScheduledAction> {
+        let event = self.staged.remove(index)?;
+        self.live.remove(&event.seq);
+        if let Some(labels) = &mut self.labels {
+            labels.remove(event.seq);
+        }
+        self.adapt();
+        Some(event)
+    }
+
+    /// Puts back an event taken off the queue with [`EventQueue::pop`], keeping its id.
+    pub(crate) fn restore(&mut self, event: ScheduledAction) {
+        self.live.insert(event.seq);
+        if let Some(labels) = &mut self.labels {
+            labels.insert(&event);
+        }
+        self.backend_push(event);
+    }
+
+    /// Switches an adaptive queue to the backend suited to its length.
+    fn adapt(&mut self) {
+        if !self.adaptive {
+            return;
+        }
+        match self.inner {
+            Backend::Heap(_) if self.live.len() > ADAPTIVE_TO_CALENDAR => self.migrate(QueueBackend::Calendar),
+            Backend::Calendar(_) if self.live.len() < ADAPTIVE_TO_HEAP => self.migrate(QueueBackend::BinaryHeap),
+            _ => {}
+        }
+    }
+
+    /// Moves the pending events to `backend`, discarding cancelled ones on the way.
+    fn migrate(&mut self, backend: QueueBackend) {
+        if backend == self.backend() {
+            return;
+        }
+        let events: Vec<ScheduledAction> = match &mut self.inner {
+            Backend::Heap(heap) => std::mem::take(heap).into_vec(),
+            Backend::Calendar(calendar) => calendar.buckets.drain(..).flatten().collect(),
+        };
+        let live = &self.live;
+        let events = events.into_iter().filter(|event| live.contains(&event.seq));
+        self.inner = match backend {
+            QueueBackend::Calendar => {
+                let mut calendar = CalendarQueue::new();
+                events.for_each(|event| calendar.push(event));
+                Backend::Calendar(calendar)
+            }
+            _ => Backend::Heap(events.collect()),
+        };
+        self.switches += 1;
+    }
+
+    /// Discards cancelled events from the front of the backend.
+    fn purge(&mut self) {
+        while let Some(front) = self.backend_peek() {
+            if self.live.contains(&front.seq) {
+                break;
+            }
+            self.backend_pop();
+        }
+    }
+
+    /// Returns the number of events cancelled before they ran.
+    pub fn cancelled(&self) -> u64 {
+        self.cancelled
+    }
+
+    /// Returns the largest number of events that have been pending at once.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Returns `true` if there are no pending events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the pending events in an unspecified order.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &ScheduledAction> + '_> {
+        let events: Box<dyn Iterator<Item = &ScheduledAction>> = match &self.inner {
+            Backend::Heap(heap) => Box::new(heap.iter()),
+            Backend::Calendar(calendar) => Box::new(calendar.buckets.iter().flatten()),
+        };
+        Box::new(self.staged.iter().chain(events.filter(|event| self.live.contains(&event.seq))))
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        EventQueue::new(QueueBackend::default())
+    }
+}
+
+/// Minimum number of calendar buckets; the calendar never shrinks below this.
+const MIN_BUCKETS: usize = 8;
+
+/// A calendar queue: events are hashed by time into a ring of buckets, each one "day" wide.
+///
+/// Each bucket is kept sorted so that its earliest event is at the end. Popping scans forward
+/// from the day of the last popped event until it finds an event that falls within the
+/// current "year", falling back to a direct search when the calendar is sparse.
+#[derive(Debug)]
+struct CalendarQueue {
+    buckets: Vec<Vec<ScheduledAction>>,
+    width: f64,
+    len: usize,
+    last_time: f64,
+}
+
+impl CalendarQueue {
+    fn new() -> Self {
+        CalendarQueue {
+            buckets: (0..MIN_BUCKETS).map(|_| Vec::new()).collect(),
+            width: 1.0,
+            len: 0,
+            last_time: 0.0,
+        }
+    }
+
+    fn day(&self, time: f64) -> i64 {
+        (time / self.width).floor() as i64
+    }
+
+    fn bucket_of(&self, time: f64) -> usize {
+        self.day(time).rem_euclid(self.buckets.len() as i64) as usize
+    }
+
+    fn push(&mut self, event: ScheduledAction) {
+        if self.len == 0 || event.time < self.last_time {
+            self.last_time = event.time;
+        }
+        self.insert(event);
+        self.len += 1;
+        if self.len > 2 * self.buckets.len() {
+            self.resize(2 * self.buckets.len());
+        }
+    }
+
+    fn insert(&mut self, event: ScheduledAction) {
+        let index = self.bucket_of(event.time);
+        let bucket = &mut self.buckets[index];
+        // Ascending by `Ord`, where earlier events compare greater, so the earliest is last.
+        let position = bucket.partition_point(|e| *e < event);
+        bucket.insert(position, event);
+    }
+
+    /// Finds the bucket holding the earliest event.
+    fn locate(&self) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        // Infinite times have no day to scan from, and every pending event is at them or later.
+        if self.last_time.is_finite() {
+            let n = self.buckets.len();
+            let start = self.bucket_of(self.last_time);
+            let mut top = self.day(self.last_time).saturating_add(1) as f64 * self.width;
+            for offset in 0..n {
+                let index = (start + offset) % n;
+                if let Some(event) = self.buckets[index].last() {
+                    if event.time < top {
+                        return Some(index);
+                    }
+                }
+                top += self.width;
+            }
+        }
+        // Sparse calendar: nothing within a year, so search for the minimum directly.
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, bucket)| bucket.last().map(|e| (i, e)))
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    fn peek(&self) -> Option<&ScheduledAction> {
+        self.locate().and_then(|index| self.buckets[index].last())
+    }
+
+    fn pop(&mut self) -> Option<ScheduledAction> {
+        let index = self.locate()?;
+        let event = self.buckets[index].pop()?;
+        self.len -= 1;
+        self.last_time = event.time;
+        if self.buckets.len() > MIN_BUCKETS && self.len < self.buckets.len() / 2 {
+            self.resize(self.buckets.len() / 2);
+        }
+        Some(event)
+    }
+
+    /// Rebuilds the calendar with `n` buckets and a width estimated from the earliest events.
+    fn resize(&mut self, n: usize) {
+        let mut events: Vec<ScheduledAction> = self.buckets.drain(..).flatten().collect();
+        events.sort_by(|a, b| b.cmp(a));
+        let sample: Vec<f64> = events.iter().take(25).map(|e| e.time).collect();
+        if sample.len() > 1 {
+            let span = sample[sample.len() - 1] - sample[0];
+            let average_gap = span / (sample.len() - 1) as f64;
+            if average_gap.is_finite() && average_gap > 0.0 {
+                self.width = 3.0 * average_gap;
+            }
+        }
+        self.buckets = (0..n.max(MIN_BUCKETS)).map(|_| Vec::new()).collect();
+        for event in events {
+            self.insert(event);
+        }
+    }
+}
+
+/// The position of an indexed event in the run order, ascending from the next to run.
+#[derive(Debug, Clone, Copy)]
+struct IndexKey {
+    time: f64,
+    microstep: u64,
+    priority: i64,
+    seq: u64,
+}
+
+impl IndexKey {
+    fn of(event: &ScheduledAction) -> Self {
+        IndexKey { time: event.time, microstep: event.microstep, priority: event.priority, seq: event.seq }
+    }
+}
+
+impl PartialEq for IndexKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexKey {}
+
+impl PartialOrd for IndexKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IndexKey {
+    /// Orders keys as [`ScheduledAction`] orders events, but ascending.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .total_cmp(&other.time)
+            .then_with(|| self.microstep.cmp(&other.microstep))
+            .then_with(|| self.priority.cmp(&other.priority))
+            .then_with(|| self.seq.cmp(&other.seq))
+    }
+}
+
+/// The pending labeled events of a queue, by label.
+#[derive(Debug, Default)]
+struct LabelIndex {
+    by_label: HashMap<String, BTreeSet<IndexKey>>,
+    by_seq: HashMap<u64, (String, IndexKey)>,
+}
+
+impl LabelIndex {
+    fn insert(&mut self, event: &ScheduledAction) {
+        if let Some(label) = &event.label {
+            let key = IndexKey::of(event);
+            self.by_label.entry(label.clone()).or_default().insert(key);
+            self.by_seq.insert(event.seq, (label.clone(), key));
+        }
+    }
+
+    fn remove(&mut self, seq: u64) {
+        let Some((label, key)) = self.by_seq.remove(&seq) else {
+            return;
+        };
+        if let Some(keys) = self.by_label.get_mut(&label) {
+            keys.remove(&key);
+            if keys.is_empty() {
+                self.by_label.remove(&label);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventScheduler;
+
+    fn drain_times(backend: QueueBackend, times: &[f64]) -> Vec<f64> {
+        let mut scheduler = EventScheduler::builder().queue_backend(backend).build();
+        for &time in times {
+            scheduler.schedule(ScheduledAction::new(time, None, None));
+        }
+        let mut popped = Vec::new();
+        while let Some(event) = scheduler.event_queue.pop() {
+            popped.push(event.time);
+        }
+        popped
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let mut rng = crate::SimRng::new(3);
+        let times: Vec<f64> = (0..500).map(|_| (rng.gen_range(0.0, 100.0) * 4.0).round() / 4.0).collect();
+        let heap = drain_times(QueueBackend::BinaryHeap, &times);
+        let calendar = drain_times(QueueBackend::Calendar, &times);
+        assert_eq!(heap, calendar);
+        assert!(heap.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_adaptive_queue_switches_and_keeps_order() {
+        let mut rng = crate::SimRng::new(5);
+        let times: Vec<f64> = (0..5000).map(|_| (rng.gen_range(0.0, 100.0) * 4.0).round() / 4.0).collect();
+        let mut queue = EventQueue::new(QueueBackend::Adaptive);
+        let ids: Vec<EventId> = times.iter().map(|&time| queue.push(ScheduledAction::new(time, None, None))).collect();
+        assert_eq!((queue.backend(), queue.backend_switches()), (QueueBackend::Calendar, 1));
+        for id in ids.iter().step_by(2) {
+            queue.cancel(*id);
+        }
+        let popped: Vec<u64> = std::iter::from_fn(|| queue.pop()).map(|e| e.seq).collect();
+        assert_eq!((queue.backend(), queue.backend_switches()), (QueueBackend::BinaryHeap, 2));
+
+        let mut expected: Vec<(f64, u64)> = ids.iter().skip(1).step_by(2).map(|id| (times[id.0 as usize - 1], id.0)).collect();
+        expected.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        assert_eq!(popped, expected.iter().map(|&(_, seq)| seq).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_label_index_agrees_with_scanning() {
+        let mut rng = crate::SimRng::new(9);
+        let mut indexed = EventQueue::new(QueueBackend::Calendar);
+        indexed.set_label_index(true);
+        let mut scanned = EventQueue::new(QueueBackend::Calendar);
+        for i in 0..300 {
+            let time = (rng.gen_range(0.0, 20.0) * 2.0).round() / 2.0;
+            let label = ["a", "b", "c"][i % 3];
+            let priority = (i % 2) as i64;
+            indexed.push(ScheduledAction::at(time).with_label(label).with_priority(priority));
+            let id = scanned.push(ScheduledAction::at(time).with_label(label).with_priority(priority));
+            if i % 7 == 0 {
+                indexed.cancel(id);
+                scanned.cancel(id);
+            }
+            if i % 5 == 0 {
+                indexed.pop();
+                scanned.pop();
+            }
+            for label in ["a", "b", "c", "d"] {
+                assert_eq!(indexed.count_label(label), scanned.count_label(label));
+                assert_eq!(indexed.earliest_with_label(label), scanned.earliest_with_label(label));
+            }
+        }
+        assert_eq!(indexed.ids_with_label("b"), scanned.ids_with_label("b"));
+    }
+
+    #[test]
+    fn test_calendar_handles_sparse_and_past_events() {
+        let mut queue = EventQueue::new(QueueBackend::Calendar);
+        queue.push(ScheduledAction::new(1_000_000.0, None, None));
+        queue.push(ScheduledAction::new(5.0, None, None));
+        assert_eq!(queue.pop().map(|e| e.time), Some(5.0));
+        queue.push(ScheduledAction::new(-2.0, None, None));
+        assert_eq!(queue.pop().map(|e| e.time), Some(-2.0));
+        assert_eq!(queue.pop().map(|e| e.time), Some(1_000_000.0));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_backends_agree_on_infinite_times() {
+        let run = |backend| {
+            let mut scheduler = EventScheduler::builder().queue_backend(backend).build();
+            scheduler.schedule(ScheduledAction::at(1.0));
+            // Enough events for the adaptive queue to switch to a calendar while the front
+            // event is at infinity.
+            for priority in 0..5000 {
+                scheduler.schedule(ScheduledAction::at(f64::INFINITY).with_priority(priority));
+            }
+            let log = scheduler.run(Box::new(|_| false), None);
+            let times: Vec<f64> = log.iter().map(|record| record.time).collect();
+            (times, scheduler.current_time, scheduler.event_queue.backend_switches())
+        };
+        let (heap, heap_end, _) = run(QueueBackend::BinaryHeap);
+        assert_eq!((heap[5000], heap_end, heap.len()), (f64::INFINITY, f64::INFINITY, 5001));
+        for backend in [QueueBackend::Calendar, QueueBackend::Adaptive] {
+            let (times, end, switches) = run(backend);
+            assert_eq!((&times, end), (&heap, heap_end));
+            assert_eq!(switches, if backend == QueueBackend::Adaptive { 2 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_cancel_buried_event() {
+        for backend in [QueueBackend::BinaryHeap, QueueBackend::Calendar] {
+            let mut queue = EventQueue::new(backend);
+            queue.push(ScheduledAction::new(1.0, None, None));
+            let buried = queue.push(ScheduledAction::new(2.0, None, None));
+            queue.push(ScheduledAction::new(3.0, None, None));
+            assert!(queue.cancel(buried));
+            assert_eq!(queue.iter().count(), 2);
+            let times: Vec<f64> = std::iter::from_fn(|| queue.pop()).map(|e| e.time).collect();
+            assert_eq!(times, vec![1.0, 3.0]);
+            assert!(!queue.contains(buried));
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! # Rate Limiting
+//!
+//! A [`RateLimiter`] is a token bucket: it holds up to `capacity` tokens and refills at a fixed
+//! rate over simulated time. Requests take tokens, waiting in first-in, first-out order until
+//! enough have accumulated, which models API throttling and traffic shaping.
+
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Called once a request's tokens have been taken.
+type ReadyFn = Box<dyn FnOnce(&mut EventScheduler)>;
+
+struct BucketState {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    last_time: f64,
+    waiting: VecDeque<(f64, ReadyFn)>,
+    wake: Option<EventId>,
+}
+
+impl BucketState {
+    fn refill(&mut self, now: f64) {
+        self.tokens = (self.tokens + self.rate * (now - self.last_time)).min(self.capacity);
+        self.last_time = now;
+    }
+
+    /// Returns `true` if `tokens` are available, allowing for rounding in the refill time.
+    fn has(&self, tokens: f64) -> bool {
+        self.tokens >= tokens - 1e-9 * self.capacity
+    }
+}
+
+/// A token bucket that requests can wait on.
+///
+/// Clones draw from the same bucket, so every client sharing the limit holds a clone.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, RateLimiter};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let mut scheduler = EventScheduler::new();
+/// // Bursts of up to 2 calls, then one call every 0.5 time units.
+/// let api = RateLimiter::new(2.0, 2.0);
+/// let served = Rc::new(RefCell::new(Vec::new()));
+/// for _ in 0..4 {
+///     let record = served.clone();
+///     api.acquire(&mut scheduler, 1.0, move |s| record.borrow_mut().push(s.current_time));
+/// }
+/// scheduler.run_until_max_time(10.0);
+/// assert_eq!(*served.borrow(), vec![0.0, 0.0, 0.5, 1.0]);
+/// ```
+pub struct RateLimiter {
+    state: Rc<RefCell<BucketState>>,
+}
+
+impl Clone for RateLimiter {
+    fn clone(&self) -> Self {
+        RateLimiter { state: self.state.clone() }
+    }
+}
+
+impl RateLimiter {
+    /// Creates a full bucket holding up to `capacity` tokens and refilling `rate` tokens per
+    /// time unit.
+    ///
+    /// # Panics
+    /// Panics if `capacity` or `rate` is not positive.
+    pub fn new(capacity: f64, rate: f64) -> Self {
+        assert!(capacity > 0.0 && rate > 0.0, "token bucket capacity and rate must be positive");
+        RateLimiter {
+            state: Rc::new(RefCell::new(BucketState {
+                capacity,
+                rate,
+                tokens: capacity,
+                last_time: 0.0,
+                waiting: VecDeque::new(),
+                wake: None,
+            })),
+        }
+    }
+
+    /// Returns the tokens available at `now`.
+    pub fn available(&self, now: f64) -> f64 {
+        let mut state = self.state.borrow_mut();
+        state.refill(now);
+        state.tokens
+    }
+
+    /// Returns the number of waiting requests.
+    pub fn waiting(&self) -> usize {
+        self.state.borrow().waiting.len()
+    }
+
+    /// Takes `tokens` at once if they are available and no request is waiting.
+    ///
+    /// # Returns
+    /// `true` if the tokens were taken.
+    pub fn try_acquire(&self, scheduler: &EventScheduler, tokens: f64) -> bool {
+        let mut state = self.state.borrow_mut();
+        state.refill(scheduler.current_time);
+        if state.waiting.is_empty() && state.has(tokens) {
+            state.tokens -= tokens;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Takes `tokens`, waiting behind earlier requests until enough have accumulated.
+    ///
+    /// `on_ready` is run by an event at the time the tokens are taken.
+    ///
+    /// # Panics
+    /// Panics if `tokens` exceeds the bucket's capacity, since the request could never be met.
+    pub fn acquire<F>(&self, scheduler: &mut EventScheduler, tokens: f64, on_ready: F)
+    where
+        F: FnOnce(&mut EventScheduler) + 'static,
+    {
+        assert!(tokens <= self.state.borrow().capacity, "request exceeds the token bucket's capacity");
+        if self.try_acquire(scheduler, tokens) {
+            let mut on_ready = Some(on_ready);
+            scheduler.schedule(ScheduledAction::at(scheduler.current_time).with_action(move |s| {
+                if let Some(f) = on_ready.take() {
+                    f(s);
+                }
+                None
+            }));
+            return;
+        }
+        self.state.borrow_mut().waiting.push_back((tokens, Box::new(on_ready)));
+        self.schedule_wake(scheduler);
+    }
+
+    /// Schedules a wake-up for when the first waiting request can be met, unless one is pending.
+    fn schedule_wake(&self, scheduler: &mut EventScheduler) {
+        let time = {
+            let state = self.state.borrow();
+            if state.wake.is_some_and(|id| scheduler.is_pending(id)) {
+                return;
+            }
+            let Some(&(tokens, _)) = state.waiting.front() else { return };
+            // Waking before the tokens are there would only schedule another wake-up.
+            scheduler.time_after_at_least(((tokens - state.tokens) / state.rate).max(0.0))
+        };
+        let limiter = self.clone();
+        let id = scheduler.schedule(ScheduledAction::at(time).with_action(move |s| {
+            limiter.wake(s);
+            None
+        }));
+        self.state.borrow_mut().wake = Some(id);
+    }
+
+    fn wake(&self, scheduler: &mut EventScheduler) {
+        loop {
+            let ready = {
+                let mut state = self.state.borrow_mut();
+                state.refill(scheduler.current_time);
+                match state.waiting.front() {
+                    Some(&(tokens, _)) if state.has(tokens) => {
+                        state.tokens = (state.tokens - tokens).max(0.0);
+                        state.waiting.pop_front().map(|(_, ready)| ready)
+                    }
+                    _ => None,
+                }
+            };
+            match ready {
+                Some(ready) => ready(scheduler),
+                None => break,
+            }
+        }
+        self.schedule_wake(scheduler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_request_blocks_later_small_ones() {
+        let mut scheduler = EventScheduler::new();
+        let shaper = RateLimiter::new(4.0, 1.0);
+        assert!(shaper.try_acquire(&scheduler, 3.0));
+        let times = Rc::new(RefCell::new(Vec::new()));
+        for tokens in [4.0, 1.0] {
+            let record = times.clone();
+            shaper.acquire(&mut scheduler, tokens, move |s| record.borrow_mut().push((tokens, s.current_time)));
+        }
+        assert!(!shaper.try_acquire(&scheduler, 1.0));
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(*times.borrow(), vec![(4.0, 3.0), (1.0, 4.0)]);
+        assert_eq!(shaper.available(10.0), 4.0);
+    }
+
+    #[test]
+    fn test_waits_round_up_to_whole_ticks() {
+        let mut scheduler = EventScheduler::builder().clock_mode(crate::ClockMode::Ticks { tick: 1.0 }).build();
+        let shaper = RateLimiter::new(1.0, 2.0);
+        assert!(shaper.try_acquire(&scheduler, 0.4));
+        let granted = Rc::new(RefCell::new(Vec::new()));
+        let record = granted.clone();
+        // The missing 0.4 tokens take 0.2 time units, which would round to no delay at all.
+        shaper.acquire(&mut scheduler, 1.0, move |s| record.borrow_mut().push(s.current_time));
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(*granted.borrow(), vec![1.0]);
+        assert_eq!(scheduler.event_log.len(), 1);
+    }
+}
@@ -0,0 +1,114 @@
+//! # Named Actions
+//!
+//! Event actions are Rust closures, which code outside Rust cannot construct. An
+//! [`ActionRegistry`] lets a model register its actions under names so that they can be
+//! scheduled by name with [`EventScheduler::schedule_named`], passing their parameters in the
+//! event's context. This is the interface that language bindings, scenario files, and remote
+//! drivers are built on: the actions stay compiled Rust, and only names and strings cross the
+//! boundary.
+//!
+//! The crate ships no Python bindings: it has no `python` feature and does not depend on PyO3.
+//! Bindings built on this interface belong in a separate crate that takes that dependency.
+
+use crate::{Context, EventId, EventScheduler, ScheduledAction, SimError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// An action that can be scheduled by name. It receives the context of its event.
+pub type NamedAction = Rc<dyn Fn(&mut EventScheduler, &Context) -> Option<String>>;
+
+/// Actions registered under names, see [`EventScheduler::register_action`].
+#[derive(Default, Clone)]
+pub struct ActionRegistry {
+    actions: BTreeMap<String, NamedAction>,
+}
+
+impl ActionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        ActionRegistry::default()
+    }
+
+    /// Registers `action` under `name`, replacing any action already registered under it.
+    pub fn register<F>(&mut self, name: impl Into<String>, action: F)
+    where
+        F: Fn(&mut EventScheduler, &Context) -> Option<String> + 'static,
+    {
+        self.actions.insert(name.into(), Rc::new(action));
+    }
+
+    /// Returns the action registered under `name`.
+    pub fn get(&self, name: &str) -> Option<NamedAction> {
+        self.actions.get(name).cloned()
+    }
+
+    /// Returns the registered names in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+}
+
+impl fmt::Debug for ActionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.actions.keys()).finish()
+    }
+}
+
+impl EventScheduler {
+    /// Registers an action that can be scheduled by name with
+    /// [`EventScheduler::schedule_named`].
+    pub fn register_action<F>(&mut self, name: impl Into<String>, action: F)
+    where
+        F: Fn(&mut EventScheduler, &Context) -> Option<String> + 'static,
+    {
+        self.actions.register(name, action);
+    }
+
+    /// Schedules the action registered under `name` to run at `time`, labeled with its name
+    /// and carrying `context`, which the action receives.
+    ///
+    /// # Errors
+    /// Returns [`SimError::UnknownAction`] if no action is registered under `name`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Context, ContextExt, EventScheduler};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.register_action("admit", |s, context| {
+    ///     Some(format!("patient {} admitted at {}", context["patient"], s.current_time))
+    /// });
+    /// scheduler.schedule_named(3.0, "admit", Context::builder().insert("patient", 17).build()).unwrap();
+    /// assert!(scheduler.schedule_named(4.0, "discharge", Context::new()).is_err());
+    ///
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!(log[0].result.as_deref(), Some("patient 17 admitted at 3"));
+    /// assert_eq!(log[0].label.as_deref(), Some("admit"));
+    /// ```
+    pub fn schedule_named(&mut self, time: f64, name: &str, context: Context) -> Result<EventId, SimError> {
+        let action = self.actions.get(name).ok_or_else(|| SimError::UnknownAction { name: name.to_string() })?;
+        let arguments = context.clone();
+        let event = ScheduledAction::at(time).with_label(name).with_context(context).with_action(move |s| action(s, &arguments));
+        Ok(self.schedule(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_actions_reschedule_themselves() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.register_action("tick", |s, _| {
+            let next = s.current_time + 1.0;
+            s.schedule_named(next, "tick", Context::new()).unwrap();
+            None
+        });
+        scheduler.register_action("tock", |_, _| None);
+        assert_eq!(scheduler.actions.names().collect::<Vec<_>>(), ["tick", "tock"]);
+        scheduler.schedule_named(0.0, "tick", Context::new()).unwrap();
+        assert_eq!(scheduler.run_until_max_time(4.5).len(), 5);
+    }
+}
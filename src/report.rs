@@ -0,0 +1,306 @@
+//! # Run Results
+//!
+//! Every `run*` method returns a [`RunResult`]: the final time, why the run stopped, the event
+//! log, the scheduler's [`SchedulerMetrics`], and summaries of the built-in collectors, with
+//! [`RunResult::report`] to print them. A `RunResult` borrows the log rather than copying it
+//! and dereferences to `[EventRecord]`, so it can be indexed and iterated like the log itself.
+
+use crate::{EventRecord, EventScheduler, LabelProfile, SchedulerMetrics, SeedAudit, SimError, Tally};
+use std::fmt;
+use std::ops::Deref;
+
+/// Why a run returned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// The stop condition was satisfied.
+    Condition,
+    /// No events were left to run.
+    QueueEmpty,
+    /// An action called [`EventScheduler::request_pause`].
+    Paused,
+    /// The run used up the scheduler's `wall_clock_budget`.
+    WallClockBudget,
+    /// The run failed; the error was also returned by the `try_run*` method.
+    Error(SimError),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Condition => f.write_str("stop condition satisfied"),
+            StopReason::QueueEmpty => f.write_str("event queue empty"),
+            StopReason::Paused => f.write_str("pause requested"),
+            StopReason::WallClockBudget => f.write_str("wall-clock budget exhausted"),
+            StopReason::Error(error) => write!(f, "error: {}", error),
+        }
+    }
+}
+
+/// The outcome of a run.
+///
+/// # Example
+/// ```
+/// use desru::EventScheduler;
+///
+/// let mut scheduler = EventScheduler::new();
+/// scheduler.timeout(2.0, Some(Box::new(|_| Some("done".to_string()))), None);
+/// let result = scheduler.run_until_max_time(10.0);
+/// assert_eq!(result.final_time, 2.0);
+/// assert_eq!(result.metrics.events_executed, 1);
+/// assert_eq!(result[0].result.as_deref(), Some("done"));
+/// println!("{}", result.report());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult<'a> {
+    /// The simulation time when the run returned.
+    pub final_time: f64,
+    pub stop_reason: StopReason,
+    /// The event log, including events logged by earlier runs.
+    pub log: &'a [EventRecord],
+    pub metrics: SchedulerMetrics,
+    /// How many executed events carried each tag, sorted by tag.
+    pub tag_counts: Vec<(String, u64)>,
+    /// The number of entities created with [`EventScheduler::create_entity`].
+    pub entities: usize,
+    /// Entity cycle times, from creation to departure.
+    pub cycle_times: Tally,
+    /// The number of completed activities.
+    pub activities: usize,
+    /// The seeds the run drew from, for reproducing it.
+    pub seeds: SeedAudit,
+    /// Action timings per event label, most expensive first, if profiling was enabled.
+    pub profile: Vec<LabelProfile>,
+}
+
+impl<'a> RunResult<'a> {
+    /// Summarises the scheduler's state after a run.
+    pub(crate) fn new(scheduler: &'a EventScheduler, stop_reason: StopReason) -> Self {
+        let mut tag_counts: Vec<_> = scheduler.tag_metrics.counts().map(|(tag, count)| (tag.to_string(), count)).collect();
+        tag_counts.sort();
+        RunResult {
+            final_time: scheduler.current_time,
+            stop_reason,
+            log: &scheduler.event_log,
+            metrics: scheduler.metrics(),
+            tag_counts,
+            entities: scheduler.entities.count(),
+            cycle_times: scheduler.entities.cycle_times(),
+            activities: scheduler.activities.completed().len(),
+            seeds: scheduler.seed_audit(),
+            profile: scheduler.profiler.labels(),
+        }
+    }
+
+    /// Formats a human-readable summary of the run.
+    pub fn report(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns a hash of the logged `(time, label, result)` sequence, for asserting in
+    /// regression tests that a model change did not alter its behaviour.
+    ///
+    /// The digest is 64-bit FNV-1a over a fixed byte encoding of each record, so it is the
+    /// same on every platform and Rust version. Contexts and event ids are not included.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// fn model() -> EventScheduler {
+    ///     let mut scheduler = EventScheduler::builder().seed(7).build();
+    ///     scheduler.timeout(1.0, Some(Box::new(|s| Some(format!("{:.3}", s.rng.next_f64())))), None);
+    ///     scheduler
+    /// }
+    ///
+    /// let first = model().run_until_max_time(10.0).trace_digest();
+    /// let second = model().run_until_max_time(10.0).trace_digest();
+    /// assert_eq!(first, second);
+    /// ```
+    pub fn trace_digest(&self) -> u64 {
+        let mut digest = Fnv1a::new();
+        for record in self.log {
+            digest.write(&record.time.to_bits().to_le_bytes());
+            digest.write_str(record.label.as_deref());
+            digest.write_str(record.result.as_deref());
+        }
+        digest.0
+    }
+}
+
+/// The 64-bit FNV-1a hash, used because `std`'s hashers may change between releases.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // Optional strings are written with a presence byte and length prefix, so that
+    // `(Some("ab"), Some(""))` and `(Some("a"), Some("b"))` hash differently.
+    fn write_str(&mut self, value: Option<&str>) {
+        match value {
+            None => self.write(&[0]),
+            Some(value) => {
+                self.write(&[1]);
+                self.write(&(value.len() as u64).to_le_bytes());
+                self.write(value.as_bytes());
+            }
+        }
+    }
+}
+
+impl Deref for RunResult<'_> {
+    type Target = [EventRecord];
+
+    fn deref(&self) -> &[EventRecord] {
+        self.log
+    }
+}
+
+impl<'a> IntoIterator for RunResult<'a> {
+    type Item = &'a EventRecord;
+    type IntoIter = std::slice::Iter<'a, EventRecord>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.log.iter()
+    }
+}
+
+impl<'a> IntoIterator for &RunResult<'a> {
+    type Item = &'a EventRecord;
+    type IntoIter = std::slice::Iter<'a, EventRecord>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.log.iter()
+    }
+}
+
+impl fmt::Display for RunResult<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "final time:        {}", self.final_time)?;
+        writeln!(f, "stopped because:   {}", self.stop_reason)?;
+        writeln!(f, "events logged:     {}", self.log.len())?;
+        writeln!(f, "seeds:             {}", self.seeds)?;
+        writeln!(f, "{}", self.metrics)?;
+        if self.entities > 0 {
+            writeln!(f, "entities:          {}", self.entities)?;
+            if self.cycle_times.count() > 0 {
+                writeln!(f, "mean cycle time:   {}", self.cycle_times.mean())?;
+            }
+        }
+        if self.activities > 0 {
+            writeln!(f, "activities:        {}", self.activities)?;
+        }
+        for (tag, count) in &self.tag_counts {
+            writeln!(f, "tag {}: {}", tag, count)?;
+        }
+        for profile in &self.profile {
+            writeln!(
+                f,
+                "profile {}: {} events, {:.3?} total, {:.3?} mean, {:.3?} max",
+                profile.label.as_deref().unwrap_or("unlabeled"),
+                profile.count,
+                profile.total,
+                profile.mean(),
+                profile.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl EventScheduler {
+    /// Asks the current run to return after the event that is executing, with
+    /// [`StopReason::Paused`]. Calling a `run*` method again resumes from the next event.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, StopReason};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.timeout(2.0, Some(Box::new(|s| {
+    ///     s.request_pause();
+    ///     None
+    /// })), None);
+    /// scheduler.timeout(4.0, None, None);
+    /// assert_eq!(scheduler.run_until_max_time(10.0).stop_reason, StopReason::Paused);
+    /// assert_eq!(scheduler.current_time, 2.0);
+    /// assert_eq!(scheduler.run_until_max_time(10.0).stop_reason, StopReason::QueueEmpty);
+    /// ```
+    pub fn request_pause(&mut self) {
+        self.pause_requested = true;
+    }
+
+    /// Returns why the most recent run returned, or `None` before the first run.
+    ///
+    /// Unlike [`RunResult::stop_reason`], this is also available after a `try_run*` method
+    /// returned an error.
+    pub fn stop_reason(&self) -> Option<&StopReason> {
+        self.stop_reason.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventScheduler, ScheduledAction, SimError, StopReason};
+    use std::time::Duration;
+
+    #[test]
+    fn test_condition_budget_and_error_reasons() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.timeout(1.0, None, None);
+        scheduler.timeout(5.0, None, None);
+        let result = scheduler.run(Box::new(|s| s.current_time >= 1.0), None);
+        assert_eq!(result.stop_reason, StopReason::Condition);
+
+        scheduler.wall_clock_budget = Some(Duration::ZERO);
+        assert_eq!(scheduler.run_until_max_time(10.0).stop_reason, StopReason::WallClockBudget);
+        assert_eq!(scheduler.current_time, 5.0);
+
+        fn ping(s: &mut EventScheduler) -> Option<String> {
+            s.schedule_now(ping);
+            None
+        }
+        let mut cascade = EventScheduler::builder().max_events_per_time(10).build();
+        cascade.schedule_now(ping);
+        let error = cascade.try_run(Box::new(|_| false), None).unwrap_err();
+        assert_eq!(cascade.stop_reason(), Some(&StopReason::Error(error.clone())));
+        assert!(matches!(error, SimError::ZeroDelayCascade { .. }));
+    }
+
+    #[test]
+    fn test_trace_digest_is_stable_and_sensitive() {
+        fn trace(results: &[&str]) -> u64 {
+            let mut scheduler = EventScheduler::new();
+            for (i, result) in results.iter().enumerate() {
+                let result = result.to_string();
+                scheduler.schedule(ScheduledAction::at(i as f64).with_label("step").with_action(move |_| Some(result.clone())));
+            }
+            scheduler.run_until_max_time(100.0).trace_digest()
+        }
+        assert_eq!(trace(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(trace(&["ab", ""]), trace(&["ab", ""]));
+        assert_ne!(trace(&["ab", ""]), trace(&["a", "b"]));
+    }
+
+    #[test]
+    fn test_summaries_and_report() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(1.0).with_tag("arrival"));
+        scheduler.schedule(ScheduledAction::at(2.0).with_tag("arrival").with_tag("vip"));
+        scheduler.schedule(ScheduledAction::at(3.0));
+        let result = scheduler.run_until_max_time(10.0);
+        assert_eq!(result.tag_counts, vec![("arrival".to_string(), 2), ("vip".to_string(), 1)]);
+        assert_eq!(result.iter().map(|r| r.time).collect::<Vec<_>>(), [1.0, 2.0, 3.0]);
+        let report = result.report();
+        assert!(report.contains("final time:        3"));
+        assert!(report.contains("tag arrival: 2"));
+    }
+}
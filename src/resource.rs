@@ -0,0 +1,734 @@
+//! # Resources
+//!
+//! A [`Resource`] models a pool of identical servers, such as machines, staff, or parking
+//! spaces. Requests are granted while capacity is available and otherwise wait in a queue.
+//! Requests carry a priority where, as in SimPy, a *lower* value is more important. A
+//! preemptive resource additionally lets an important request evict a less important user.
+//! The order in which waiting requests are served is set by a [`QueueDiscipline`].
+//!
+//! Every resource tracks its own queue length, utilization, and waiting times, available
+//! through [`Resource::stats`]. Requests are not tied to an [`crate::Entity`], so a model that
+//! tracks entity milestones records them in its grant and release code.
+//!
+//! Resources are handles: cloning a `Resource` yields another handle to the same pool, which
+//! makes them easy to capture in event actions.
+
+use crate::discipline::{Priority, QueueDiscipline, QueuedRequest};
+use crate::stats::{Monitored, Tally};
+use crate::{EventId, EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Identifies a request made to a [`Resource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestId(pub u64);
+
+/// Passed to a request's grant callback once the resource has been acquired.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grant {
+    pub id: RequestId,
+    pub priority: i64,
+    pub requested_at: f64,
+    pub granted_at: f64,
+}
+
+/// Passed to a user's preemption callback when a more important request evicts it, or when
+/// the resource is interrupted, for example by a [`crate::Breakdown`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preempted {
+    pub id: RequestId,
+    /// The request that took the unit, or `None` if the resource was interrupted.
+    pub by: Option<RequestId>,
+    pub usage_since: f64,
+}
+
+/// What happens to a job served with [`Resource::serve`] when it is preempted.
+///
+/// Either way the job rejoins the queue with its original priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreemptionMode {
+    /// The job later continues with the service time it had left.
+    #[default]
+    Resume,
+    /// The job later restarts its service from the beginning.
+    Repeat,
+}
+
+/// Called when a request is granted.
+pub type GrantFn = Box<dyn FnOnce(&mut EventScheduler, Grant)>;
+
+/// Called when a user is preempted.
+pub type PreemptFn = Box<dyn FnOnce(&mut EventScheduler, Preempted)>;
+
+struct Callbacks {
+    on_grant: GrantFn,
+    on_preempt: Option<PreemptFn>,
+}
+
+struct User {
+    id: RequestId,
+    priority: i64,
+    granted_at: f64,
+    on_preempt: Option<PreemptFn>,
+}
+
+/// Called when a job started with [`Resource::serve`] completes.
+type DoneFn = Box<dyn FnOnce(&mut EventScheduler)>;
+
+/// The progress of a job started with [`Resource::serve`].
+struct Job {
+    duration: f64,
+    remaining: f64,
+    mode: PreemptionMode,
+    completion: Option<EventId>,
+    on_done: Option<DoneFn>,
+}
+
+struct ResourceState {
+    capacity: usize,
+    preemptive: bool,
+    available: bool,
+    users: Vec<User>,
+    // Parallel vectors in arrival order, so disciplines can inspect `waiting` as a slice.
+    waiting: Vec<QueuedRequest>,
+    callbacks: Vec<Callbacks>,
+    discipline: Box<dyn QueueDiscipline>,
+    next_id: u64,
+    queue_length: Monitored,
+    busy: Monitored,
+    waits: Tally,
+}
+
+impl ResourceState {
+    /// Records the current queue length and number of busy units if either has changed.
+    fn record_levels(&mut self, now: f64) {
+        let waiting = self.waiting.len() as f64;
+        if self.queue_length.trajectory().is_empty() || self.queue_length.value() != waiting {
+            self.queue_length.record(now, waiting);
+        }
+        let busy = self.users.len() as f64;
+        if self.busy.trajectory().is_empty() || self.busy.value() != busy {
+            self.busy.record(now, busy);
+        }
+    }
+
+    /// Moves the waiting request at `index` to the users, returning its grant callback and the
+    /// grant to deliver.
+    fn grant(&mut self, index: usize, now: f64) -> (GrantFn, Grant) {
+        let waiting = self.waiting.remove(index);
+        let callbacks = self.callbacks.remove(index);
+        self.users.push(User { id: waiting.id, priority: waiting.priority, granted_at: now, on_preempt: callbacks.on_preempt });
+        self.waits.record(now - waiting.requested_at);
+        self.record_levels(now);
+        let grant = Grant { id: waiting.id, priority: waiting.priority, requested_at: waiting.requested_at, granted_at: now };
+        (callbacks.on_grant, grant)
+    }
+}
+
+/// A snapshot of a resource's statistics, see [`Resource::stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceStats {
+    pub capacity: usize,
+    /// Time-averaged fraction of units in use.
+    pub utilization: f64,
+    /// Time-averaged number of waiting requests.
+    pub mean_queue_length: f64,
+    pub max_queue_length: usize,
+    /// Waiting times of all granted requests, in grant order.
+    pub waits: Tally,
+}
+
+/// A shared pool of `capacity` identical units.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Resource};
+///
+/// let mut scheduler = EventScheduler::new();
+/// let pump = Resource::new(1);
+///
+/// for car in 0..2 {
+///     let handle = pump.clone();
+///     pump.request(&mut scheduler, 0, move |s, grant| {
+///         println!("car {} starts refuelling at {}", car, s.current_time);
+///         s.timeout(5.0, Some(Box::new(move |s| {
+///             handle.release(s, grant.id);
+///             None
+///         })), None);
+///     });
+/// }
+/// scheduler.run_until_max_time(20.0);
+/// assert_eq!(pump.in_use(), 0);
+/// ```
+#[derive(Clone)]
+pub struct Resource {
+    state: Rc<RefCell<ResourceState>>,
+}
+
+impl Resource {
+    /// Creates a resource with `capacity` units that waiting requests cannot preempt.
+    pub fn new(capacity: usize) -> Self {
+        Resource::with_preemption(capacity, false)
+    }
+
+    /// Creates a resource with `capacity` units where more important requests preempt less
+    /// important users, mirroring SimPy's `PreemptiveResource`.
+    ///
+    /// Only the request the queue discipline would serve next can preempt, and it is the one
+    /// granted the evicted unit.
+    pub fn preemptive(capacity: usize) -> Self {
+        Resource::with_preemption(capacity, true)
+    }
+
+    fn with_preemption(capacity: usize, preemptive: bool) -> Self {
+        Resource {
+            state: Rc::new(RefCell::new(ResourceState {
+                capacity,
+                preemptive,
+                available: true,
+                users: Vec::new(),
+                waiting: Vec::new(),
+                callbacks: Vec::new(),
+                discipline: Box::new(Priority),
+                next_id: 0,
+                queue_length: Monitored::new(0.0),
+                busy: Monitored::new(0.0),
+                waits: Tally::new(),
+            })),
+        }
+    }
+
+    /// Returns the resource's statistics, measured from its first request up to `now`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, Resource};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let desk = Resource::new(1);
+    /// for _ in 0..2 {
+    ///     let handle = desk.clone();
+    ///     desk.request(&mut scheduler, 0, move |s, grant| {
+    ///         s.timeout(4.0, Some(Box::new(move |s| {
+    ///             handle.release(s, grant.id);
+    ///             None
+    ///         })), None);
+    ///     });
+    /// }
+    /// scheduler.run_until_max_time(10.0);
+    ///
+    /// let stats = desk.stats(10.0);
+    /// assert_eq!(stats.utilization, 0.8);
+    /// assert_eq!(stats.waits.values(), &[0.0, 4.0]);
+    /// assert_eq!(stats.max_queue_length, 1);
+    /// ```
+    pub fn stats(&self, now: f64) -> ResourceStats {
+        let state = self.state.borrow();
+        ResourceStats {
+            capacity: state.capacity,
+            utilization: if state.capacity == 0 { 0.0 } else { state.busy.time_average(now) / state.capacity as f64 },
+            mean_queue_length: state.queue_length.time_average(now),
+            max_queue_length: state.queue_length.max() as usize,
+            waits: state.waits.clone(),
+        }
+    }
+
+    /// Sets the order in which waiting requests are served. Defaults to [`Priority`].
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Lifo, Resource};
+    ///
+    /// let stack = Resource::new(1).with_discipline(Lifo);
+    /// ```
+    pub fn with_discipline<D: QueueDiscipline + 'static>(self, discipline: D) -> Self {
+        self.state.borrow_mut().discipline = Box::new(discipline);
+        self
+    }
+
+    /// Returns the number of units.
+    pub fn capacity(&self) -> usize {
+        self.state.borrow().capacity
+    }
+
+    /// Returns the number of units currently held.
+    pub fn in_use(&self) -> usize {
+        self.state.borrow().users.len()
+    }
+
+    /// Returns the number of requests waiting to be granted.
+    pub fn queue_len(&self) -> usize {
+        self.state.borrow().waiting.len()
+    }
+
+    /// Returns whether the resource is currently accepting new grants.
+    pub fn is_available(&self) -> bool {
+        self.state.borrow().available
+    }
+
+    /// Requests one unit.
+    ///
+    /// `on_grant` is run by an event at the time the unit is acquired: immediately if a unit is
+    /// free, otherwise once one is released. Users holding the unit cannot be told about
+    /// preemption; use [`Resource::request_preemptible`] for that.
+    ///
+    /// # Parameters
+    /// - `scheduler`: The scheduler used to deliver the grant.
+    /// - `priority`: The request priority; lower values are served first.
+    /// - `on_grant`: Called with the [`Grant`] once the unit is acquired.
+    ///
+    /// # Returns
+    /// The [`RequestId`] used to release or cancel the request.
+    pub fn request<F>(&self, scheduler: &mut EventScheduler, priority: i64, on_grant: F) -> RequestId
+    where
+        F: FnOnce(&mut EventScheduler, Grant) + 'static,
+    {
+        self.enqueue(scheduler, priority, 0.0, Box::new(on_grant), None)
+    }
+
+    /// Requests one unit with a sort key visible to the queue discipline, such as the expected
+    /// service time used by [`crate::ShortestProcessingTime`].
+    pub fn request_keyed<F>(&self, scheduler: &mut EventScheduler, priority: i64, key: f64, on_grant: F) -> RequestId
+    where
+        F: FnOnce(&mut EventScheduler, Grant) + 'static,
+    {
+        self.enqueue(scheduler, priority, key, Box::new(on_grant), None)
+    }
+
+    /// Requests one unit, with a callback run if the unit is later preempted.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, Resource};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let machine = Resource::preemptive(1);
+    /// machine.request_preemptible(&mut scheduler, 5,
+    ///     |_, _| println!("routine job started"),
+    ///     |s, p| println!("routine job preempted at {} by {:?}", s.current_time, p.by));
+    /// scheduler.timeout(1.0, Some(Box::new({
+    ///     let machine = machine.clone();
+    ///     move |s| {
+    ///         machine.request(s, 0, |_, _| println!("urgent job started"));
+    ///         None
+    ///     }
+    /// })), None);
+    /// scheduler.run_until_max_time(10.0);
+    /// ```
+    pub fn request_preemptible<F, P>(&self, scheduler: &mut EventScheduler, priority: i64, on_grant: F, on_preempt: P) -> RequestId
+    where
+        F: FnOnce(&mut EventScheduler, Grant) + 'static,
+        P: FnOnce(&mut EventScheduler, Preempted) + 'static,
+    {
+        self.enqueue(scheduler, priority, 0.0, Box::new(on_grant), Some(Box::new(on_preempt)))
+    }
+
+    /// Holds one unit for `duration` time units of service, then releases it and runs
+    /// `on_done`.
+    ///
+    /// If the job is preempted, its pending completion is cancelled and it requests the unit
+    /// again, continuing or restarting its service according to `mode`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, PreemptionMode, Resource};
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let machine = Resource::preemptive(1);
+    /// let finished = Rc::new(Cell::new(0.0));
+    /// let record = finished.clone();
+    /// machine.serve(&mut scheduler, 5, 4.0, PreemptionMode::Resume, move |s| record.set(s.current_time));
+    /// let urgent = machine.clone();
+    /// scheduler.timeout(1.0, Some(Box::new(move |s| {
+    ///     urgent.serve(s, 0, 2.0, PreemptionMode::Resume, |_| {});
+    ///     None
+    /// })), None);
+    /// scheduler.run_until_max_time(20.0);
+    /// // One unit of service before the interruption, three after it ends at 3.
+    /// assert_eq!(finished.get(), 6.0);
+    /// ```
+    pub fn serve<F>(&self, scheduler: &mut EventScheduler, priority: i64, duration: f64, mode: PreemptionMode, on_done: F)
+    where
+        F: FnOnce(&mut EventScheduler) + 'static,
+    {
+        let job = Rc::new(RefCell::new(Job { duration, remaining: duration, mode, completion: None, on_done: Some(Box::new(on_done)) }));
+        self.start_job(scheduler, priority, job);
+    }
+
+    fn start_job(&self, scheduler: &mut EventScheduler, priority: i64, job: Rc<RefCell<Job>>) {
+        let on_grant = {
+            let (resource, job) = (self.clone(), job.clone());
+            move |s: &mut EventScheduler, grant: Grant| {
+                let remaining = job.borrow().remaining;
+                let finish = job.clone();
+                let id = s.timeout(remaining, Some(Box::new(move |s| {
+                    resource.release(s, grant.id);
+                    let on_done = finish.borrow_mut().on_done.take();
+                    if let Some(on_done) = on_done {
+                        on_done(s);
+                    }
+                    None
+                })), None);
+                job.borrow_mut().completion = Some(id);
+            }
+        };
+        let on_preempt = {
+            let resource = self.clone();
+            move |s: &mut EventScheduler, preempted: Preempted| {
+                let finished = {
+                    let mut state = job.borrow_mut();
+                    if let Some(id) = state.completion.take() {
+                        s.cancel(id);
+                    }
+                    let left = state.remaining - (s.current_time - preempted.usage_since);
+                    // A job preempted at the instant it completes, or whose completion already
+                    // ran at that instant, is done rather than due zero more service.
+                    if left <= 0.0 || state.on_done.is_none() {
+                        true
+                    } else {
+                        state.remaining = match state.mode {
+                            PreemptionMode::Resume => left,
+                            PreemptionMode::Repeat => state.duration,
+                        };
+                        false
+                    }
+                };
+                if !finished {
+                    resource.start_job(s, priority, job);
+                    return;
+                }
+                let on_done = job.borrow_mut().on_done.take();
+                if let Some(on_done) = on_done {
+                    on_done(s);
+                }
+            }
+        };
+        self.request_preemptible(scheduler, priority, on_grant, on_preempt);
+    }
+
+    fn enqueue(&self, scheduler: &mut EventScheduler, priority: i64, key: f64, on_grant: GrantFn, on_preempt: Option<PreemptFn>) -> RequestId {
+        let id = {
+            let mut state = self.state.borrow_mut();
+            let id = RequestId(state.next_id);
+            state.next_id += 1;
+            state.waiting.push(QueuedRequest { id, priority, key, requested_at: scheduler.current_time });
+            state.callbacks.push(Callbacks { on_grant, on_preempt });
+            state.record_levels(scheduler.current_time);
+            id
+        };
+        self.try_preempt(scheduler);
+        self.dispatch(scheduler);
+        id
+    }
+
+    /// Releases the unit held by request `id`, granting it to the next waiting request.
+    ///
+    /// # Returns
+    /// `true` if `id` was holding a unit, `false` otherwise (for example if it was preempted).
+    pub fn release(&self, scheduler: &mut EventScheduler, id: RequestId) -> bool {
+        let released = {
+            let mut state = self.state.borrow_mut();
+            let before = state.users.len();
+            state.users.retain(|user| user.id != id);
+            state.record_levels(scheduler.current_time);
+            state.users.len() < before
+        };
+        if released {
+            self.dispatch(scheduler);
+        }
+        released
+    }
+
+    /// Withdraws a request that is still waiting.
+    ///
+    /// # Returns
+    /// `true` if the request was waiting and has been removed.
+    pub fn cancel(&self, scheduler: &EventScheduler, id: RequestId) -> bool {
+        let mut state = self.state.borrow_mut();
+        match state.waiting.iter().position(|waiting| waiting.id == id) {
+            Some(position) => {
+                state.waiting.remove(position);
+                state.callbacks.remove(position);
+                state.record_levels(scheduler.current_time);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Opens or closes the resource to new grants, for example from a [`crate::Calendar`].
+    ///
+    /// Closing does not interrupt current users; waiting requests are held until reopening.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{Calendar, EventScheduler, Resource};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// let clerk = Resource::new(1);
+    /// let gate = clerk.clone();
+    /// Calendar::new(24.0).window(9.0, 17.0).drive(&mut scheduler, move |s, open| {
+    ///     gate.set_available(s, open);
+    /// });
+    /// scheduler.run_until_max_time(1.0);
+    /// assert!(!clerk.is_available());
+    /// ```
+    pub fn set_available(&self, scheduler: &mut EventScheduler, available: bool) {
+        self.state.borrow_mut().available = available;
+        if available {
+            self.dispatch(scheduler);
+        }
+    }
+
+    /// Evicts the least important user if the request the discipline would serve next
+    /// outranks it, and grants the unit to that request.
+    fn try_preempt(&self, scheduler: &mut EventScheduler) {
+        let (user, on_grant, grant) = {
+            let mut state = self.state.borrow_mut();
+            if !state.preemptive || !state.available || state.users.len() < state.capacity || state.waiting.is_empty() {
+                return;
+            }
+            let state = &mut *state;
+            let next = state.discipline.select(&state.waiting, &mut scheduler.rng);
+            let victim = state
+                .users
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.priority.cmp(&b.priority).then(a.granted_at.total_cmp(&b.granted_at)))
+                .map(|(i, _)| i);
+            match victim {
+                Some(v) if state.users[v].priority > state.waiting[next].priority => {
+                    let user = state.users.remove(v);
+                    let (on_grant, grant) = state.grant(next, scheduler.current_time);
+                    (user, on_grant, grant)
+                }
+                _ => return,
+            }
+        };
+        Resource::notify_preempted(scheduler, user, Some(grant.id));
+        Resource::deliver(scheduler, on_grant, grant);
+    }
+
+    /// Evicts every current user, running their preemption callbacks as for preemption by a
+    /// request. Users without a preemption callback silently lose their unit.
+    ///
+    /// Evicted units are granted again at once unless the resource has been closed with
+    /// [`Resource::set_available`].
+    ///
+    /// # Returns
+    /// The number of users evicted.
+    pub fn interrupt(&self, scheduler: &mut EventScheduler) -> usize {
+        let users = {
+            let mut state = self.state.borrow_mut();
+            let users = std::mem::take(&mut state.users);
+            state.record_levels(scheduler.current_time);
+            users
+        };
+        let count = users.len();
+        for user in users {
+            Resource::notify_preempted(scheduler, user, None);
+        }
+        self.dispatch(scheduler);
+        count
+    }
+
+    fn notify_preempted(scheduler: &mut EventScheduler, user: User, by: Option<RequestId>) {
+        if let Some(on_preempt) = user.on_preempt {
+            let info = Preempted { id: user.id, by, usage_since: user.granted_at };
+            let mut on_preempt = Some(on_preempt);
+            scheduler.schedule(ScheduledAction::new(
+                scheduler.current_time,
+                Some(Box::new(move |s: &mut EventScheduler| {
+                    if let Some(f) = on_preempt.take() {
+                        f(s, info);
+                    }
+                    None
+                })),
+                None,
+            ));
+        }
+    }
+
+    /// Grants free units to waiting requests.
+    fn dispatch(&self, scheduler: &mut EventScheduler) {
+        loop {
+            let granted = {
+                let mut state = self.state.borrow_mut();
+                if !state.available || state.users.len() >= state.capacity {
+                    return;
+                }
+                if state.waiting.is_empty() {
+                    return;
+                }
+                let state = &mut *state;
+                let next = state.discipline.select(&state.waiting, &mut scheduler.rng);
+                state.grant(next, scheduler.current_time)
+            };
+            let (on_grant, grant) = granted;
+            Resource::deliver(scheduler, on_grant, grant);
+        }
+    }
+
+    /// Schedules the event that runs a grant callback.
+    fn deliver(scheduler: &mut EventScheduler, on_grant: GrantFn, grant: Grant) {
+        let mut on_grant = Some(on_grant);
+        scheduler.schedule(ScheduledAction::new(
+            scheduler.current_time,
+            Some(Box::new(move |s: &mut EventScheduler| {
+                if let Some(f) = on_grant.take() {
+                    f(s, grant);
+                }
+                None
+            })),
+            None,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hold(resource: &Resource, scheduler: &mut EventScheduler, priority: i64, duration: f64, log: Rc<RefCell<Vec<(i64, f64)>>>) {
+        let handle = resource.clone();
+        resource.request(scheduler, priority, move |s, grant| {
+            log.borrow_mut().push((priority, s.current_time));
+            s.timeout(duration, Some(Box::new(move |s| {
+                handle.release(s, grant.id);
+                None
+            })), None);
+        });
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut scheduler = EventScheduler::new();
+        let resource = Resource::new(1);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        hold(&resource, &mut scheduler, 3, 1.0, log.clone());
+        hold(&resource, &mut scheduler, 2, 1.0, log.clone());
+        hold(&resource, &mut scheduler, 1, 1.0, log.clone());
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(*log.borrow(), vec![(3, 0.0), (1, 1.0), (2, 2.0)]);
+    }
+
+    #[test]
+    fn test_preemption() {
+        let mut scheduler = EventScheduler::new();
+        let resource = Resource::preemptive(1);
+        let preempted = Rc::new(RefCell::new(None));
+        let record = preempted.clone();
+        let low = resource.request_preemptible(&mut scheduler, 5, |_, _| {}, move |s, p| {
+            *record.borrow_mut() = Some((s.current_time, p.usage_since));
+        });
+        let handle = resource.clone();
+        scheduler.timeout(2.0, Some(Box::new(move |s| {
+            handle.request(s, 0, |_, _| {});
+            None
+        })), None);
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(*preempted.borrow(), Some((2.0, 0.0)));
+        assert!(!resource.release(&mut scheduler, low));
+        assert_eq!(resource.in_use(), 1);
+    }
+
+    #[test]
+    fn test_preemption_follows_the_discipline() {
+        let mut scheduler = EventScheduler::new();
+        let resource = Resource::preemptive(1).with_discipline(crate::Fifo);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        resource.request_preemptible(&mut scheduler, 5, |_, _| {}, |_, _| {});
+        let handle = resource.clone();
+        resource.request(&mut scheduler, 9, |_, _| {});
+        let record = log.clone();
+        scheduler.timeout(1.0, Some(Box::new(move |s| {
+            // The oldest waiting request is served next and is less important than the user,
+            // so the urgent request waits its turn instead of preempting.
+            let record = record.clone();
+            handle.request(s, 0, move |s, _| record.borrow_mut().push(s.current_time));
+            None
+        })), None);
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(resource.in_use(), 1);
+        assert_eq!(resource.queue_len(), 2);
+        assert!(log.borrow().is_empty());
+
+        let resource = Resource::preemptive(1).with_discipline(crate::Lifo);
+        let victim = resource.request_preemptible(&mut scheduler, 5, |_, _| {}, |_, _| {});
+        resource.request(&mut scheduler, 9, |_, _| {});
+        let urgent = resource.request(&mut scheduler, 0, |_, _| {});
+        // The newest request is served next, and it takes the unit from the user.
+        assert!(!resource.release(&mut scheduler, victim));
+        assert!(resource.release(&mut scheduler, urgent));
+    }
+
+    #[test]
+    fn test_preemptive_repeat() {
+        let mut scheduler = EventScheduler::new();
+        let resource = Resource::preemptive(1);
+        let finished = Rc::new(RefCell::new(Vec::new()));
+        let record = finished.clone();
+        resource.serve(&mut scheduler, 5, 4.0, PreemptionMode::Repeat, move |s| record.borrow_mut().push(s.current_time));
+        for start in [1.0, 6.0] {
+            let (handle, record) = (resource.clone(), finished.clone());
+            scheduler.timeout(start, Some(Box::new(move |s| {
+                let record = record.clone();
+                handle.serve(s, 0, 2.0, PreemptionMode::Repeat, move |s| record.borrow_mut().push(s.current_time));
+                None
+            })), None);
+        }
+        scheduler.run_until_max_time(20.0);
+        // Restarted at 3, interrupted again at 6, restarted at 8 and finished at 12.
+        assert_eq!(*finished.borrow(), vec![3.0, 8.0, 12.0]);
+        assert_eq!(resource.in_use(), 0);
+    }
+
+    #[test]
+    fn test_preemption_at_completion_finishes_the_job() {
+        for mode in [PreemptionMode::Resume, PreemptionMode::Repeat] {
+            let mut scheduler = EventScheduler::new();
+            let resource = Resource::preemptive(1);
+            let finished = Rc::new(RefCell::new(Vec::new()));
+            let handle = resource.clone();
+            // Scheduled first, the urgent arrival runs before the routine job's completion.
+            scheduler.timeout(2.0, Some(Box::new(move |s| {
+                handle.request(s, 0, |_, _| {});
+                None
+            })), None);
+            let record = finished.clone();
+            resource.serve(&mut scheduler, 5, 2.0, mode, move |s| record.borrow_mut().push(s.current_time));
+            scheduler.run_until_max_time(10.0);
+            assert_eq!(*finished.borrow(), vec![2.0]);
+            // Granted once each: the routine job is not queued again for zero service.
+            assert_eq!(resource.stats(10.0).waits.values().len(), 2);
+            assert_eq!(resource.queue_len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_lifo_discipline() {
+        let mut scheduler = EventScheduler::new();
+        let resource = Resource::new(1).with_discipline(crate::Lifo);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        for priority in 0..3 {
+            hold(&resource, &mut scheduler, priority, 1.0, log.clone());
+        }
+        scheduler.run_until_max_time(10.0);
+        assert_eq!(*log.borrow(), vec![(0, 0.0), (2, 1.0), (1, 2.0)]);
+    }
+
+    #[test]
+    fn test_cancel_and_gating() {
+        let mut scheduler = EventScheduler::new();
+        let resource = Resource::new(1);
+        resource.set_available(&mut scheduler, false);
+        let id = resource.request(&mut scheduler, 0, |_, _| {});
+        assert_eq!((resource.in_use(), resource.queue_len()), (0, 1));
+        assert!(resource.cancel(&scheduler, id));
+        resource.request(&mut scheduler, 0, |_, _| {});
+        resource.set_available(&mut scheduler, true);
+        assert_eq!(resource.in_use(), 1);
+    }
+}
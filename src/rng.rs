@@ -0,0 +1,426 @@
+//! # Random Number Generation
+//!
+//! A small, dependency-free pseudo-random number generator used by the scheduler and by the
+//! stochastic components built on top of it.
+//!
+//! The generator is [xoshiro256**](https://prng.di.unimi.it/), seeded through SplitMix64. Its
+//! output for a given seed is fixed by this crate, so simulations seeded with the same value
+//! reproduce exactly across platforms and releases.
+//!
+//! Two variance-reduction techniques are supported. Named streams from [`RngStreams`] keep
+//! each source of randomness, such as arrivals or service times, on its own generator, so that
+//! scenarios run with the same seed see the same draws from each source (common random
+//! numbers) even when they consume other streams differently. An antithetic generator, from
+//! [`SimRng::antithetic`], mirrors every draw `u` of its twin to roughly `1 - u`.
+//!
+//! A [`SeedSequence`] derives a tree of independent seeds from one entropy value, like NumPy's
+//! `SeedSequence`, for handing each replication or component its own reproducible generator.
+//! Every run records the seeds it used in [`crate::RunResult::seeds`], a [`SeedAudit`] that can
+//! be printed or written to a [`crate::LogSink`] to reproduce the run later.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Seed used when no explicit seed is configured, so that unseeded runs are still reproducible.
+pub const DEFAULT_SEED: u64 = 0x05EE_DDE5_2024;
+
+/// A seedable pseudo-random number generator for simulation use.
+///
+/// # Example
+/// ```
+/// use desru::SimRng;
+///
+/// let mut a = SimRng::new(42);
+/// let mut b = SimRng::new(42);
+/// assert_eq!(a.next_u64(), b.next_u64());
+///
+/// let u = a.next_f64();
+/// assert!((0.0..1.0).contains(&u));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimRng {
+    state: [u64; 4],
+    antithetic: bool,
+}
+
+impl SimRng {
+    /// Creates a new generator from a 64-bit seed.
+    ///
+    /// # Parameters
+    /// - `seed`: Any 64-bit value. Distinct seeds give statistically independent streams.
+    ///
+    /// # Returns
+    /// A new `SimRng` instance.
+    pub fn new(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut state = [0u64; 4];
+        for word in state.iter_mut() {
+            *word = splitmix64(&mut sm);
+        }
+        SimRng { state, antithetic: false }
+    }
+
+    /// Returns the antithetic twin of this generator: a copy in the same state whose draws are
+    /// mirrored, so that `next_f64` yields about `1 - u` where the original yields `u`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::SimRng;
+    ///
+    /// let mut rng = SimRng::new(3);
+    /// let mut twin = rng.antithetic();
+    /// let (u, v) = (rng.next_f64(), twin.next_f64());
+    /// assert!((u + v - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn antithetic(&self) -> SimRng {
+        SimRng { state: self.state, antithetic: !self.antithetic }
+    }
+
+    /// Returns `true` if this generator mirrors its draws.
+    pub fn is_antithetic(&self) -> bool {
+        self.antithetic
+    }
+
+    /// Returns the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        let bits = self.next_raw();
+        if self.antithetic {
+            !bits
+        } else {
+            bits
+        }
+    }
+
+    fn next_raw(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Returns a uniformly distributed `f64` in the half-open interval `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Returns a uniformly distributed `f64` in the half-open interval `[low, high)`.
+    pub fn gen_range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    /// Returns a uniformly distributed index in `0..n`.
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    pub fn gen_index(&mut self, n: usize) -> usize {
+        assert!(n > 0, "gen_index requires a non-empty range");
+        // Lemire's multiply-shift reduction; the bias is negligible for simulation sizes.
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        SimRng::new(DEFAULT_SEED)
+    }
+}
+
+/// A set of independent generators identified by name, all derived from one seed.
+///
+/// A stream's seed depends only on the base seed and its name, so a stream gives the same draws
+/// however many other streams exist or how much they have been used.
+///
+/// # Example
+/// ```
+/// use desru::RngStreams;
+///
+/// let mut a = RngStreams::new(1);
+/// let mut b = RngStreams::new(1);
+/// a.stream("service").next_u64();
+/// assert_eq!(a.stream("arrivals").next_u64(), b.stream("arrivals").next_u64());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RngStreams {
+    seed: u64,
+    antithetic: bool,
+    streams: BTreeMap<String, SimRng>,
+}
+
+impl RngStreams {
+    /// Creates an empty set of streams derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        RngStreams { seed, antithetic: false, streams: BTreeMap::new() }
+    }
+
+    /// Returns the set of antithetic twins: every stream, including those not yet created,
+    /// mirrors the corresponding stream of this set.
+    pub fn antithetic(&self) -> RngStreams {
+        RngStreams {
+            seed: self.seed,
+            antithetic: !self.antithetic,
+            streams: self.streams.iter().map(|(name, rng)| (name.clone(), rng.antithetic())).collect(),
+        }
+    }
+
+    /// Returns the stream called `name`, creating it on first use.
+    pub fn stream(&mut self, name: &str) -> &mut SimRng {
+        if !self.streams.contains_key(name) {
+            let mut rng = SimRng::new(self.stream_seed(name));
+            rng.antithetic = self.antithetic;
+            self.streams.insert(name.to_string(), rng);
+        }
+        self.streams.get_mut(name).expect("stream was just inserted")
+    }
+
+    /// Returns the seed the streams are derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the seed of the stream called `name`, whether or not it has been created.
+    pub fn stream_seed(&self, name: &str) -> u64 {
+        // FNV-1a, which unlike the standard library's hasher is fixed across releases.
+        let hash = name.bytes().fold(0xCBF2_9CE4_8422_2325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01B3));
+        let mut state = self.seed ^ hash;
+        splitmix64(&mut state)
+    }
+
+    /// Returns the names of the streams created so far, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.streams.keys().map(String::as_str)
+    }
+}
+
+/// The seeds a run used: the scheduler's seed and the seed of every named stream it drew from.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, SimRng};
+///
+/// let mut scheduler = EventScheduler::builder().seed(21).build();
+/// scheduler.stream("service").next_f64();
+/// let seeds = scheduler.seed_audit();
+/// assert_eq!(seeds.seed, 21);
+/// assert_eq!(seeds.streams[0].0, "service");
+///
+/// // The recorded stream seed reproduces the stream's draws.
+/// let mut replay = SimRng::new(seeds.streams[0].1);
+/// assert_eq!(replay.next_f64(), EventScheduler::builder().seed(21).build().stream("service").next_f64());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedAudit {
+    /// The seed of the scheduler's generator and the base of its streams.
+    pub seed: u64,
+    /// Whether the scheduler draws antithetic variates, see [`SimRng::antithetic`].
+    pub antithetic: bool,
+    /// Each stream created, with its derived seed, sorted by name.
+    pub streams: Vec<(String, u64)>,
+}
+
+impl SeedAudit {
+    /// Records the seeds of a set of streams.
+    pub fn of(streams: &RngStreams) -> Self {
+        SeedAudit {
+            seed: streams.seed,
+            antithetic: streams.antithetic,
+            streams: streams.names().map(|name| (name.to_string(), streams.stream_seed(name))).collect(),
+        }
+    }
+}
+
+impl fmt::Display for SeedAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "seed {}", self.seed)?;
+        if self.antithetic {
+            write!(f, " (antithetic)")?;
+        }
+        for (name, seed) in &self.streams {
+            write!(f, ", stream {} seed {}", name, seed)?;
+        }
+        Ok(())
+    }
+}
+
+/// A node in a tree of seeds derived from one entropy value.
+///
+/// Each child is identified by its path of spawn indices from the root, its spawn key, so the
+/// seed given to replication 3's arrival process is the same however many other children were
+/// spawned before it and however the tree is traversed.
+///
+/// # Example
+/// ```
+/// use desru::{SeedSequence, SimRng};
+///
+/// let mut root = SeedSequence::new(2024);
+/// let replications = root.spawn(3);
+/// assert_eq!(replications[2].spawn_key(), &[2]);
+///
+/// // Components of a replication spawn their own children.
+/// let arrivals = replications[1].child(0);
+/// let service = replications[1].child(1);
+/// assert_ne!(arrivals.seed(), service.seed());
+/// assert_eq!(arrivals, SeedSequence::new(2024).child(1).child(0));
+/// let _rng: SimRng = arrivals.rng();
+///
+/// // Spawning again continues where the last spawn stopped.
+/// assert_eq!(root.spawn(1)[0].spawn_key(), &[3]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedSequence {
+    entropy: u64,
+    spawn_key: Vec<u64>,
+    spawned: u64,
+}
+
+impl SeedSequence {
+    /// Creates the root of a tree of seeds.
+    pub fn new(entropy: u64) -> Self {
+        SeedSequence { entropy, spawn_key: Vec::new(), spawned: 0 }
+    }
+
+    /// Returns the root's entropy.
+    pub fn entropy(&self) -> u64 {
+        self.entropy
+    }
+
+    /// Returns the spawn indices leading from the root to this node.
+    pub fn spawn_key(&self) -> &[u64] {
+        &self.spawn_key
+    }
+
+    /// Returns the child with spawn index `index`.
+    pub fn child(&self, index: u64) -> SeedSequence {
+        let mut spawn_key = self.spawn_key.clone();
+        spawn_key.push(index);
+        SeedSequence { entropy: self.entropy, spawn_key, spawned: 0 }
+    }
+
+    /// Returns the next `n` children, continuing from the children spawned before.
+    pub fn spawn(&mut self, n: usize) -> Vec<SeedSequence> {
+        let start = self.spawned;
+        self.spawned += n as u64;
+        (start..self.spawned).map(|index| self.child(index)).collect()
+    }
+
+    /// Returns this node's seed.
+    pub fn seed(&self) -> u64 {
+        let mut state = self.entropy;
+        let mut seed = splitmix64(&mut state);
+        for &index in &self.spawn_key {
+            // Mixing in the index and the previous level's seed keeps sibling and nested
+            // keys, such as [0, 1] and [1], apart.
+            let mut state = seed ^ index.wrapping_add(1).wrapping_mul(0xD1B5_4A32_D192_ED03);
+            seed = splitmix64(&mut state);
+        }
+        seed
+    }
+
+    /// Returns a generator seeded with this node's seed.
+    pub fn rng(&self) -> SimRng {
+        SimRng::new(self.seed())
+    }
+}
+
+/// How the seeds of independent replications are chosen.
+///
+/// # Example
+/// ```
+/// use desru::SeedStrategy;
+///
+/// assert_eq!(SeedStrategy::Sequential(100).seed(2), 102);
+/// assert_eq!(SeedStrategy::Explicit(vec![7, 9]).seed(1), 9);
+/// assert_ne!(SeedStrategy::Hashed(100).seed(0), SeedStrategy::Hashed(100).seed(1));
+/// assert_eq!(SeedStrategy::Spawned(100).seed(4), desru::SeedSequence::new(100).child(4).seed());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedStrategy {
+    /// Replication `r` uses seed `base + r`.
+    Sequential(u64),
+    /// Replication `r` uses a seed hashed from `base` and `r`, so that nearby bases do not give
+    /// overlapping sets of seeds.
+    Hashed(u64),
+    /// Replication `r` uses the `r`-th listed seed.
+    Explicit(Vec<u64>),
+    /// Replication `r` uses the seed of child `r` of a [`SeedSequence`] with entropy `base`,
+    /// so a replication's own components can spawn from the same tree.
+    Spawned(u64),
+}
+
+impl SeedStrategy {
+    /// Returns the seed of replication `replication`.
+    ///
+    /// # Panics
+    /// Panics if an explicit seed list has no entry for `replication`.
+    pub fn seed(&self, replication: usize) -> u64 {
+        match self {
+            SeedStrategy::Sequential(base) => base.wrapping_add(replication as u64),
+            SeedStrategy::Hashed(base) => {
+                let mut state = base ^ (replication as u64).wrapping_mul(0xD1B5_4A32_D192_ED03);
+                splitmix64(&mut state)
+            }
+            SeedStrategy::Explicit(seeds) => *seeds
+                .get(replication)
+                .unwrap_or_else(|| panic!("no explicit seed for replication {}", replication)),
+            SeedStrategy::Spawned(base) => SeedSequence::new(*base).child(replication as u64).seed(),
+        }
+    }
+}
+
+impl Default for SeedStrategy {
+    fn default() -> Self {
+        SeedStrategy::Hashed(DEFAULT_SEED)
+    }
+}
+
+/// Advances a SplitMix64 state and returns the next output.
+pub(crate) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_stream() {
+        let mut a = SimRng::new(7);
+        let mut b = SimRng::new(7);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(SimRng::new(7).next_u64(), SimRng::new(8).next_u64());
+    }
+
+    #[test]
+    fn test_antithetic_streams_mirror() {
+        let mut streams = RngStreams::new(11);
+        let mut twins = streams.antithetic();
+        for _ in 0..100 {
+            let u = streams.stream("service").next_f64();
+            let v = twins.stream("service").next_f64();
+            assert!((0.0..1.0).contains(&v));
+            assert!((u + v - 1.0).abs() < 1e-9);
+        }
+        assert!(twins.antithetic().stream("x") == streams.stream("x"));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = SimRng::new(1);
+        for _ in 0..1000 {
+            let x = rng.gen_range(2.0, 3.0);
+            assert!((2.0..3.0).contains(&x));
+            assert!(rng.gen_index(5) < 5);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! # Time-Travel Debugging
+//!
+//! An anomaly noticed at t = 10 000 is easiest to understand by rewinding to just before it
+//! and running again with more logging. A [`TimeTravel`] run records a checkpoint at regular
+//! intervals of simulated time, and [`TimeTravel::rollback_to`] returns the run to the latest
+//! checkpoint at or before a given time, from where it can be inspected, reconfigured, and
+//! continued.
+//!
+//! A scheduler's pending actions are closures, which cannot be copied, so a checkpoint records
+//! how far the run had got rather than a copy of its state. Rolling back rebuilds the run from
+//! the start and repeats events up to the checkpoint, which reproduces it exactly when the
+//! model draws all of its randomness from the scheduler, as replications already must. A
+//! rollback therefore costs as much as running to the checkpoint again.
+
+use crate::{EventScheduler, LogPolicy};
+
+/// A point a [`TimeTravel`] run can be rolled back to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    /// The simulated time of the checkpoint.
+    pub time: f64,
+    /// The number of events run before it.
+    pub events: u64,
+    /// The number of pending events, used to detect a rebuilt run that diverges.
+    pending: usize,
+}
+
+/// A run that can be rolled back to earlier checkpoints.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduledAction, TimeTravel};
+///
+/// fn tick(s: &mut EventScheduler) -> Option<String> {
+///     *s.state_mut::<u32>() += 1;
+///     s.schedule(ScheduledAction::at(s.current_time + 1.0).with_label("tick").with_action(tick));
+///     None
+/// }
+///
+/// let mut run = TimeTravel::new(|| {
+///     let mut s = EventScheduler::builder().seed(8).logging(false).build();
+///     s.world.set(0_u32);
+///     s.schedule(ScheduledAction::at(0.5).with_label("tick").with_action(tick));
+///     s
+/// }, 100.0);
+/// run.run_until(10_000.0);
+/// assert_eq!(*run.scheduler().state::<u32>(), 10_000);
+///
+/// // Something looked wrong near t = 9 950: rewind and watch closely from there.
+/// let scheduler = run.rollback_to(9_950.0);
+/// assert_eq!((scheduler.current_time, *scheduler.state::<u32>()), (9_899.5, 9_900));
+/// scheduler.logging = true;
+/// run.run_until(9_960.0);
+/// assert_eq!(run.scheduler().event_log.len(), 60);
+/// ```
+pub struct TimeTravel<F> {
+    build: F,
+    interval: f64,
+    scheduler: EventScheduler,
+    checkpoints: Vec<Checkpoint>,
+    events: u64,
+}
+
+impl<F> TimeTravel<F>
+where
+    F: Fn() -> EventScheduler,
+{
+    /// Starts a run from the scheduler returned by `build`, which must set up the model the
+    /// same way every time it is called, and checkpoints it every `interval` of simulated time.
+    ///
+    /// # Panics
+    /// Panics if `interval` is not positive.
+    pub fn new(build: F, interval: f64) -> Self {
+        assert!(interval > 0.0, "the checkpoint interval must be positive");
+        let scheduler = build();
+        let start = Checkpoint { time: scheduler.current_time, events: 0, pending: scheduler.event_queue.len() };
+        TimeTravel { build, interval, scheduler, checkpoints: vec![start], events: 0 }
+    }
+
+    /// Returns the scheduler of the run, to inspect.
+    pub fn scheduler(&self) -> &EventScheduler {
+        &self.scheduler
+    }
+
+    /// Returns the scheduler of the run, to inspect or reconfigure, such as to turn on logging
+    /// after a rollback.
+    pub fn scheduler_mut(&mut self) -> &mut EventScheduler {
+        &mut self.scheduler
+    }
+
+    /// Returns the checkpoints recorded so far, oldest first.
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Runs until the next event would occur at or after `max_time`, as
+    /// [`EventScheduler::run_until_max_time`] does, recording checkpoints on the way.
+    ///
+    /// # Panics
+    /// Panics if `max_events_per_time` is exceeded or an event was scheduled at a NaN time, as
+    /// [`EventScheduler::run`] does.
+    pub fn run_until(&mut self, max_time: f64) {
+        let (interval, checkpoints, events) = (self.interval, &mut self.checkpoints, &mut self.events);
+        record_due(checkpoints, interval, max_time, *events, &self.scheduler);
+        let stop = crate::stop_at_max_time_factory(max_time);
+        self.scheduler
+            .try_run_observed(stop, &LogPolicy::Full, |scheduler, _, _| {
+                *events += 1;
+                record_due(checkpoints, interval, max_time, *events, scheduler);
+            })
+            .unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    /// Returns the run to the latest checkpoint at or before `time`, discarding the later
+    /// ones, and returns its scheduler.
+    ///
+    /// Settings changed on the scheduler since it was built, such as `logging`, are reset by
+    /// the rebuild and can be changed again on the returned scheduler.
+    ///
+    /// # Panics
+    /// Panics if the rebuilt run diverges from the original one, which happens when the model
+    /// depends on randomness or state outside the scheduler.
+    pub fn rollback_to(&mut self, time: f64) -> &mut EventScheduler {
+        let keep = self.checkpoints.iter().rposition(|checkpoint| checkpoint.time <= time).unwrap_or(0);
+        self.checkpoints.truncate(keep + 1);
+        let target = self.checkpoints[keep];
+        self.scheduler = (self.build)();
+        let start = self.scheduler.counters.executed;
+        self.scheduler
+            .try_run_observed(|scheduler: &EventScheduler| scheduler.counters.executed - start >= target.events, &LogPolicy::Full, |_, _, _| {})
+            .unwrap_or_else(|error| panic!("{}", error));
+        self.events = self.scheduler.counters.executed - start;
+        assert_eq!(self.events, target.events, "the rebuilt run ran out of events after {} of {}", self.events, target.events);
+        assert_eq!(self.scheduler.event_queue.len(), target.pending, "the rebuilt run diverged from the original before t = {}", target.time);
+        &mut self.scheduler
+    }
+}
+
+/// Records a checkpoint for every interval boundary before `max_time` that the next event lies
+/// beyond, describing the run as it stands after `events` events.
+fn record_due(checkpoints: &mut Vec<Checkpoint>, interval: f64, max_time: f64, events: u64, scheduler: &EventScheduler) {
+    let Some(next_time) = scheduler.event_queue.peek().map(|event| event.time) else {
+        return;
+    };
+    let mut due = checkpoints.last().map_or(scheduler.current_time, |last| last.time) + interval;
+    while due <= next_time && due < max_time {
+        checkpoints.push(Checkpoint { time: due, events, pending: scheduler.event_queue.len() });
+        due += interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScheduledAction;
+
+    #[test]
+    fn test_rollback_reproduces_random_runs_and_detects_divergence() {
+        let build = || {
+            let mut s = EventScheduler::builder().seed(3).build();
+            s.world.set(0.0_f64);
+            for t in 1..=50 {
+                s.schedule(ScheduledAction::at(f64::from(t)).with_action(|s| {
+                    *s.state_mut::<f64>() += s.rng.next_f64();
+                    None
+                }));
+            }
+            s
+        };
+        let mut run = TimeTravel::new(build, 10.0);
+        run.run_until(30.5);
+        let at_30 = *run.scheduler().state::<f64>();
+        run.run_until(100.0);
+        assert_eq!(run.checkpoints().len(), 6);
+        run.rollback_to(35.0);
+        assert_eq!(run.checkpoints().len(), 4);
+        run.run_until(30.5);
+        assert_eq!(*run.scheduler().state::<f64>(), at_30);
+
+        let calls = std::cell::Cell::new(0);
+        let mut unstable = TimeTravel::new(
+            || {
+                calls.set(calls.get() + 1);
+                let mut s = EventScheduler::new();
+                for t in 0..calls.get() * 5 {
+                    s.schedule(ScheduledAction::at(f64::from(t)));
+                }
+                s
+            },
+            2.0,
+        );
+        unstable.run_until(3.0);
+        let diverged = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            unstable.rollback_to(2.0);
+        }));
+        assert!(diverged.is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! # Routing
+//!
+//! A [`Router`] is a [`Block`] that passes each entity on to one of several targets, chosen by
+//! a [`RoutingPolicy`]. The built-in policies cover round-robin, uniformly random,
+//! join-the-shortest-queue, and fixed-probability routing; implement the trait for anything
+//! else. [`routing_matrix`] builds the routers of a Jackson network from its routing matrix.
+
+use crate::blocks::{Block, Queue, Sink};
+use crate::entity::Entity;
+use crate::rng::SimRng;
+use crate::EventScheduler;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Chooses which target a router sends each entity to.
+pub trait RoutingPolicy {
+    /// Returns the index of the chosen target, given the [`Block::load`] of every target.
+    fn route(&mut self, loads: &[usize], rng: &mut SimRng) -> usize;
+}
+
+/// Cycles through the targets in order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+impl RoutingPolicy for RoundRobin {
+    fn route(&mut self, loads: &[usize], _rng: &mut SimRng) -> usize {
+        let index = self.next % loads.len();
+        self.next = index + 1;
+        index
+    }
+}
+
+/// Picks a target uniformly at random, drawn from the scheduler's random number generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomRoute;
+
+impl RoutingPolicy for RandomRoute {
+    fn route(&mut self, loads: &[usize], rng: &mut SimRng) -> usize {
+        rng.gen_index(loads.len())
+    }
+}
+
+/// Picks the target with the smallest load, the first such target among ties.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShortestQueue;
+
+impl RoutingPolicy for ShortestQueue {
+    fn route(&mut self, loads: &[usize], _rng: &mut SimRng) -> usize {
+        (0..loads.len()).min_by_key(|&i| loads[i]).unwrap_or(0)
+    }
+}
+
+/// Picks each target with a fixed probability.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probabilities {
+    cumulative: Vec<f64>,
+}
+
+impl Probabilities {
+    /// Creates the policy from one weight per target. Weights are normalized to sum to one.
+    ///
+    /// # Panics
+    /// Panics if a weight is negative or all weights are zero.
+    pub fn new(weights: &[f64]) -> Self {
+        assert!(weights.iter().all(|&w| w >= 0.0), "routing weights must be non-negative");
+        let total: f64 = weights.iter().sum();
+        assert!(total > 0.0, "routing weights must not all be zero");
+        let mut sum = 0.0;
+        let cumulative = weights
+            .iter()
+            .map(|w| {
+                sum += w / total;
+                sum
+            })
+            .collect();
+        Probabilities { cumulative }
+    }
+}
+
+impl RoutingPolicy for Probabilities {
+    fn route(&mut self, loads: &[usize], rng: &mut SimRng) -> usize {
+        let u = rng.next_f64();
+        let last = self.cumulative.len().min(loads.len()) - 1;
+        self.cumulative.iter().position(|&c| u < c).unwrap_or(last).min(last)
+    }
+}
+
+struct RouterState {
+    targets: Vec<Rc<dyn Block>>,
+    policy: Box<dyn RoutingPolicy>,
+    routed: Vec<usize>,
+}
+
+/// Sends each entity to one of its targets, as chosen by its routing policy.
+///
+/// Like the other blocks, a `Router` is a cheaply cloneable handle.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Queue, Router, Server, ShortestQueue, Sink, Source};
+///
+/// let mut scheduler = EventScheduler::new();
+/// let exit = Sink::new();
+/// let lanes: Vec<Queue> = (0..2).map(|_| Queue::new()).collect();
+/// let dispatcher = Router::new(ShortestQueue);
+/// for lane in &lanes {
+///     let cashier = Server::new(1, |_| 5.0);
+///     lane.connect(&cashier);
+///     cashier.connect(&exit);
+///     dispatcher.connect(lane);
+/// }
+/// let shoppers = Source::new(|_| 1.0).with_limit(4);
+/// shoppers.connect(&dispatcher);
+/// shoppers.start(&mut scheduler);
+/// scheduler.run_until_max_time(100.0);
+/// assert_eq!(dispatcher.routed(), vec![2, 2]);
+/// assert_eq!(exit.count(), 4);
+/// ```
+pub struct Router {
+    state: Rc<RefCell<RouterState>>,
+}
+
+impl Clone for Router {
+    fn clone(&self) -> Self {
+        Router { state: self.state.clone() }
+    }
+}
+
+impl Router {
+    /// Creates a router with no targets, choosing between them with `policy`.
+    pub fn new<P: RoutingPolicy + 'static>(policy: P) -> Self {
+        Router { state: Rc::new(RefCell::new(RouterState { targets: Vec::new(), policy: Box::new(policy), routed: Vec::new() })) }
+    }
+
+    /// Adds a target. Targets are indexed in the order they were connected.
+    pub fn connect<B: Block + Clone + 'static>(&self, target: &B) {
+        let mut state = self.state.borrow_mut();
+        state.targets.push(Rc::new(target.clone()));
+        state.routed.push(0);
+    }
+
+    /// Returns how many entities have been sent to each target.
+    pub fn routed(&self) -> Vec<usize> {
+        self.state.borrow().routed.clone()
+    }
+}
+
+impl Block for Router {
+    /// # Panics
+    /// Panics if the router has no targets.
+    fn accept(&self, scheduler: &mut EventScheduler, entity: Entity) {
+        let target = {
+            let mut state = self.state.borrow_mut();
+            assert!(!state.targets.is_empty(), "router has no targets");
+            let loads: Vec<usize> = state.targets.iter().map(|target| target.load()).collect();
+            let index = state.policy.route(&loads, &mut scheduler.rng).min(loads.len() - 1);
+            state.routed[index] += 1;
+            state.targets[index].clone()
+        };
+        target.accept(scheduler, entity);
+    }
+
+    fn load(&self) -> usize {
+        self.state.borrow().targets.iter().map(|target| target.load()).sum()
+    }
+}
+
+/// Builds the routers of an open Jackson network.
+///
+/// Row `i` of `matrix` gives the probability of moving from station `i` to each station; the
+/// remaining probability leaves the network through `exit`. Connect station `i`'s servers to
+/// router `i`.
+///
+/// # Panics
+/// Panics if a row's length differs from the number of stations or a row sums to more than one.
+pub fn routing_matrix(matrix: &[Vec<f64>], stations: &[Queue], exit: &Sink) -> Vec<Router> {
+    matrix
+        .iter()
+        .map(|row| {
+            assert_eq!(row.len(), stations.len(), "routing matrix rows must have one entry per station");
+            let mut weights = row.clone();
+            let leave = 1.0 - row.iter().sum::<f64>();
+            assert!(leave >= -1e-9, "routing probabilities in a row must not exceed one");
+            weights.push(leave.max(0.0));
+            let router = Router::new(Probabilities::new(&weights));
+            for station in stations {
+                router.connect(station);
+            }
+            router.connect(exit);
+            router
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Server, Source};
+
+    #[test]
+    fn test_policies() {
+        let mut rng = SimRng::new(1);
+        let mut round_robin = RoundRobin::default();
+        let picks: Vec<usize> = (0..4).map(|_| round_robin.route(&[0, 0, 0], &mut rng)).collect();
+        assert_eq!(picks, vec![0, 1, 2, 0]);
+        assert_eq!(ShortestQueue.route(&[3, 1, 1], &mut rng), 1);
+        let mut always_second = Probabilities::new(&[0.0, 2.0]);
+        assert!((0..20).all(|_| always_second.route(&[0, 0], &mut rng) == 1));
+    }
+
+    #[test]
+    fn test_feedback_network() {
+        let mut scheduler = EventScheduler::new();
+        let exit = Sink::new();
+        let station = Queue::new();
+        let server = Server::new(1, |_| 0.1);
+        station.connect(&server);
+        let routers = routing_matrix(&[vec![0.5]], std::slice::from_ref(&station), &exit);
+        server.connect(&routers[0]);
+        let source = Source::new(|_| 1.0).with_limit(200);
+        source.connect(&station);
+        source.start(&mut scheduler);
+        scheduler.run_until_max_time(1_000.0);
+        assert_eq!(exit.count(), 200);
+        // Each entity is served a geometric number of times with mean 2.
+        let visits = server.served() as f64 / 200.0;
+        assert!((visits - 2.0).abs() < 0.3, "{}", visits);
+    }
+}
@@ -0,0 +1,253 @@
+//! # Sensitivity Analysis
+//!
+//! A [`Sensitivity`] design runs an [`Experiment`] over points in parameter space chosen to
+//! show how each output metric responds to each parameter:
+//!
+//! - [`Sensitivity::one_at_a_time`] varies each parameter in turn over its levels while the
+//!   others stay at a baseline, tracing each parameter's response curve.
+//! - [`Sensitivity::latin_hypercube`] draws points spread evenly over the range of every
+//!   parameter at once, so that every parameter's effect is seen against variation in the
+//!   others.
+//!
+//! The results are a tidy [`SensitivityResults`] table with one row per design point, parameter,
+//! and metric, giving the metric's mean over the replications at that point.
+
+use crate::csv::csv_field;
+use crate::{Experiment, ExperimentResults, Parameters, SimConfig, SimModel, SimRng, Tally};
+use std::io::{self, Write};
+
+/// A sensitivity design: the points to run and the parameters each one varies.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, Sensitivity, SimConfig, SimModel};
+///
+/// // A noisy response that rises with `a` and does not depend on `b`.
+/// struct Response { a: f64 }
+///
+/// impl SimModel for Response {
+///     type Output = [(&'static str, f64); 1];
+///     fn init(&mut self, _: &mut EventScheduler) {}
+///     fn finalize(&mut self, s: &mut EventScheduler) -> Self::Output {
+///         [("y", 2.0 * self.a + s.rng.next_f64())]
+///     }
+/// }
+///
+/// let design = Sensitivity::latin_hypercube(SimConfig::new(1.0), &[("a", 0.0, 1.0), ("b", 0.0, 1.0)], 50, 7);
+/// let results = design.replications(4).run(|p| Response { a: p["a"] });
+/// assert_eq!(results.rows().len(), 50 * 2);
+/// assert!(results.correlation("a", "y") > 0.9);
+/// assert!(results.correlation("b", "y").abs() < 0.3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sensitivity {
+    experiment: Experiment<Parameters>,
+    varied: Vec<Vec<String>>,
+}
+
+impl Sensitivity {
+    /// Creates a one-at-a-time design: a `baseline` scenario, then one scenario per level of
+    /// each factor with the other parameters at their baseline values. Scenarios are named
+    /// `baseline` and like `servers=3`.
+    ///
+    /// In the results, the baseline point contributes a row for every parameter, so each
+    /// parameter's rows trace its whole response curve.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, Sensitivity, SimConfig, SimModel};
+    ///
+    /// struct Cost { servers: f64, rate: f64 }
+    ///
+    /// impl SimModel for Cost {
+    ///     type Output = [(&'static str, f64); 1];
+    ///     fn init(&mut self, _: &mut EventScheduler) {}
+    ///     fn finalize(&mut self, _: &mut EventScheduler) -> Self::Output {
+    ///         [("cost", self.servers + self.rate / self.servers)]
+    ///     }
+    /// }
+    ///
+    /// let design = Sensitivity::one_at_a_time(
+    ///     SimConfig::new(1.0),
+    ///     &[("servers", 2.0), ("rate", 4.0)],
+    ///     &[("servers", &[1.0, 4.0]), ("rate", &[8.0])],
+    /// );
+    /// let results = design.run(|p| Cost { servers: p["servers"], rate: p["rate"] });
+    /// let servers: Vec<(f64, f64)> = results.response("servers", "cost").collect();
+    /// assert_eq!(servers, [(2.0, 4.0), (1.0, 5.0), (4.0, 5.0)]);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if a factor is not one of the baseline parameters.
+    pub fn one_at_a_time(config: SimConfig, baseline: &[(&str, f64)], factors: &[(&str, &[f64])]) -> Self {
+        let baseline: Parameters = baseline.iter().map(|&(name, value)| (name.to_string(), value)).collect();
+        let mut experiment = Experiment::new(config).scenario("baseline", baseline.clone());
+        let mut varied = vec![baseline.keys().cloned().collect()];
+        for &(name, levels) in factors {
+            assert!(baseline.contains_key(name), "the factor {} has no baseline value", name);
+            for &level in levels {
+                let mut parameters = baseline.clone();
+                parameters.insert(name.to_string(), level);
+                experiment = experiment.scenario(format!("{}={}", name, level), parameters);
+                varied.push(vec![name.to_string()]);
+            }
+        }
+        Sensitivity { experiment, varied }
+    }
+
+    /// Creates a Latin hypercube design of `samples` points: each parameter's range
+    /// `[low, high)` is cut into `samples` equal strata, and each stratum is sampled by
+    /// exactly one point, with the strata paired across parameters at random. Scenarios are
+    /// named `sample 1`, `sample 2`, and so on.
+    pub fn latin_hypercube(config: SimConfig, bounds: &[(&str, f64, f64)], samples: usize, seed: u64) -> Self {
+        let mut rng = SimRng::new(seed);
+        let mut points = vec![Parameters::new(); samples];
+        for &(name, low, high) in bounds {
+            let mut strata: Vec<usize> = (0..samples).collect();
+            for i in (1..samples).rev() {
+                strata.swap(i, rng.gen_index(i + 1));
+            }
+            for (point, stratum) in points.iter_mut().zip(strata) {
+                let u = (stratum as f64 + rng.next_f64()) / samples as f64;
+                point.insert(name.to_string(), low + (high - low) * u);
+            }
+        }
+        let names: Vec<String> = bounds.iter().map(|(name, _, _)| name.to_string()).collect();
+        let mut experiment = Experiment::new(config);
+        for (i, point) in points.into_iter().enumerate() {
+            experiment = experiment.scenario(format!("sample {}", i + 1), point);
+        }
+        Sensitivity { experiment, varied: vec![names; samples] }
+    }
+
+    /// Sets the number of replications at each point. Defaults to 1.
+    pub fn replications(mut self, replications: usize) -> Self {
+        self.experiment = self.experiment.replications(replications);
+        self
+    }
+
+    /// Returns the experiment that runs the design.
+    pub fn experiment(&self) -> &Experiment<Parameters> {
+        &self.experiment
+    }
+
+    /// Runs every point of the design, as [`Experiment::run`] does.
+    pub fn run<M, F, K>(&self, make_model: F) -> SensitivityResults
+    where
+        F: Fn(&Parameters) -> M,
+        M: SimModel,
+        M::Output: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        let runs = self.experiment.run(make_model);
+        let mut rows = Vec::new();
+        for ((scenario, parameters), varied) in self.experiment.scenarios().iter().zip(&self.varied) {
+            for parameter in varied {
+                for metric in runs.metrics() {
+                    let tally: Tally = runs.tally(scenario, metric);
+                    rows.push(SensitivityRow {
+                        scenario: scenario.clone(),
+                        parameter: parameter.clone(),
+                        value: parameters[parameter],
+                        metric: metric.to_string(),
+                        mean: tally.mean(),
+                        std_dev: tally.std_dev(),
+                    });
+                }
+            }
+        }
+        SensitivityResults { rows, runs }
+    }
+}
+
+/// The mean of one metric at one design point, against one of the parameters varied there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensitivityRow {
+    pub scenario: String,
+    pub parameter: String,
+    pub value: f64,
+    pub metric: String,
+    pub mean: f64,
+    /// The standard deviation across replications, or zero for a single replication.
+    pub std_dev: f64,
+}
+
+/// The responses of every metric to every parameter over a [`Sensitivity`] design.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensitivityResults {
+    rows: Vec<SensitivityRow>,
+    runs: ExperimentResults,
+}
+
+impl SensitivityResults {
+    /// Returns every row of the table, ordered by design point, then parameter, then metric.
+    pub fn rows(&self) -> &[SensitivityRow] {
+        &self.rows
+    }
+
+    /// Returns the per-replication results of the underlying experiment.
+    pub fn runs(&self) -> &ExperimentResults {
+        &self.runs
+    }
+
+    /// Returns `(parameter value, metric mean)` for each design point varying `parameter`.
+    pub fn response<'a>(&'a self, parameter: &'a str, metric: &'a str) -> impl Iterator<Item = (f64, f64)> + 'a {
+        self.rows.iter().filter(move |row| row.parameter == parameter && row.metric == metric).map(|row| (row.value, row.mean))
+    }
+
+    /// Returns the Pearson correlation between `parameter` and the mean of `metric` across the
+    /// design points varying it: near 1 or -1 if the metric follows the parameter closely, near
+    /// 0 if it does not respond.
+    ///
+    /// # Returns
+    /// `NaN` if there are fewer than two such points or either side does not vary.
+    pub fn correlation(&self, parameter: &str, metric: &str) -> f64 {
+        let points: Vec<(f64, f64)> = self.response(parameter, metric).collect();
+        let n = points.len() as f64;
+        let (mean_x, mean_y) = (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n);
+        let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+        for (x, y) in &points {
+            sxy += (x - mean_x) * (y - mean_y);
+            sxx += (x - mean_x).powi(2);
+            syy += (y - mean_y).powi(2);
+        }
+        if points.len() < 2 || sxx == 0.0 || syy == 0.0 {
+            return f64::NAN;
+        }
+        sxy / (sxx * syy).sqrt()
+    }
+
+    /// Writes the table as CSV with columns `scenario,parameter,value,metric,mean,std_dev`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "scenario,parameter,value,metric,mean,std_dev")?;
+        for row in &self.rows {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                csv_field(&row.scenario),
+                csv_field(&row.parameter),
+                row.value,
+                csv_field(&row.metric),
+                row.mean,
+                row.std_dev
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latin_hypercube_samples_every_stratum_once() {
+        let design = Sensitivity::latin_hypercube(SimConfig::new(1.0), &[("x", 10.0, 20.0), ("y", -1.0, 0.0)], 8, 3);
+        for (name, low) in [("x", 10.0), ("y", -1.0)] {
+            let width = if name == "x" { 10.0 } else { 1.0 };
+            let mut strata: Vec<usize> = design.experiment().scenarios().iter().map(|(_, p)| ((p[name] - low) / width * 8.0) as usize).collect();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..8).collect::<Vec<_>>());
+        }
+    }
+}
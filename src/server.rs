@@ -0,0 +1,325 @@
+//! # Co-Simulation Server
+//!
+//! [`EventScheduler::serve`] hands the clock to external tools over TCP, so that a model can
+//! take part in a distributed co-simulation that another process steps. Clients send one
+//! command per line and receive one JSON object per line in reply, with `"ok"` telling
+//! whether the command succeeded:
+//!
+//! - `advance <time>` runs the events before `time` and moves the clock to it.
+//! - `inject <time> <action> [key=value ...]` schedules an action registered with
+//!   [`EventScheduler::register_action`], passing the pairs as its context.
+//! - `query` reports the clock, the queue, the number of events run, and the blackboard.
+//! - `shutdown` ends [`EventScheduler::serve`].
+//!
+//! Times must be finite numbers.
+//!
+//! [`EventScheduler::serve_http`] offers the same commands as a REST interface over HTTP/1.1,
+//! for clients that would rather speak HTTP than hold a socket open. Both need nothing beyond
+//! the standard library. There is no gRPC front end, which would need HTTP/2 and protocol
+//! buffers; one can be layered on [`EventScheduler::handle_command`] in the same way.
+
+use crate::csv::{json_number, json_string};
+use crate::{Context, EventScheduler, SimError, StopReason};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+impl EventScheduler {
+    /// Runs the events scheduled before `time` and then moves the clock to `time`, so that
+    /// events at `time` itself, including ones injected afterwards, run on the next advance.
+    ///
+    /// # Returns
+    /// The number of events run.
+    ///
+    /// # Errors
+    /// Returns [`SimError::InvalidAdvance`] if `time` is NaN,
+    /// [`SimError::CausalityViolation`] if `time` is before the current time, and
+    /// [`SimError::Interrupted`] if the run paused or used up its wall-clock budget before
+    /// reaching `time`; the clock then stays where the run stopped, so that no event due
+    /// before `time` is skipped. Otherwise fails as for [`EventScheduler::try_run`].
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// scheduler.timeout(1.0, None, None);
+    /// scheduler.timeout(2.0, None, None);
+    /// assert_eq!(scheduler.advance_to(2.0), Ok(1));
+    /// assert_eq!(scheduler.current_time, 2.0);
+    /// assert!(scheduler.advance_to(1.0).is_err());
+    /// ```
+    pub fn advance_to(&mut self, time: f64) -> Result<usize, SimError> {
+        if time.is_nan() {
+            return Err(SimError::InvalidAdvance { time });
+        }
+        if time < self.current_time {
+            return Err(SimError::CausalityViolation { time, now: self.current_time });
+        }
+        let before = self.counters.executed;
+        match self.try_run(Box::new(crate::stop_at_max_time_factory(time)), None)?.stop_reason {
+            StopReason::Condition | StopReason::QueueEmpty => self.current_time = time,
+            _ => return Err(SimError::Interrupted { time, now: self.current_time }),
+        }
+        Ok((self.counters.executed - before) as usize)
+    }
+
+    /// Carries out one command of the server protocol and returns its JSON reply.
+    pub fn handle_command(&mut self, line: &str) -> String {
+        let mut words = line.split_whitespace();
+        let reply = match (words.next(), words.next()) {
+            (Some("advance"), Some(time)) => parse_time(time).and_then(|time| {
+                let executed = self.advance_to(time).map_err(|error| error.to_string())?;
+                Ok(format!("\"time\":{},\"executed\":{}", json_number(Some(self.current_time)), executed))
+            }),
+            (Some("inject"), Some(time)) => parse_time(time).and_then(|time| {
+                let name = words.next().ok_or("inject requires an action name")?;
+                if time < self.current_time {
+                    return Err(SimError::CausalityViolation { time, now: self.current_time }.to_string());
+                }
+                let mut context = Context::new();
+                for pair in words {
+                    let (key, value) = pair.split_once('=').ok_or_else(|| format!("expected `key=value`, found `{}`", pair))?;
+                    context.insert(key.to_string(), value.to_string());
+                }
+                let id = self.schedule_named(time, name, context).map_err(|error| error.to_string())?;
+                Ok(format!("\"id\":{}", id.0))
+            }),
+            (Some("query"), None) => Ok(self.state_json()),
+            (Some("shutdown"), None) => Ok(String::new()),
+            _ => Err(format!("unknown command `{}`", line.trim())),
+        };
+        match reply {
+            Ok(fields) if fields.is_empty() => "{\"ok\":true}".to_string(),
+            Ok(fields) => format!("{{\"ok\":true,{}}}", fields),
+            Err(error) => format!("{{\"ok\":false,\"error\":{}}}", json_string(&error)),
+        }
+    }
+
+    /// Accepts clients on `listener` one at a time and carries out their commands until one
+    /// sends `shutdown`.
+    ///
+    /// # Errors
+    /// Returns an error if accepting a connection fails. A client that disconnects or fails
+    /// mid-session only ends its own session.
+    pub fn serve(&mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let mut writer = stream.try_clone()?;
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if writeln!(writer, "{}", self.handle_command(&line)).is_err() {
+                    break;
+                }
+                if line.trim() == "shutdown" {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts HTTP/1.1 clients on `listener`, one request per connection, and carries out
+    /// each request as a command until one shuts the server down.
+    ///
+    /// The path names the command and its arguments and the query string holds its
+    /// `key=value` pairs, which are passed on without percent-decoding: `POST /advance/4`,
+    /// `POST /inject/1.5/arrive?who=ada`, `GET /query` and `POST /shutdown`. The body of the
+    /// response is the command's JSON reply, with status 200 if the command succeeded and 400
+    /// if it failed; other paths get 404 and other methods 405.
+    ///
+    /// # Errors
+    /// Returns an error if accepting a connection fails. A client that disconnects or sends a
+    /// malformed request only loses its own connection.
+    pub fn serve_http(&mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if let Ok(true) = self.answer_http(stream?) {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers one HTTP request, returning whether it shut the server down.
+    fn answer_http(&mut self, stream: TcpStream) -> io::Result<bool> {
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        io::copy(&mut reader.take(length), &mut io::sink())?;
+
+        let mut parts = request.split_whitespace();
+        let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut words: Vec<&str> = path.split('/').filter(|word| !word.is_empty()).collect();
+        let allowed = match words.first() {
+            Some(&"query") => "GET",
+            Some(&"advance" | &"inject" | &"shutdown") => "POST",
+            _ => "",
+        };
+        let failure = |error: String| format!("{{\"ok\":false,\"error\":{}}}", json_string(&error));
+        let (status, body) = if allowed.is_empty() {
+            ("404 Not Found", failure(format!("no command at `{}`", path)))
+        } else if method != allowed {
+            ("405 Method Not Allowed", failure(format!("`{}` expects {}", path, allowed)))
+        } else {
+            words.extend(query.split('&').filter(|pair| !pair.is_empty()));
+            let reply = self.handle_command(&words.join(" "));
+            (if reply.starts_with("{\"ok\":true") { "200 OK" } else { "400 Bad Request" }, reply)
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        Ok(status == "200 OK" && words == ["shutdown"])
+    }
+
+    fn state_json(&self) -> String {
+        let mut entries: Vec<String> = self
+            .blackboard
+            .keys()
+            .filter_map(|key| self.blackboard.get(key).map(|value| format!("{}:{}", json_string(key), json_string(value))))
+            .collect();
+        entries.sort();
+        format!(
+            "\"time\":{},\"pending\":{},\"next\":{},\"executed\":{},\"blackboard\":{{{}}}",
+            json_number(Some(self.current_time)),
+            self.event_queue.len(),
+            json_number(self.event_queue.peek().map(|event| event.time)),
+            self.counters.executed,
+            entries.join(",")
+        )
+    }
+}
+
+fn parse_time(word: &str) -> Result<f64, String> {
+    word.parse().ok().filter(|time: &f64| time.is_finite()).ok_or_else(|| format!("expected a finite time, found `{}`", word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_step_the_clock_and_inject_events() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.register_action("arrive", |s, context| {
+            s.post("last", &context["who"]);
+            None
+        });
+        assert_eq!(scheduler.handle_command("inject 1.5 arrive who=ada"), "{\"ok\":true,\"id\":1}");
+        assert_eq!(scheduler.handle_command("advance 2"), "{\"ok\":true,\"time\":2,\"executed\":1}");
+        assert_eq!(
+            scheduler.handle_command("query"),
+            "{\"ok\":true,\"time\":2,\"pending\":0,\"next\":null,\"executed\":1,\"blackboard\":{\"last\":\"ada\"}}"
+        );
+        assert!(scheduler.handle_command("inject 1 arrive").contains("\"ok\":false"));
+        assert!(scheduler.handle_command("inject 3 depart").contains("no action registered"));
+        assert!(scheduler.handle_command("rewind").contains("unknown command"));
+    }
+
+    #[test]
+    fn test_non_finite_times_are_rejected() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.register_action("arrive", |_, _| None);
+        scheduler.timeout(1.0, None, None);
+        for command in ["advance NaN", "advance inf", "inject NaN arrive", "inject -inf arrive"] {
+            let word = command.split_whitespace().nth(1).unwrap();
+            assert_eq!(scheduler.handle_command(command), format!("{{\"ok\":false,\"error\":\"expected a finite time, found `{}`\"}}", word));
+        }
+        assert_eq!((scheduler.current_time, scheduler.event_queue.len()), (0.0, 1));
+        let error = scheduler.advance_to(f64::NAN).unwrap_err();
+        assert!(matches!(error, SimError::InvalidAdvance { time } if time.is_nan()));
+        assert_eq!(scheduler.advance_to(3.0), Ok(1));
+    }
+
+    #[test]
+    fn test_pause_stops_the_clock_short_of_the_target() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.timeout(1.0, Some(Box::new(|s| {
+            s.request_pause();
+            None
+        })), None);
+        scheduler.timeout(2.0, None, None);
+        assert_eq!(scheduler.advance_to(5.0), Err(SimError::Interrupted { time: 5.0, now: 1.0 }));
+        assert_eq!(scheduler.current_time, 1.0);
+        assert_eq!(scheduler.event_queue.len(), 1);
+
+        assert_eq!(scheduler.advance_to(5.0), Ok(1));
+        assert_eq!(scheduler.current_time, 5.0);
+        assert_eq!(scheduler.event_log.iter().map(|record| record.time).collect::<Vec<_>>(), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_serve_answers_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            writeln!(stream, "advance 4\nshutdown").unwrap();
+            BufReader::new(stream).lines().map(Result::unwrap).collect::<Vec<_>>()
+        });
+        let mut scheduler = EventScheduler::new();
+        scheduler.serve(listener).unwrap();
+        assert_eq!(client.join().unwrap(), ["{\"ok\":true,\"time\":4,\"executed\":0}", "{\"ok\":true}"]);
+        assert_eq!(scheduler.current_time, 4.0);
+    }
+
+    #[test]
+    fn test_serve_http_maps_routes_to_commands() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let requests = [
+                "POST /inject/1.5/arrive?who=ada HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}",
+                "POST /advance/2 HTTP/1.1\r\n\r\n",
+                "GET /query HTTP/1.1\r\n\r\n",
+                "POST /advance/NaN HTTP/1.1\r\n\r\n",
+                "GET /advance/3 HTTP/1.1\r\n\r\n",
+                "GET /rewind HTTP/1.1\r\n\r\n",
+                "POST /shutdown HTTP/1.1\r\n\r\n",
+            ];
+            requests.map(|request| {
+                let mut stream = TcpStream::connect(address).unwrap();
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                let (head, body) = response.split_once("\r\n\r\n").unwrap();
+                (head.lines().next().unwrap().to_string(), body.to_string())
+            })
+        });
+        let mut scheduler = EventScheduler::new();
+        scheduler.register_action("arrive", |s, context| {
+            s.post("last", &context["who"]);
+            None
+        });
+        scheduler.serve_http(listener).unwrap();
+
+        let responses = client.join().unwrap();
+        let statuses: Vec<_> = responses.iter().map(|(status, _)| status.as_str()).collect();
+        assert_eq!(
+            statuses,
+            ["HTTP/1.1 200 OK", "HTTP/1.1 200 OK", "HTTP/1.1 200 OK", "HTTP/1.1 400 Bad Request", "HTTP/1.1 405 Method Not Allowed", "HTTP/1.1 404 Not Found", "HTTP/1.1 200 OK"]
+        );
+        assert_eq!(responses[0].1, "{\"ok\":true,\"id\":1}");
+        assert_eq!(responses[2].1, "{\"ok\":true,\"time\":2,\"pending\":0,\"next\":null,\"executed\":1,\"blackboard\":{\"last\":\"ada\"}}");
+        assert_eq!(scheduler.current_time, 2.0);
+    }
+}
@@ -0,0 +1,161 @@
+//! # Completion Events
+//!
+//! A [`SimEvent`] is a one-shot completion token in the style of SimPy's `Event`: it starts
+//! pending, is triggered once with [`SimEvent::succeed`] or [`SimEvent::fail`], and calls every
+//! callback attached with [`SimEvent::on_complete`] with the outcome. Unlike a
+//! [`crate::ScheduledAction`], which runs at a scheduled time, a `SimEvent` has no time of its
+//! own; it completes whenever some part of the model triggers it.
+
+use crate::{EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A callback waiting for a [`SimEvent`].
+type Callback<T, E> = Box<dyn FnOnce(&mut EventScheduler, Result<T, E>)>;
+
+enum Outcome<T, E> {
+    Pending(Vec<Callback<T, E>>),
+    Done(Result<T, E>),
+}
+
+/// A completion token carrying a value of type `T` on success or an error of type `E` on
+/// failure.
+///
+/// Like [`crate::Channel`], a `SimEvent` is a cheaply cloneable handle: every clone refers to
+/// the same token. Callbacks run in the order they were attached, each in its own event at the
+/// time the token is triggered, or at the current time if it was triggered already.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, SimEvent};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let mut scheduler = EventScheduler::new();
+/// let reply: SimEvent<u32> = SimEvent::new();
+///
+/// let got = Rc::new(RefCell::new(None));
+/// let record = got.clone();
+/// reply.on_complete(&mut scheduler, move |s, outcome| *record.borrow_mut() = Some((s.current_time, outcome)));
+///
+/// let trigger = reply.clone();
+/// scheduler.timeout(4.0, Some(Box::new(move |s| {
+///     trigger.succeed(s, 42);
+///     None
+/// })), None);
+/// scheduler.run_until_max_time(10.0);
+/// assert_eq!(*got.borrow(), Some((4.0, Ok(42))));
+/// ```
+pub struct SimEvent<T, E = String> {
+    state: Rc<RefCell<Outcome<T, E>>>,
+}
+
+impl<T, E> Clone for SimEvent<T, E> {
+    fn clone(&self) -> Self {
+        SimEvent { state: self.state.clone() }
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> SimEvent<T, E> {
+    /// Creates a pending token.
+    pub fn new() -> Self {
+        SimEvent { state: Rc::new(RefCell::new(Outcome::Pending(Vec::new()))) }
+    }
+
+    /// Completes the token successfully with `value`.
+    ///
+    /// # Panics
+    /// Panics if the token was already triggered.
+    pub fn succeed(&self, scheduler: &mut EventScheduler, value: T) {
+        self.trigger(scheduler, Ok(value));
+    }
+
+    /// Completes the token with the error `err`.
+    ///
+    /// # Panics
+    /// Panics if the token was already triggered.
+    pub fn fail(&self, scheduler: &mut EventScheduler, err: E) {
+        self.trigger(scheduler, Err(err));
+    }
+
+    /// Calls `callback` with the outcome once the token is triggered. If it has been triggered
+    /// already, `callback` is called by an event at the current time.
+    pub fn on_complete<F>(&self, scheduler: &mut EventScheduler, callback: F)
+    where
+        F: FnOnce(&mut EventScheduler, Result<T, E>) + 'static,
+    {
+        let mut state = self.state.borrow_mut();
+        match &mut *state {
+            Outcome::Pending(callbacks) => callbacks.push(Box::new(callback)),
+            Outcome::Done(outcome) => deliver(scheduler, Box::new(callback), outcome.clone()),
+        }
+    }
+
+    /// Returns `true` once the token has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        matches!(*self.state.borrow(), Outcome::Done(_))
+    }
+
+    /// Returns the outcome, or `None` while the token is pending.
+    pub fn outcome(&self) -> Option<Result<T, E>> {
+        match &*self.state.borrow() {
+            Outcome::Pending(_) => None,
+            Outcome::Done(outcome) => Some(outcome.clone()),
+        }
+    }
+
+    fn trigger(&self, scheduler: &mut EventScheduler, outcome: Result<T, E>) {
+        let previous = std::mem::replace(&mut *self.state.borrow_mut(), Outcome::Done(outcome.clone()));
+        let Outcome::Pending(callbacks) = previous else {
+            panic!("SimEvent triggered twice");
+        };
+        for callback in callbacks {
+            deliver(scheduler, callback, outcome.clone());
+        }
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Default for SimEvent<T, E> {
+    fn default() -> Self {
+        SimEvent::new()
+    }
+}
+
+fn deliver<T: 'static, E: 'static>(scheduler: &mut EventScheduler, callback: Callback<T, E>, outcome: Result<T, E>) {
+    let mut delivery = Some((callback, outcome));
+    scheduler.schedule(ScheduledAction::at(scheduler.current_time).with_action(move |s| {
+        if let Some((callback, outcome)) = delivery.take() {
+            callback(s, outcome);
+        }
+        None
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_reaches_late_and_early_callbacks() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut scheduler = EventScheduler::new();
+        let done: SimEvent<(), String> = SimEvent::new();
+        let early = seen.clone();
+        done.on_complete(&mut scheduler, move |s, outcome| early.borrow_mut().push((s.current_time, outcome)));
+        let trigger = done.clone();
+        scheduler.timeout(2.0, Some(Box::new(move |s| {
+            trigger.fail(s, "broken".to_string());
+            None
+        })), None);
+        scheduler.timeout(5.0, None, None);
+        scheduler.run_until_max_time(6.0);
+        assert!(done.is_triggered());
+
+        let late = seen.clone();
+        done.on_complete(&mut scheduler, move |s, outcome| late.borrow_mut().push((s.current_time, outcome)));
+        scheduler.run_until_max_time(10.0);
+        let expected = vec![(2.0, Err("broken".to_string())), (5.0, Err("broken".to_string()))];
+        assert_eq!(*seen.borrow(), expected);
+        assert_eq!(done.outcome(), Some(Err("broken".to_string())));
+    }
+}
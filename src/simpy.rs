@@ -0,0 +1,163 @@
+//! # SimPy Compatibility
+//!
+//! Models ported from SimPy are written as generator functions that `yield` what they wait
+//! for. The [`Environment`] trait gives [`EventScheduler`] SimPy's vocabulary, and a process
+//! is a closure that is resumed each time what it last yielded completes:
+//!
+//! | SimPy                         | desru                                          |
+//! |-------------------------------|------------------------------------------------|
+//! | `env = simpy.Environment()`   | `let mut env = EventScheduler::new();`         |
+//! | `env.now`                     | `env.now()`                                    |
+//! | `env.process(gen(env))`       | `env.process(move \|env\| { ... })`           |
+//! | `yield env.timeout(d)`        | `return Yield::Timeout(d)`                     |
+//! | `yield event`                 | `return Yield::Event(event)`                   |
+//! | `env.event()`                 | `env.event()`, triggered with `succeed`        |
+//! | `env.run(until=t)`            | `env.run_until(t)`                             |
+//!
+//! A generator's local variables become variables captured by the closure, and the point it
+//! resumes from becomes a state it keeps there, as in the example on [`Environment::process`].
+
+use crate::{EventScheduler, ScheduledAction, SimError, SimEvent};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// What a process waits for before it is resumed.
+pub enum Yield {
+    /// Resume after a delay.
+    Timeout(f64),
+    /// Resume once the event is triggered, whether it succeeds or fails.
+    Event(SimEvent<()>),
+    /// The process has finished.
+    Done,
+}
+
+/// A process body, resumed until it returns [`Yield::Done`].
+type ProcessFn = Rc<RefCell<dyn FnMut(&mut EventScheduler) -> Yield>>;
+
+/// SimPy's `Environment` interface, implemented by [`EventScheduler`].
+pub trait Environment {
+    /// Returns the current simulation time.
+    fn now(&self) -> f64;
+
+    /// Starts a process at the current time.
+    ///
+    /// # Returns
+    /// An event that succeeds when the process finishes, which other processes can yield.
+    ///
+    /// # Example
+    /// SimPy's car example, alternating between parking for 5 and driving for 2:
+    /// ```
+    /// use desru::{Environment, EventScheduler, Yield};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let mut env = EventScheduler::new();
+    /// let trace = Rc::new(RefCell::new(Vec::new()));
+    /// let log = trace.clone();
+    /// let mut parking = false;
+    /// env.process(move |env| {
+    ///     parking = !parking;
+    ///     if parking {
+    ///         log.borrow_mut().push(format!("Start parking at {}", env.now()));
+    ///         Yield::Timeout(5.0)
+    ///     } else {
+    ///         log.borrow_mut().push(format!("Start driving at {}", env.now()));
+    ///         Yield::Timeout(2.0)
+    ///     }
+    /// });
+    /// env.run_until(15.0);
+    /// assert_eq!(trace.borrow()[..4], ["Start parking at 0", "Start driving at 5", "Start parking at 7", "Start driving at 12"]);
+    /// assert_eq!(env.now(), 15.0);
+    /// ```
+    fn process<F>(&mut self, body: F) -> SimEvent<()>
+    where
+        F: FnMut(&mut EventScheduler) -> Yield + 'static;
+
+    /// Creates an event for processes to yield, triggered with [`SimEvent::succeed`].
+    fn event(&mut self) -> SimEvent<()> {
+        SimEvent::new()
+    }
+
+    /// Runs until `until`, leaving the clock there, like SimPy's `env.run(until=...)`.
+    ///
+    /// If an action requests a pause or the wall-clock budget runs out, the run returns early
+    /// with the clock where it stopped, and a later call carries on from there.
+    ///
+    /// # Panics
+    /// Panics if `until` is before the current time or the run raises an error.
+    fn run_until(&mut self, until: f64);
+}
+
+impl Environment for EventScheduler {
+    fn now(&self) -> f64 {
+        self.current_time
+    }
+
+    fn process<F>(&mut self, body: F) -> SimEvent<()>
+    where
+        F: FnMut(&mut EventScheduler) -> Yield + 'static,
+    {
+        let done = SimEvent::new();
+        let body: ProcessFn = Rc::new(RefCell::new(body));
+        let finished = done.clone();
+        self.schedule_now(move |s| {
+            resume(s, body.clone(), finished.clone());
+            None
+        });
+        done
+    }
+
+    fn run_until(&mut self, until: f64) {
+        match self.advance_to(until) {
+            Ok(_) | Err(SimError::Interrupted { .. }) => {}
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
+
+/// Runs a process up to its next yield and arranges for it to be resumed.
+fn resume(scheduler: &mut EventScheduler, body: ProcessFn, done: SimEvent<()>) {
+    let next = (body.borrow_mut())(scheduler);
+    match next {
+        Yield::Timeout(delay) => {
+            let time = scheduler.time_after(delay);
+            scheduler.schedule(ScheduledAction::at(time).with_action(move |s| {
+                resume(s, body.clone(), done.clone());
+                None
+            }));
+        }
+        Yield::Event(event) => event.on_complete(scheduler, move |s, _| resume(s, body, done)),
+        Yield::Done => done.succeed(scheduler, ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processes_wait_for_each_other() {
+        let mut env = EventScheduler::new();
+        let mut charged = false;
+        let charging = env.process(move |_| {
+            if charged {
+                return Yield::Done;
+            }
+            charged = true;
+            Yield::Timeout(3.0)
+        });
+        let departed = Rc::new(RefCell::new(None));
+        let record = departed.clone();
+        let mut waited = false;
+        env.process(move |env| {
+            if !waited {
+                waited = true;
+                return Yield::Event(charging.clone());
+            }
+            *record.borrow_mut() = Some(env.now());
+            Yield::Done
+        });
+        env.run_until(10.0);
+        assert_eq!(*departed.borrow(), Some(3.0));
+    }
+}
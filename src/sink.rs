@@ -0,0 +1,449 @@
+//! # Streaming Sinks
+//!
+//! Long runs can produce logs too large to hold in memory. A [`LogSink`] receives each event
+//! as it executes, and numeric samples such as queue lengths as the model records them, so
+//! that they can be written out during the run instead of after it. A [`StreamSink`] attaches
+//! a sink to a scheduler as a hook and keeps the first write error for
+//! [`StreamSink::finish`] to report. Wrapping a sink in a [`crate::BackgroundSink`] moves its
+//! writing to another thread.
+//!
+//! [`CsvSink`] and [`JsonLinesSink`] write one row per event or sample with the columns
+//! `time,kind,name,value,id,context`, which Polars and Spark read directly. The crate writes
+//! neither Arrow IPC nor Parquet and has no feature for them; sinks for those formats
+//! implement [`LogSink`] in crates that take the dependencies.
+//!
+//! The `context` column of CSV rows and of the SQL `events` table holds an event's context as
+//! `key=value` pairs sorted by key and joined with `;`. A `\`, `;` or `=` inside a key or value
+//! is escaped with a backslash, so every pair can be recovered.
+//!
+//! Each sink can also record the seeds a run used, from [`crate::SeedAudit`], so that an
+//! exported log says how to reproduce it.
+//!
+//! [`SqlSink`] writes a SQL script that creates and fills `events`, `samples`, `summary`, and
+//! `seeds` tables. Piping it into `sqlite3 run.db` produces a database that can be queried with SQL,
+//! without parsing the log by hand. The sink does not open a database itself: the crate has no
+//! `sqlite` feature and does not link SQLite.
+
+use crate::csv::{csv_field, json_number, json_string};
+use crate::{Context, EventHook, EventId, EventRecord, EventScheduler, ScheduledAction, SeedAudit};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// A destination for events and samples written while a run progresses.
+pub trait LogSink {
+    /// Writes an executed event.
+    fn write_event(&mut self, record: &EventRecord) -> io::Result<()>;
+
+    /// Writes a sample of the named series.
+    fn write_sample(&mut self, series: &str, time: f64, value: f64) -> io::Result<()>;
+
+    /// Writes the seeds a run has used as of `time`. By default, nothing is written.
+    fn write_seeds(&mut self, time: f64, seeds: &SeedAudit) -> io::Result<()> {
+        let _ = (time, seeds);
+        Ok(())
+    }
+
+    /// Flushes anything buffered.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes events and samples as CSV rows.
+#[derive(Debug)]
+pub struct CsvSink<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl<W: Write> CsvSink<W> {
+    /// Creates a sink writing to `writer`. The header is written with the first row.
+    pub fn new(writer: W) -> Self {
+        CsvSink { writer, header_written: false }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn row(&mut self, time: f64, kind: &str, name: &str, value: &str, id: &str, context: &str) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(self.writer, "time,kind,name,value,id,context")?;
+            self.header_written = true;
+        }
+        writeln!(self.writer, "{},{},{},{},{},{}", time, kind, csv_field(name), csv_field(value), id, csv_field(context))
+    }
+}
+
+impl<W: Write> LogSink for CsvSink<W> {
+    fn write_event(&mut self, record: &EventRecord) -> io::Result<()> {
+        let context = context_pairs(&record.context);
+        self.row(
+            record.time,
+            "event",
+            record.label.as_deref().unwrap_or(""),
+            record.result.as_deref().unwrap_or(""),
+            &record.id.0.to_string(),
+            &context,
+        )
+    }
+
+    fn write_sample(&mut self, series: &str, time: f64, value: f64) -> io::Result<()> {
+        self.row(time, "sample", series, &value.to_string(), "", "")
+    }
+
+    /// Writes one `seed` row for the scheduler, with an empty name, and one for each stream.
+    fn write_seeds(&mut self, time: f64, seeds: &SeedAudit) -> io::Result<()> {
+        let context = if seeds.antithetic { "antithetic=true" } else { "" };
+        self.row(time, "seed", "", &seeds.seed.to_string(), "", context)?;
+        for (stream, seed) in &seeds.streams {
+            self.row(time, "seed", stream, &seed.to_string(), "", context)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes events and samples as newline-delimited JSON objects.
+#[derive(Debug)]
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    /// Creates a sink writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink { writer }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> LogSink for JsonLinesSink<W> {
+    fn write_event(&mut self, record: &EventRecord) -> io::Result<()> {
+        let mut pairs: Vec<_> = record.context.iter().collect();
+        pairs.sort();
+        let context: Vec<String> = pairs.iter().map(|(k, v)| format!("{}:{}", json_string(k), json_string(v))).collect();
+        writeln!(
+            self.writer,
+            "{{\"time\":{},\"kind\":\"event\",\"name\":{},\"value\":{},\"id\":{},\"context\":{{{}}}}}",
+            json_number(Some(record.time)),
+            record.label.as_deref().map_or("null".to_string(), json_string),
+            record.result.as_deref().map_or("null".to_string(), json_string),
+            record.id.0,
+            context.join(",")
+        )
+    }
+
+    fn write_sample(&mut self, series: &str, time: f64, value: f64) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{{\"time\":{},\"kind\":\"sample\",\"name\":{},\"value\":{}}}",
+            json_number(Some(time)),
+            json_string(series),
+            json_number(Some(value))
+        )
+    }
+
+    /// Writes one `seed` object for the scheduler, named `null`, and one for each stream. Seeds
+    /// are written as strings, since JSON readers may not hold 64-bit integers exactly.
+    fn write_seeds(&mut self, time: f64, seeds: &SeedAudit) -> io::Result<()> {
+        let streams = std::iter::once((None, seeds.seed)).chain(seeds.streams.iter().map(|(name, seed)| (Some(name.as_str()), *seed)));
+        for (stream, seed) in streams {
+            writeln!(
+                self.writer,
+                "{{\"time\":{},\"kind\":\"seed\",\"name\":{},\"value\":\"{}\",\"antithetic\":{}}}",
+                json_number(Some(time)),
+                stream.map_or("null".to_string(), json_string),
+                seed,
+                seeds.antithetic
+            )?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// How many rows [`SqlSink`] groups into one transaction.
+const SQL_BATCH: usize = 1000;
+
+/// Writes events and samples as a SQL script of `INSERT` statements for SQLite.
+///
+/// The script begins by creating its tables and groups rows into transactions, which SQLite
+/// imports far faster than individual statements.
+///
+/// # Example
+/// ```
+/// use desru::{LogSink, SqlSink};
+///
+/// let mut sink = SqlSink::new(Vec::new());
+/// sink.write_sample("queue", 1.0, 2.0).unwrap();
+/// sink.write_summary("mean_wait", 0.5).unwrap();
+/// sink.flush().unwrap();
+/// let script = String::from_utf8(sink.into_inner()).unwrap();
+/// assert!(script.contains("INSERT INTO samples VALUES ('queue', 1, 2);"));
+/// assert!(script.ends_with("COMMIT;\n"));
+/// ```
+#[derive(Debug)]
+pub struct SqlSink<W: Write> {
+    writer: W,
+    created: bool,
+    rows_in_transaction: usize,
+}
+
+impl<W: Write> SqlSink<W> {
+    /// Creates a sink writing to `writer`. The tables are created with the first row.
+    pub fn new(writer: W) -> Self {
+        SqlSink { writer, created: false, rows_in_transaction: 0 }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a named summary statistic, such as a mean or a count over the run.
+    pub fn write_summary(&mut self, name: &str, value: f64) -> io::Result<()> {
+        let statement = format!("INSERT INTO summary VALUES ({}, {});", sql_string(name), sql_number(value));
+        self.statement(&statement)
+    }
+
+    fn statement(&mut self, statement: &str) -> io::Result<()> {
+        if !self.created {
+            writeln!(self.writer, "CREATE TABLE IF NOT EXISTS events (time REAL, id INTEGER, label TEXT, result TEXT, context TEXT);")?;
+            writeln!(self.writer, "CREATE TABLE IF NOT EXISTS samples (series TEXT, time REAL, value REAL);")?;
+            writeln!(self.writer, "CREATE TABLE IF NOT EXISTS summary (name TEXT, value REAL);")?;
+            writeln!(self.writer, "CREATE TABLE IF NOT EXISTS seeds (stream TEXT, seed TEXT);")?;
+            self.created = true;
+        }
+        if self.rows_in_transaction == 0 {
+            writeln!(self.writer, "BEGIN;")?;
+        }
+        writeln!(self.writer, "{}", statement)?;
+        self.rows_in_transaction += 1;
+        if self.rows_in_transaction == SQL_BATCH {
+            writeln!(self.writer, "COMMIT;")?;
+            self.rows_in_transaction = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> LogSink for SqlSink<W> {
+    fn write_event(&mut self, record: &EventRecord) -> io::Result<()> {
+        let statement = format!(
+            "INSERT INTO events VALUES ({}, {}, {}, {}, {});",
+            sql_number(record.time),
+            record.id.0,
+            record.label.as_deref().map_or("NULL".to_string(), sql_string),
+            record.result.as_deref().map_or("NULL".to_string(), sql_string),
+            sql_string(&context_pairs(&record.context))
+        );
+        self.statement(&statement)
+    }
+
+    fn write_sample(&mut self, series: &str, time: f64, value: f64) -> io::Result<()> {
+        let statement = format!("INSERT INTO samples VALUES ({}, {}, {});", sql_string(series), sql_number(time), sql_number(value));
+        self.statement(&statement)
+    }
+
+    /// Inserts one row into `seeds` for the scheduler, with a `NULL` stream, and one for each
+    /// stream. Seeds are stored as text, since SQLite integers are signed.
+    fn write_seeds(&mut self, _time: f64, seeds: &SeedAudit) -> io::Result<()> {
+        self.statement(&format!("INSERT INTO seeds VALUES (NULL, '{}');", seeds.seed))?;
+        for (stream, seed) in &seeds.streams {
+            self.statement(&format!("INSERT INTO seeds VALUES ({}, '{}');", sql_string(stream), seed))?;
+        }
+        Ok(())
+    }
+
+    /// Commits the open transaction and flushes the writer.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.rows_in_transaction > 0 {
+            writeln!(self.writer, "COMMIT;")?;
+            self.rows_in_transaction = 0;
+        }
+        self.writer.flush()
+    }
+}
+
+/// The state shared by the clones of a [`StreamSink`].
+struct StreamState<S> {
+    sink: S,
+    error: Option<io::Error>,
+}
+
+/// A [`LogSink`] shared between a scheduler's hook and the model. Every clone refers to the
+/// same sink.
+///
+/// Once a write fails, later writes are skipped and the error is returned by
+/// [`StreamSink::finish`].
+///
+/// # Example
+/// ```
+/// use desru::{CsvSink, EventScheduler, StreamSink};
+///
+/// let sink = StreamSink::new(CsvSink::new(Vec::new()));
+/// let mut scheduler = EventScheduler::builder().hook(sink.hook()).build();
+/// let samples = sink.clone();
+/// scheduler.timeout(2.0, Some(Box::new(move |s| {
+///     samples.sample("queue", s.current_time, 3.0);
+///     Some("arrival".to_string())
+/// })), None);
+/// scheduler.run_until_max_time(10.0);
+/// drop(scheduler);
+///
+/// let csv = String::from_utf8(sink.finish().unwrap().into_inner()).unwrap();
+/// assert_eq!(csv, "time,kind,name,value,id,context\n2,sample,queue,3,,\n2,event,,arrival,1,\n");
+/// ```
+pub struct StreamSink<S> {
+    state: Rc<RefCell<StreamState<S>>>,
+}
+
+impl<S> Clone for StreamSink<S> {
+    fn clone(&self) -> Self {
+        StreamSink { state: self.state.clone() }
+    }
+}
+
+impl<S: LogSink + 'static> StreamSink<S> {
+    /// Wraps `sink`.
+    pub fn new(sink: S) -> Self {
+        StreamSink { state: Rc::new(RefCell::new(StreamState { sink, error: None })) }
+    }
+
+    /// Returns a hook that writes every executed event to the sink.
+    pub fn hook(&self) -> EventHook {
+        let sink = self.clone();
+        Box::new(move |_: &EventScheduler, event: &ScheduledAction, result: &Option<String>| {
+            let record = EventRecord {
+                id: EventId(event.seq),
+                time: event.time,
+                label: event.label.clone(),
+                context: event.context.clone(),
+                result: result.clone(),
+            };
+            sink.write(|s| s.write_event(&record));
+        })
+    }
+
+    /// Writes a sample of the named series.
+    pub fn sample(&self, series: &str, time: f64, value: f64) {
+        self.write(|s| s.write_sample(series, time, value));
+    }
+
+    /// Writes the seeds the scheduler has used so far, typically once a run has finished.
+    pub fn record_seeds(&self, scheduler: &EventScheduler) {
+        let seeds = scheduler.seed_audit();
+        self.write(|s| s.write_seeds(scheduler.current_time, &seeds));
+    }
+
+    /// Flushes the sink and returns it.
+    ///
+    /// # Errors
+    /// Returns the first error any write or the flush raised.
+    ///
+    /// # Panics
+    /// Panics if other clones of this handle are still alive, such as one held by a
+    /// scheduler's hook.
+    pub fn finish(self) -> io::Result<S> {
+        let state = Rc::try_unwrap(self.state).unwrap_or_else(|_| panic!("StreamSink finished while still shared"));
+        let StreamState { mut sink, error } = state.into_inner();
+        match error {
+            Some(error) => Err(error),
+            None => sink.flush().map(|_| sink),
+        }
+    }
+
+    fn write(&self, f: impl FnOnce(&mut S) -> io::Result<()>) {
+        let mut state = self.state.borrow_mut();
+        if state.error.is_none() {
+            if let Err(error) = f(&mut state.sink) {
+                state.error = Some(error);
+            }
+        }
+    }
+}
+
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Formats a number for SQL, writing non-finite values as `NULL`.
+fn sql_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "NULL".to_string()
+    }
+}
+
+fn context_pairs(context: &Context) -> String {
+    let mut pairs: Vec<_> = context.iter().collect();
+    pairs.sort();
+    pairs.iter().map(|(k, v)| format!("{}={}", escape_pair_part(k), escape_pair_part(v))).collect::<Vec<_>>().join(";")
+}
+
+/// Escapes the characters that separate context pairs and their keys and values.
+fn escape_pair_part(part: &str) -> String {
+    let mut escaped = String::with_capacity(part.len());
+    for c in part.chars() {
+        if matches!(c, '\\' | ';' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_lines_stream_events_with_context() {
+        let sink = StreamSink::new(JsonLinesSink::new(Vec::new()));
+        let mut scheduler = EventScheduler::builder().hook(sink.hook()).build();
+        let context = Context::from([("b".to_string(), "2".to_string()), ("a".to_string(), "1".to_string())]);
+        scheduler.schedule(ScheduledAction::at(1.5).with_label("ship \"x\"").with_context(context));
+        scheduler.run_until_max_time(10.0);
+        scheduler.stream("demand");
+        sink.record_seeds(&scheduler);
+        let seed = scheduler.streams.stream_seed("demand");
+        drop(scheduler);
+
+        let json = String::from_utf8(sink.finish().unwrap().into_inner()).unwrap();
+        let lines: Vec<&str> = json.lines().collect();
+        assert_eq!(lines[0], "{\"time\":1.5,\"kind\":\"event\",\"name\":\"ship \\\"x\\\"\",\"value\":null,\"id\":1,\"context\":{\"a\":\"1\",\"b\":\"2\"}}");
+        assert_eq!(lines[1], format!("{{\"time\":1.5,\"kind\":\"seed\",\"name\":null,\"value\":\"{}\",\"antithetic\":false}}", crate::DEFAULT_SEED));
+        assert_eq!(lines[2], format!("{{\"time\":1.5,\"kind\":\"seed\",\"name\":\"demand\",\"value\":\"{}\",\"antithetic\":false}}", seed));
+    }
+
+    #[test]
+    fn test_sql_script_quotes_and_batches() {
+        let mut sink = SqlSink::new(Vec::new());
+        for id in 0..SQL_BATCH as u64 + 1 {
+            let record = EventRecord { id: EventId(id), time: 1.0, label: Some("o'clock".to_string()), context: Context::new(), result: None };
+            sink.write_event(&record).unwrap();
+        }
+        sink.flush().unwrap();
+        let script = String::from_utf8(sink.into_inner()).unwrap();
+        assert!(script.contains("INSERT INTO events VALUES (1, 0, 'o''clock', NULL, '');"));
+
+        let context = Context::from([("url".to_string(), "a=1;b=2".to_string()), ("dir".to_string(), "C:\\tmp".to_string())]);
+        assert_eq!(context_pairs(&context), "dir=C:\\\\tmp;url=a\\=1\\;b\\=2");
+        assert_eq!(script.matches("BEGIN;").count(), 2);
+        assert_eq!(script.matches("COMMIT;").count(), 2);
+    }
+}
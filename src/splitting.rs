@@ -0,0 +1,219 @@
+//! # Rare-Event Splitting
+//!
+//! A probability such as a buffer overflowing once in a million runs is too small to estimate
+//! by counting plain replications. [`Splitting`] estimates it by multilevel splitting: an
+//! importance function measures how close a run is to the rare event, a sequence of
+//! increasing levels leads up to it, and runs that cross a level are continued as several
+//! copies with fresh randomness, so most of the effort is spent near the event. The
+//! probability is the product of the conditional probabilities of reaching each level from
+//! the one before.
+//!
+//! A scheduler's pending actions are closures, which cannot be copied, so a copy of a run at a
+//! crossing is rebuilt instead: the run is repeated from the start with the seeds of its
+//! earlier segments, which reproduces it exactly up to the crossing, and then reseeded for
+//! the new branch. This requires the model to draw all of its randomness from the
+//! scheduler's generator and streams.
+
+use crate::{EventScheduler, RngStreams, SeedSequence, SimRng};
+
+/// A fixed-effort multilevel splitting estimator.
+///
+/// Each stage runs `effort` trials, each started from a crossing of the previous level chosen
+/// at random, and counts the trials that reach the next level before the horizon or before the
+/// run runs out of events.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduledAction, Splitting};
+///
+/// // A random walk that steps up with probability 0.3 and is absorbed at 0: estimate the
+/// // probability of reaching 10 from 1.
+/// fn step(s: &mut EventScheduler) -> Option<String> {
+///     let up = s.rng.next_f64() < 0.3;
+///     let position = s.state_mut::<i64>();
+///     *position += if up { 1 } else { -1 };
+///     if *position > 0 {
+///         s.schedule(ScheduledAction::at(s.current_time + 1.0).with_action(step));
+///     }
+///     None
+/// }
+///
+/// let levels: Vec<f64> = (2..=10).map(f64::from).collect();
+/// let estimate = Splitting::new(&levels, f64::INFINITY).effort(500).estimate(
+///     |s| {
+///         s.world.set(1_i64);
+///         s.schedule(ScheduledAction::at(1.0).with_action(step));
+///     },
+///     |s| *s.state::<i64>() as f64,
+/// );
+/// // Gambler's ruin: (r - 1) / (r^10 - 1) with r = 0.7 / 0.3.
+/// let r: f64 = 0.7 / 0.3;
+/// let exact = (r - 1.0) / (r.powi(10) - 1.0);
+/// assert!((estimate.probability / exact - 1.0).abs() < 0.3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Splitting {
+    levels: Vec<f64>,
+    horizon: f64,
+    effort: usize,
+    seed: u64,
+}
+
+/// The result of [`Splitting::estimate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplittingEstimate {
+    /// The estimated probability of reaching the last level.
+    pub probability: f64,
+    /// The estimated probability of reaching each level given that the previous one was
+    /// reached. Stops at the first level that no trial reached.
+    pub level_probabilities: Vec<f64>,
+    /// The number of trials run.
+    pub trials: usize,
+}
+
+/// How a trial segment ended.
+enum Segment {
+    Crossed,
+    Failed,
+}
+
+impl Splitting {
+    /// Creates an estimator for reaching the last of `levels`, which are compared with the
+    /// importance function after every event. A run that has not reached it by `horizon`
+    /// fails.
+    ///
+    /// # Panics
+    /// Panics if `levels` is empty or not increasing.
+    pub fn new(levels: &[f64], horizon: f64) -> Self {
+        assert!(!levels.is_empty(), "splitting needs at least one level");
+        assert!(levels.windows(2).all(|w| w[0] < w[1]), "splitting levels must be increasing");
+        Splitting { levels: levels.to_vec(), horizon, effort: 1000, seed: crate::DEFAULT_SEED }
+    }
+
+    /// Sets the number of trials per level. Defaults to 1000.
+    ///
+    /// # Panics
+    /// Panics if `effort` is zero.
+    pub fn effort(mut self, effort: usize) -> Self {
+        assert!(effort > 0, "splitting needs at least one trial per level");
+        self.effort = effort;
+        self
+    }
+
+    /// Sets the seed from which every trial's seeds are derived.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Estimates the probability of reaching the last level.
+    ///
+    /// # Parameters
+    /// - `init`: Sets up the model on a fresh scheduler, as at the start of a replication.
+    /// - `importance`: Measures how close the run is to the rare event.
+    ///
+    /// A trial that pauses or uses up its wall-clock budget counts as not crossing its level.
+    ///
+    /// # Panics
+    /// Panics if a run exceeds `max_events_per_time` or an event was scheduled at a NaN time,
+    /// as [`EventScheduler::run`] does.
+    pub fn estimate<I, F>(&self, init: I, importance: F) -> SplittingEstimate
+    where
+        I: Fn(&mut EventScheduler),
+        F: Fn(&EventScheduler) -> f64,
+    {
+        let root = SeedSequence::new(self.seed);
+        let mut choose = root.child(self.levels.len() as u64).rng();
+        // The seeds of each segment of a trial that crossed the latest level.
+        let mut crossings: Vec<Vec<u64>> = vec![Vec::new()];
+        let mut estimate = SplittingEstimate { probability: 1.0, level_probabilities: Vec::new(), trials: 0 };
+        for level in 0..self.levels.len() {
+            let stage = root.child(level as u64);
+            let mut next = Vec::new();
+            for trial in 0..self.effort {
+                let mut path = crossings[choose.gen_index(crossings.len())].clone();
+                path.push(stage.child(trial as u64).seed());
+                if let Segment::Crossed = self.replay(&path, &init, &importance) {
+                    next.push(path);
+                }
+            }
+            estimate.trials += self.effort;
+            let fraction = next.len() as f64 / self.effort as f64;
+            estimate.level_probabilities.push(fraction);
+            estimate.probability *= fraction;
+            if next.is_empty() {
+                break;
+            }
+            crossings = next;
+        }
+        estimate
+    }
+
+    /// Runs a trial whose `k`-th segment uses seed `path[k]` and runs until level `k` is
+    /// crossed, returning how its last segment ended.
+    fn replay<I, F>(&self, path: &[u64], init: &I, importance: &F) -> Segment
+    where
+        I: Fn(&mut EventScheduler),
+        F: Fn(&EventScheduler) -> f64,
+    {
+        let mut scheduler = EventScheduler::builder().seed(path[0]).logging(false).build();
+        init(&mut scheduler);
+        for (level, &seed) in path.iter().enumerate() {
+            if level > 0 {
+                reseed(&mut scheduler, seed);
+            }
+            if let Segment::Failed = self.run_segment(&mut scheduler, self.levels[level], importance) {
+                return Segment::Failed;
+            }
+        }
+        Segment::Crossed
+    }
+
+    /// Runs until the importance reaches `level`, the horizon passes, or no events are left.
+    fn run_segment<F>(&self, scheduler: &mut EventScheduler, level: f64, importance: &F) -> Segment
+    where
+        F: Fn(&EventScheduler) -> f64,
+    {
+        let beyond_horizon = |s: &EventScheduler| s.event_queue.peek().is_some_and(|event| event.time > self.horizon);
+        let stop = |s: &EventScheduler| importance(s) >= level || beyond_horizon(s);
+        scheduler.try_run_observed(stop, &crate::LogPolicy::Full, |_, _, _| {}).unwrap_or_else(|error| panic!("{}", error));
+        if importance(scheduler) >= level {
+            Segment::Crossed
+        } else {
+            Segment::Failed
+        }
+    }
+}
+
+/// Gives a copy of a run fresh randomness for its new branch.
+fn reseed(scheduler: &mut EventScheduler, seed: u64) {
+    scheduler.rng = SimRng::new(seed);
+    scheduler.streams = RngStreams::new(seed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScheduledAction;
+
+    #[test]
+    fn test_replayed_branches_reproduce_the_crossing() {
+        // A deterministic ramp crosses every level, so every trial succeeds.
+        let ramp = |s: &mut EventScheduler| {
+            s.world.set(0.0_f64);
+            for t in 1..=5 {
+                s.schedule(ScheduledAction::at(f64::from(t)).with_action(|s| {
+                    *s.state_mut::<f64>() += 1.0 + s.rng.next_f64() * 0.1;
+                    None
+                }));
+            }
+        };
+        let sure = Splitting::new(&[2.0, 4.0], 10.0).effort(20).estimate(ramp, |s| *s.state::<f64>());
+        assert_eq!((sure.probability, sure.trials), (1.0, 40));
+        // Level 6 is never reached, and the horizon cuts the ramp at 3.
+        let none = Splitting::new(&[2.0, 6.0], 10.0).effort(20).estimate(ramp, |s| *s.state::<f64>());
+        assert_eq!(none.level_probabilities, [1.0, 0.0]);
+        let cut = Splitting::new(&[3.5], 3.0).effort(5).estimate(ramp, |s| *s.state::<f64>());
+        assert_eq!(cut.probability, 0.0);
+    }
+}
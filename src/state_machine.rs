@@ -0,0 +1,222 @@
+//! # State Machines
+//!
+//! A [`StateMachine`] declares states and the timed transitions between them; the scheduler
+//! fires an event for each transition and runs the enter/exit hooks of the states involved.
+//! This formalizes the park/drive pattern from the crate-level car examples:
+//!
+//! ```
+//! use desru::{EventScheduler, StateMachine};
+//!
+//! let mut scheduler = EventScheduler::new();
+//! let car = StateMachine::new("Park")
+//!     .transition("Park", "Drive", 5.0)
+//!     .transition("Drive", "Park", 2.0)
+//!     .on_enter("Park", |s, _| println!("Start parking at {}", s.current_time))
+//!     .on_enter("Drive", |s, _| println!("Start driving at {}", s.current_time));
+//! car.start(&mut scheduler);
+//! scheduler.run_until_max_time(15.0);
+//!
+//! assert_eq!(car.state(), "Park");
+//! assert_eq!(car.history().len(), 5);
+//! ```
+
+use crate::{EventScheduler, SimRng};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A hook run when a state is entered or exited, receiving the state's name.
+pub type StateHook = Box<dyn FnMut(&mut EventScheduler, &str)>;
+
+/// Produces the delay of a timed transition.
+pub type DelayFn = Box<dyn FnMut(&mut SimRng) -> f64>;
+
+struct Transition {
+    to: String,
+    delay: DelayFn,
+}
+
+struct MachineState {
+    current: String,
+    transitions: HashMap<String, Transition>,
+    history: Vec<(f64, String)>,
+    // Incremented on every transition so that superseded timed transitions are ignored.
+    generation: u64,
+}
+
+#[derive(Default)]
+struct Hooks {
+    enter: HashMap<String, Vec<StateHook>>,
+    exit: HashMap<String, Vec<StateHook>>,
+}
+
+/// A state machine whose timed transitions are fired by the scheduler.
+///
+/// Each state has at most one timed transition; declaring another for the same state replaces
+/// it. States without a timed transition are left only through [`StateMachine::trigger`].
+/// Clones share the current state, so event actions can trigger transitions through a clone.
+#[derive(Clone)]
+pub struct StateMachine {
+    state: Rc<RefCell<MachineState>>,
+    hooks: Rc<RefCell<Hooks>>,
+}
+
+impl StateMachine {
+    /// Creates a machine that will begin in `initial` once started.
+    pub fn new(initial: &str) -> Self {
+        StateMachine {
+            state: Rc::new(RefCell::new(MachineState {
+                current: initial.to_string(),
+                transitions: HashMap::new(),
+                history: Vec::new(),
+                generation: 0,
+            })),
+            hooks: Rc::new(RefCell::new(Hooks::default())),
+        }
+    }
+
+    /// Declares a transition from `from` to `to` taking place `delay` after entering `from`.
+    pub fn transition(self, from: &str, to: &str, delay: f64) -> Self {
+        self.transition_with(from, to, move |_| delay)
+    }
+
+    /// Declares a transition whose delay is drawn from the scheduler's random number generator
+    /// each time `from` is entered.
+    pub fn transition_with<F>(self, from: &str, to: &str, delay: F) -> Self
+    where
+        F: FnMut(&mut SimRng) -> f64 + 'static,
+    {
+        self.state
+            .borrow_mut()
+            .transitions
+            .insert(from.to_string(), Transition { to: to.to_string(), delay: Box::new(delay) });
+        self
+    }
+
+    /// Adds a hook run whenever `state` is entered.
+    pub fn on_enter<F>(self, state: &str, hook: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler, &str) + 'static,
+    {
+        self.hooks.borrow_mut().enter.entry(state.to_string()).or_default().push(Box::new(hook));
+        self
+    }
+
+    /// Adds a hook run whenever `state` is exited.
+    pub fn on_exit<F>(self, state: &str, hook: F) -> Self
+    where
+        F: FnMut(&mut EventScheduler, &str) + 'static,
+    {
+        self.hooks.borrow_mut().exit.entry(state.to_string()).or_default().push(Box::new(hook));
+        self
+    }
+
+    /// Returns the name of the current state.
+    pub fn state(&self) -> String {
+        self.state.borrow().current.clone()
+    }
+
+    /// Returns every `(time, state)` entered so far, in order.
+    pub fn history(&self) -> Vec<(f64, String)> {
+        self.state.borrow().history.clone()
+    }
+
+    /// Returns the total time spent in each state up to `now`.
+    pub fn occupancy(&self, now: f64) -> HashMap<String, f64> {
+        let state = self.state.borrow();
+        let mut totals = HashMap::new();
+        let ends = state.history.iter().skip(1).map(|(t, _)| *t).chain(std::iter::once(now));
+        for ((start, name), end) in state.history.iter().zip(ends) {
+            *totals.entry(name.clone()).or_insert(0.0) += end - start;
+        }
+        totals
+    }
+
+    /// Enters the initial state at the current time.
+    pub fn start(&self, scheduler: &mut EventScheduler) {
+        let initial = self.state();
+        self.enter(scheduler, &initial);
+    }
+
+    /// Moves to `to` immediately, exiting the current state and superseding its timed transition.
+    pub fn trigger(&self, scheduler: &mut EventScheduler, to: &str) {
+        let from = self.state();
+        self.run_hooks(scheduler, &from, false);
+        self.enter(scheduler, to);
+    }
+
+    fn enter(&self, scheduler: &mut EventScheduler, to: &str) {
+        let next = {
+            let mut state = self.state.borrow_mut();
+            state.current = to.to_string();
+            state.history.push((scheduler.current_time, to.to_string()));
+            state.generation += 1;
+            let generation = state.generation;
+            state
+                .transitions
+                .get_mut(to)
+                .map(|t| ((t.delay)(&mut scheduler.rng), t.to.clone(), generation))
+        };
+        self.run_hooks(scheduler, to, true);
+        if let Some((delay, target, generation)) = next {
+            let machine = self.clone();
+            scheduler.timeout(
+                delay,
+                Some(Box::new(move |s: &mut EventScheduler| {
+                    if machine.state.borrow().generation == generation {
+                        machine.trigger(s, &target);
+                    }
+                    None
+                })),
+                None,
+            );
+        }
+    }
+
+    fn run_hooks(&self, scheduler: &mut EventScheduler, state: &str, entering: bool) {
+        let taken = {
+            let mut hooks = self.hooks.borrow_mut();
+            let map = if entering { &mut hooks.enter } else { &mut hooks.exit };
+            map.remove(state)
+        };
+        if let Some(mut list) = taken {
+            for hook in list.iter_mut() {
+                hook(scheduler, state);
+            }
+            let mut hooks = self.hooks.borrow_mut();
+            let map = if entering { &mut hooks.enter } else { &mut hooks.exit };
+            // Keep any hooks registered while these were running.
+            list.append(map.entry(state.to_string()).or_default());
+            map.insert(state.to_string(), list);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_supersedes_timed_transition() {
+        let exits = Rc::new(RefCell::new(Vec::new()));
+        let record = exits.clone();
+        let mut scheduler = EventScheduler::new();
+        let machine = StateMachine::new("Idle")
+            .transition("Idle", "Busy", 10.0)
+            .transition("Broken", "Idle", 3.0)
+            .on_exit("Idle", move |s, _| record.borrow_mut().push(s.current_time));
+        machine.start(&mut scheduler);
+        let handle = machine.clone();
+        scheduler.timeout(4.0, Some(Box::new(move |s| {
+            handle.trigger(s, "Broken");
+            None
+        })), None);
+        scheduler.run_until_max_time(12.0);
+        // Idle -> Broken at 4, Broken -> Idle at 7; the Idle -> Busy timer from 0 is void.
+        assert_eq!(machine.state(), "Idle");
+        assert_eq!(*exits.borrow(), vec![4.0]);
+        let occupancy = machine.occupancy(12.0);
+        assert_eq!(occupancy["Idle"], 9.0);
+        assert_eq!(occupancy["Broken"], 3.0);
+    }
+}
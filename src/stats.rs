@@ -0,0 +1,187 @@
+//! # Statistics Collectors
+//!
+//! - [`Tally`]: Collects independent observations, such as waiting times.
+//! - [`Monitored`]: Tracks a piecewise-constant value over simulated time, such as a queue
+//!   length, and reports time-weighted averages.
+
+/// Collects observations and reports summary statistics.
+///
+/// Observations are kept in order so that they can be post-processed, for example into
+/// batch means.
+///
+/// # Example
+/// ```
+/// use desru::Tally;
+///
+/// let mut waits = Tally::new();
+/// for w in [1.0, 2.0, 3.0, 4.0] {
+///     waits.record(w);
+/// }
+/// assert_eq!(waits.count(), 4);
+/// assert_eq!(waits.mean(), 2.5);
+/// assert_eq!(waits.max(), Some(4.0));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tally {
+    values: Vec<f64>,
+    mean: f64,
+    m2: f64,
+}
+
+impl Tally {
+    /// Creates an empty tally.
+    pub fn new() -> Self {
+        Tally::default()
+    }
+
+    /// Records an observation.
+    pub fn record(&mut self, value: f64) {
+        // Welford's online update keeps the variance numerically stable.
+        self.values.push(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.values.len() as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Returns the number of observations.
+    pub fn count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the observations in the order they were recorded.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Returns the sample mean, or `0.0` if there are no observations.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the unbiased sample variance, or `0.0` with fewer than two observations.
+    pub fn variance(&self) -> f64 {
+        if self.values.len() < 2 {
+            0.0
+        } else {
+            self.m2 / (self.values.len() - 1) as f64
+        }
+    }
+
+    /// Returns the sample standard deviation.
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Returns the smallest observation.
+    pub fn min(&self) -> Option<f64> {
+        self.values.iter().copied().reduce(f64::min)
+    }
+
+    /// Returns the largest observation.
+    pub fn max(&self) -> Option<f64> {
+        self.values.iter().copied().reduce(f64::max)
+    }
+}
+
+/// A piecewise-constant value observed over simulated time.
+///
+/// Statistics are measured from the time of the first [`Monitored::record`] call.
+///
+/// # Example
+/// ```
+/// use desru::Monitored;
+///
+/// let mut queue_length = Monitored::new(0.0);
+/// queue_length.record(0.0, 2.0);
+/// queue_length.record(5.0, 0.0);
+/// assert_eq!(queue_length.time_average(10.0), 1.0);
+/// assert_eq!(queue_length.max(), 2.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitored {
+    start: Option<f64>,
+    last_time: f64,
+    value: f64,
+    area: f64,
+    max: f64,
+    trajectory: Vec<(f64, f64)>,
+}
+
+impl Monitored {
+    /// Creates a monitored value holding `initial`.
+    pub fn new(initial: f64) -> Self {
+        Monitored { start: None, last_time: 0.0, value: initial, area: 0.0, max: initial, trajectory: Vec::new() }
+    }
+
+    /// Records that the value changed to `value` at `time`.
+    pub fn record(&mut self, time: f64, value: f64) {
+        match self.start {
+            None => self.start = Some(time),
+            Some(_) => self.area += self.value * (time - self.last_time),
+        }
+        self.last_time = time;
+        self.value = value;
+        self.max = self.max.max(value);
+        self.trajectory.push((time, value));
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns the largest value held.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Returns the `(time, value)` changes in the order they were recorded.
+    pub fn trajectory(&self) -> &[(f64, f64)] {
+        &self.trajectory
+    }
+
+    /// Returns the time-weighted average from the first record up to `now`.
+    ///
+    /// Returns the current value if no time has elapsed.
+    pub fn time_average(&self, now: f64) -> f64 {
+        match self.start {
+            Some(start) if now > start => (self.area + self.value * (now - self.last_time)) / (now - start),
+            _ => self.value,
+        }
+    }
+}
+
+impl Default for Monitored {
+    fn default() -> Self {
+        Monitored::new(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally_variance() {
+        let mut tally = Tally::new();
+        assert_eq!(tally.variance(), 0.0);
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            tally.record(x);
+        }
+        assert_eq!(tally.mean(), 5.0);
+        assert!((tally.variance() - 32.0 / 7.0).abs() < 1e-12);
+        assert_eq!(tally.min(), Some(2.0));
+    }
+
+    #[test]
+    fn test_monitored_time_average() {
+        let mut monitored = Monitored::new(0.0);
+        assert_eq!(monitored.time_average(3.0), 0.0);
+        monitored.record(2.0, 1.0);
+        monitored.record(4.0, 3.0);
+        monitored.record(6.0, 0.0);
+        // 1 for 2 units, 3 for 2 units, 0 for 2 units, measured from t = 2.
+        assert!((monitored.time_average(8.0) - 8.0 / 6.0).abs() < 1e-12);
+        assert_eq!(monitored.trajectory().len(), 3);
+    }
+}
@@ -0,0 +1,90 @@
+//! # Superdense Time
+//!
+//! Formalisms such as DEVS and Ptolemy's discrete-event domain stamp events with superdense
+//! time, a pair `(t, n)` of a time and a microstep. A chain of zero-delay reactions then
+//! advances the microstep without advancing the time, so cause and effect at one instant stay
+//! distinct and ordered. With [`crate::EventSchedulerBuilder::superdense_time`], an event
+//! scheduled for the current time gets the next microstep after the event scheduling it, and
+//! every event at a microstep runs before any event at the next, whatever their priorities.
+
+use crate::EventScheduler;
+use std::fmt;
+
+/// A time and a microstep, ordered by time and then by microstep.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct SuperdenseTime {
+    pub time: f64,
+    pub microstep: u64,
+}
+
+impl fmt::Display for SuperdenseTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.time, self.microstep)
+    }
+}
+
+impl EventScheduler {
+    /// Returns the superdense time of the event executing, or of the last event executed.
+    ///
+    /// The microstep is always zero unless superdense time is enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::builder().superdense_time(true).build();
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+    ///     // A zero-delay reaction, even one marked urgent, runs a microstep later.
+    ///     s.schedule(ScheduledAction::at(1.0).with_priority(-10).with_action(|s| Some(s.superdense_now().to_string())));
+    ///     Some(s.superdense_now().to_string())
+    /// }));
+    /// scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| Some(s.superdense_now().to_string())));
+    ///
+    /// let log = scheduler.run_until_max_time(5.0);
+    /// let stamps: Vec<_> = log.iter().filter_map(|record| record.result.as_deref()).collect();
+    /// assert_eq!(stamps, ["(1, 0)", "(1, 0)", "(1, 1)"]);
+    /// ```
+    pub fn superdense_now(&self) -> SuperdenseTime {
+        SuperdenseTime { time: self.current_time, microstep: self.microstep }
+    }
+
+    /// Returns the microstep an event scheduled now for `time` is given.
+    pub(crate) fn microstep_at(&self, time: f64) -> u64 {
+        if self.superdense && time == self.current_time {
+            self.microstep + 1
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScheduledAction;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_microsteps_count_zero_delay_chains_and_reset_with_time() {
+        let stamps = Rc::new(RefCell::new(Vec::new()));
+        let record = stamps.clone();
+        let mut scheduler = EventScheduler::builder()
+            .superdense_time(true)
+            .hook(Box::new(move |s: &EventScheduler, _: &ScheduledAction, _: &Option<String>| record.borrow_mut().push(s.superdense_now())))
+            .build();
+        scheduler.schedule(ScheduledAction::at(2.0).with_action(|s| {
+            s.schedule(ScheduledAction::at(2.0).with_action(|s| {
+                s.timeout(1.0, None, None);
+                s.timeout(0.0, None, None);
+                None
+            }));
+            None
+        }));
+        scheduler.run_until_max_time(5.0);
+
+        let stamps: Vec<(f64, u64)> = stamps.borrow().iter().map(|t| (t.time, t.microstep)).collect();
+        assert_eq!(stamps, [(2.0, 0), (2.0, 1), (2.0, 2), (3.0, 0)]);
+        assert!(SuperdenseTime { time: 2.0, microstep: 5 } < SuperdenseTime { time: 3.0, microstep: 0 });
+    }
+}
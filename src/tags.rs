@@ -0,0 +1,97 @@
+//! # Event Tags
+//!
+//! Events can carry any number of tags, such as `"arrival"` or `"priority-customer"`. The
+//! scheduler counts the executed events carrying each tag in its [`TagMetrics`], from which
+//! per-tag throughput follows at any point during or after a run.
+
+use crate::csv::csv_field;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Counts of executed events per tag.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduledAction};
+///
+/// let mut scheduler = EventScheduler::new();
+/// for t in 1..=4 {
+///     scheduler.schedule(ScheduledAction::at(t as f64).with_tag("arrival"));
+/// }
+/// scheduler.schedule(ScheduledAction::at(2.0).with_tag("arrival").with_tag("vip"));
+/// scheduler.run_until_max_time(10.0);
+///
+/// assert_eq!(scheduler.tag_metrics.count("arrival"), 5);
+/// assert_eq!(scheduler.tag_metrics.count("vip"), 1);
+/// assert_eq!(scheduler.tag_metrics.rate("arrival", 10.0), 0.5);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagMetrics {
+    start: f64,
+    counts: BTreeMap<String, u64>,
+}
+
+impl TagMetrics {
+    /// Creates empty metrics with rates measured from `start`.
+    pub fn new(start: f64) -> Self {
+        TagMetrics { start, counts: BTreeMap::new() }
+    }
+
+    /// Counts one executed event carrying `tag`.
+    pub fn record(&mut self, tag: &str) {
+        match self.counts.get_mut(tag) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(tag.to_string(), 1);
+            }
+        }
+    }
+
+    /// Returns the number of executed events carrying `tag`.
+    pub fn count(&self, tag: &str) -> u64 {
+        self.counts.get(tag).copied().unwrap_or(0)
+    }
+
+    /// Returns the executed events carrying `tag` per unit of simulated time up to `now`, or
+    /// `0.0` if no time has elapsed.
+    pub fn rate(&self, tag: &str, now: f64) -> f64 {
+        if now > self.start {
+            self.count(tag) as f64 / (now - self.start)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns every tag seen with its count, sorted by tag.
+    pub fn counts(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.counts.iter().map(|(tag, &count)| (tag.as_str(), count))
+    }
+
+    /// Writes the metrics as CSV with columns `tag,count,rate`, with rates measured up to `now`.
+    pub fn write_csv<W: Write>(&self, mut writer: W, now: f64) -> io::Result<()> {
+        writeln!(writer, "tag,count,rate")?;
+        for (tag, count) in self.counts() {
+            writeln!(writer, "{},{},{}", csv_field(tag), count, self.rate(tag, now))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates_measured_from_start() {
+        let mut metrics = TagMetrics::new(10.0);
+        metrics.record("done");
+        metrics.record("done");
+        assert_eq!(metrics.rate("done", 10.0), 0.0);
+        assert_eq!(metrics.rate("done", 14.0), 0.5);
+        assert_eq!(metrics.count("missing"), 0);
+
+        let mut out = Vec::new();
+        metrics.write_csv(&mut out, 14.0).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "tag,count,rate\ndone,2,0.5\n");
+    }
+}
@@ -0,0 +1,223 @@
+//! # Property Testing
+//!
+//! Helpers for testing models against many generated scenarios rather than a few hand-written
+//! ones:
+//!
+//! - [`ScheduleSpec`]: A plain description of an event schedule that can be generated at
+//!   random, replayed into a scheduler, and shrunk.
+//! - [`check_property`]: Runs a property over generated [`Scenario`]s and, on failure, shrinks
+//!   the failing scenario to a small counterexample.
+//! - [`invariant_hook`]: A hook that checks an invariant after every event and panics with the
+//!   offending event when it breaks.
+//!
+//! The helpers do their own generation and shrinking and have no dependencies; in particular
+//! the crate has no `proptest` feature and provides no `Arbitrary` implementations or
+//! strategies. Scenario types are ordinary structs with public fields, so a strategy written
+//! downstream can build them.
+
+use crate::{EventHook, EventScheduler, ScheduledAction, SimRng};
+use std::fmt;
+
+/// A scenario description that can be simplified while searching for a counterexample.
+pub trait Scenario: Clone + fmt::Debug {
+    /// Returns simpler variants of the scenario, simplest first.
+    fn shrink(&self) -> Vec<Self>;
+}
+
+/// One event of a [`ScheduleSpec`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventSpec {
+    pub time: f64,
+    pub priority: i64,
+    pub label: String,
+}
+
+/// A generated schedule of labeled events with no actions.
+///
+/// # Example
+/// ```
+/// use desru::{EventScheduler, ScheduleSpec, SimRng};
+///
+/// let spec = ScheduleSpec::random(&mut SimRng::new(3), 20, 100.0, &["arrival", "departure"]);
+/// let mut scheduler = EventScheduler::new();
+/// spec.schedule_into(&mut scheduler);
+/// assert_eq!(scheduler.run_until_max_time(f64::INFINITY).len(), spec.events.len());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScheduleSpec {
+    pub events: Vec<EventSpec>,
+}
+
+impl ScheduleSpec {
+    /// Generates up to `max_events` events at uniform times in `[0, horizon)`, with priorities
+    /// in `-2..=2` and labels drawn from `labels`.
+    ///
+    /// # Panics
+    /// Panics if `labels` is empty.
+    pub fn random(rng: &mut SimRng, max_events: usize, horizon: f64, labels: &[&str]) -> Self {
+        assert!(!labels.is_empty(), "labels must not be empty");
+        let count = rng.gen_index(max_events + 1);
+        let events = (0..count)
+            .map(|_| EventSpec {
+                time: rng.gen_range(0.0, horizon),
+                priority: rng.gen_index(5) as i64 - 2,
+                label: labels[rng.gen_index(labels.len())].to_string(),
+            })
+            .collect();
+        ScheduleSpec { events }
+    }
+
+    /// Schedules every event of the spec as a labeled no-op event.
+    pub fn schedule_into(&self, scheduler: &mut EventScheduler) {
+        for spec in &self.events {
+            scheduler.schedule(ScheduledAction::at(spec.time).with_priority(spec.priority).with_label(spec.label.clone()));
+        }
+    }
+}
+
+impl Scenario for ScheduleSpec {
+    /// Tries dropping half the events, then each single event, then rounding times down and
+    /// clearing priorities.
+    fn shrink(&self) -> Vec<Self> {
+        let mut candidates = Vec::new();
+        let n = self.events.len();
+        if n > 1 {
+            candidates.push(ScheduleSpec { events: self.events[..n / 2].to_vec() });
+            candidates.push(ScheduleSpec { events: self.events[n / 2..].to_vec() });
+        }
+        for i in 0..n {
+            let mut events = self.events.clone();
+            events.remove(i);
+            candidates.push(ScheduleSpec { events });
+        }
+        let simplified: Vec<_> =
+            self.events.iter().map(|e| EventSpec { time: e.time.floor(), priority: 0, label: e.label.clone() }).collect();
+        if simplified != self.events {
+            candidates.push(ScheduleSpec { events: simplified });
+        }
+        candidates
+    }
+}
+
+/// A scenario that falsified a property, see [`check_property`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample<S> {
+    /// The seed of the generator that produced the original failing scenario.
+    pub seed: u64,
+    /// The smallest failing scenario found.
+    pub scenario: S,
+    /// How many shrinking steps led from the original scenario to `scenario`.
+    pub shrinks: usize,
+}
+
+/// The most shrinking steps [`check_property`] takes before reporting a counterexample.
+const MAX_SHRINKS: usize = 1000;
+
+/// Checks `property` against `cases` scenarios produced by `generate`.
+///
+/// Case `i` is generated from `SimRng::new(seed + i)`, so a failure is reproducible from its
+/// seed alone. The first failing scenario is shrunk by repeatedly taking the first
+/// [`Scenario::shrink`] candidate that still fails.
+///
+/// # Errors
+/// Returns the shrunk [`Counterexample`] if the property fails for some case.
+///
+/// # Example
+/// ```
+/// use desru::{check_property, EventScheduler, ScheduleSpec};
+///
+/// // Claim: no schedule ever runs two "departure" events in a row. False, and the
+/// // counterexample shrinks to exactly two departures.
+/// let failure = check_property(100, 1, |rng| ScheduleSpec::random(rng, 10, 50.0, &["arrival", "departure"]), |spec| {
+///     let mut scheduler = EventScheduler::new();
+///     spec.schedule_into(&mut scheduler);
+///     let log = scheduler.run_until_max_time(f64::INFINITY);
+///     !log.windows(2).any(|w| w.iter().all(|r| r.label.as_deref() == Some("departure")))
+/// })
+/// .unwrap_err();
+/// assert_eq!(failure.scenario.events.len(), 2);
+/// ```
+pub fn check_property<S, G, P>(cases: usize, seed: u64, mut generate: G, mut property: P) -> Result<(), Counterexample<S>>
+where
+    S: Scenario,
+    G: FnMut(&mut SimRng) -> S,
+    P: FnMut(&S) -> bool,
+{
+    for case in 0..cases as u64 {
+        let case_seed = seed.wrapping_add(case);
+        let mut scenario = generate(&mut SimRng::new(case_seed));
+        if property(&scenario) {
+            continue;
+        }
+        let mut shrinks = 0;
+        while shrinks < MAX_SHRINKS {
+            match scenario.shrink().into_iter().find(|candidate| !property(candidate)) {
+                Some(smaller) => {
+                    scenario = smaller;
+                    shrinks += 1;
+                }
+                None => break,
+            }
+        }
+        return Err(Counterexample { seed: case_seed, scenario, shrinks });
+    }
+    Ok(())
+}
+
+/// Returns a hook that checks `invariant` after every event.
+///
+/// # Panics
+/// The hook panics, naming the invariant and the event that broke it, the first time
+/// `invariant` returns `false`.
+///
+/// # Example
+/// ```should_panic
+/// use desru::{invariant_hook, EventScheduler};
+///
+/// let mut scheduler = EventScheduler::builder()
+///     .state(0_i32)
+///     .hook(invariant_hook("queue length never negative", |s| *s.state::<i32>() >= 0))
+///     .build();
+/// scheduler.timeout(1.0, Some(Box::new(|s| {
+///     *s.state_mut::<i32>() -= 1;
+///     None
+/// })), None);
+/// scheduler.run_until_max_time(10.0);
+/// ```
+pub fn invariant_hook<F>(name: impl Into<String>, invariant: F) -> EventHook
+where
+    F: Fn(&EventScheduler) -> bool + 'static,
+{
+    let name = name.into();
+    Box::new(move |scheduler: &EventScheduler, event: &ScheduledAction, _: &Option<String>| {
+        if !invariant(scheduler) {
+            panic!("invariant `{}` violated after event {:?} at time {}", name, event.label, scheduler.current_time);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passing_property_and_shrink_candidates() {
+        let ordered = check_property(50, 9, |rng| ScheduleSpec::random(rng, 15, 10.0, &["a"]), |spec| {
+            let mut scheduler = EventScheduler::new();
+            spec.schedule_into(&mut scheduler);
+            let log = scheduler.run_until_max_time(f64::INFINITY);
+            log.windows(2).all(|w| w[0].time <= w[1].time)
+        });
+        assert_eq!(ordered, Ok(()));
+
+        let spec = ScheduleSpec {
+            events: vec![
+                EventSpec { time: 1.5, priority: 1, label: "a".to_string() },
+                EventSpec { time: 2.0, priority: 0, label: "b".to_string() },
+            ],
+        };
+        let candidates = spec.shrink();
+        assert_eq!(candidates.len(), 5);
+        assert_eq!(candidates.last().unwrap().events[0], EventSpec { time: 1.0, priority: 0, label: "a".to_string() });
+    }
+}
@@ -0,0 +1,206 @@
+//! # Simultaneous Events
+//!
+//! Some models have rules for events that happen at the same time: arrivals at an
+//! intersection resolved by a right-of-way rule, or bids at one instant settled together.
+//! [`EventScheduler::pop_simultaneous`] takes every event at the next event time off the
+//! queue as a batch, and [`EventScheduler::run_timestep_with`] lets the model reorder, drop, or
+//! merge the batch before running it. Times are compared with the scheduler's
+//! [`crate::TimeComparison`].
+
+use crate::{EventScheduler, LogPolicy, RunResult, ScheduledAction, SimError, StopReason};
+use std::time::Instant;
+
+impl EventScheduler {
+    /// Removes and returns every pending event at the next event time, in the order they
+    /// would run, including the order a [`crate::ClassPolicy`] sets.
+    ///
+    /// # Returns
+    /// The batch, empty if no events are pending. The clock is not moved.
+    ///
+    /// # Panics
+    /// Panics if an event was scheduled at a NaN time; use
+    /// [`EventScheduler::try_pop_simultaneous`] to handle this as an error instead.
+    pub fn pop_simultaneous(&mut self) -> Vec<ScheduledAction> {
+        self.try_pop_simultaneous().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Removes and returns every pending event at the next event time, in the order they
+    /// would run, returning an error instead of panicking when the run cannot continue.
+    ///
+    /// # Errors
+    /// Returns [`SimError::InvalidEventTime`] if an event was scheduled at a NaN time.
+    pub fn try_pop_simultaneous(&mut self) -> Result<Vec<ScheduledAction>, SimError> {
+        if let Some(error) = self.invalid_event.take() {
+            return Err(error);
+        }
+        let next_time = loop {
+            let Some(next_time) = self.event_queue.peek().map(|e| e.time) else {
+                return Ok(Vec::new());
+            };
+            if !self.advance_continuous(next_time) {
+                break next_time;
+            }
+        };
+        if !self.time_comparison.same(next_time, self.current_time) {
+            self.events_at_time = 0;
+        }
+        let mut batch = Vec::new();
+        while self.event_queue.peek().is_some_and(|event| self.time_comparison.same(event.time, next_time)) {
+            batch.extend(self.pop_next_event());
+        }
+        Ok(batch)
+    }
+
+    /// Runs every event at the next event time, logging them as a run would.
+    ///
+    /// Events that the batch schedules for the same time run in the next timestep.
+    ///
+    /// # Returns
+    /// The number of events run.
+    ///
+    /// # Panics
+    /// Panics if an event was scheduled at a NaN time.
+    pub fn run_one_timestep(&mut self) -> usize {
+        self.run_timestep_with(|_| {})
+    }
+
+    /// Takes the events at the next event time off the queue, passes them to `arrange`, and
+    /// runs what it leaves in the order it leaves them. Events that `arrange` removes are
+    /// discarded without running.
+    ///
+    /// # Returns
+    /// The number of events run.
+    ///
+    /// # Panics
+    /// Panics if an event was scheduled at a NaN time.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, ScheduledAction};
+    ///
+    /// let mut scheduler = EventScheduler::new();
+    /// for (bidder, bid) in [("ann", 3), ("bo", 9), ("cy", 5)] {
+    ///     scheduler.schedule(ScheduledAction::at(1.0).with_label(bidder).with_priority(bid).with_action(move |_| Some(format!("{} bids {}", bidder, bid))));
+    /// }
+    /// // Settle the auction: only the highest bid at an instant counts.
+    /// let ran = scheduler.run_timestep_with(|bids| {
+    ///     bids.sort_by_key(|bid| std::cmp::Reverse(bid.priority));
+    ///     bids.truncate(1);
+    /// });
+    /// assert_eq!(ran, 1);
+    /// assert_eq!(scheduler.event_log[0].result.as_deref(), Some("bo bids 9"));
+    /// assert!(scheduler.event_queue.is_empty());
+    /// ```
+    pub fn run_timestep_with<F>(&mut self, arrange: F) -> usize
+    where
+        F: FnOnce(&mut Vec<ScheduledAction>),
+    {
+        let mut batch = self.pop_simultaneous();
+        arrange(&mut batch);
+        let ran = batch.len();
+        for event in batch {
+            let (event, result) = self.execute(event);
+            self.log_or_recycle(event, result, &LogPolicy::Full);
+        }
+        ran
+    }
+
+    /// Runs timestep by timestep until the next event would occur at or after `max_time`,
+    /// moving the clock to each batch and passing it to `run_batch` to run.
+    ///
+    /// The run stops, and reports why, in the same ways as [`EventScheduler::try_run`]. When a
+    /// batch would exceed `max_events_per_time`, its events are put back on the queue.
+    pub(crate) fn try_run_timesteps<F>(&mut self, max_time: f64, mut run_batch: F) -> Result<RunResult<'_>, SimError>
+    where
+        F: FnMut(&mut EventScheduler, Vec<ScheduledAction>),
+    {
+        let stop = crate::stop_at_max_time_factory(max_time);
+        let started = Instant::now();
+        self.pause_requested = false;
+        let result = loop {
+            if stop(self) {
+                break Ok(StopReason::Condition);
+            }
+            let batch = match self.try_pop_simultaneous() {
+                Ok(batch) if batch.is_empty() => break Ok(StopReason::QueueEmpty),
+                Ok(batch) => batch,
+                Err(error) => break Err(error),
+            };
+            if let Some(limit) = self.max_events_per_time.filter(|limit| self.events_at_time + batch.len() > *limit) {
+                let time = batch[0].time;
+                for event in batch {
+                    self.event_queue.restore(event);
+                }
+                break Err(SimError::ZeroDelayCascade { time, limit });
+            }
+            self.current_time = batch[0].time;
+            run_batch(self, batch);
+            if std::mem::take(&mut self.pause_requested) {
+                break Ok(StopReason::Paused);
+            }
+            if self.wall_clock_budget.is_some_and(|budget| started.elapsed() >= budget) {
+                break Ok(StopReason::WallClockBudget);
+            }
+        };
+        self.counters.wall_time += started.elapsed();
+        match result {
+            Ok(reason) => {
+                self.stop_reason = Some(reason.clone());
+                Ok(RunResult::new(self, reason))
+            }
+            Err(error) => {
+                self.stop_reason = Some(StopReason::Error(error.clone()));
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClassPolicy;
+
+    #[test]
+    fn test_timesteps_leave_later_and_newly_scheduled_events() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(2.0).with_label("a").with_action(|s| {
+            s.schedule(ScheduledAction::at(2.0).with_label("echo"));
+            None
+        }));
+        scheduler.schedule(ScheduledAction::at(2.0).with_label("b"));
+        scheduler.schedule(ScheduledAction::at(3.0).with_label("c"));
+
+        assert_eq!(scheduler.run_one_timestep(), 2);
+        assert_eq!(scheduler.current_time, 2.0);
+        assert_eq!(scheduler.run_one_timestep(), 1);
+        assert_eq!(scheduler.event_log[2].label.as_deref(), Some("echo"));
+        assert_eq!(scheduler.run_one_timestep(), 1);
+        assert_eq!(scheduler.current_time, 3.0);
+        assert_eq!(scheduler.run_one_timestep(), 0);
+        assert!(scheduler.pop_simultaneous().is_empty());
+    }
+
+    #[test]
+    fn test_nan_times_fail_the_timestep() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.schedule(ScheduledAction::at(1.0));
+        scheduler.schedule(ScheduledAction::at(f64::NAN).with_label("broken"));
+        let error = scheduler.try_pop_simultaneous().unwrap_err();
+        assert!(matches!(error, SimError::InvalidEventTime { label: Some(label), .. } if label == "broken"));
+        assert_eq!(scheduler.try_pop_simultaneous().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_batches_follow_the_class_policy() {
+        let mut scheduler = EventScheduler::new();
+        scheduler.set_class_policy(ClassPolicy::StrictPriority(vec!["urgent".to_string()]));
+        scheduler.schedule_in_class("routine", ScheduledAction::at(1.0).with_label("routine"));
+        let urgent = scheduler.schedule_in_class("urgent", ScheduledAction::at(1.0).with_label("urgent"));
+        let batch = scheduler.pop_simultaneous();
+        let labels: Vec<_> = batch.iter().map(|event| event.label.as_deref().unwrap()).collect();
+        assert_eq!(labels, ["urgent", "routine"]);
+        assert_eq!(scheduler.class_of(urgent), None);
+        assert_eq!(scheduler.served_by_class(), [("routine".to_string(), 1), ("urgent".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,422 @@
+//! # Optimistic Parallel Execution
+//!
+//! A [`Federation`](crate::Federation) is conservative: a member only advances as far as the
+//! others' lookahead guarantees is safe, so a model whose parts can affect each other almost
+//! immediately advances in tiny steps. [`TimeWarp`] is an experimental optimistic engine
+//! (Jefferson's Time Warp) for models partitioned into [`LogicalProcess`]es that interact
+//! only by timestamped messages. Each process handles its messages as fast as it can without
+//! waiting for the others. When a message arrives in a process's past, a straggler, the
+//! process rolls back: its state is restored from the copy saved before the first event later
+//! than the straggler, those events are handled again, and every message they sent is
+//! cancelled by an anti-message, which annihilates the message or rolls back its receiver in
+//! turn.
+//!
+//! Execution proceeds in rounds. In a round, each process handles up to a batch of messages,
+//! in parallel with [`TimeWarp::run_until_parallel`] under the `rayon` feature; messages sent
+//! in a round are delivered at the start of the next. Global virtual time (GVT), the earliest
+//! time of any unhandled or undelivered message, can never be rolled back past, so state
+//! copies older than it are discarded and the events before it are committed.
+//!
+//! Messages at the same time are handled in an order fixed by the messages that caused them,
+//! and after the message that sent them, so the outcome is the same as a sequential run whatever the batch size or thread count.
+
+use crate::rng::splitmix64;
+use crate::Context;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// A timestamped message to a logical process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarpMessage {
+    pub time: f64,
+    /// The index of the receiving process.
+    pub to: usize,
+    pub name: String,
+    pub context: Context,
+}
+
+/// A part of a [`TimeWarp`] model, whose state is copied before every message it handles so it
+/// can be rolled back.
+pub trait LogicalProcess: Clone {
+    /// Handles a message, sending any messages it causes through `outbox`.
+    fn handle(&mut self, message: &WarpMessage, outbox: &mut WarpOutbox);
+}
+
+/// Collects the messages sent while a logical process handles a message.
+#[derive(Debug)]
+pub struct WarpOutbox {
+    now: f64,
+    sent: Vec<WarpMessage>,
+}
+
+impl WarpOutbox {
+    /// Returns the time of the message being handled.
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// Sends a message to process `to` at `time`.
+    ///
+    /// # Panics
+    /// Panics if `time` is earlier than the message being handled.
+    pub fn send(&mut self, time: f64, to: usize, name: impl Into<String>, context: Context) {
+        assert!(time >= self.now, "message at time {} is earlier than the current time {}", time, self.now);
+        self.sent.push(WarpMessage { time, to, name: name.into(), context });
+    }
+}
+
+/// Counters describing a [`TimeWarp`] run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWarpStats {
+    /// Messages handled, including those later rolled back.
+    pub processed: u64,
+    /// Handled messages undone by rollbacks.
+    pub rolled_back: u64,
+    /// Anti-messages sent to cancel messages from rolled-back events.
+    pub anti_messages: u64,
+    /// Handled messages that can no longer be rolled back.
+    pub committed: u64,
+    pub rounds: u64,
+}
+
+/// The order of messages: by time, then after the message that caused them if at the same
+/// time, then by an id derived from the message that caused them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Key {
+    time: f64,
+    step: u64,
+    id: u64,
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time.total_cmp(&other.time).then(self.step.cmp(&other.step)).then(self.id.cmp(&other.id))
+    }
+}
+
+/// Returns the key of the `index`-th message sent at `time` while handling message `parent`,
+/// which is the same each time the parent is handled again.
+fn child_key(parent: Key, index: usize, time: f64) -> Key {
+    let step = if time == parent.time { parent.step + 1 } else { 0 };
+    let mut state = parent.id ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    Key { time, step, id: splitmix64(&mut state) }
+}
+
+/// A handled message, kept until it is committed.
+#[derive(Debug)]
+struct Processed<P> {
+    key: Key,
+    message: WarpMessage,
+    /// The process's state before it handled the message.
+    before: P,
+    /// The receivers and keys of the messages it sent.
+    sent: Vec<(usize, Key)>,
+}
+
+#[derive(Debug)]
+enum Transit {
+    Positive(Key, Box<WarpMessage>),
+    Anti(usize, Key),
+}
+
+#[derive(Debug)]
+struct Lp<P> {
+    process: P,
+    pending: BTreeMap<Key, WarpMessage>,
+    processed: Vec<Processed<P>>,
+}
+
+impl<P: LogicalProcess> Lp<P> {
+    /// Handles up to `batch` pending messages earlier than `horizon`, returning the messages
+    /// sent and the number handled.
+    fn advance(&mut self, batch: usize, horizon: f64) -> (Vec<Transit>, u64) {
+        let mut outgoing = Vec::new();
+        let mut handled = 0;
+        while handled < batch as u64 {
+            let Some(entry) = self.pending.first_entry().filter(|entry| entry.key().time < horizon) else {
+                break;
+            };
+            let (key, message) = entry.remove_entry();
+            let before = self.process.clone();
+            let mut outbox = WarpOutbox { now: key.time, sent: Vec::new() };
+            self.process.handle(&message, &mut outbox);
+            let mut sent = Vec::new();
+            for (index, out) in outbox.sent.into_iter().enumerate() {
+                let out_key = child_key(key, index, out.time);
+                sent.push((out.to, out_key));
+                outgoing.push(Transit::Positive(out_key, Box::new(out)));
+            }
+            self.processed.push(Processed { key, message, before, sent });
+            handled += 1;
+        }
+        (outgoing, handled)
+    }
+}
+
+/// An optimistic engine running logical processes that exchange messages.
+///
+/// # Example
+/// ```
+/// use desru::{Context, LogicalProcess, TimeWarp, WarpMessage, WarpOutbox};
+///
+/// // Two players pass a ball back and forth, each holding it for a second.
+/// #[derive(Clone)]
+/// struct Player { touches: u32 }
+///
+/// impl LogicalProcess for Player {
+///     fn handle(&mut self, message: &WarpMessage, outbox: &mut WarpOutbox) {
+///         self.touches += 1;
+///         outbox.send(message.time + 1.0, 1 - message.to, "ball", Context::default());
+///     }
+/// }
+///
+/// let mut engine = TimeWarp::new().batch(4);
+/// engine.add(Player { touches: 0 });
+/// engine.add(Player { touches: 0 });
+/// engine.send(0.0, 0, "ball", Context::default());
+/// let stats = engine.run_until(10.0);
+/// assert_eq!((engine.process(0).touches, engine.process(1).touches), (5, 5));
+/// assert_eq!(stats.committed, 10);
+/// ```
+#[derive(Debug)]
+pub struct TimeWarp<P> {
+    lps: Vec<Lp<P>>,
+    transit: Vec<Transit>,
+    batch: usize,
+    next_id: u64,
+    gvt: f64,
+    stats: TimeWarpStats,
+}
+
+impl<P: LogicalProcess> TimeWarp<P> {
+    /// Creates an engine with no processes, handling up to 16 messages per process per round.
+    pub fn new() -> Self {
+        TimeWarp { lps: Vec::new(), transit: Vec::new(), batch: 16, next_id: 0, gvt: 0.0, stats: TimeWarpStats::default() }
+    }
+
+    /// Sets how many messages each process may handle per round. Larger batches let processes
+    /// run further ahead of each other, at the risk of longer rollbacks.
+    ///
+    /// # Panics
+    /// Panics if `batch` is zero.
+    pub fn batch(mut self, batch: usize) -> Self {
+        assert!(batch > 0, "each process must handle at least one message per round");
+        self.batch = batch;
+        self
+    }
+
+    /// Adds a process, returning its index.
+    pub fn add(&mut self, process: P) -> usize {
+        self.lps.push(Lp { process, pending: BTreeMap::new(), processed: Vec::new() });
+        self.lps.len() - 1
+    }
+
+    /// Sends a message from outside the model, such as the first event of a process.
+    ///
+    /// # Panics
+    /// Panics if `time` is earlier than the global virtual time.
+    pub fn send(&mut self, time: f64, to: usize, name: impl Into<String>, context: Context) {
+        assert!(time >= self.gvt, "message at time {} is earlier than the global virtual time {}", time, self.gvt);
+        self.next_id += 1;
+        let mut state = u64::MAX ^ self.next_id;
+        let key = Key { time, step: 0, id: splitmix64(&mut state) };
+        self.transit.push(Transit::Positive(key, Box::new(WarpMessage { time, to, name: name.into(), context })));
+    }
+
+    /// Returns the process with index `index`.
+    pub fn process(&self, index: usize) -> &P {
+        &self.lps[index].process
+    }
+
+    /// Returns the global virtual time reached.
+    pub fn gvt(&self) -> f64 {
+        self.gvt
+    }
+
+    /// Returns the counters accumulated over every run so far.
+    pub fn stats(&self) -> TimeWarpStats {
+        self.stats
+    }
+
+    /// Runs until every message earlier than `horizon` has been handled and committed.
+    ///
+    /// # Panics
+    /// Panics if a message is sent to a process that does not exist.
+    pub fn run_until(&mut self, horizon: f64) -> TimeWarpStats {
+        let batch = self.batch;
+        self.run_rounds(horizon, |lps| lps.iter_mut().map(|lp| lp.advance(batch, horizon)).collect())
+    }
+
+    /// Runs as [`TimeWarp::run_until`] does, handling each round's batches in parallel.
+    /// Requires the `rayon` feature.
+    ///
+    /// The outcome is the same as with [`TimeWarp::run_until`].
+    #[cfg(feature = "rayon")]
+    pub fn run_until_parallel(&mut self, horizon: f64) -> TimeWarpStats
+    where
+        P: Send,
+    {
+        use rayon::prelude::*;
+
+        let batch = self.batch;
+        self.run_rounds(horizon, |lps| lps.par_iter_mut().map(|lp| lp.advance(batch, horizon)).collect())
+    }
+
+    fn run_rounds<A>(&mut self, horizon: f64, advance: A) -> TimeWarpStats
+    where
+        A: Fn(&mut [Lp<P>]) -> Vec<(Vec<Transit>, u64)>,
+    {
+        loop {
+            self.deliver();
+            let gvt = self.lps.iter().filter_map(|lp| lp.pending.keys().next()).chain(self.transit.iter().map(|transit| match transit {
+                Transit::Positive(key, _) | Transit::Anti(_, key) => key,
+            }));
+            let gvt = gvt.map(|key| key.time).fold(f64::INFINITY, f64::min);
+            self.commit_before(gvt.min(horizon));
+            if gvt >= horizon && self.transit.is_empty() {
+                return self.stats;
+            }
+            for (outgoing, handled) in advance(&mut self.lps) {
+                self.transit.extend(outgoing);
+                self.stats.processed += handled;
+            }
+            self.stats.rounds += 1;
+        }
+    }
+
+    /// Delivers the messages sent in the last round, anti-messages first, rolling back the
+    /// receivers of stragglers.
+    fn deliver(&mut self) {
+        let mut transit = std::mem::take(&mut self.transit);
+        transit.sort_by_key(|transit| matches!(transit, Transit::Positive(..)));
+        for transit in transit {
+            match transit {
+                Transit::Positive(key, message) => {
+                    let to = message.to;
+                    assert!(to < self.lps.len(), "message {:?} sent to process {}, but there are only {}", message.name, to, self.lps.len());
+                    if self.lps[to].processed.last().is_some_and(|last| last.key > key) {
+                        self.rollback(to, key);
+                    }
+                    self.lps[to].pending.insert(key, *message);
+                }
+                Transit::Anti(to, key) => {
+                    if self.lps[to].pending.remove(&key).is_none() {
+                        self.rollback(to, key);
+                        self.lps[to].pending.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Undoes every message process `index` handled at or after `key`, cancelling what they sent.
+    fn rollback(&mut self, index: usize, key: Key) {
+        let lp = &mut self.lps[index];
+        while let Some(last) = lp.processed.pop() {
+            if last.key < key {
+                lp.processed.push(last);
+                break;
+            }
+            lp.process = last.before;
+            lp.pending.insert(last.key, last.message);
+            self.stats.rolled_back += 1;
+            for (to, sent) in last.sent {
+                self.stats.anti_messages += 1;
+                self.transit.push(Transit::Anti(to, sent));
+            }
+        }
+    }
+
+    /// Commits the handled messages earlier than `gvt`, discarding their state copies.
+    fn commit_before(&mut self, gvt: f64) {
+        self.gvt = self.gvt.max(gvt);
+        for lp in &mut self.lps {
+            let committed = lp.processed.partition_point(|processed| processed.key.time < gvt);
+            lp.processed.drain(..committed);
+            self.stats.committed += committed as u64;
+        }
+    }
+}
+
+impl<P: LogicalProcess> Default for TimeWarp<P> {
+    fn default() -> Self {
+        TimeWarp::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimRng;
+
+    /// A PHOLD-style process: every message is forwarded to a random process after a random
+    /// delay, which may be zero.
+    #[derive(Clone)]
+    struct Hold {
+        rng: SimRng,
+        processes: usize,
+        trace: Vec<(f64, String)>,
+    }
+
+    impl LogicalProcess for Hold {
+        fn handle(&mut self, message: &WarpMessage, outbox: &mut WarpOutbox) {
+            self.trace.push((message.time, message.name.clone()));
+            let delay = if self.rng.next_f64() < 0.2 { 0.0 } else { self.rng.next_f64() };
+            let to = self.rng.gen_index(self.processes);
+            outbox.send(message.time + delay, to, format!("{}.", message.name), Context::default());
+        }
+    }
+
+    fn engine(batch: usize) -> TimeWarp<Hold> {
+        let mut engine = TimeWarp::new().batch(batch);
+        for seed in 0..4 {
+            engine.add(Hold { rng: SimRng::new(seed), processes: 4, trace: Vec::new() });
+        }
+        for i in 0..8 {
+            engine.send(0.0, i % 4, i.to_string(), Context::default());
+        }
+        engine
+    }
+
+    #[test]
+    fn test_optimistic_runs_match_a_sequential_run() {
+        // The reference handles every message in key order, one at a time.
+        let mut sequential = engine(1);
+        let mut processes: Vec<Hold> = sequential.lps.iter().map(|lp| lp.process.clone()).collect();
+        let mut pending: BTreeMap<Key, WarpMessage> = BTreeMap::new();
+        for transit in std::mem::take(&mut sequential.transit) {
+            if let Transit::Positive(key, message) = transit {
+                pending.insert(key, *message);
+            }
+        }
+        while let Some((key, message)) = pending.pop_first().filter(|(key, _)| key.time < 5.0) {
+            let mut outbox = WarpOutbox { now: key.time, sent: Vec::new() };
+            processes[message.to].handle(&message, &mut outbox);
+            for (index, out) in outbox.sent.into_iter().enumerate() {
+                pending.insert(child_key(key, index, out.time), out);
+            }
+        }
+
+        let mut optimistic = engine(8);
+        let stats = optimistic.run_until(5.0);
+        assert!(stats.rolled_back > 0 && stats.anti_messages > 0);
+        assert_eq!(stats.committed, stats.processed - stats.rolled_back);
+        for (index, process) in processes.iter().enumerate() {
+            assert_eq!(optimistic.process(index).trace, process.trace);
+        }
+        #[cfg(feature = "rayon")]
+        {
+            let mut parallel = engine(8);
+            assert_eq!(parallel.run_until_parallel(5.0), stats);
+            assert_eq!(parallel.process(3).trace, processes[3].trace);
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! # Trace Data
+//!
+//! Readers for measured data, such as service durations exported from a ticketing system,
+//! that drive trace-driven simulations. [`read_column`] extracts one numeric column of a CSV
+//! file with a header row; the values can be resampled with [`crate::Empirical`] or smoothed
+//! with [`crate::KernelDensity`].
+//!
+//! A workload trace can also be replayed as it was recorded. [`read_csv_trace`] and
+//! [`read_json_lines_trace`] turn each record of a timestamped trace into a [`TraceRecord`]
+//! whose other fields become event context, and [`EventScheduler::replay`] schedules one
+//! event per record. Records are scheduled one at a time, as the previous one runs, so even a
+//! long trace adds a single event to the queue.
+
+use crate::{Context, EventScheduler, ScheduledAction};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+use std::rc::Rc;
+
+/// An error reading trace data.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TraceError {
+    /// The data could not be read.
+    Io(std::io::Error),
+    /// The header has no column with the requested name.
+    MissingColumn(String),
+    /// A record could not be parsed.
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(error) => write!(f, "cannot read trace: {}", error),
+            TraceError::MissingColumn(column) => write!(f, "trace has no column `{}`", column),
+            TraceError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for TraceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TraceError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the numeric column named `column` from CSV data with a header row.
+///
+/// Blank lines and records with an empty field in the column are skipped.
+///
+/// # Errors
+/// Returns [`TraceError::MissingColumn`] if the header has no such column, and
+/// [`TraceError::Syntax`] for a field that is not a number.
+///
+/// # Example
+/// ```
+/// use desru::{read_column, Distribution, Empirical};
+///
+/// let csv = "ticket,minutes\n1,12.5\n2,\n3,7.5\n";
+/// let durations = read_column(csv.as_bytes(), "minutes").unwrap();
+/// assert_eq!(durations, [12.5, 7.5]);
+/// assert_eq!(Empirical::new(durations).mean(), 10.0);
+/// ```
+pub fn read_column(reader: impl BufRead, column: &str) -> Result<Vec<f64>, TraceError> {
+    let mut lines = reader.lines();
+    let header = match lines.next() {
+        Some(line) => crate::csv::split_csv_line(&line.map_err(TraceError::Io)?),
+        None => return Err(TraceError::MissingColumn(column.to_string())),
+    };
+    let index = header.iter().position(|name| name.trim() == column).ok_or_else(|| TraceError::MissingColumn(column.to_string()))?;
+    let mut values = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line.map_err(TraceError::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = crate::csv::split_csv_line(&line);
+        let field = fields.get(index).map_or("", |field| field.trim());
+        if field.is_empty() {
+            continue;
+        }
+        let value = field.parse().map_err(|_| TraceError::Syntax { line: number + 2, message: format!("`{}` is not a number", field) })?;
+        values.push(value);
+    }
+    Ok(values)
+}
+
+/// Reads the numeric column named `column` from a CSV file.
+///
+/// # Errors
+/// Returns [`TraceError::Io`] if the file cannot be opened, and otherwise as for
+/// [`read_column`].
+pub fn load_column(path: impl AsRef<Path>, column: &str) -> Result<Vec<f64>, TraceError> {
+    let file = std::fs::File::open(path).map_err(TraceError::Io)?;
+    read_column(std::io::BufReader::new(file), column)
+}
+
+/// One record of a timestamped trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceRecord {
+    pub time: f64,
+    /// The record's other fields. Empty fields are left out.
+    pub context: Context,
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.time)?;
+        if !self.context.is_empty() {
+            write!(f, " ({})", crate::context::display_pairs(&self.context))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a trace from CSV data with a header row, taking each record's time from the column
+/// named `time_column`.
+///
+/// # Errors
+/// Returns [`TraceError::MissingColumn`] if the header has no such column, and
+/// [`TraceError::Syntax`] for a record whose time is missing or not a number.
+///
+/// # Example
+/// ```
+/// use desru::read_csv_trace;
+///
+/// let csv = "arrived,customer,items\n0.5,ann,3\n2.0,bo,\n";
+/// let trace = read_csv_trace(csv.as_bytes(), "arrived").unwrap();
+/// assert_eq!(trace[1].time, 2.0);
+/// assert_eq!(trace[0].context["items"], "3");
+/// assert!(!trace[1].context.contains_key("items"));
+/// ```
+pub fn read_csv_trace(reader: impl BufRead, time_column: &str) -> Result<Vec<TraceRecord>, TraceError> {
+    let mut lines = reader.lines();
+    let header = match lines.next() {
+        Some(line) => crate::csv::split_csv_line(&line.map_err(TraceError::Io)?),
+        None => return Err(TraceError::MissingColumn(time_column.to_string())),
+    };
+    let header: Vec<String> = header.iter().map(|name| name.trim().to_string()).collect();
+    let time_index = header.iter().position(|name| name == time_column).ok_or_else(|| TraceError::MissingColumn(time_column.to_string()))?;
+    let mut records = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line.map_err(TraceError::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let syntax = |message: String| TraceError::Syntax { line: number + 2, message };
+        let fields = crate::csv::split_csv_line(&line);
+        let time = fields.get(time_index).map_or("", |field| field.trim());
+        let time = time.parse().map_err(|_| syntax(format!("`{}` is not a time", time)))?;
+        let mut context = Context::new();
+        for (name, field) in header.iter().zip(fields) {
+            if name != time_column && !field.is_empty() {
+                context.insert(name.clone(), field);
+            }
+        }
+        records.push(TraceRecord { time, context });
+    }
+    Ok(records)
+}
+
+/// Reads a trace in JSON lines, one flat object per line, taking each record's time from the
+/// field named `time_field`. Strings, numbers, and booleans become context values; `null`
+/// fields are left out.
+///
+/// # Errors
+/// Returns [`TraceError::Syntax`] for a line that is not a flat JSON object or whose time is
+/// missing or not a number.
+///
+/// # Example
+/// ```
+/// use desru::read_json_lines_trace;
+///
+/// let jsonl = r#"{"ts": 1.5, "path": "/checkout", "cached": false}"#;
+/// let trace = read_json_lines_trace(jsonl.as_bytes(), "ts").unwrap();
+/// assert_eq!(trace[0].time, 1.5);
+/// assert_eq!(trace[0].context["path"], "/checkout");
+/// assert_eq!(trace[0].context["cached"], "false");
+/// ```
+pub fn read_json_lines_trace(reader: impl BufRead, time_field: &str) -> Result<Vec<TraceRecord>, TraceError> {
+    let mut records = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(TraceError::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let syntax = |message: String| TraceError::Syntax { line: number + 1, message };
+        let mut context = Context::new();
+        let mut time = None;
+        for (key, value) in parse_flat_object(&line).map_err(syntax)? {
+            match value {
+                Some(value) if key == time_field => time = Some(value.parse().map_err(|_| syntax(format!("`{}` is not a time", value)))?),
+                Some(value) => {
+                    context.insert(key, value);
+                }
+                None => {}
+            }
+        }
+        let time = time.ok_or_else(|| syntax(format!("missing `{}`", time_field)))?;
+        records.push(TraceRecord { time, context });
+    }
+    Ok(records)
+}
+
+/// Parses a JSON object whose values are scalars, returning each value as text, or `None`
+/// for `null`.
+fn parse_flat_object(text: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut chars = text.trim().chars().peekable();
+    let mut pairs = Vec::new();
+    let skip_space = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    };
+    if chars.next() != Some('{') {
+        return Err("expected a JSON object".to_string());
+    }
+    skip_space(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_space(&mut chars);
+            if chars.next() != Some('"') {
+                return Err("expected a quoted key".to_string());
+            }
+            let key = parse_json_string(&mut chars)?;
+            skip_space(&mut chars);
+            if chars.next() != Some(':') {
+                return Err(format!("expected `:` after `{}`", key));
+            }
+            skip_space(&mut chars);
+            let value = match chars.peek() {
+                Some('"') => {
+                    chars.next();
+                    Some(parse_json_string(&mut chars)?)
+                }
+                Some('{' | '[') => return Err(format!("`{}` is not a scalar", key)),
+                _ => {
+                    let mut word = String::new();
+                    while chars.peek().is_some_and(|c| !matches!(c, ',' | '}') && !c.is_whitespace()) {
+                        word.push(chars.next().unwrap());
+                    }
+                    match word.as_str() {
+                        "null" => None,
+                        "true" | "false" => Some(word),
+                        _ if word.parse::<f64>().is_ok() => Some(word),
+                        _ => return Err(format!("`{}` has an invalid value `{}`", key, word)),
+                    }
+                }
+            };
+            pairs.push((key, value));
+            skip_space(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("expected `,` or `}`".to_string()),
+            }
+        }
+    }
+    skip_space(&mut chars);
+    match chars.next() {
+        None => Ok(pairs),
+        Some(_) => Err("unexpected text after the object".to_string()),
+    }
+}
+
+/// Parses the rest of a JSON string literal after its opening quote.
+fn parse_json_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(value),
+            Some('\\') => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('b') => value.push('\u{8}'),
+                Some('f') => value.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape `\\u{}`", hex))?;
+                    value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                Some(c @ ('"' | '\\' | '/')) => value.push(c),
+                _ => return Err("invalid escape".to_string()),
+            },
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+impl EventScheduler {
+    /// Replays a trace, running `action` at each record's time with the record's context.
+    ///
+    /// Each event is labeled `label` and carries the record's context, so the replayed
+    /// workload appears in logs and exports. Records are replayed in time order; records
+    /// before the current time are skipped.
+    ///
+    /// # Returns
+    /// The number of records that will be replayed.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{read_csv_trace, EventScheduler};
+    ///
+    /// let csv = "time,request\n3.0,b\n1.0,a\n";
+    /// let mut scheduler = EventScheduler::new();
+    /// let replayed = scheduler.replay("request", read_csv_trace(csv.as_bytes(), "time").unwrap(), |s, context| {
+    ///     Some(format!("{} at {}", context["request"], s.current_time))
+    /// });
+    /// assert_eq!(replayed, 2);
+    /// let log = scheduler.run_until_max_time(10.0);
+    /// assert_eq!(log[0].result.as_deref(), Some("a at 1"));
+    /// assert_eq!(log[1].context["request"], "b");
+    /// ```
+    pub fn replay<F>(&mut self, label: &str, mut records: Vec<TraceRecord>, action: F) -> usize
+    where
+        F: FnMut(&mut EventScheduler, &Context) -> Option<String> + 'static,
+    {
+        records.retain(|record| record.time >= self.current_time);
+        records.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let count = records.len();
+        let replay = Rc::new(Replay { label: label.to_string(), records: RefCell::new(records.into_iter()), action: RefCell::new(action) });
+        replay.schedule_next(self);
+        count
+    }
+}
+
+/// The records of a trace still to be replayed.
+struct Replay<F> {
+    label: String,
+    records: RefCell<std::vec::IntoIter<TraceRecord>>,
+    action: RefCell<F>,
+}
+
+impl<F> Replay<F>
+where
+    F: FnMut(&mut EventScheduler, &Context) -> Option<String> + 'static,
+{
+    fn schedule_next(self: Rc<Self>, scheduler: &mut EventScheduler) {
+        let Some(record) = self.records.borrow_mut().next() else {
+            return;
+        };
+        let context = record.context.clone();
+        let event = ScheduledAction::at(record.time).with_label(self.label.clone()).with_context(record.context).with_action(move |s| {
+            self.clone().schedule_next(s);
+            (self.action.borrow_mut())(s, &context)
+        });
+        scheduler.schedule(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted_fields_and_errors() {
+        let csv = "\"note, free text\",duration\n\"late, \"\"urgent\"\"\",3\n";
+        assert_eq!(read_column(csv.as_bytes(), "duration").unwrap(), [3.0]);
+        assert!(matches!(read_column(csv.as_bytes(), "wait"), Err(TraceError::MissingColumn(_))));
+        let bad = "duration\n1\nabc\n";
+        assert!(matches!(read_column(bad.as_bytes(), "duration"), Err(TraceError::Syntax { line: 3, .. })));
+        let jsonl = "{\"t\": 2, \"note\": \"say \\\"hi\\\"\", \"skip\": null}\n\n{\"t\": 1, \"nested\": {}}\n";
+        assert!(matches!(read_json_lines_trace(jsonl.as_bytes(), "t"), Err(TraceError::Syntax { line: 3, .. })));
+        let trace = read_json_lines_trace(jsonl.lines().next().unwrap().as_bytes(), "t").unwrap();
+        assert_eq!(trace[0].context["note"], "say \"hi\"");
+        assert_eq!(trace[0].context.len(), 1);
+    }
+}
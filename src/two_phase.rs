@@ -0,0 +1,192 @@
+//! # Two-Phase Events
+//!
+//! When several events at the same time read and write the same state, the outcome can
+//! depend on the order they happen to run in: two cars both see a free parking space, and
+//! whichever runs first takes it. A [`TwoPhaseEvent`] splits its work into a plan, made
+//! against a read-only view of the state, and a commit that applies it.
+//! [`EventScheduler::run_two_phase_until`] plans every two-phase event at a timestep before
+//! any of them commits, so they all see the state as it was at the start of the timestep and
+//! conflicts can be resolved in the commits.
+//!
+//! Outside [`EventScheduler::run_two_phase_until`], a two-phase event plans and commits in a
+//! single step when it runs.
+
+use crate::{EventId, EventScheduler, LogPolicy, RunResult, ScheduledAction, SimError};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// An event that plans against the state at the start of its timestep and commits afterwards.
+pub trait TwoPhaseEvent {
+    /// Reads the scheduler's state and records the change to make.
+    fn plan(&mut self, scheduler: &EventScheduler);
+
+    /// Applies the planned change.
+    ///
+    /// # Returns
+    /// The event's result, as for an action.
+    fn commit(&mut self, scheduler: &mut EventScheduler) -> Option<String>;
+}
+
+/// A scheduled two-phase event and whether it has planned.
+pub(crate) struct Planned {
+    event: Box<dyn TwoPhaseEvent>,
+    planned: bool,
+}
+
+/// The pending two-phase events, by event id.
+pub(crate) type TwoPhaseEvents = HashMap<u64, Rc<RefCell<Planned>>>;
+
+impl EventScheduler {
+    /// Schedules a two-phase event at `time`.
+    ///
+    /// # Returns
+    /// The id of the event, as for [`EventScheduler::schedule`].
+    pub fn schedule_two_phase(&mut self, time: f64, event: impl TwoPhaseEvent + 'static) -> EventId {
+        let state = Rc::new(RefCell::new(Planned { event: Box::new(event), planned: false }));
+        let own_id = Rc::new(Cell::new(EventId(0)));
+        let (id, shared) = (own_id.clone(), state.clone());
+        let event_id = self.schedule(ScheduledAction::at(time).with_action(move |s| {
+            s.two_phase.remove(&id.get().0);
+            let mut state = shared.borrow_mut();
+            if !std::mem::take(&mut state.planned) {
+                state.event.plan(s);
+            }
+            state.event.commit(s)
+        }));
+        if event_id == EventId(0) {
+            // The time was NaN, so the event was not queued and the run will fail.
+            return event_id;
+        }
+        own_id.set(event_id);
+        self.two_phase.insert(event_id.0, state);
+        event_id
+    }
+
+    /// Runs timestep by timestep until the next event would occur at or after `max_time`,
+    /// planning every two-phase event of a timestep before running the timestep's events.
+    ///
+    /// # Panics
+    /// Panics if `max_events_per_time` is exceeded or an event was scheduled at a NaN time; use
+    /// [`EventScheduler::try_run_two_phase_until`] to handle these as errors instead.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, TwoPhaseEvent};
+    ///
+    /// // Cars head for a space if they see one free when they arrive.
+    /// struct Park { saw_space: bool }
+    ///
+    /// impl TwoPhaseEvent for Park {
+    ///     fn plan(&mut self, s: &EventScheduler) {
+    ///         self.saw_space = *s.state::<u32>() > 0;
+    ///     }
+    ///     fn commit(&mut self, s: &mut EventScheduler) -> Option<String> {
+    ///         let spaces = s.state_mut::<u32>();
+    ///         let outcome = match (self.saw_space, *spaces > 0) {
+    ///             (true, true) => "parked",
+    ///             (true, false) => "lost the space",
+    ///             (false, _) => "drove on",
+    ///         };
+    ///         *spaces = spaces.saturating_sub(1);
+    ///         Some(outcome.to_string())
+    ///     }
+    /// }
+    ///
+    /// let mut scheduler = EventScheduler::builder().state(1_u32).build();
+    /// scheduler.schedule_two_phase(1.0, Park { saw_space: false });
+    /// scheduler.schedule_two_phase(1.0, Park { saw_space: false });
+    /// let log = scheduler.run_two_phase_until(10.0);
+    /// // Both cars saw the one free space, and the commits resolve the conflict.
+    /// assert_eq!(log[0].result.as_deref(), Some("parked"));
+    /// assert_eq!(log[1].result.as_deref(), Some("lost the space"));
+    /// ```
+    pub fn run_two_phase_until(&mut self, max_time: f64) -> RunResult<'_> {
+        self.try_run_two_phase_until(max_time).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Runs as [`EventScheduler::run_two_phase_until`] does, returning an error instead of
+    /// panicking when the run cannot continue.
+    ///
+    /// Like [`EventScheduler::try_run`], the run also stops when an action requests a pause
+    /// or the wall-clock budget runs out, after the timestep in which that happened.
+    ///
+    /// # Errors
+    /// Returns [`SimError::ZeroDelayCascade`] if `max_events_per_time` would be exceeded, and
+    /// [`SimError::InvalidEventTime`] if an event was scheduled at a NaN time.
+    pub fn try_run_two_phase_until(&mut self, max_time: f64) -> Result<RunResult<'_>, SimError> {
+        self.try_run_timesteps(max_time, |s, batch| {
+            for event in &batch {
+                if let Some(state) = s.two_phase.get(&event.seq).cloned() {
+                    let mut state = state.borrow_mut();
+                    state.event.plan(s);
+                    state.planned = true;
+                }
+            }
+            for event in batch {
+                let (event, result) = s.execute(event);
+                s.log_or_recycle(event, result, &LogPolicy::Full);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Swap {
+        from: usize,
+        seen: u32,
+    }
+
+    impl TwoPhaseEvent for Swap {
+        fn plan(&mut self, s: &EventScheduler) {
+            self.seen = s.state::<[u32; 2]>()[self.from];
+        }
+
+        fn commit(&mut self, s: &mut EventScheduler) -> Option<String> {
+            s.state_mut::<[u32; 2]>()[1 - self.from] = self.seen;
+            None
+        }
+    }
+
+    #[test]
+    fn test_simultaneous_swaps_see_the_same_state() {
+        let run = |two_phase: bool| {
+            let mut scheduler = EventScheduler::builder().state([1_u32, 2]).build();
+            scheduler.schedule_two_phase(1.0, Swap { from: 0, seen: 0 });
+            scheduler.schedule_two_phase(1.0, Swap { from: 1, seen: 0 });
+            if two_phase {
+                scheduler.run_two_phase_until(5.0);
+            } else {
+                scheduler.run_until_max_time(5.0);
+            }
+            assert!(scheduler.two_phase.is_empty());
+            *scheduler.state::<[u32; 2]>()
+        };
+        assert_eq!(run(true), [2, 1]);
+        assert_eq!(run(false), [1, 1]);
+
+        let mut scheduler = EventScheduler::builder().state([1_u32, 2]).build();
+        assert_eq!(scheduler.schedule_two_phase(f64::NAN, Swap { from: 0, seen: 0 }), EventId(0));
+        assert!(scheduler.two_phase.is_empty());
+    }
+
+    #[test]
+    fn test_try_run_stops_like_run() {
+        let mut scheduler = EventScheduler::builder().state([1_u32, 2]).max_events_per_time(1).build();
+        scheduler.schedule(ScheduledAction::at(1.0).with_action(|s| {
+            s.request_pause();
+            None
+        }));
+        scheduler.schedule_two_phase(2.0, Swap { from: 0, seen: 0 });
+        scheduler.schedule_two_phase(2.0, Swap { from: 1, seen: 0 });
+        assert_eq!(scheduler.try_run_two_phase_until(5.0).unwrap().stop_reason, crate::StopReason::Paused);
+        assert_eq!(scheduler.current_time, 1.0);
+
+        let error = scheduler.try_run_two_phase_until(5.0).unwrap_err();
+        assert_eq!(error, SimError::ZeroDelayCascade { time: 2.0, limit: 1 });
+        assert_eq!(scheduler.event_queue.len(), 2);
+    }
+}
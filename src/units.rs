@@ -0,0 +1,325 @@
+//! # Time Units
+//!
+//! Simulation time is a bare `f64`, so nothing stops one part of a model from treating it as
+//! minutes while another treats it as hours. Configuring the scheduler with a [`TimeUnit`] lets
+//! delays be written as durations such as `5.minutes()`, which the scheduler converts into its
+//! own unit, and lets diagnostics label times with the unit.
+//!
+//! Adding delays to a floating-point clock rounds at every step, so after billions of events
+//! times drift away from the values they should have. With [`ClockMode::Ticks`], the
+//! scheduler instead computes event times as whole multiples of a tick, which no number of
+//! additions can shift. Where times are compared, to decide whether a horizon has been
+//! reached or whether two events are simultaneous, a [`TimeComparison`] tolerance absorbs the
+//! rounding that remains.
+
+use crate::EventScheduler;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+/// The calendar length of one unit of simulation time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeUnit {
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+}
+
+impl TimeUnit {
+    /// Returns the length of one unit in seconds.
+    pub fn seconds(self) -> f64 {
+        match self {
+            TimeUnit::Seconds => 1.0,
+            TimeUnit::Minutes => 60.0,
+            TimeUnit::Hours => 3_600.0,
+            TimeUnit::Days => 86_400.0,
+        }
+    }
+
+    /// Returns the unit's symbol, such as `"min"`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TimeUnit::Seconds => "s",
+            TimeUnit::Minutes => "min",
+            TimeUnit::Hours => "h",
+            TimeUnit::Days => "d",
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// A length of time independent of the scheduler's unit.
+///
+/// # Example
+/// ```
+/// use desru::{TimeUnit, TimeUnits};
+///
+/// let shift = 7.5.hours() + 30.minutes();
+/// assert_eq!(shift.in_unit(TimeUnit::Hours), 8.0);
+/// assert_eq!(shift.in_unit(TimeUnit::Minutes), 480.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct SimDuration {
+    seconds: f64,
+}
+
+impl SimDuration {
+    /// Creates a duration of `seconds` seconds.
+    pub fn from_seconds(seconds: f64) -> Self {
+        SimDuration { seconds }
+    }
+
+    /// Returns the duration in seconds.
+    pub fn as_seconds(self) -> f64 {
+        self.seconds
+    }
+
+    /// Returns the duration measured in `unit`.
+    pub fn in_unit(self, unit: TimeUnit) -> f64 {
+        self.seconds / unit.seconds()
+    }
+}
+
+impl Add for SimDuration {
+    type Output = SimDuration;
+
+    fn add(self, other: SimDuration) -> SimDuration {
+        SimDuration::from_seconds(self.seconds + other.seconds)
+    }
+}
+
+impl Sub for SimDuration {
+    type Output = SimDuration;
+
+    fn sub(self, other: SimDuration) -> SimDuration {
+        SimDuration::from_seconds(self.seconds - other.seconds)
+    }
+}
+
+impl Mul<f64> for SimDuration {
+    type Output = SimDuration;
+
+    fn mul(self, factor: f64) -> SimDuration {
+        SimDuration::from_seconds(self.seconds * factor)
+    }
+}
+
+/// Constructors for [`SimDuration`] on numbers, as in `5.minutes()` or `1.5.hours()`.
+pub trait TimeUnits {
+    fn seconds(self) -> SimDuration;
+    fn minutes(self) -> SimDuration;
+    fn hours(self) -> SimDuration;
+    fn days(self) -> SimDuration;
+}
+
+macro_rules! impl_time_units {
+    ($($t:ty),*) => {
+        $(
+            impl TimeUnits for $t {
+                fn seconds(self) -> SimDuration {
+                    SimDuration::from_seconds(self as f64)
+                }
+
+                fn minutes(self) -> SimDuration {
+                    SimDuration::from_seconds(self as f64 * TimeUnit::Minutes.seconds())
+                }
+
+                fn hours(self) -> SimDuration {
+                    SimDuration::from_seconds(self as f64 * TimeUnit::Hours.seconds())
+                }
+
+                fn days(self) -> SimDuration {
+                    SimDuration::from_seconds(self as f64 * TimeUnit::Days.seconds())
+                }
+            }
+        )*
+    };
+}
+
+impl_time_units!(f64, i32, i64, u32, u64);
+
+/// How the scheduler adds delays to the current time, see
+/// [`crate::EventSchedulerBuilder::clock_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[non_exhaustive]
+pub enum ClockMode {
+    /// Times are the current time plus the delay in floating point.
+    #[default]
+    Float,
+    /// Times are whole multiples of `tick`: the current time and the delay are each rounded
+    /// to the nearest number of ticks, which are added as integers and multiplied by `tick`
+    /// once.
+    ///
+    /// A delay shorter than half a tick therefore rounds to zero and its event runs at the
+    /// current tick. Components that must not act early, such as a [`crate::RateLimiter`]
+    /// waiting for tokens or the lookahead checks of [`crate::Outbox::send`], round their
+    /// delays up to whole ticks instead.
+    Ticks { tick: f64 },
+}
+
+/// How the scheduler compares times, see [`crate::EventSchedulerBuilder::time_comparison`].
+///
+/// The comparison decides when a run's horizon is reached and which events count as
+/// simultaneous for [`crate::EventSchedulerBuilder::max_events_per_time`]. The queue still
+/// orders events by their exact times.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[non_exhaustive]
+pub enum TimeComparison {
+    /// Times are equal only if they are identical.
+    #[default]
+    Exact,
+    /// Times closer than the tolerance are equal.
+    Tolerance(f64),
+}
+
+impl TimeComparison {
+    /// Returns `true` if `a` and `b` count as the same time.
+    pub fn same(&self, a: f64, b: f64) -> bool {
+        match *self {
+            TimeComparison::Exact => a == b,
+            TimeComparison::Tolerance(epsilon) => (a - b).abs() <= epsilon,
+        }
+    }
+
+    /// Returns `true` if `time` is at or after `limit`.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::TimeComparison;
+    ///
+    /// let limit = 0.3;
+    /// let time = 0.1 + 0.2; // 0.30000000000000004
+    /// assert!(TimeComparison::Exact.reached(time, limit));
+    /// assert!(!TimeComparison::Exact.reached(0.3 - 1e-12, limit));
+    /// assert!(TimeComparison::Tolerance(1e-9).reached(0.3 - 1e-12, limit));
+    /// ```
+    pub fn reached(&self, time: f64, limit: f64) -> bool {
+        time >= limit || self.same(time, limit)
+    }
+}
+
+/// A delay accepted by the scheduler: either a bare number of simulation time units or a
+/// [`SimDuration`].
+pub trait IntoSimTime {
+    /// Converts the delay into simulation time units.
+    ///
+    /// # Panics
+    /// Implementations for durations panic if `unit` is `None`.
+    fn into_sim_time(self, unit: Option<TimeUnit>) -> f64;
+}
+
+impl IntoSimTime for f64 {
+    fn into_sim_time(self, _unit: Option<TimeUnit>) -> f64 {
+        self
+    }
+}
+
+impl IntoSimTime for SimDuration {
+    fn into_sim_time(self, unit: Option<TimeUnit>) -> f64 {
+        let unit = unit.expect("durations need a time unit; use EventSchedulerBuilder::time_unit");
+        self.in_unit(unit)
+    }
+}
+
+impl EventScheduler {
+    /// Converts a delay into the scheduler's simulation time units.
+    ///
+    /// # Panics
+    /// Panics if `delay` is a [`SimDuration`] and the scheduler has no time unit.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{EventScheduler, TimeUnit, TimeUnits};
+    ///
+    /// let mut scheduler = EventScheduler::builder().time_unit(TimeUnit::Minutes).build();
+    /// assert_eq!(scheduler.delay(2.hours()), 120.0);
+    /// scheduler.timeout(90.seconds(), None, None);
+    /// assert_eq!(scheduler.event_queue.peek().map(|e| e.time), Some(1.5));
+    /// assert_eq!(scheduler.format_time(1.5), "1.5 min");
+    /// ```
+    pub fn delay(&self, delay: impl IntoSimTime) -> f64 {
+        delay.into_sim_time(self.time_unit)
+    }
+
+    /// Returns the time at which an event scheduled `delay` from now occurs, following the
+    /// scheduler's [`ClockMode`]. Delays given to [`EventScheduler::timeout`] and the
+    /// components built on the scheduler go through this function.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::{ClockMode, EventScheduler};
+    ///
+    /// let mut float = EventScheduler::new();
+    /// let mut ticks = EventScheduler::builder().clock_mode(ClockMode::Ticks { tick: 0.1 }).build();
+    /// for _ in 0..1000 {
+    ///     float.current_time = float.time_after(0.1);
+    ///     ticks.current_time = ticks.time_after(0.1);
+    /// }
+    /// assert_ne!(float.current_time, 100.0);
+    /// assert_eq!(ticks.current_time, 100.0);
+    /// ```
+    pub fn time_after(&self, delay: f64) -> f64 {
+        match self.clock_mode {
+            ClockMode::Float => self.current_time + delay,
+            ClockMode::Ticks { tick } => ((self.current_time / tick).round() + (delay / tick).round()) * tick,
+        }
+    }
+
+    /// Returns the time at which an event scheduled `delay` from now occurs, as for
+    /// [`EventScheduler::time_after`], but rounding the delay up to whole ticks so that the
+    /// time is never earlier than `delay` from now.
+    pub(crate) fn time_after_at_least(&self, delay: f64) -> f64 {
+        match self.clock_mode {
+            ClockMode::Float => self.current_time + delay,
+            ClockMode::Ticks { tick } => ((self.current_time / tick).round() + (delay / tick).ceil()) * tick,
+        }
+    }
+
+    /// Formats a simulation time with the scheduler's unit, if it has one.
+    pub fn format_time(&self, time: f64) -> String {
+        match self.time_unit {
+            Some(unit) => format!("{} {}", time, unit),
+            None => time.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "durations need a time unit")]
+    fn test_duration_without_unit_panics() {
+        let scheduler = EventScheduler::new();
+        assert_eq!(scheduler.delay(3.0), 3.0);
+        scheduler.delay(3.minutes());
+    }
+
+    #[test]
+    fn test_tolerance_groups_nearly_equal_times_as_simultaneous() {
+        let mut scheduler = EventScheduler::builder().max_events_per_time(1).time_comparison(TimeComparison::Tolerance(1e-9)).build();
+        scheduler.timeout(1.0, None, None);
+        scheduler.timeout(1.0 + 1e-12, None, None);
+        let error = scheduler.try_run(Box::new(|_| false), None).unwrap_err();
+        assert!(matches!(error, crate::SimError::ZeroDelayCascade { limit: 1, .. }));
+    }
+
+    #[test]
+    fn test_sub_tick_delays_round_to_the_nearest_tick() {
+        let mut scheduler = EventScheduler::builder().clock_mode(ClockMode::Ticks { tick: 0.5 }).build();
+        scheduler.current_time = 2.0;
+        assert_eq!(scheduler.time_after(0.2), 2.0);
+        assert_eq!(scheduler.time_after(0.25), 2.5);
+        assert_eq!(scheduler.time_after_at_least(0.2), 2.5);
+        assert_eq!(scheduler.time_after_at_least(0.5), 2.5);
+
+        scheduler.register_min_delay("host", 0.2);
+        assert_eq!(scheduler.lookahead(), 2.0);
+    }
+}
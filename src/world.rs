@@ -0,0 +1,113 @@
+//! # World State
+//!
+//! A scheduler can own one value of any type as the model's shared state. Every action receives
+//! the scheduler mutably and so can reach the state through [`EventScheduler::state`] and
+//! [`EventScheduler::state_mut`], without capturing `Rc<RefCell<...>>` handles in closures.
+
+use crate::EventScheduler;
+use std::any::{type_name, Any};
+use std::fmt;
+
+/// A slot holding the model's shared state as a value of any `'static` type.
+#[derive(Default)]
+pub struct WorldState {
+    value: Option<Box<dyn Any>>,
+}
+
+impl WorldState {
+    /// Creates an empty slot.
+    pub fn new() -> Self {
+        WorldState::default()
+    }
+
+    /// Stores `value`, replacing any previous state.
+    pub fn set<T: 'static>(&mut self, value: T) {
+        self.value = Some(Box::new(value));
+    }
+
+    /// Returns `true` if a state value has been set.
+    pub fn is_set(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Returns the state if it is set and has type `T`.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.value.as_ref().and_then(|value| value.downcast_ref())
+    }
+
+    /// Returns the state mutably if it is set and has type `T`.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.value.as_mut().and_then(|value| value.downcast_mut())
+    }
+
+    /// Removes and returns the state if it has type `T`; otherwise leaves it in place.
+    pub fn take<T: 'static>(&mut self) -> Option<T> {
+        match self.value.take()?.downcast() {
+            Ok(value) => Some(*value),
+            Err(value) => {
+                self.value = Some(value);
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WorldState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorldState").field("is_set", &self.is_set()).finish()
+    }
+}
+
+impl EventScheduler {
+    /// Returns the shared model state.
+    ///
+    /// # Panics
+    /// Panics if no state of type `T` has been set.
+    pub fn state<T: 'static>(&self) -> &T {
+        self.world.get().unwrap_or_else(|| panic!("no world state of type {}", type_name::<T>()))
+    }
+
+    /// Returns the shared model state mutably.
+    ///
+    /// # Panics
+    /// Panics if no state of type `T` has been set.
+    ///
+    /// # Example
+    /// ```
+    /// use desru::EventScheduler;
+    ///
+    /// #[derive(Default)]
+    /// struct Shop {
+    ///     customers: u32,
+    /// }
+    ///
+    /// fn arrive(s: &mut EventScheduler) -> Option<String> {
+    ///     s.state_mut::<Shop>().customers += 1;
+    ///     s.timeout(1.0, Some(Box::new(arrive)), None);
+    ///     None
+    /// }
+    ///
+    /// let mut scheduler = EventScheduler::builder().state(Shop::default()).build();
+    /// scheduler.timeout(1.0, Some(Box::new(arrive)), None);
+    /// scheduler.run_until_max_time(5.0);
+    /// assert_eq!(scheduler.state::<Shop>().customers, 4);
+    /// ```
+    pub fn state_mut<T: 'static>(&mut self) -> &mut T {
+        self.world.get_mut().unwrap_or_else(|| panic!("no world state of type {}", type_name::<T>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_checks_type() {
+        let mut world = WorldState::new();
+        world.set(3_u32);
+        assert_eq!(world.take::<i64>(), None);
+        assert_eq!(world.get::<u32>(), Some(&3));
+        assert_eq!(world.take::<u32>(), Some(3));
+        assert!(!world.is_set());
+    }
+}